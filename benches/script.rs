@@ -0,0 +1,99 @@
+//! Parsing a block of typical outputs, and with the `smallvec` feature its
+//! scripts as [`Script`]s against `InlineScript`s:
+//!
+//! ```text
+//! cargo bench --bench script --features smallvec
+//! ```
+//!
+//! With glibc's allocator the two parse about as fast in this loop:
+//! `InlineScript` saves an allocation per short script, which counts for
+//! more when the allocator is under pressure than when it reuses one slot.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use rust_week_3_exercises::{
+    BitcoinTransaction, Block, Network, OutPoint, Script, TransactionInput, TransactionOutput,
+};
+use std::hint::black_box;
+
+// 2,000 one-input transactions paying a P2WPKH and a P2TR output, close to
+// a full block's output count.
+fn typical_block() -> Vec<u8> {
+    let transactions = (0..2_000u32)
+        .map(|i| {
+            let mut txid = [0u8; 32];
+            txid[..4].copy_from_slice(&i.to_le_bytes());
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(txid, 0),
+                    Script::new(vec![]),
+                    u32::MAX,
+                )],
+                0,
+            )
+            .with_outputs(vec![
+                TransactionOutput::new(50_000, Script::new([&[0x00, 0x14][..], &[1; 20]].concat())),
+                TransactionOutput::new(10_000, Script::new([&[0x51, 0x20][..], &[2; 32]].concat())),
+            ])
+        })
+        .collect();
+    Block::new(Block::genesis(Network::Mainnet).header, transactions).to_bytes()
+}
+
+fn parse_block(c: &mut Criterion) {
+    let bytes = typical_block();
+    let mut group = c.benchmark_group("script");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("parse_block", |b| {
+        b.iter(|| Block::from_bytes(black_box(&bytes)).unwrap())
+    });
+    let output =
+        TransactionOutput::new(10_000, Script::new([&[0x51, 0x20][..], &[2; 32]].concat()))
+            .to_bytes();
+    group.throughput(Throughput::Bytes(output.len() as u64));
+    group.bench_function("parse_output", |b| {
+        b.iter(|| TransactionOutput::from_bytes(black_box(&output)).unwrap())
+    });
+
+    // The length-prefixed scripts of both outputs, 2,000 times over.
+    let scripts: Vec<u8> = (0..2_000)
+        .flat_map(|_| {
+            [
+                Script::new([&[0x00, 0x14][..], &[1; 20]].concat()).to_bytes(),
+                Script::new([&[0x51, 0x20][..], &[2; 32]].concat()).to_bytes(),
+            ]
+            .concat()
+        })
+        .collect();
+    group.throughput(Throughput::Bytes(scripts.len() as u64));
+    group.bench_function("parse_scripts", |b| {
+        b.iter(|| {
+            let mut rest = black_box(&scripts[..]);
+            let mut parsed = Vec::with_capacity(4_000);
+            while !rest.is_empty() {
+                let (script, consumed) = Script::from_bytes(rest).unwrap();
+                parsed.push(script);
+                rest = &rest[consumed..];
+            }
+            parsed
+        })
+    });
+    #[cfg(feature = "smallvec")]
+    group.bench_function("parse_scripts_inline", |b| {
+        use rust_week_3_exercises::InlineScript;
+        b.iter(|| {
+            let mut rest = black_box(&scripts[..]);
+            let mut parsed = Vec::with_capacity(4_000);
+            while !rest.is_empty() {
+                let (script, consumed) = InlineScript::from_bytes(rest).unwrap();
+                parsed.push(script);
+                rest = &rest[consumed..];
+            }
+            parsed
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_block);
+criterion_main!(benches);
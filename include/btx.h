@@ -0,0 +1,62 @@
+#ifndef BTX_H
+#define BTX_H
+
+/* Generated from src/ffi.rs with cbindgen; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define BTX_OK 0
+
+#define BTX_ERR_NULL -1
+
+#define BTX_ERR_INDEX -2
+
+/**
+ * Opaque handle to a decoded transaction.
+ */
+typedef struct BtxTransaction BtxTransaction;
+
+/**
+ * Decodes `len` bytes at `data`. Returns NULL if they are not exactly one
+ * serialized transaction.
+ */
+BtxTransaction *btx_tx_decode(const uint8_t *data, size_t len);
+
+void btx_tx_free(BtxTransaction *tx);
+
+int32_t btx_tx_version(const BtxTransaction *tx, uint32_t *out);
+
+int32_t btx_tx_lock_time(const BtxTransaction *tx, uint32_t *out);
+
+int32_t btx_tx_input_count(const BtxTransaction *tx, size_t *out);
+
+int32_t btx_tx_output_count(const BtxTransaction *tx, size_t *out);
+
+/**
+ * Writes the txid in internal (little-endian) byte order.
+ */
+int32_t btx_tx_txid(const BtxTransaction *tx, uint8_t (*out)[32]);
+
+int32_t btx_tx_input_prevout(const BtxTransaction *tx,
+                             size_t index,
+                             uint8_t (*txid_out)[32],
+                             uint32_t *vout_out);
+
+int32_t btx_tx_input_sequence(const BtxTransaction *tx, size_t index, uint32_t *out);
+
+int32_t btx_tx_output_value(const BtxTransaction *tx, size_t index, uint64_t *out);
+
+/**
+ * Borrows an output's scriptPubKey. The pointer stays valid until the
+ * handle is freed.
+ */
+int32_t btx_tx_output_script(const BtxTransaction *tx,
+                             size_t index,
+                             const uint8_t **data_out,
+                             size_t *len_out);
+
+#endif /* BTX_H */
@@ -0,0 +1,257 @@
+//! Bitcoin addresses: Base58Check P2PKH/P2SH (BIP13) and bech32/bech32m
+//! segwit addresses (BIP173, BIP350).
+//!
+//! An address encodes a network only loosely: testnet and signet share
+//! every prefix, and regtest shares their Base58 versions. Parsing picks
+//! the first network that matches, so check the result with
+//! [`Address::require_network`] before paying to it.
+
+use crate::bech32::{self, Variant};
+use crate::witness_program::{WitnessProgram, WitnessVersion};
+use crate::{BitcoinError, Network, Script, base58};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AddressError {
+    /// Bad Base58 characters or checksum.
+    Base58,
+    /// Bad bech32 characters, case or checksum.
+    Bech32,
+    UnknownVersion(u8),
+    UnknownHrp(String),
+    /// Bech32 used for v1+ programs or bech32m for v0.
+    WrongVariant,
+    InvalidWitnessProgram,
+    /// The script has no address form.
+    UnsupportedScript,
+    /// The address belongs to another network.
+    NetworkMismatch {
+        expected: Network,
+        found: Network,
+    },
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Base58 => write!(f, "invalid base58"),
+            AddressError::Bech32 => write!(f, "invalid bech32"),
+            AddressError::UnknownVersion(version) => {
+                write!(f, "unknown address version {:#04x}", version)
+            }
+            AddressError::UnknownHrp(hrp) => write!(f, "unknown address prefix {}", hrp),
+            AddressError::WrongVariant => write!(f, "wrong bech32 checksum variant"),
+            AddressError::InvalidWitnessProgram => write!(f, "invalid witness program"),
+            AddressError::UnsupportedScript => write!(f, "script has no address"),
+            AddressError::NetworkMismatch { expected, found } => {
+                write!(f, "address is for {:?}, not {:?}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Payload {
+    PubkeyHash([u8; 20]),
+    ScriptHash([u8; 20]),
+    WitnessProgram(WitnessProgram),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Address {
+    pub network: Network,
+    pub payload: Payload,
+}
+
+const NETWORKS: [Network; 4] = [
+    Network::Mainnet,
+    Network::Testnet,
+    Network::Signet,
+    Network::Regtest,
+];
+
+impl Network {
+    pub fn p2pkh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    pub fn p2sh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    /// Human-readable part of segwit addresses.
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+impl Address {
+    pub fn new(network: Network, payload: Payload) -> Self {
+        Self { network, payload }
+    }
+
+    /// The address paying to `script`, for standard output types.
+    pub fn from_script(script: &Script, network: Network) -> Result<Self, AddressError> {
+        let b = &script.bytes;
+        let payload = match &b[..] {
+            [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if b.len() == 25 => {
+                Payload::PubkeyHash(hash.try_into().unwrap())
+            }
+            [0xa9, 0x14, hash @ .., 0x87] if b.len() == 23 => {
+                Payload::ScriptHash(hash.try_into().unwrap())
+            }
+            _ => Payload::WitnessProgram(
+                script
+                    .witness_program()
+                    .ok_or(AddressError::UnsupportedScript)?,
+            ),
+        };
+        Ok(Self::new(network, payload))
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        match &self.payload {
+            Payload::PubkeyHash(hash) => {
+                Script::new([&[0x76, 0xa9, 0x14], &hash[..], &[0x88, 0xac]].concat())
+            }
+            Payload::ScriptHash(hash) => Script::new([&[0xa9, 0x14], &hash[..], &[0x87]].concat()),
+            Payload::WitnessProgram(program) => program.to_script(),
+        }
+    }
+
+    /// Whether this address's string form is also valid on `network`. True
+    /// between networks sharing prefixes, e.g. testnet and signet.
+    pub fn is_valid_for_network(&self, network: Network) -> bool {
+        match self.payload {
+            Payload::PubkeyHash(_) => self.network.p2pkh_prefix() == network.p2pkh_prefix(),
+            Payload::ScriptHash(_) => self.network.p2sh_prefix() == network.p2sh_prefix(),
+            Payload::WitnessProgram(_) => self.network.bech32_hrp() == network.bech32_hrp(),
+        }
+    }
+
+    /// This address, if its encoding is valid on `network`, marked as
+    /// belonging to it.
+    pub fn require_network(self, network: Network) -> Result<Self, AddressError> {
+        if !self.is_valid_for_network(network) {
+            return Err(AddressError::NetworkMismatch {
+                expected: network,
+                found: self.network,
+            });
+        }
+        Ok(Self { network, ..self })
+    }
+
+    /// The same payload re-encoded for `network`. Only moves between test
+    /// networks; anything to or from mainnet is a mismatch, since coins
+    /// there aren't interchangeable.
+    pub fn for_network(&self, network: Network) -> Result<Self, AddressError> {
+        if (self.network == Network::Mainnet) != (network == Network::Mainnet) {
+            return Err(AddressError::NetworkMismatch {
+                expected: network,
+                found: self.network,
+            });
+        }
+        Ok(Self::new(network, self.payload.clone()))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, hash) = match &self.payload {
+            Payload::PubkeyHash(hash) => (self.network.p2pkh_prefix(), hash),
+            Payload::ScriptHash(hash) => (self.network.p2sh_prefix(), hash),
+            Payload::WitnessProgram(program) => {
+                let variant = match program.version {
+                    WitnessVersion::V0 => Variant::Bech32,
+                    _ => Variant::Bech32m,
+                };
+                let mut data = vec![program.version.to_u8()];
+                data.extend(bech32::convert_bits(&program.program, 8, 5, true).unwrap());
+                return write!(
+                    f,
+                    "{}",
+                    bech32::encode(self.network.bech32_hrp(), &data, variant)
+                );
+            }
+        };
+        write!(
+            f,
+            "{}",
+            base58::encode_check(&[&[prefix], &hash[..]].concat())
+        )
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, AddressError> {
+        // Segwit addresses start with an hrp and "1", which Base58 can't
+        // produce for 25-byte payloads.
+        let lower = s.to_ascii_lowercase();
+        if let Some(network) = NETWORKS
+            .into_iter()
+            .find(|network| lower.starts_with(&format!("{}1", network.bech32_hrp())))
+        {
+            return from_bech32(s, network);
+        }
+        let data = base58::decode_check(s).map_err(|_| AddressError::Base58)?;
+        let [version, hash @ ..] = &data[..] else {
+            return Err(AddressError::Base58);
+        };
+        let hash: [u8; 20] = hash.try_into().map_err(|_| AddressError::Base58)?;
+        NETWORKS
+            .into_iter()
+            .find_map(|network| {
+                if *version == network.p2pkh_prefix() {
+                    Some(Address::new(network, Payload::PubkeyHash(hash)))
+                } else if *version == network.p2sh_prefix() {
+                    Some(Address::new(network, Payload::ScriptHash(hash)))
+                } else {
+                    None
+                }
+            })
+            .ok_or(AddressError::UnknownVersion(*version))
+    }
+}
+
+fn from_bech32(s: &str, network: Network) -> Result<Address, AddressError> {
+    let (hrp, data, variant) = bech32::decode(s).map_err(|_| AddressError::Bech32)?;
+    if hrp != network.bech32_hrp() {
+        return Err(AddressError::UnknownHrp(hrp));
+    }
+    let [version, program @ ..] = &data[..] else {
+        return Err(AddressError::InvalidWitnessProgram);
+    };
+    let version = WitnessVersion::new(*version).map_err(|_| AddressError::InvalidWitnessProgram)?;
+    let expected = match version {
+        WitnessVersion::V0 => Variant::Bech32,
+        _ => Variant::Bech32m,
+    };
+    if variant != expected {
+        return Err(AddressError::WrongVariant);
+    }
+    let program = bech32::convert_bits(program, 5, 8, false)
+        .and_then(|program| WitnessProgram::new(version, program))
+        .map_err(|_| AddressError::InvalidWitnessProgram)?;
+    Ok(Address::new(network, Payload::WitnessProgram(program)))
+}
+
+impl From<AddressError> for BitcoinError {
+    fn from(err: AddressError) -> Self {
+        BitcoinError::Address(err)
+    }
+}
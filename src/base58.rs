@@ -0,0 +1,69 @@
+//! Base58 and Base58Check, as used by legacy addresses and extended keys.
+
+use crate::BitcoinError;
+use crate::hashes::sha256d;
+
+const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    // Little-endian base-58 digits of the big-endian number in `data`.
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    out
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let zeros = s.bytes().take_while(|&c| c == b'1').count();
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+    for c in s[zeros..].bytes() {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(BitcoinError::InvalidFormat)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+/// Appends the first four bytes of `sha256d(data)` before encoding.
+pub fn encode_check(data: &[u8]) -> String {
+    let mut v = data.to_vec();
+    v.extend_from_slice(&sha256d(data)[..4]);
+    encode(&v)
+}
+
+pub fn decode_check(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let mut v = decode(s)?;
+    if v.len() < 4 {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let checksum = v.split_off(v.len() - 4);
+    if sha256d(&v)[..4] != checksum[..] {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(v)
+}
@@ -0,0 +1,115 @@
+//! Bech32 (BIP173) and Bech32m (BIP350), as used by segwit addresses.
+
+use crate::BitcoinError;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+/// Longest string BIP173 allows.
+const MAX_LENGTH: usize = 90;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+    /// For v0 witness programs.
+    Bech32,
+    /// For v1 and later.
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut v: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    v.push(0);
+    v.extend(hrp.bytes().map(|b| b & 31));
+    v
+}
+
+/// Encodes 5-bit `data` under a lowercase `hrp`.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = polymod(values) ^ variant.constant();
+    let mut out = format!("{}1", hrp);
+    out.extend(data.iter().map(|&d| CHARSET[d as usize] as char));
+    out.extend((0..6).map(|i| CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char));
+    out
+}
+
+/// Decodes to the lowercase human-readable part, the 5-bit data and the
+/// checksum variant. Mixed case is rejected.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant), BitcoinError> {
+    if s.len() > MAX_LENGTH
+        || (s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()))
+    {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1').ok_or(BitcoinError::InvalidFormat)?;
+    let (hrp, rest) = (&s[..separator], &s[separator + 1..]);
+    if hrp.is_empty() || rest.len() < 6 || hrp.bytes().any(|b| !(33..=126).contains(&b)) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let data = rest
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(BitcoinError::InvalidFormat)?;
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    let variant = match polymod(values) {
+        1 => Variant::Bech32,
+        0x2bc830a3 => Variant::Bech32m,
+        _ => return Err(BitcoinError::InvalidFormat),
+    };
+    Ok((hrp.to_string(), data[..data.len() - 6].to_vec(), variant))
+}
+
+/// Regroups bits, e.g. bytes into 5-bit groups. Without `pad`, leftover
+/// bits must be zero padding of fewer than `from` bits.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, BitcoinError> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut out = Vec::new();
+    let max = (1 << to) - 1;
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(out)
+}
@@ -0,0 +1,204 @@
+//! `btx decode [--pretty] <hex|file|->`
+//!
+//! Decodes a raw transaction or block given as a hex argument, a file (hex
+//! text or raw bytes) or `-` for stdin, and prints Core-style JSON. With the
+//! `color` feature, `--pretty` output to a terminal is colored unless
+//! `NO_COLOR` is set.
+//!
+//! `btx create [--psbt] <spec.json|->`
+//!
+//! Builds an unsigned transaction from a JSON spec and prints its raw hex, or
+//! a base64 PSBT with `--psbt`:
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "locktime": 0,
+//!   "inputs": [{ "txid": "<hex, RPC order>", "vout": 0, "sequence": 4294967293 }],
+//!   "outputs": [{ "value": 10000, "script_pubkey": "<hex>" }]
+//! }
+//! ```
+
+use rust_week_3_exercises::constants::MAX_BIP125_RBF_SEQUENCE;
+use rust_week_3_exercises::format::FormatOptions;
+use rust_week_3_exercises::psbt::Psbt;
+use rust_week_3_exercises::{
+    BitcoinTransaction, Block, OutPoint, Script, TransactionBuilder, core_json,
+};
+use serde::Deserialize;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: btx decode [--pretty] <hex|file|->\n       btx create [--psbt] <spec.json|->";
+
+#[derive(Deserialize)]
+struct Spec {
+    #[serde(default = "default_version")]
+    version: u32,
+    #[serde(default)]
+    locktime: u32,
+    inputs: Vec<InputSpec>,
+    outputs: Vec<OutputSpec>,
+}
+
+#[derive(Deserialize)]
+struct InputSpec {
+    txid: String,
+    vout: u32,
+    #[serde(default = "default_sequence")]
+    sequence: u32,
+}
+
+#[derive(Deserialize)]
+struct OutputSpec {
+    value: u64,
+    script_pubkey: String,
+}
+
+fn default_version() -> u32 {
+    2
+}
+
+// Opts in to BIP125 replacement, like Core's wallet.
+fn default_sequence() -> u32 {
+    MAX_BIP125_RBF_SEQUENCE
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("btx: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    match args.first().map(String::as_str) {
+        Some("decode") => decode(&args[1..]),
+        Some("create") => create(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn decode(args: &[String]) -> Result<String, String> {
+    let pretty = args.iter().any(|a| a == "--pretty");
+    let source = match args.iter().find(|a| a.as_str() != "--pretty") {
+        Some(source) => source,
+        None => return Err(USAGE.to_string()),
+    };
+    let bytes = read_input(source)?;
+
+    // Try a transaction first; anything that doesn't consume the whole
+    // input as a transaction is retried as a block.
+    if let Ok((tx, used)) = BitcoinTransaction::from_bytes(&bytes)
+        && used == bytes.len()
+    {
+        return Ok(if pretty {
+            pretty_tx(&tx)
+        } else {
+            to_json(&core_json::transaction_to_json(&tx))
+        });
+    }
+    match Block::from_bytes(&bytes) {
+        Ok((block, used)) if used == bytes.len() => Ok(if pretty {
+            block
+                .transactions
+                .iter()
+                .map(pretty_tx)
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            to_json(&core_json::block_to_json(&block))
+        }),
+        Ok((_, used)) => Err(format!("{} trailing bytes", bytes.len() - used)),
+        Err(err) => Err(format!("not a transaction or block: {}", err)),
+    }
+}
+
+fn pretty_tx(tx: &BitcoinTransaction) -> String {
+    tx.format_with(&FormatOptions {
+        #[cfg(feature = "color")]
+        color: std::io::IsTerminal::is_terminal(&std::io::stdout())
+            && std::env::var_os("NO_COLOR").is_none(),
+        ..Default::default()
+    })
+}
+
+fn create(args: &[String]) -> Result<String, String> {
+    let psbt = args.iter().any(|a| a == "--psbt");
+    let source = match args.iter().find(|a| a.as_str() != "--psbt") {
+        Some(source) => source,
+        None => return Err(USAGE.to_string()),
+    };
+    let text = if source == "-" {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| e.to_string())?;
+        buf
+    } else {
+        std::fs::read_to_string(source).map_err(|e| format!("{}: {}", source, e))?
+    };
+    let spec: Spec = serde_json::from_str(&text).map_err(|e| format!("invalid spec: {}", e))?;
+
+    let mut builder = TransactionBuilder::new()
+        .version(spec.version)
+        .lock_time(spec.locktime);
+    for input in &spec.inputs {
+        let mut txid: [u8; 32] = hex::decode(&input.txid)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("invalid txid: {}", input.txid))?;
+        txid.reverse();
+        builder = builder.add_input(OutPoint::new(txid, input.vout), input.sequence);
+    }
+    for output in &spec.outputs {
+        let script = hex::decode(&output.script_pubkey)
+            .map_err(|_| format!("invalid script_pubkey: {}", output.script_pubkey))?;
+        builder = builder.add_output(output.value, Script::new(script));
+    }
+    let tx = builder.build().map_err(|e| e.to_string())?;
+
+    if psbt {
+        Ok(Psbt::from_unsigned_tx(tx)
+            .map_err(|e| e.to_string())?
+            .to_base64())
+    } else {
+        Ok(hex::encode(tx.to_bytes()))
+    }
+}
+
+fn read_input(source: &str) -> Result<Vec<u8>, String> {
+    let raw = if source == "-" {
+        let mut buf = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .map_err(|e| e.to_string())?;
+        buf
+    } else if Path::new(source).is_file() {
+        std::fs::read(source).map_err(|e| format!("{}: {}", source, e))?
+    } else {
+        source.as_bytes().to_vec()
+    };
+    // Hex text is decoded; anything else is taken as raw serialized bytes.
+    match std::str::from_utf8(&raw) {
+        Ok(text) if !text.trim().is_empty() => match hex::decode(text.trim()) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) if source == "-" || Path::new(source).is_file() => Ok(raw),
+            Err(e) => Err(format!("invalid hex: {}", e)),
+        },
+        _ => Ok(raw),
+    }
+}
+
+fn to_json(value: &serde_json::Value) -> String {
+    core_json::to_string_pretty(value)
+}
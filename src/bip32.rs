@@ -0,0 +1,479 @@
+//! BIP32 hierarchical deterministic keys, with SLIP-132 `ypub`/`zpub`
+//! style version prefixes.
+//!
+//! Parsing and serialization are always available; derivation needs the
+//! `secp` feature.
+
+use crate::hashes::hash160;
+use crate::{BitcoinError, base58};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+pub const HARDENED: u32 = 0x80000000;
+pub const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+pub const TPUB_VERSION: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+pub const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+pub const TPRV_VERSION: [u8; 4] = [0x04, 0x35, 0x83, 0x94];
+pub const EXTENDED_KEY_SIZE: usize = 78;
+/// The secp256k1 group order; private keys must lie in `1..n`.
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// The script type a SLIP-132 version prefix implies.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum KeyScriptType {
+    /// `xpub`/`tpub`; also used by wallets that don't follow SLIP-132.
+    P2pkh,
+    /// `ypub`/`upub` (BIP49).
+    P2shP2wpkh,
+    /// `zpub`/`vpub` (BIP84).
+    P2wpkh,
+}
+
+/// SLIP-132 `(public, private, script type, testnet)` versions.
+const SLIP132_VERSIONS: [([u8; 4], [u8; 4], KeyScriptType, bool); 6] = [
+    (XPUB_VERSION, XPRV_VERSION, KeyScriptType::P2pkh, false),
+    (
+        [0x04, 0x9d, 0x7c, 0xb2],
+        [0x04, 0x9d, 0x78, 0x78],
+        KeyScriptType::P2shP2wpkh,
+        false,
+    ),
+    (
+        [0x04, 0xb2, 0x47, 0x46],
+        [0x04, 0xb2, 0x43, 0x0c],
+        KeyScriptType::P2wpkh,
+        false,
+    ),
+    (TPUB_VERSION, TPRV_VERSION, KeyScriptType::P2pkh, true),
+    (
+        [0x04, 0x4a, 0x52, 0x62],
+        [0x04, 0x4a, 0x4e, 0x28],
+        KeyScriptType::P2shP2wpkh,
+        true,
+    ),
+    (
+        [0x04, 0x5f, 0x1c, 0xf6],
+        [0x04, 0x5f, 0x18, 0xbc],
+        KeyScriptType::P2wpkh,
+        true,
+    ),
+];
+
+/// Fails with `InvalidFormat` for versions outside the table, which the
+/// public fields let callers set.
+fn slip132_entry(
+    version: [u8; 4],
+) -> Result<([u8; 4], [u8; 4], KeyScriptType, bool), BitcoinError> {
+    SLIP132_VERSIONS
+        .iter()
+        .find(|entry| entry.0 == version || entry.1 == version)
+        .copied()
+        .ok_or(BitcoinError::InvalidFormat)
+}
+
+fn slip132_version(script_type: KeyScriptType, testnet: bool) -> ([u8; 4], [u8; 4]) {
+    let entry = SLIP132_VERSIONS
+        .iter()
+        .find(|entry| entry.2 == script_type && entry.3 == testnet)
+        .unwrap();
+    (entry.0, entry.1)
+}
+
+/// First four bytes of a public key's hash160.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Fingerprint(pub [u8; 4]);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 4];
+        hex::decode_to_slice(s, &mut bytes).map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Fingerprint(bytes))
+    }
+}
+
+/// Child indexes from a master key, hardened ones with the top bit set.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct DerivationPath(pub Vec<u32>);
+
+impl Deref for DerivationPath {
+    type Target = Vec<u32>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    /// `m/84'/0'/0'/0/5`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for &index in &self.0 {
+            write!(f, "/{}", format_step(index))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = BitcoinError;
+
+    /// Accepts an optional leading `m`, and `'` or `h` for hardened steps.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('m').unwrap_or(s);
+        let s = s.strip_prefix('/').unwrap_or(s);
+        if s.is_empty() {
+            return Ok(DerivationPath::default());
+        }
+        s.split('/')
+            .map(parse_step)
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+/// One path step, with `'`, `h` or `H` marking it hardened.
+pub(crate) fn parse_step(s: &str) -> Result<u32, BitcoinError> {
+    let (index, hardened) = match s.strip_suffix(['\'', 'h', 'H']) {
+        Some(index) => (index, HARDENED),
+        None => (s, 0),
+    };
+    match index.parse::<u32>() {
+        Ok(index) if index < HARDENED => Ok(index | hardened),
+        _ => Err(BitcoinError::InvalidFormat),
+    }
+}
+
+/// One path step, hardened ones written with `'`.
+pub(crate) fn format_step(step: u32) -> String {
+    if step >= HARDENED {
+        format!("{}'", step - HARDENED)
+    } else {
+        step.to_string()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Xpub {
+    pub version: [u8; 4],
+    pub depth: u8,
+    pub parent_fingerprint: Fingerprint,
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub public_key: [u8; 33],
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Xpriv {
+    pub version: [u8; 4],
+    pub depth: u8,
+    pub parent_fingerprint: Fingerprint,
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub private_key: [u8; 32],
+}
+
+impl Xpub {
+    pub fn script_type(&self) -> Result<KeyScriptType, BitcoinError> {
+        Ok(slip132_entry(self.version)?.2)
+    }
+
+    pub fn is_testnet(&self) -> Result<bool, BitcoinError> {
+        Ok(slip132_entry(self.version)?.3)
+    }
+
+    /// The same key under the SLIP-132 version for `script_type`, keeping
+    /// the network; `KeyScriptType::P2pkh` gives a plain `xpub`/`tpub`.
+    pub fn with_script_type(&self, script_type: KeyScriptType) -> Result<Xpub, BitcoinError> {
+        Ok(Xpub {
+            version: slip132_version(script_type, self.is_testnet()?).0,
+            ..self.clone()
+        })
+    }
+
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint(hash160(&self.public_key)[..4].try_into().unwrap())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize(
+            self.version,
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key,
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let versions: Vec<_> = SLIP132_VERSIONS.iter().map(|entry| entry.0).collect();
+        let header = parse_header(bytes, &versions)?;
+        if bytes[45] != 0x02 && bytes[45] != 0x03 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        #[cfg(feature = "secp")]
+        secp256k1::PublicKey::from_slice(&bytes[45..78])
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        let xpub = Xpub {
+            version: header.0,
+            depth: header.1,
+            parent_fingerprint: header.2,
+            child_number: header.3,
+            chain_code: header.4,
+            public_key: bytes[45..78].try_into().unwrap(),
+        };
+        Ok((xpub, EXTENDED_KEY_SIZE))
+    }
+}
+
+impl Xpriv {
+    pub fn script_type(&self) -> Result<KeyScriptType, BitcoinError> {
+        Ok(slip132_entry(self.version)?.2)
+    }
+
+    pub fn is_testnet(&self) -> Result<bool, BitcoinError> {
+        Ok(slip132_entry(self.version)?.3)
+    }
+
+    pub fn with_script_type(&self, script_type: KeyScriptType) -> Result<Xpriv, BitcoinError> {
+        Ok(Xpriv {
+            version: slip132_version(script_type, self.is_testnet()?).1,
+            ..self.clone()
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut key = vec![0];
+        key.extend_from_slice(&self.private_key);
+        serialize(
+            self.version,
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let versions: Vec<_> = SLIP132_VERSIONS.iter().map(|entry| entry.1).collect();
+        let header = parse_header(bytes, &versions)?;
+        let private_key: [u8; 32] = bytes[46..78].try_into().unwrap();
+        if bytes[45] != 0 || private_key == [0; 32] || private_key >= CURVE_ORDER {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let xpriv = Xpriv {
+            version: header.0,
+            depth: header.1,
+            parent_fingerprint: header.2,
+            child_number: header.3,
+            chain_code: header.4,
+            private_key,
+        };
+        Ok((xpriv, EXTENDED_KEY_SIZE))
+    }
+}
+
+#[cfg(feature = "secp")]
+impl Xpub {
+    /// Public (CKDpub) derivation; hardened indexes fail with `Unsupported`
+    /// since they need the private key.
+    pub fn derive_child(&self, index: u32) -> Result<Xpub, BitcoinError> {
+        use secp256k1::{PublicKey, Scalar, Secp256k1};
+
+        if index >= HARDENED {
+            return Err(BitcoinError::Unsupported);
+        }
+        let mut data = self.public_key.to_vec();
+        data.extend_from_slice(&index.to_be_bytes());
+        let (tweak, chain_code) = hmac_sha512(&self.chain_code, &data);
+
+        let tweak = Scalar::from_be_bytes(tweak).map_err(|_| BitcoinError::InvalidFormat)?;
+        let key = PublicKey::from_slice(&self.public_key)
+            .and_then(|key| key.add_exp_tweak(&Secp256k1::verification_only(), &tweak))
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Xpub {
+            version: self.version,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(BitcoinError::InvalidFormat)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code,
+            public_key: key.serialize(),
+        })
+    }
+
+    pub fn derive_path(&self, path: &[u32]) -> Result<Xpub, BitcoinError> {
+        path.iter()
+            .try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+}
+
+#[cfg(feature = "secp")]
+impl Xpriv {
+    /// The master key for `seed`, with testnet (`tprv`) or mainnet versions.
+    pub fn new_master(seed: &[u8], testnet: bool) -> Result<Xpriv, BitcoinError> {
+        let (private_key, chain_code) = hmac_sha512(b"Bitcoin seed", seed);
+        secp256k1::SecretKey::from_slice(&private_key).map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Xpriv {
+            version: if testnet { TPRV_VERSION } else { XPRV_VERSION },
+            depth: 0,
+            parent_fingerprint: Fingerprint::default(),
+            child_number: 0,
+            chain_code,
+            private_key,
+        })
+    }
+
+    pub fn public_key(&self) -> Result<[u8; 33], BitcoinError> {
+        use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+        let secret =
+            SecretKey::from_slice(&self.private_key).map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret).serialize())
+    }
+
+    pub fn fingerprint(&self) -> Result<Fingerprint, BitcoinError> {
+        Ok(Fingerprint(
+            hash160(&self.public_key()?)[..4].try_into().unwrap(),
+        ))
+    }
+
+    pub fn to_xpub(&self) -> Result<Xpub, BitcoinError> {
+        Ok(Xpub {
+            version: slip132_entry(self.version)?.0,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key: self.public_key()?,
+        })
+    }
+
+    /// Private (CKDpriv) derivation, hardened or not.
+    pub fn derive_child(&self, index: u32) -> Result<Xpriv, BitcoinError> {
+        use secp256k1::{Scalar, SecretKey};
+
+        let mut data = if index >= HARDENED {
+            [&[0][..], &self.private_key].concat()
+        } else {
+            self.public_key()?.to_vec()
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+        let (tweak, chain_code) = hmac_sha512(&self.chain_code, &data);
+
+        let tweak = Scalar::from_be_bytes(tweak).map_err(|_| BitcoinError::InvalidFormat)?;
+        let key = SecretKey::from_slice(&self.private_key)
+            .and_then(|key| key.add_tweak(&tweak))
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Xpriv {
+            version: self.version,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(BitcoinError::InvalidFormat)?,
+            parent_fingerprint: self.fingerprint()?,
+            child_number: index,
+            chain_code,
+            private_key: key.secret_bytes(),
+        })
+    }
+
+    pub fn derive_path(&self, path: &[u32]) -> Result<Xpriv, BitcoinError> {
+        path.iter()
+            .try_fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+}
+
+macro_rules! impl_base58_key {
+    ($t:ty) => {
+        impl FromStr for $t {
+            type Err = BitcoinError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let bytes = base58::decode_check(s)?;
+                if bytes.len() != EXTENDED_KEY_SIZE {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                Ok(<$t>::from_bytes(&bytes)?.0)
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&base58::encode_check(&self.to_bytes()))
+            }
+        }
+    };
+}
+
+impl_base58_key!(Xpub);
+impl_base58_key!(Xpriv);
+
+fn serialize(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: Fingerprint,
+    child_number: u32,
+    chain_code: &[u8; 32],
+    key: &[u8],
+) -> Vec<u8> {
+    let mut v = version.to_vec();
+    v.push(depth);
+    v.extend_from_slice(&parent_fingerprint.0);
+    v.extend_from_slice(&child_number.to_be_bytes());
+    v.extend_from_slice(chain_code);
+    v.extend_from_slice(key);
+    v
+}
+
+type Header = ([u8; 4], u8, Fingerprint, u32, [u8; 32]);
+
+fn parse_header(bytes: &[u8], versions: &[[u8; 4]]) -> Result<Header, BitcoinError> {
+    if bytes.len() < EXTENDED_KEY_SIZE {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let version: [u8; 4] = bytes[0..4].try_into().unwrap();
+    if !versions.contains(&version) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let depth = bytes[4];
+    let parent_fingerprint = Fingerprint(bytes[5..9].try_into().unwrap());
+    let child_number = u32::from_be_bytes(bytes[9..13].try_into().unwrap());
+    // A master key has no parent.
+    if depth == 0 && (parent_fingerprint != Fingerprint::default() || child_number != 0) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok((
+        version,
+        depth,
+        parent_fingerprint,
+        child_number,
+        bytes[13..45].try_into().unwrap(),
+    ))
+}
+
+/// Splits `HMAC-SHA512(key, data)` into its left and right halves.
+#[cfg(feature = "secp")]
+pub(crate) fn hmac_sha512(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha512;
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    let out = mac.finalize().into_bytes();
+    (out[..32].try_into().unwrap(), out[32..].try_into().unwrap())
+}
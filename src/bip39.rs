@@ -0,0 +1,129 @@
+//! BIP39 mnemonic codes with the English wordlist.
+//!
+//! Entropy is supplied by the caller. Passphrases are used as given, so
+//! non-ASCII ones must already be NFKD-normalized.
+
+use crate::BitcoinError;
+use crate::hashes::sha256;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const ENGLISH: &str = include_str!("bip39_english.txt");
+pub const PBKDF2_ROUNDS: u32 = 2048;
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| ENGLISH.lines().collect())
+}
+
+/// A 12 to 24 word mnemonic, stored as wordlist indexes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mnemonic {
+    indexes: Vec<u16>,
+}
+
+impl Mnemonic {
+    /// Encodes 16 to 32 bytes of entropy, in multiples of 4.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Mnemonic, BitcoinError> {
+        if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut bits: Vec<bool> = entropy.iter().flat_map(|&b| byte_bits(b)).collect();
+        let checksum = sha256(entropy)[0];
+        bits.extend(byte_bits(checksum).take(entropy.len() / 4));
+        let indexes = bits
+            .chunks(11)
+            .map(|chunk| chunk.iter().fold(0u16, |acc, &bit| acc << 1 | bit as u16))
+            .collect();
+        Ok(Mnemonic { indexes })
+    }
+
+    pub fn entropy(&self) -> Vec<u8> {
+        let (entropy, _) = self.split_checksum();
+        entropy
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.indexes.iter().map(|&i| wordlist()[i as usize])
+    }
+
+    /// The 64-byte seed: PBKDF2-HMAC-SHA512 of the sentence, salted with
+    /// `"mnemonic"` and the passphrase.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt = format!("mnemonic{}", passphrase);
+        let mut seed = [0u8; 64];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha512>(
+            self.to_string().as_bytes(),
+            salt.as_bytes(),
+            PBKDF2_ROUNDS,
+            &mut seed,
+        );
+        seed
+    }
+
+    #[cfg(feature = "secp")]
+    pub fn to_xpriv(
+        &self,
+        passphrase: &str,
+        testnet: bool,
+    ) -> Result<crate::bip32::Xpriv, BitcoinError> {
+        crate::bip32::Xpriv::new_master(&self.to_seed(passphrase), testnet)
+    }
+
+    fn split_checksum(&self) -> (Vec<u8>, u8) {
+        let bits: Vec<bool> = self
+            .indexes
+            .iter()
+            .flat_map(|&i| (0..11).rev().map(move |shift| i >> shift & 1 == 1))
+            .collect();
+        let entropy_bits = bits.len() * 32 / 33;
+        let to_byte = |chunk: &[bool]| chunk.iter().fold(0u8, |acc, &bit| acc << 1 | bit as u8);
+        let entropy = bits[..entropy_bits].chunks(8).map(to_byte).collect();
+        (entropy, to_byte(&bits[entropy_bits..]))
+    }
+}
+
+fn byte_bits(byte: u8) -> impl Iterator<Item = bool> {
+    (0..8).rev().map(move |shift| byte >> shift & 1 == 1)
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, word) in self.words().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(word)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = BitcoinError;
+
+    /// Parses a whitespace-separated sentence, checking the word count,
+    /// that every word is in the wordlist and the checksum.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let indexes = s
+            .split_whitespace()
+            .map(|word| {
+                wordlist()
+                    .binary_search(&word.to_lowercase().as_str())
+                    .map(|i| i as u16)
+                    .map_err(|_| BitcoinError::InvalidFormat)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !(12..=24).contains(&indexes.len()) || !indexes.len().is_multiple_of(3) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mnemonic = Mnemonic { indexes };
+        let (entropy, checksum) = mnemonic.split_checksum();
+        let checksum_bits = entropy.len() / 4;
+        if sha256(&entropy)[0] >> (8 - checksum_bits) != checksum {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(mnemonic)
+    }
+}
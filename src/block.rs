@@ -0,0 +1,509 @@
+use crate::constants::{COIN, WITNESS_SCALE_FACTOR};
+use crate::opcodes::OP_CHECKSIG;
+use crate::{
+    BitcoinError, BitcoinTransaction, BlockHash, CompactSize, Network, Script, TransactionInput,
+    TransactionOutput, Txid, hashes, hex_codec,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    pub fn new(
+        version: u32,
+        prev_blockhash: [u8; 32],
+        merkle_root: [u8; 32],
+        time: u32,
+        bits: u32,
+        nonce: u32,
+    ) -> Self {
+        Self {
+            version,
+            prev_blockhash,
+            merkle_root,
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(Self::SIZE);
+        self.encode_to(&mut v);
+        v
+    }
+
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.prev_blockhash);
+        out.extend_from_slice(&self.merkle_root);
+        out.extend_from_slice(&self.time.to_le_bytes());
+        out.extend_from_slice(&self.bits.to_le_bytes());
+        out.extend_from_slice(&self.nonce.to_le_bytes());
+    }
+
+    pub fn block_hash(&self) -> [u8; 32] {
+        hashes::sha256d(&self.to_bytes())
+    }
+
+    /// The block hash as a [`BlockHash`], which displays in RPC order.
+    pub fn hash(&self) -> BlockHash {
+        BlockHash(self.block_hash())
+    }
+
+    /// The target `bits` encodes, as a little-endian 256-bit number, or
+    /// `None` if it is zero, negative or overflows.
+    pub fn target(&self) -> Option<[u8; 32]> {
+        let exponent = (self.bits >> 24) as usize;
+        let mantissa = self.bits & 0x007f_ffff;
+        if self.bits & 0x0080_0000 != 0 && mantissa != 0 {
+            return None;
+        }
+        let mut target = [0u8; 32];
+        if exponent <= 3 {
+            let value = mantissa >> (8 * (3 - exponent));
+            target[..4].copy_from_slice(&value.to_le_bytes());
+        } else {
+            for (i, byte) in mantissa.to_le_bytes()[..3].iter().enumerate() {
+                match target.get_mut(exponent - 3 + i) {
+                    Some(slot) => *slot = *byte,
+                    None if *byte != 0 => return None,
+                    None => {}
+                }
+            }
+        }
+        (target != [0; 32]).then_some(target)
+    }
+
+    /// Hashes expected to meet the target, `2^256 / (target + 1)`, as a
+    /// little-endian 256-bit number; Core sums these into `chainwork`.
+    /// `None` when the target is invalid.
+    pub fn work(&self) -> Option<[u8; 32]> {
+        self.target().map(|target| crate::hash_types::work(&target))
+    }
+
+    /// True if the header's hash meets its own target.
+    pub fn check_pow(&self) -> bool {
+        let Some(target) = self.target() else {
+            return false;
+        };
+        let hash = self.block_hash();
+        // Both are little-endian, so compare from the most significant end.
+        hash.iter().rev().le(target.iter().rev())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < Self::SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut prev_blockhash = [0u8; 32];
+        prev_blockhash.copy_from_slice(&bytes[4..36]);
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[36..68]);
+        Ok((
+            BlockHeader::new(
+                u32_at(0),
+                prev_blockhash,
+                merkle_root,
+                u32_at(68),
+                u32_at(72),
+                u32_at(76),
+            ),
+            Self::SIZE,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn new(header: BlockHeader, transactions: Vec<BitcoinTransaction>) -> Self {
+        Self {
+            header,
+            transactions,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::new();
+        self.encode_to(&mut v);
+        v
+    }
+
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        self.header.encode_to(out);
+        CompactSize::new(self.transactions.len() as u64).encode_to(out);
+        for tx in &self.transactions {
+            tx.encode_to(out);
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = bytes.len()))
+    )]
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        let (header, mut cursor) = BlockHeader::from_bytes(bytes)?;
+        let (tx_count, offset) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += offset;
+        let mut transactions = Vec::new();
+        for _ in 0..tx_count.value {
+            let (tx, used) = BitcoinTransaction::from_bytes(&bytes[cursor..])?;
+            transactions.push(tx);
+            cursor += used;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            size = cursor,
+            tx_count = tx_count.value,
+            elapsed_us = start.elapsed().as_micros() as u64,
+            "decoded block"
+        );
+        Ok((Block::new(header, transactions), cursor))
+    }
+
+    /// Like `from_bytes`, but `bytes` must hold exactly one block.
+    pub fn from_bytes_exact(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        crate::exact(bytes, Self::from_bytes)
+    }
+
+    /// Decodes a hex string holding exactly one block.
+    pub fn from_hex_exact(hex_str: &str) -> Result<Self, BitcoinError> {
+        Self::from_bytes_exact(&hex_codec::decode(hex_str)?)
+    }
+
+    pub fn block_hash(&self) -> [u8; 32] {
+        self.header.block_hash()
+    }
+
+    pub fn hash(&self) -> BlockHash {
+        self.header.hash()
+    }
+
+    /// BIP141 weight: the size without witnesses times three plus the
+    /// full size.
+    pub fn weight(&self) -> usize {
+        let count_len = CompactSize::new(self.transactions.len() as u64)
+            .to_bytes()
+            .len();
+        (BlockHeader::SIZE + count_len) * WITNESS_SCALE_FACTOR
+            + self
+                .transactions
+                .iter()
+                .map(BitcoinTransaction::weight)
+                .sum::<usize>()
+    }
+
+    /// The merkle root of the block's txids.
+    pub fn compute_merkle_root(&self) -> [u8; 32] {
+        let txids: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.txid().0).collect();
+        merkle_root(&txids)
+    }
+
+    /// The merkle branch proving `txid` is in the block, or `None` if it
+    /// isn't.
+    pub fn merkle_branch(&self, txid: &Txid) -> Option<MerkleBranch> {
+        let mut level: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.txid().0).collect();
+        let index = level.iter().position(|hash| *hash == txid.0)?;
+        let mut position = index;
+        let mut hashes = Vec::new();
+        while level.len() > 1 {
+            let sibling = level.get(position ^ 1).unwrap_or(&level[position]);
+            hashes.push(*sibling);
+            level = merkle_level(&level);
+            position /= 2;
+        }
+        Some(MerkleBranch {
+            index: index as u32,
+            hashes,
+        })
+    }
+
+    /// The hard-coded first block of `network`. All networks share the
+    /// same coinbase and differ only in header time, bits and nonce.
+    pub fn genesis(network: Network) -> Self {
+        let mut script_sig = Script::new(Vec::new());
+        script_sig
+            .push_slice(&0x1d00ffffu32.to_le_bytes())
+            .push_slice(&[4])
+            .push_slice(GENESIS_MESSAGE);
+        let mut script_pubkey = Script::new(Vec::new());
+        script_pubkey
+            .push_slice(&GENESIS_OUTPUT_KEY)
+            .push_opcode(OP_CHECKSIG);
+        let coinbase = BitcoinTransaction::new(1, vec![TransactionInput::coinbase(script_sig)], 0)
+            .with_outputs(vec![TransactionOutput::new(50 * COIN, script_pubkey)]);
+
+        let (time, bits, nonce) = match network {
+            Network::Mainnet => (1231006505, 0x1d00ffff, 2083236893),
+            Network::Testnet => (1296688602, 0x1d00ffff, 414098458),
+            Network::Signet => (1598918400, 0x1e0377ae, 52613770),
+            Network::Regtest => (1296688602, 0x207fffff, 2),
+        };
+        // A single transaction's txid is the merkle root.
+        let header = BlockHeader::new(1, [0; 32], coinbase.txid().0, time, bits, nonce);
+        Block::new(header, vec![coinbase])
+    }
+
+    /// Returns `(txid, offset, length)` for every transaction, with offsets
+    /// counted from the start of the serialized block. Lengths come from
+    /// re-serializing, which matches the bytes `Block::from_bytes` read
+    /// since it refuses segwit encodings without a witness.
+    pub fn transaction_offsets(&self) -> Vec<(Txid, usize, usize)> {
+        let mut offset = BlockHeader::SIZE
+            + CompactSize::new(self.transactions.len() as u64)
+                .to_bytes()
+                .len();
+        let mut offsets = Vec::with_capacity(self.transactions.len());
+        for tx in &self.transactions {
+            let len = tx.to_bytes().len();
+            offsets.push((tx.txid(), offset, len));
+            offset += len;
+        }
+        offsets
+    }
+}
+
+/// Bitcoin's merkle root, duplicating the last hash of odd-length levels.
+/// An empty list gives all zeros.
+pub fn merkle_root(hashes: &[[u8; 32]]) -> [u8; 32] {
+    if hashes.is_empty() {
+        return [0; 32];
+    }
+    let mut level = hashes.to_vec();
+    while level.len() > 1 {
+        level = merkle_level(&level);
+    }
+    level[0]
+}
+
+fn merkle_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| {
+            let right = pair.get(1).unwrap_or(&pair[0]);
+            hashes::sha256d(&[pair[0], *right].concat())
+        })
+        .collect()
+}
+
+/// The sibling hashes from a transaction up to the merkle root, and the
+/// transaction's position in the block, whose bits say which side each
+/// sibling is on.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MerkleBranch {
+    pub index: u32,
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl MerkleBranch {
+    /// The root the branch leads to from `txid`.
+    pub fn root(&self, txid: &Txid) -> [u8; 32] {
+        let mut node = txid.0;
+        for (depth, sibling) in self.hashes.iter().enumerate() {
+            let data = if (self.index >> depth) & 1 == 0 {
+                [node, *sibling]
+            } else {
+                [*sibling, node]
+            };
+            node = hashes::sha256d(&data.concat());
+        }
+        node
+    }
+}
+
+/// True if `branch` links `txid` to the merkle `root`.
+pub fn verify_merkle_branch(txid: &Txid, branch: &MerkleBranch, root: &[u8; 32]) -> bool {
+    // Deeper branches can't come from a block; the index must fit too.
+    branch.hashes.len() < 32
+        && (branch.index >> branch.hashes.len()) == 0
+        && branch.root(txid) == *root
+}
+
+const GENESIS_MESSAGE: &[u8] =
+    b"The Times 03/Jan/2009 Chancellor on brink of second bailout for banks";
+
+const GENESIS_OUTPUT_KEY: [u8; 65] = [
+    0x04, 0x67, 0x8a, 0xfd, 0xb0, 0xfe, 0x55, 0x48, 0x27, 0x19, 0x67, 0xf1, 0xa6, 0x71, 0x30, 0xb7,
+    0x10, 0x5c, 0xd6, 0xa8, 0x28, 0xe0, 0x39, 0x09, 0xa6, 0x79, 0x62, 0xe0, 0xea, 0x1f, 0x61, 0xde,
+    0xb6, 0x49, 0xf6, 0xbc, 0x3f, 0x4c, 0xef, 0x38, 0xc4, 0xf3, 0x55, 0x04, 0xe5, 0x1e, 0xc1, 0x12,
+    0xde, 0x5c, 0x38, 0x4d, 0xf7, 0xba, 0x0b, 0x8d, 0x57, 0x8a, 0x4c, 0x70, 0x2b, 0x6b, 0xf1, 0x1d,
+    0x5f,
+];
+
+/// A serialized block borrowed from a larger buffer (for example a memory
+/// mapped block file). Nothing is copied until a transaction is parsed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BlockView<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BlockView<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, BitcoinError> {
+        BlockHeader::from_bytes(bytes)?;
+        CompactSize::from_bytes(&bytes[BlockHeader::SIZE..])?;
+        Ok(Self { bytes })
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader::from_bytes(self.bytes).unwrap().0
+    }
+
+    pub fn transactions(&self) -> TransactionViews<'a> {
+        let (count, consumed) = CompactSize::from_bytes(&self.bytes[BlockHeader::SIZE..]).unwrap();
+        TransactionViews {
+            bytes: self.bytes,
+            cursor: BlockHeader::SIZE + consumed,
+            remaining: count.value,
+        }
+    }
+
+    /// Like `Block::transaction_offsets`, but reads the raw bytes directly.
+    pub fn transaction_offsets(&self) -> Result<Vec<(Txid, usize, usize)>, BitcoinError> {
+        let start = self.bytes.as_ptr() as usize;
+        self.transactions()
+            .map(|tx| {
+                let tx = tx?;
+                let offset = tx.as_bytes().as_ptr() as usize - start;
+                Ok((tx.txid()?, offset, tx.as_bytes().len()))
+            })
+            .collect()
+    }
+
+    pub fn to_block(&self) -> Result<Block, BitcoinError> {
+        Block::from_bytes(self.bytes).map(|(block, _)| block)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TransactionView<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> TransactionView<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn parse(&self) -> Result<BitcoinTransaction, BitcoinError> {
+        BitcoinTransaction::from_bytes(self.bytes).map(|(tx, _)| tx)
+    }
+
+    // Legacy transactions hash their raw bytes; segwit ones (marker byte 0
+    // where the input count would be) have to be re-serialized.
+    pub fn txid(&self) -> Result<Txid, BitcoinError> {
+        if self.bytes[4] == 0x00 {
+            Ok(self.parse()?.txid())
+        } else {
+            Ok(Txid(hashes::sha256d(self.bytes)))
+        }
+    }
+}
+
+pub struct TransactionViews<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+    remaining: u64,
+}
+
+impl<'a> Iterator for TransactionViews<'a> {
+    type Item = Result<TransactionView<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let rest = &self.bytes[self.cursor..];
+        match transaction_len(rest) {
+            Ok(len) => {
+                self.cursor += len;
+                Some(Ok(TransactionView {
+                    bytes: &rest[..len],
+                }))
+            }
+            Err(err) => {
+                self.remaining = 0;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Measures the serialized transaction at the start of `bytes` without
+/// allocating.
+pub fn transaction_len(bytes: &[u8]) -> Result<usize, BitcoinError> {
+    let mut cursor = Skipper { bytes, pos: 0 };
+    cursor.skip(4)?;
+    let mut input_count = cursor.compact_size()?;
+    let segwit = input_count == 0 && bytes.get(cursor.pos).is_some_and(|&b| b != 0);
+    if segwit {
+        cursor.skip(1)?;
+        input_count = cursor.compact_size()?;
+    }
+    for _ in 0..input_count {
+        cursor.skip(36)?;
+        let script_len = cursor.compact_size()?;
+        cursor.skip(script_len)?;
+        cursor.skip(4)?;
+    }
+    for _ in 0..cursor.compact_size()? {
+        cursor.skip(8)?;
+        let script_len = cursor.compact_size()?;
+        cursor.skip(script_len)?;
+    }
+    if segwit {
+        for _ in 0..input_count {
+            for _ in 0..cursor.compact_size()? {
+                let item_len = cursor.compact_size()?;
+                cursor.skip(item_len)?;
+            }
+        }
+    }
+    cursor.skip(4)?;
+    Ok(cursor.pos)
+}
+
+struct Skipper<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Skipper<'_> {
+    fn skip(&mut self, len: u64) -> Result<(), BitcoinError> {
+        let len = usize::try_from(len).map_err(|_| BitcoinError::NumericOverflow)?;
+        if self.bytes.len() - self.pos < len {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        self.pos += len;
+        Ok(())
+    }
+
+    fn compact_size(&mut self) -> Result<u64, BitcoinError> {
+        let (value, consumed) = CompactSize::from_bytes(&self.bytes[self.pos..])?;
+        self.pos += consumed;
+        Ok(value.value)
+    }
+}
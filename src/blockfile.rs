@@ -0,0 +1,317 @@
+//! Reader for Bitcoin Core's `blocks/blk*.dat` files.
+//!
+//! Each record is the network magic, a little-endian `u32` length and the
+//! serialized block. Core pre-allocates files in chunks and may be killed
+//! mid-write, so zero padding and a truncated final record are treated as the
+//! end of the file rather than as errors.
+//!
+//! Since Core 28 the files are XORed with an 8-byte key stored in
+//! `blocks/xor.dat`; `BlockFileReader::open` picks it up automatically.
+
+#[cfg(feature = "rayon")]
+use crate::cancel::CancellationToken;
+use crate::progress::{ProgressObserver, ProgressTracker};
+use crate::{BitcoinError, Block, Network};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+pub use crate::constants::MAX_BLOCK_SERIALIZED_SIZE;
+pub const XOR_KEY_FILE: &str = "xor.dat";
+
+/// De-obfuscates a stream XORed with a repeating 8-byte key, starting at
+/// `offset` bytes into the file.
+pub struct XorReader<R> {
+    inner: R,
+    key: [u8; 8],
+    offset: u64,
+}
+
+impl<R: Read> XorReader<R> {
+    pub fn new(inner: R, key: [u8; 8]) -> Self {
+        Self::with_offset(inner, key, 0)
+    }
+
+    pub fn with_offset(inner: R, key: [u8; 8], offset: u64) -> Self {
+        Self { inner, key, offset }
+    }
+}
+
+impl<R: Read> Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.key != [0u8; 8] {
+            xor_in_place(&mut buf[..n], self.key, self.offset);
+        }
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+pub fn xor_in_place(data: &mut [u8], key: [u8; 8], offset: u64) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= key[((offset + i as u64) % 8) as usize];
+    }
+}
+
+/// Reads the obfuscation key from a `blocks` directory. A missing `xor.dat`
+/// (datadirs created before Core 28) means no obfuscation.
+pub fn read_xor_key<P: AsRef<Path>>(blocks_dir: P) -> Result<[u8; 8], BitcoinError> {
+    match fs::read(blocks_dir.as_ref().join(XOR_KEY_FILE)) {
+        Ok(bytes) => bytes.try_into().map_err(|_| BitcoinError::InvalidFormat),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok([0u8; 8]),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub struct BlockFileReader<R> {
+    reader: R,
+    magic: [u8; 4],
+    done: bool,
+    position: u64,
+    len: Option<u64>,
+    progress: Option<(ProgressTracker, Box<dyn ProgressObserver + Send>)>,
+}
+
+impl BlockFileReader<BufReader<XorReader<File>>> {
+    /// Opens a block file, de-obfuscating it with the `xor.dat` key found
+    /// next to it.
+    pub fn open<P: AsRef<Path>>(path: P, network: Network) -> Result<Self, BitcoinError> {
+        let path = path.as_ref();
+        let key = read_xor_key(path.parent().unwrap_or(Path::new(".")))?;
+        Self::open_with_key(path, network, key)
+    }
+
+    pub fn open_with_key<P: AsRef<Path>>(
+        path: P,
+        network: Network,
+        key: [u8; 8],
+    ) -> Result<Self, BitcoinError> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut reader = Self::new(BufReader::new(XorReader::new(file, key)), network);
+        reader.len = Some(len);
+        Ok(reader)
+    }
+}
+
+impl<R: Read> BlockFileReader<R> {
+    pub fn new(reader: R, network: Network) -> Self {
+        Self {
+            reader,
+            magic: network.magic(),
+            done: false,
+            position: 0,
+            len: None,
+            progress: None,
+        }
+    }
+
+    /// Reports to `observer` after each record. The total is known only
+    /// for readers made by [`open`](BlockFileReader::open) or
+    /// [`open_with_key`](BlockFileReader::open_with_key).
+    pub fn with_progress(mut self, observer: impl ProgressObserver + Send + 'static) -> Self {
+        self.progress = Some((ProgressTracker::new(self.len), Box::new(observer)));
+        self
+    }
+
+    /// Bytes consumed from the underlying reader so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the next serialized block, or `None` once no complete record
+    /// remains. Bytes that do not start with the magic are skipped.
+    pub fn next_raw(&mut self) -> Result<Option<Vec<u8>>, BitcoinError> {
+        let mut counted = CountingReader {
+            inner: &mut self.reader,
+            count: 0,
+        };
+        let record = read_record(&mut counted, self.magic, 80..=MAX_BLOCK_SERIALIZED_SIZE, 0);
+        self.position += counted.count;
+        let record = record?;
+        if record.is_some()
+            && let Some((tracker, observer)) = &mut self.progress
+        {
+            observer.on_progress(&tracker.block_parsed(self.position));
+        }
+        Ok(record.map(|(payload, _)| payload))
+    }
+}
+
+struct CountingReader<'a, R> {
+    inner: &'a mut R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Payload and trailer bytes of one framed record.
+pub(crate) type RawRecord = (Vec<u8>, Vec<u8>);
+
+/// Reads one `magic | len | payload | trailer` record, as shared by the
+/// `blk*.dat` and `rev*.dat` files. Records whose length falls outside
+/// `lengths` are treated as garbage and scanning resumes after their magic.
+pub(crate) fn read_record<R: Read>(
+    reader: &mut R,
+    magic: [u8; 4],
+    lengths: RangeInclusive<u32>,
+    trailer_len: usize,
+) -> Result<Option<RawRecord>, BitcoinError> {
+    loop {
+        let mut window = [0u8; 4];
+        if !read_fully(reader, &mut window)? {
+            return Ok(None);
+        }
+        while window != magic {
+            let mut byte = [0u8; 1];
+            if !read_fully(reader, &mut byte)? {
+                return Ok(None);
+            }
+            window.rotate_left(1);
+            window[3] = byte[0];
+        }
+
+        let mut len = [0u8; 4];
+        if !read_fully(reader, &mut len)? {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes(len);
+        if !lengths.contains(&len) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(len, "skipping record with invalid length");
+            continue;
+        }
+
+        let mut payload = vec![0u8; len as usize];
+        let mut trailer = vec![0u8; trailer_len];
+        if !read_fully(reader, &mut payload)? || !read_fully(reader, &mut trailer)? {
+            return Ok(None);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(len, "read record");
+        return Ok(Some((payload, trailer)));
+    }
+}
+
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, BitcoinError> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+impl<R: Read> Iterator for BlockFileReader<R> {
+    type Item = Result<Block, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_raw() {
+            Ok(Some(raw)) => Some(Block::from_bytes(&raw).map(|(block, _)| block)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Splits an in-memory (already de-obfuscated) block file into the payloads
+/// of its complete records, with the same resync rules as `BlockFileReader`.
+pub fn block_records(data: &[u8], network: Network) -> Vec<&[u8]> {
+    let magic = network.magic();
+    let mut records = Vec::new();
+    let mut cursor = 0;
+    while let Some(found) = data[cursor..].windows(4).position(|w| w == magic) {
+        let start = cursor + found + 8;
+        if start > data.len() {
+            break;
+        }
+        let len = u32::from_le_bytes(data[start - 4..start].try_into().unwrap());
+        if !(80..=MAX_BLOCK_SERIALIZED_SIZE).contains(&len) {
+            cursor += found + 4;
+            continue;
+        }
+        let end = start + len as usize;
+        if end > data.len() {
+            break;
+        }
+        records.push(&data[start..end]);
+        cursor = end;
+    }
+    records
+}
+
+/// Parses whole block files on the rayon thread pool. Files are read and
+/// decoded concurrently, and the blocks inside each file are decoded in
+/// parallel too; the result keeps file order and in-file order.
+#[cfg(feature = "rayon")]
+pub fn parse_blocks_parallel<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    network: Network,
+) -> Result<Vec<Block>, BitcoinError> {
+    parse_blocks_parallel_cancellable(paths, network, &CancellationToken::new())
+}
+
+/// [`parse_blocks_parallel`], checking `cancel` before each file and each
+/// block and failing with [`BitcoinError::Cancelled`] once it is set.
+#[cfg(feature = "rayon")]
+pub fn parse_blocks_parallel_cancellable<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    network: Network,
+    cancel: &CancellationToken,
+) -> Result<Vec<Block>, BitcoinError> {
+    let per_file = paths
+        .par_iter()
+        .map(|path| {
+            cancel.check()?;
+            let path = path.as_ref();
+            let key = read_xor_key(path.parent().unwrap_or(Path::new(".")))?;
+            let mut data = fs::read(path)?;
+            xor_in_place(&mut data, key, 0);
+            block_records(&data, network)
+                .par_iter()
+                .map(|raw| {
+                    cancel.check()?;
+                    Block::from_bytes(raw).map(|(block, _)| block)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, BitcoinError>>()?;
+    Ok(per_file.into_iter().flatten().collect())
+}
+
+/// Lists the `blk?????.dat` files in a Core `blocks` directory in file order.
+pub fn blk_files<P: AsRef<Path>>(blocks_dir: P) -> Result<Vec<PathBuf>, BitcoinError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(blocks_dir)? {
+        let path = entry?.path();
+        let is_blk = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("blk"))
+            .and_then(|rest| rest.strip_suffix(".dat"))
+            .is_some_and(|num| !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()));
+        if is_blk {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
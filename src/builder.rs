@@ -0,0 +1,80 @@
+use crate::fee::{InputType, change_outputs};
+use crate::{
+    BitcoinError, BitcoinTransaction, OutPoint, Script, TransactionInput, TransactionOutput,
+};
+
+pub use crate::constants::MAX_MONEY;
+
+/// Assembles an unsigned transaction, checking on `build` the structural
+/// rules a node would reject it for.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionBuilder {
+    version: u32,
+    lock_time: u32,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self {
+            version: 2,
+            lock_time: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    pub fn add_input(mut self, previous_output: OutPoint, sequence: u32) -> Self {
+        self.inputs.push(TransactionInput::new(
+            previous_output,
+            Script::new(vec![]),
+            sequence,
+        ));
+        self
+    }
+
+    pub fn add_output(mut self, value: u64, script_pubkey: Script) -> Self {
+        self.outputs
+            .push(TransactionOutput::new(value, script_pubkey));
+        self
+    }
+
+    /// Adds change for what `input_value` leaves after the outputs so far
+    /// and the fee at `fee_rate` sat/kvB, given how the inputs will be
+    /// spent. See [`change_outputs`] for how it's split or dropped.
+    pub fn add_change(
+        mut self,
+        input_value: u64,
+        inputs: &[InputType],
+        scripts: &[Script],
+        fee_rate: u64,
+    ) -> Self {
+        let change = change_outputs(input_value, inputs, &self.outputs, scripts, fee_rate);
+        self.outputs.extend(change);
+        self
+    }
+
+    pub fn build(self) -> Result<BitcoinTransaction, BitcoinError> {
+        let tx = BitcoinTransaction::new(self.version, self.inputs, self.lock_time)
+            .with_outputs(self.outputs);
+        tx.check_transaction()?;
+        Ok(tx)
+    }
+}
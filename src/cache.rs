@@ -0,0 +1,125 @@
+//! A least-recently-used cache of decoded transactions by txid, for code
+//! that sees the same transactions repeatedly, such as compact block
+//! reconstruction or a mempool, and would otherwise decode and hash them
+//! again each time.
+
+use crate::{BitcoinTransaction, Txid, Wtxid};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// A decoded transaction with the values worth not recomputing.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CachedTx {
+    pub tx: BitcoinTransaction,
+    pub txid: Txid,
+    pub wtxid: Wtxid,
+    pub weight: usize,
+}
+
+impl CachedTx {
+    pub fn new(tx: BitcoinTransaction) -> Self {
+        Self {
+            txid: tx.txid(),
+            wtxid: tx.wtxid(),
+            weight: tx.weight(),
+            tx,
+        }
+    }
+}
+
+/// Entries are shared behind an [`Arc`], so handing one out doesn't copy
+/// the transaction and it stays valid after eviction.
+#[derive(Debug)]
+pub struct TxCache {
+    capacity: usize,
+    entries: HashMap<Txid, (Arc<CachedTx>, u64)>,
+    // Last-use tick to txid; the first entry is evicted next.
+    recency: BTreeMap<u64, Txid>,
+    tick: u64,
+}
+
+impl TxCache {
+    /// A cache holding at most `capacity` transactions; zero disables it.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, txid: &Txid) -> bool {
+        self.entries.contains_key(txid)
+    }
+
+    /// The cached entry for `txid`, marking it most recently used.
+    pub fn get(&mut self, txid: &Txid) -> Option<Arc<CachedTx>> {
+        let tick = self.next_tick();
+        let (entry, last_used) = self.entries.get_mut(txid)?;
+        self.recency.remove(last_used);
+        self.recency.insert(tick, txid.clone());
+        *last_used = tick;
+        Some(entry.clone())
+    }
+
+    /// Caches `tx`, evicting the least recently used entry if full.
+    pub fn insert(&mut self, tx: BitcoinTransaction) -> Arc<CachedTx> {
+        let entry = Arc::new(CachedTx::new(tx));
+        if self.capacity == 0 {
+            return entry;
+        }
+        self.remove(&entry.txid);
+        if self.entries.len() == self.capacity
+            && let Some((_, oldest)) = self.recency.pop_first()
+        {
+            self.entries.remove(&oldest);
+        }
+        let tick = self.next_tick();
+        self.recency.insert(tick, entry.txid.clone());
+        self.entries
+            .insert(entry.txid.clone(), (entry.clone(), tick));
+        entry
+    }
+
+    /// The entry for `txid`, decoding and caching the transaction with
+    /// `decode` on a miss.
+    pub fn get_or_insert_with<E>(
+        &mut self,
+        txid: &Txid,
+        decode: impl FnOnce() -> Result<BitcoinTransaction, E>,
+    ) -> Result<Arc<CachedTx>, E> {
+        match self.get(txid) {
+            Some(entry) => Ok(entry),
+            None => Ok(self.insert(decode()?)),
+        }
+    }
+
+    pub fn remove(&mut self, txid: &Txid) -> Option<Arc<CachedTx>> {
+        let (entry, last_used) = self.entries.remove(txid)?;
+        self.recency.remove(&last_used);
+        Some(entry)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
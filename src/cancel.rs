@@ -0,0 +1,47 @@
+//! Cooperative cancellation for long scans.
+//!
+//! A [`CancellationToken`] is shared between the code running a scan and
+//! whoever may want to stop it. The scan checks it between blocks and stops
+//! with [`BitcoinError::Cancelled`], so a service can shut down mid-scan
+//! without killing threads.
+
+use crate::BitcoinError;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Clones share one flag; cancelling any of them cancels them all.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [`BitcoinError::Cancelled`] once cancelled.
+    pub fn check(&self) -> Result<(), BitcoinError> {
+        if self.is_cancelled() {
+            return Err(BitcoinError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Tokens are equal when they share a flag.
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+impl Eq for CancellationToken {}
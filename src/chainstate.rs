@@ -0,0 +1,81 @@
+//! Entries of Core's `chainstate` LevelDB database, the UTXO set.
+//!
+//! Reading the database itself needs a LevelDB library; this decodes the
+//! raw keys and values it yields. Coins are stored under `'C'`, the txid
+//! and the output index as a [`VarInt`], with values XORed by the key
+//! stored under [`OBFUSCATE_KEY_KEY`].
+
+use crate::blockfile::xor_in_place;
+use crate::undo::Coin;
+use crate::{BitcoinError, CompactSize, OutPoint, VarInt};
+
+pub const DB_COIN: u8 = b'C';
+pub const DB_BEST_BLOCK: u8 = b'B';
+/// The key of the obfuscation key, a length-prefixed `"\0obfuscate_key"`.
+pub const OBFUSCATE_KEY_KEY: &[u8; 15] = b"\x0e\x00obfuscate_key";
+
+/// The key coins of `outpoint` are stored under.
+pub fn coin_key(outpoint: &OutPoint) -> Vec<u8> {
+    let mut key = vec![DB_COIN];
+    key.extend_from_slice(&outpoint.txid.0);
+    key.extend(VarInt::new(outpoint.vout as u64).to_bytes());
+    key
+}
+
+/// The outpoint of a coin key; fails on keys of other records.
+pub fn decode_coin_key(key: &[u8]) -> Result<OutPoint, BitcoinError> {
+    let [DB_COIN, rest @ ..] = key else {
+        return Err(BitcoinError::InvalidFormat);
+    };
+    let txid: [u8; 32] = rest
+        .get(..32)
+        .ok_or(BitcoinError::InsufficientBytes)?
+        .try_into()
+        .unwrap();
+    let (vout, used) = VarInt::from_bytes(&rest[32..])?;
+    if 32 + used != rest.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let vout = u32::try_from(vout.value).map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok(OutPoint::new(txid, vout))
+}
+
+/// Parses the value stored under [`OBFUSCATE_KEY_KEY`], which is itself
+/// never obfuscated.
+pub fn decode_obfuscation_key(value: &[u8]) -> Result<[u8; 8], BitcoinError> {
+    let (len, used) = CompactSize::from_bytes(value)?;
+    if len.value != 8 || value.len() != used + 8 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(value[used..].try_into().unwrap())
+}
+
+/// XORs a value with the obfuscation key, from its first byte.
+pub fn deobfuscate(value: &[u8], key: [u8; 8]) -> Vec<u8> {
+    let mut value = value.to_vec();
+    xor_in_place(&mut value, key, 0);
+    value
+}
+
+/// Decodes one coin record from its raw key and (still obfuscated) value.
+pub fn decode_coin(
+    key: &[u8],
+    value: &[u8],
+    obfuscation: [u8; 8],
+) -> Result<(OutPoint, Coin), BitcoinError> {
+    let outpoint = decode_coin_key(key)?;
+    let value = deobfuscate(value, obfuscation);
+    let (coin, used) = Coin::from_bytes(&value)?;
+    if used != value.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok((outpoint, coin))
+}
+
+/// The hash of the block the coins are current as of, stored under
+/// [`DB_BEST_BLOCK`].
+pub fn decode_best_block(value: &[u8], obfuscation: [u8; 8]) -> Result<[u8; 32], BitcoinError> {
+    deobfuscate(value, obfuscation)
+        .try_into()
+        .map_err(|_| BitcoinError::InvalidFormat)
+}
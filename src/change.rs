@@ -0,0 +1,113 @@
+//! Change output heuristics for chain analysis research.
+//!
+//! Each heuristic that points at an output adds evidence, combined as if
+//! independent: `1 - Π(1 - weight)`. The weights are rough priors, not
+//! measured rates, and wallets that randomize amounts and script types
+//! defeat most of these.
+
+use crate::rescan::ScriptSet;
+use crate::{BitcoinTransaction, ScriptType, TransactionOutput};
+
+/// Payments in whole multiples of this many satoshis (0.0001 BTC) look
+/// chosen by a person.
+pub const ROUND_AMOUNT: u64 = 10_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Heuristic {
+    /// Pays a script known to belong to the sending wallet.
+    KnownScript,
+    /// Pays back to a script one of the inputs spent.
+    AddressReuse,
+    /// The only output whose script type matches every input's.
+    ScriptTypeMatch,
+    /// Every other output is a round amount and this one isn't.
+    RoundPayment,
+}
+
+impl Heuristic {
+    pub fn weight(self) -> f64 {
+        match self {
+            Heuristic::KnownScript => 0.9,
+            Heuristic::AddressReuse => 0.8,
+            Heuristic::RoundPayment => 0.4,
+            Heuristic::ScriptTypeMatch => 0.3,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChangeCandidate {
+    pub vout: u32,
+    /// Between 0 and 1.
+    pub confidence: f64,
+    pub heuristics: Vec<Heuristic>,
+}
+
+/// Scores the outputs of `tx` as possible change, most likely first.
+/// `spent` are the outputs its inputs spend, in input order, and `wallet`
+/// the sender's known scripts. Outputs no heuristic points at are left
+/// out, as are all outputs of transactions with fewer than two payments.
+pub fn guess_change(
+    tx: &BitcoinTransaction,
+    spent: &[TransactionOutput],
+    wallet: &ScriptSet,
+) -> Vec<ChangeCandidate> {
+    let payments: Vec<(u32, &TransactionOutput)> = (0..)
+        .zip(&tx.outputs)
+        .filter(|(_, output)| output.script_pubkey.script_type() != ScriptType::NullData)
+        .collect();
+    if payments.len() < 2 {
+        return Vec::new();
+    }
+    let input_type = spent
+        .first()
+        .map(|output| output.script_pubkey.script_type())
+        .filter(|first| {
+            spent
+                .iter()
+                .all(|output| output.script_pubkey.script_type() == *first)
+        });
+    let type_matches: Vec<u32> = payments
+        .iter()
+        .filter(|(_, output)| Some(output.script_pubkey.script_type()) == input_type)
+        .map(|(vout, _)| *vout)
+        .collect();
+    let round = |output: &TransactionOutput| output.value.is_multiple_of(ROUND_AMOUNT);
+
+    let mut candidates: Vec<ChangeCandidate> = payments
+        .iter()
+        .filter_map(|&(vout, output)| {
+            let mut heuristics = Vec::new();
+            if wallet.contains_script(&output.script_pubkey) {
+                heuristics.push(Heuristic::KnownScript);
+            }
+            if spent
+                .iter()
+                .any(|prevout| prevout.script_pubkey == output.script_pubkey)
+            {
+                heuristics.push(Heuristic::AddressReuse);
+            }
+            if type_matches == [vout] {
+                heuristics.push(Heuristic::ScriptTypeMatch);
+            }
+            if !round(output)
+                && payments
+                    .iter()
+                    .all(|(other, payment)| *other == vout || round(payment))
+            {
+                heuristics.push(Heuristic::RoundPayment);
+            }
+            if heuristics.is_empty() {
+                return None;
+            }
+            let doubt: f64 = heuristics.iter().map(|h| 1.0 - h.weight()).product();
+            Some(ChangeCandidate {
+                vout,
+                confidence: 1.0 - doubt,
+                heuristics,
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
@@ -0,0 +1,212 @@
+//! Arrow record batches of parsed chain data, and Parquet files from them,
+//! for going from blk files to DataFrames.
+//!
+//! Rows go into three tables joined on `txid`: one per transaction, one per
+//! input and one per output. Txids and block hashes are hex in RPC byte
+//! order, as explorers show them.
+
+use crate::core_json::reversed_hex;
+use crate::{BitcoinTransaction, Block};
+use arrow_array::builder::{BinaryBuilder, StringBuilder, UInt32Builder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The transaction, input and output tables for a set of transactions.
+#[derive(Debug, Clone)]
+pub struct ChainBatches {
+    /// `block_hash` (null outside a block), `txid`, `version`, `lock_time`,
+    /// `size`, `vsize`, `weight`, `input_count`, `output_count` and
+    /// `output_value` in satoshis.
+    pub transactions: RecordBatch,
+    /// `txid`, `vin`, `prev_txid`, `prev_vout`, `sequence`, `script_sig`
+    /// and `witness_items`.
+    pub inputs: RecordBatch,
+    /// `txid`, `vout`, `value` in satoshis, `script_type` and
+    /// `script_pubkey`.
+    pub outputs: RecordBatch,
+}
+
+impl ChainBatches {
+    /// Writes `transactions.parquet`, `inputs.parquet` and
+    /// `outputs.parquet` into `dir`.
+    pub fn write_parquet_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), ParquetError> {
+        let dir = dir.as_ref();
+        for (name, batch) in [
+            ("transactions", &self.transactions),
+            ("inputs", &self.inputs),
+            ("outputs", &self.outputs),
+        ] {
+            let file = File::create(dir.join(format!("{name}.parquet")))
+                .map_err(|err| ParquetError::External(Box::new(err)))?;
+            write_parquet(batch, file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `batch` as a Parquet file.
+pub fn write_parquet<W: Write + Send>(batch: &RecordBatch, writer: W) -> Result<(), ParquetError> {
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Builders {
+    tx_block_hash: StringBuilder,
+    tx_txid: StringBuilder,
+    tx_version: UInt32Builder,
+    tx_lock_time: UInt32Builder,
+    tx_size: UInt64Builder,
+    tx_vsize: UInt64Builder,
+    tx_weight: UInt64Builder,
+    tx_input_count: UInt32Builder,
+    tx_output_count: UInt32Builder,
+    tx_output_value: UInt64Builder,
+    in_txid: StringBuilder,
+    in_vin: UInt32Builder,
+    in_prev_txid: StringBuilder,
+    in_prev_vout: UInt32Builder,
+    in_sequence: UInt32Builder,
+    in_script_sig: BinaryBuilder,
+    in_witness_items: UInt32Builder,
+    out_txid: StringBuilder,
+    out_vout: UInt32Builder,
+    out_value: UInt64Builder,
+    out_script_type: StringBuilder,
+    out_script_pubkey: BinaryBuilder,
+}
+
+impl Builders {
+    fn push(&mut self, block_hash: Option<&str>, tx: &BitcoinTransaction) {
+        let txid = reversed_hex(&tx.txid().0);
+        self.tx_block_hash.append_option(block_hash);
+        self.tx_txid.append_value(&txid);
+        self.tx_version.append_value(tx.version);
+        self.tx_lock_time.append_value(tx.lock_time);
+        self.tx_size.append_value(tx.to_bytes().len() as u64);
+        self.tx_vsize.append_value(tx.vsize() as u64);
+        self.tx_weight.append_value(tx.weight() as u64);
+        self.tx_input_count.append_value(tx.inputs.len() as u32);
+        self.tx_output_count.append_value(tx.outputs.len() as u32);
+        self.tx_output_value
+            .append_value(tx.outputs.iter().map(|output| output.value).sum());
+        for (vin, input) in tx.inputs.iter().enumerate() {
+            self.in_txid.append_value(&txid);
+            self.in_vin.append_value(vin as u32);
+            self.in_prev_txid
+                .append_value(reversed_hex(&input.previous_output.txid.0));
+            self.in_prev_vout.append_value(input.previous_output.vout);
+            self.in_sequence.append_value(input.sequence);
+            self.in_script_sig.append_value(&input.script_sig[..]);
+            self.in_witness_items
+                .append_value(input.witness.items.len() as u32);
+        }
+        for (vout, output) in tx.outputs.iter().enumerate() {
+            self.out_txid.append_value(&txid);
+            self.out_vout.append_value(vout as u32);
+            self.out_value.append_value(output.value);
+            self.out_script_type
+                .append_value(output.script_pubkey.script_type().as_str());
+            self.out_script_pubkey
+                .append_value(&output.script_pubkey[..]);
+        }
+    }
+
+    fn finish(mut self) -> ChainBatches {
+        let transactions = batch(vec![
+            ("block_hash", Arc::new(self.tx_block_hash.finish()), true),
+            ("txid", Arc::new(self.tx_txid.finish()), false),
+            ("version", Arc::new(self.tx_version.finish()), false),
+            ("lock_time", Arc::new(self.tx_lock_time.finish()), false),
+            ("size", Arc::new(self.tx_size.finish()), false),
+            ("vsize", Arc::new(self.tx_vsize.finish()), false),
+            ("weight", Arc::new(self.tx_weight.finish()), false),
+            ("input_count", Arc::new(self.tx_input_count.finish()), false),
+            (
+                "output_count",
+                Arc::new(self.tx_output_count.finish()),
+                false,
+            ),
+            (
+                "output_value",
+                Arc::new(self.tx_output_value.finish()),
+                false,
+            ),
+        ]);
+        let inputs = batch(vec![
+            ("txid", Arc::new(self.in_txid.finish()), false),
+            ("vin", Arc::new(self.in_vin.finish()), false),
+            ("prev_txid", Arc::new(self.in_prev_txid.finish()), false),
+            ("prev_vout", Arc::new(self.in_prev_vout.finish()), false),
+            ("sequence", Arc::new(self.in_sequence.finish()), false),
+            ("script_sig", Arc::new(self.in_script_sig.finish()), false),
+            (
+                "witness_items",
+                Arc::new(self.in_witness_items.finish()),
+                false,
+            ),
+        ]);
+        let outputs = batch(vec![
+            ("txid", Arc::new(self.out_txid.finish()), false),
+            ("vout", Arc::new(self.out_vout.finish()), false),
+            ("value", Arc::new(self.out_value.finish()), false),
+            (
+                "script_type",
+                Arc::new(self.out_script_type.finish()),
+                false,
+            ),
+            (
+                "script_pubkey",
+                Arc::new(self.out_script_pubkey.finish()),
+                false,
+            ),
+        ]);
+        ChainBatches {
+            transactions,
+            inputs,
+            outputs,
+        }
+    }
+}
+
+fn batch(columns: Vec<(&str, ArrayRef, bool)>) -> RecordBatch {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, array, nullable)| Field::new(*name, array.data_type().clone(), *nullable))
+        .collect();
+    let arrays = columns.into_iter().map(|(_, array, _)| array).collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .expect("columns are built row by row to the same length")
+}
+
+/// Tables for `blocks`, with each transaction tagged with its block's hash.
+pub fn blocks_to_batches<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> ChainBatches {
+    let mut builders = Builders::default();
+    for block in blocks {
+        let hash = reversed_hex(&block.block_hash());
+        for tx in &block.transactions {
+            builders.push(Some(&hash), tx);
+        }
+    }
+    builders.finish()
+}
+
+/// Tables for loose transactions, such as a mempool dump; `block_hash` is
+/// null.
+pub fn transactions_to_batches<'a>(
+    transactions: impl IntoIterator<Item = &'a BitcoinTransaction>,
+) -> ChainBatches {
+    let mut builders = Builders::default();
+    for tx in transactions {
+        builders.push(None, tx);
+    }
+    builders.finish()
+}
@@ -0,0 +1,145 @@
+//! Core's compact amount and script encodings for coins in the chainstate
+//! database, undo files and UTXO snapshots.
+
+use crate::constants::MAX_SCRIPT_SIZE;
+use crate::{BitcoinError, Script, VarInt};
+
+/// Number of special script types; other scripts store their size plus
+/// this.
+const SPECIAL_SCRIPTS: u64 = 6;
+
+/// Core's `CompressAmount`: strips trailing decimal zeros into an exponent,
+/// so round amounts encode as small numbers (1 BTC is 9).
+pub fn compress_amount(amount: u64) -> u64 {
+    if amount == 0 {
+        return 0;
+    }
+    let mut n = amount;
+    let mut e = 0;
+    while n.is_multiple_of(10) && e < 9 {
+        n /= 10;
+        e += 1;
+    }
+    if e < 9 {
+        let d = n % 10;
+        n /= 10;
+        1 + (n * 9 + d - 1) * 10 + e
+    } else {
+        1 + (n - 1) * 10 + 9
+    }
+}
+
+/// Inverse of [`compress_amount`]. Values no encoder produces wrap, as in
+/// Core, rather than overflowing.
+pub fn decompress_amount(x: u64) -> u64 {
+    if x == 0 {
+        return 0;
+    }
+    let mut x = x - 1;
+    let mut e = x % 10;
+    x /= 10;
+    let mut n = if e < 9 {
+        let d = (x % 9) + 1;
+        x /= 9;
+        x.wrapping_mul(10).wrapping_add(d)
+    } else {
+        x + 1
+    };
+    while e > 0 {
+        n = n.wrapping_mul(10);
+        e -= 1;
+    }
+    n
+}
+
+/// Encodes P2PKH, P2SH and P2PK scripts as a type byte and their 20- or
+/// 32-byte payload, and anything else as a `VarInt` size and the raw
+/// script. Uncompressed P2PK keys need the `secp` feature to check the key
+/// is on the curve, and fail with `Unsupported` without it.
+pub fn compress_script(script: &Script) -> Result<Vec<u8>, BitcoinError> {
+    let b = &script.bytes[..];
+    let special = match b {
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if b.len() == 25 => {
+            Some([&[0x00], hash].concat())
+        }
+        [0xa9, 0x14, hash @ .., 0x87] if b.len() == 23 => Some([&[0x01], hash].concat()),
+        [0x21, key @ .., 0xac] if b.len() == 35 && matches!(key[0], 0x02 | 0x03) => {
+            Some(key.to_vec())
+        }
+        [0x41, key @ .., 0xac] if b.len() == 67 && key[0] == 0x04 => {
+            if is_valid_pubkey(key)? {
+                Some([&[0x04 | (key[64] & 1)], &key[1..33]].concat())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    Ok(special.unwrap_or_else(|| {
+        let mut v = VarInt::new(b.len() as u64 + SPECIAL_SCRIPTS).to_bytes();
+        v.extend_from_slice(b);
+        v
+    }))
+}
+
+/// Inverse of [`compress_script`]. Expanding an uncompressed P2PK key
+/// needs the `secp` feature. Sizes over `MAX_SCRIPT_SIZE` are
+/// `InvalidFormat`.
+pub fn decompress_script(bytes: &[u8]) -> Result<(Script, usize), BitcoinError> {
+    let (size, consumed) = VarInt::from_bytes(bytes)?;
+    let size = size.value;
+    let data_len = match size {
+        0 | 1 => 20,
+        2..=5 => 32,
+        // Core never writes a script over the consensus limit.
+        n if n - SPECIAL_SCRIPTS > MAX_SCRIPT_SIZE as u64 => {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        n => (n - SPECIAL_SCRIPTS) as usize,
+    };
+    let end = consumed
+        .checked_add(data_len)
+        .ok_or(BitcoinError::InvalidFormat)?;
+    let data = bytes
+        .get(consumed..end)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    let script = match size {
+        0 => [&[0x76, 0xa9, 0x14], data, &[0x88, 0xac]].concat(),
+        1 => [&[0xa9, 0x14], data, &[0x87]].concat(),
+        2 | 3 => [&[0x21, size as u8], data, &[0xac]].concat(),
+        4 | 5 => [
+            &[0x41],
+            &decompress_pubkey(size as u8 - 2, data)?[..],
+            &[0xac],
+        ]
+        .concat(),
+        _ => data.to_vec(),
+    };
+    Ok((Script::new(script), end))
+}
+
+#[cfg(feature = "secp")]
+fn is_valid_pubkey(key: &[u8]) -> Result<bool, BitcoinError> {
+    Ok(secp256k1::PublicKey::from_slice(key).is_ok())
+}
+
+#[cfg(not(feature = "secp"))]
+fn is_valid_pubkey(_key: &[u8]) -> Result<bool, BitcoinError> {
+    Err(BitcoinError::Unsupported)
+}
+
+#[cfg(feature = "secp")]
+fn decompress_pubkey(parity: u8, x: &[u8]) -> Result<[u8; 65], BitcoinError> {
+    let mut compressed = [0u8; 33];
+    compressed[0] = parity;
+    compressed[1..].copy_from_slice(x);
+    secp256k1::PublicKey::from_slice(&compressed)
+        .map(|key| key.serialize_uncompressed())
+        .map_err(|_| BitcoinError::InvalidFormat)
+}
+
+// Recovering the y coordinate of an uncompressed P2PK key needs curve math.
+#[cfg(not(feature = "secp"))]
+fn decompress_pubkey(_parity: u8, _x: &[u8]) -> Result<[u8; 65], BitcoinError> {
+    Err(BitcoinError::Unsupported)
+}
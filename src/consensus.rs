@@ -0,0 +1,181 @@
+//! Context-free consensus checks, following Core's `CheckTransaction`.
+
+use crate::opcodes::{
+    OP_1, OP_16, OP_CHECKMULTISIG, OP_CHECKMULTISIGVERIFY, OP_CHECKSIG, OP_CHECKSIGVERIFY,
+};
+use crate::script::{Instruction, MAX_MULTISIG_KEYS};
+use crate::{
+    BitcoinError, BitcoinTransaction, Block, CompactSize, OutPoint, Script, TransactionOutput,
+};
+use std::collections::HashSet;
+
+pub use crate::constants::{
+    MAX_BLOCK_SIGOPS_COST, MAX_BLOCK_WEIGHT, MAX_MONEY, MAX_SCRIPT_SIZE, WITNESS_SCALE_FACTOR,
+};
+
+impl Script {
+    /// Core's `GetSigOpCount`. Without `accurate`, every multisig counts as
+    /// the 20-key maximum; with it, a preceding `OP_1`..`OP_16` gives the
+    /// count. Counting stops at the first malformed push.
+    pub fn sigop_count(&self, accurate: bool) -> usize {
+        let mut count = 0;
+        let mut last = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else { break };
+            match instruction {
+                Instruction::Op(OP_CHECKSIG | OP_CHECKSIGVERIFY) => count += 1,
+                Instruction::Op(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => {
+                    count += match last {
+                        Some(Instruction::Op(n @ OP_1..=OP_16)) if accurate => {
+                            (n - OP_1 + 1) as usize
+                        }
+                        _ => MAX_MULTISIG_KEYS as usize,
+                    }
+                }
+                _ => {}
+            }
+            last = Some(instruction);
+        }
+        count
+    }
+}
+
+impl BitcoinTransaction {
+    /// Checks the rules that need no chain context, failing with Core's
+    /// reject reason for the first one broken.
+    pub fn check_transaction(&self) -> Result<(), BitcoinError> {
+        let reject = |reason| Err(BitcoinError::InvalidTransaction(reason));
+        if self.inputs.is_empty() {
+            return reject("bad-txns-vin-empty");
+        }
+        if self.outputs.is_empty() {
+            return reject("bad-txns-vout-empty");
+        }
+        if self.to_bytes_without_witness().len() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT {
+            return reject("bad-txns-oversize");
+        }
+
+        let mut total: u64 = 0;
+        for output in &self.outputs {
+            if output.value > MAX_MONEY {
+                return reject("bad-txns-vout-toolarge");
+            }
+            total += output.value;
+            if total > MAX_MONEY {
+                return reject("bad-txns-txouttotal-toolarge");
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for input in &self.inputs {
+            let outpoint = &input.previous_output;
+            if !seen.insert((outpoint.txid.0, outpoint.vout)) {
+                return reject("bad-txns-inputs-duplicate");
+            }
+        }
+
+        if self.is_coinbase() {
+            if !(2..=100).contains(&self.inputs[0].script_sig.len()) {
+                return reject("bad-cb-length");
+            }
+        } else if self
+            .inputs
+            .iter()
+            .any(|input| input.previous_output.is_null())
+        {
+            return reject("bad-txns-prevout-null");
+        }
+        Ok(())
+    }
+
+    /// Sigops in the scriptSigs and output scripts, counted without
+    /// looking at spent outputs.
+    pub fn legacy_sigop_count(&self) -> usize {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| input.script_sig.sigop_count(false));
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| output.script_pubkey.sigop_count(false));
+        inputs.chain(outputs).sum()
+    }
+
+    /// Input value minus output value. `prevout` looks up the output each
+    /// input spends; the first one it can't find is returned as
+    /// `MissingPrevout`.
+    pub fn fee<F>(&self, prevout: F) -> Result<u64, BitcoinError>
+    where
+        F: Fn(&OutPoint) -> Option<TransactionOutput>,
+    {
+        let mut input_value: u64 = 0;
+        for input in &self.inputs {
+            let outpoint = &input.previous_output;
+            let spent =
+                prevout(outpoint).ok_or_else(|| BitcoinError::MissingPrevout(outpoint.clone()))?;
+            input_value = input_value
+                .checked_add(spent.value)
+                .filter(|&total| spent.value <= MAX_MONEY && total <= MAX_MONEY)
+                .ok_or(BitcoinError::InvalidTransaction(
+                    "bad-txns-inputvalues-outofrange",
+                ))?;
+        }
+        let output_value = self
+            .outputs
+            .iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.value))
+            .filter(|&total| total <= MAX_MONEY)
+            .ok_or(BitcoinError::InvalidTransaction(
+                "bad-txns-txouttotal-toolarge",
+            ))?;
+        input_value
+            .checked_sub(output_value)
+            .ok_or(BitcoinError::InvalidTransaction("bad-txns-in-belowout"))
+    }
+}
+
+impl Block {
+    /// The size, coinbase and sigop limits from Core's `CheckBlock`, plus
+    /// the BIP141 weight limit. Sigops are the legacy count scaled to cost;
+    /// P2SH and witness sigops need the spent outputs and aren't counted.
+    pub fn check_block_limits(&self) -> Result<(), BitcoinError> {
+        let reject = |reason| Err(BitcoinError::InvalidTransaction(reason));
+        let stripped_len = self.header.to_bytes().len()
+            + CompactSize::new(self.transactions.len() as u64)
+                .to_bytes()
+                .len()
+            + self
+                .transactions
+                .iter()
+                .map(|tx| tx.to_bytes_without_witness().len())
+                .sum::<usize>();
+        if self.transactions.is_empty()
+            || self.transactions.len() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
+            || stripped_len * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
+        {
+            return reject("bad-blk-length");
+        }
+        if !self.transactions[0].is_coinbase() {
+            return reject("bad-cb-missing");
+        }
+        if self.transactions[1..]
+            .iter()
+            .any(BitcoinTransaction::is_coinbase)
+        {
+            return reject("bad-cb-multiple");
+        }
+        let sigops: usize = self
+            .transactions
+            .iter()
+            .map(BitcoinTransaction::legacy_sigop_count)
+            .sum();
+        if sigops * WITNESS_SCALE_FACTOR > MAX_BLOCK_SIGOPS_COST {
+            return reject("bad-blk-sigops");
+        }
+        if self.weight() > MAX_BLOCK_WEIGHT {
+            return reject("bad-blk-weight");
+        }
+        Ok(())
+    }
+}
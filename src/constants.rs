@@ -0,0 +1,37 @@
+//! Consensus values the rest of the crate is written against, gathered so
+//! callers can use the same numbers. The modules that first defined them
+//! (`consensus`, `locktime`, `blockfile`) re-export them under their old
+//! paths.
+
+/// Satoshis per bitcoin.
+pub const COIN: u64 = 100_000_000;
+pub const MAX_MONEY: u64 = 21_000_000 * COIN;
+
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+/// Largest block record in a `blk*.dat` file, witnesses included.
+pub const MAX_BLOCK_SERIALIZED_SIZE: u32 = 4_000_000;
+pub const MAX_BLOCK_SIGOPS_COST: usize = 80_000;
+/// Weight units per byte of non-witness data; also the divisor turning
+/// weight into virtual size.
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Largest serialized object Core will read, bounding any length prefix.
+pub const MAX_SIZE: u32 = 0x02000000;
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+/// Largest push a script may execute.
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+/// Blocks before a coinbase output may be spent.
+pub const COINBASE_MATURITY: u32 = 100;
+
+/// Lock times below this are block heights, above it UNIX times.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+pub const SEQUENCE_FINAL: u32 = 0xFFFFFFFF;
+/// The highest sequence signaling BIP125 replaceability.
+pub const MAX_BIP125_RBF_SEQUENCE: u32 = 0xFFFFFFFD;
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+/// Time-based relative locks count in units of 512 seconds.
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
@@ -0,0 +1,183 @@
+//! JSON in the shape of Bitcoin Core's `decoderawtransaction` and
+//! `getblock <hash> 2` RPC results.
+//!
+//! Amounts are `f64` BTC values in the returned [`Value`]s. [`to_string`]
+//! and [`to_string_pretty`] print them with exactly eight decimals, as Core
+//! does; serde_json's own output would give `1e-8` and `21000000.0`.
+
+use crate::constants::COIN;
+use crate::{
+    BitcoinError, BitcoinTransaction, Block, TransactionInput, TransactionOutput, hex_codec,
+};
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter, Serializer};
+use serde_json::{Value, json};
+use std::io;
+
+/// Hex in RPC byte order (reversed), as Core prints txids and block hashes.
+pub fn reversed_hex(hash: &[u8; 32]) -> String {
+    hex_codec::encode_reversed(hash)
+}
+
+/// Parses a 32-byte hash given in RPC byte order back to internal order.
+pub fn hash_from_reversed_hex(hex_str: &str) -> Result<[u8; 32], BitcoinError> {
+    let mut bytes = [0u8; 32];
+    hex_codec::decode_to_slice(hex_str, &mut bytes)?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Any amount up to `MAX_MONEY` survives the trip through `f64` to eight
+/// decimals.
+pub fn satoshis_to_btc(value: u64) -> Value {
+    json!(value as f64 / COIN as f64)
+}
+
+/// `value` as compact JSON, amounts with eight decimals.
+pub fn to_string(value: &Value) -> String {
+    write_json(value, CompactFormatter)
+}
+
+/// `value` as indented JSON, amounts with eight decimals.
+pub fn to_string_pretty(value: &Value) -> String {
+    write_json(value, PrettyFormatter::new())
+}
+
+fn write_json<F: Formatter>(value: &Value, formatter: F) -> String {
+    let mut out = Vec::new();
+    let mut serializer = Serializer::with_formatter(&mut out, AmountFormatter(formatter));
+    serde::Serialize::serialize(value, &mut serializer).expect("a Value always serializes");
+    String::from_utf8(out).expect("serde_json writes UTF-8")
+}
+
+/// Writes every float as a fixed-point BTC amount, and hands the layout
+/// to the wrapped formatter. The only floats in this module's JSON are
+/// amounts.
+struct AmountFormatter<F>(F);
+
+impl<F: Formatter> Formatter for AmountFormatter<F> {
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        write!(writer, "{:.8}", value)
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.0.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.0.begin_object_key(writer, first)
+    }
+
+    fn end_object_key<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.end_object_key(writer)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.0.end_object_value(writer)
+    }
+}
+
+pub fn transaction_to_json(tx: &BitcoinTransaction) -> Value {
+    let vin: Vec<Value> = tx.inputs.iter().map(input_to_json).collect();
+    let vout: Vec<Value> = tx
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(n, output)| output_to_json(n, output))
+        .collect();
+    json!({
+        "txid": reversed_hex(&tx.txid().0),
+        "hash": reversed_hex(&tx.wtxid().0),
+        "version": tx.version,
+        "size": tx.to_bytes().len(),
+        "vsize": tx.vsize(),
+        "weight": tx.weight(),
+        "locktime": tx.lock_time,
+        "vin": vin,
+        "vout": vout,
+    })
+}
+
+fn input_to_json(input: &TransactionInput) -> Value {
+    let mut value = if input.previous_output.is_null() {
+        json!({ "coinbase": hex_codec::encode(&input.script_sig.bytes) })
+    } else {
+        json!({
+            "txid": reversed_hex(&input.previous_output.txid.0),
+            "vout": input.previous_output.vout,
+            "scriptSig": { "hex": hex_codec::encode(&input.script_sig.bytes) },
+        })
+    };
+    if !input.witness.is_empty() {
+        let items: Vec<String> = input
+            .witness
+            .iter()
+            .map(|item| hex_codec::encode(item))
+            .collect();
+        value["txinwitness"] = json!(items);
+    }
+    value["sequence"] = json!(input.sequence);
+    value
+}
+
+fn output_to_json(n: usize, output: &TransactionOutput) -> Value {
+    json!({
+        "value": satoshis_to_btc(output.value),
+        "n": n,
+        "scriptPubKey": {
+            "hex": hex_codec::encode(&output.script_pubkey.bytes),
+            "type": output.script_pubkey.script_type().as_str(),
+        },
+    })
+}
+
+pub fn block_to_json(block: &Block) -> Value {
+    let header = &block.header;
+    let tx: Vec<Value> = block.transactions.iter().map(transaction_to_json).collect();
+    let mut value = json!({
+        "hash": reversed_hex(&block.block_hash()),
+        "version": header.version,
+        "merkleroot": reversed_hex(&header.merkle_root),
+        "time": header.time,
+        "nonce": header.nonce,
+        "bits": format!("{:08x}", header.bits),
+        "nTx": block.transactions.len(),
+        "size": block.to_bytes().len(),
+        "tx": tx,
+    });
+    if header.prev_blockhash != [0u8; 32] {
+        value["previousblockhash"] = json!(reversed_hex(&header.prev_blockhash));
+    }
+    value
+}
@@ -0,0 +1,90 @@
+//! Child-pays-for-parent: a child transaction whose fee lifts its parent's
+//! package to a target feerate, as when spending an anchor output.
+
+use crate::constants::{MAX_BIP125_RBF_SEQUENCE, WITNESS_SCALE_FACTOR};
+use crate::fee::{InputType, estimate_weight, fee_for_weight};
+use crate::policy::{Policy, Version};
+use crate::{
+    BitcoinError, BitcoinTransaction, OutPoint, Script, TransactionInput, TransactionOutput,
+};
+
+/// The fee a child of `child_weight` must pay so that it and a parent of
+/// `parent_weight` paying `parent_fee` together reach `fee_rate` sat/kvB.
+/// Never less than the child's own fee at that rate, so a parent that
+/// already pays enough gets a child that does too.
+pub fn child_fee(parent_weight: usize, parent_fee: u64, child_weight: usize, fee_rate: u64) -> u64 {
+    let package_vsize = (parent_weight.div_ceil(WITNESS_SCALE_FACTOR)
+        + child_weight.div_ceil(WITNESS_SCALE_FACTOR)) as u64;
+    (package_vsize * fee_rate)
+        .div_ceil(1000)
+        .saturating_sub(parent_fee)
+        .max(fee_for_weight(child_weight, fee_rate))
+}
+
+/// An unsigned child spending output `vout` of the signed `parent`, which
+/// pays `parent_fee`, so the package reaches `fee_rate` sat/kvB. What's
+/// left goes to one output paying `change_script`.
+///
+/// If the spent output can't cover the fee and a change output above dust,
+/// as with an anchor, the largest of `coins` are added until they do. The
+/// child is version 3 if the parent is, and signals replaceability so it
+/// can be bumped in turn. Spent outputs must be P2PKH, P2WPKH, taproot or
+/// anchors; others are `Unsupported`.
+pub fn build_child(
+    parent: &BitcoinTransaction,
+    parent_fee: u64,
+    vout: u32,
+    fee_rate: u64,
+    coins: &[(OutPoint, TransactionOutput)],
+    change_script: Script,
+) -> Result<BitcoinTransaction, BitcoinError> {
+    let spent = parent
+        .outputs
+        .get(vout as usize)
+        .ok_or(BitcoinError::InvalidFormat)?;
+    let input_type = |output: &TransactionOutput| {
+        InputType::from_script_pubkey(&output.script_pubkey).ok_or(BitcoinError::Unsupported)
+    };
+    let version = if parent.tx_version().is_truc() {
+        Version::THREE
+    } else {
+        Version::TWO
+    };
+    let sequence = MAX_BIP125_RBF_SEQUENCE;
+
+    let mut types = vec![input_type(spent)?];
+    let mut available = spent.value;
+    let mut child = BitcoinTransaction::new(
+        version.0,
+        vec![TransactionInput::new(
+            OutPoint::new(parent.txid().0, vout),
+            Script::new(vec![]),
+            sequence,
+        )],
+        0,
+    )
+    .with_outputs(vec![TransactionOutput::new(0, change_script)]);
+    let dust = Policy::default().dust_threshold(&child.outputs[0]);
+
+    let mut coins: Vec<&(OutPoint, TransactionOutput)> = coins.iter().collect();
+    coins.sort_by_key(|(_, coin)| std::cmp::Reverse(coin.value));
+    let mut coins = coins.into_iter();
+    loop {
+        let weight = estimate_weight(&types, &child.outputs);
+        let fee = child_fee(parent.weight(), parent_fee, weight, fee_rate);
+        if let Some(value) = available.checked_sub(fee).filter(|&value| value >= dust) {
+            child.outputs[0].value = value;
+            return Ok(child);
+        }
+        let (outpoint, coin) = coins
+            .next()
+            .ok_or(BitcoinError::InvalidTransaction("insufficient-funds"))?;
+        types.push(input_type(coin)?);
+        available += coin.value;
+        child.inputs.push(TransactionInput::new(
+            outpoint.clone(),
+            Script::new(vec![]),
+            sequence,
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+//! BIP119 `OP_CHECKTEMPLATEVERIFY` template hashes.
+
+use crate::hashes::sha256;
+use crate::{BitcoinError, BitcoinTransaction, Script};
+
+/// `OP_NOP4`, which CTV redefines.
+pub const OP_CHECKTEMPLATEVERIFY: u8 = 0xb3;
+
+impl BitcoinTransaction {
+    /// The `DefaultCheckTemplateVerifyHash` committed to by a CTV script
+    /// spent at `input_index`.
+    pub fn ctv_template_hash(&self, input_index: usize) -> Result<[u8; 32], BitcoinError> {
+        if input_index >= self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut data = self.version.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.lock_time.to_le_bytes());
+        // scriptSigs are only committed to when any is non-empty.
+        if self.inputs.iter().any(|input| !input.script_sig.is_empty()) {
+            let script_sigs: Vec<u8> = self
+                .inputs
+                .iter()
+                .flat_map(|input| input.script_sig.to_bytes())
+                .collect();
+            data.extend_from_slice(&sha256(&script_sigs));
+        }
+        data.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        let sequences: Vec<u8> = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.sequence.to_le_bytes())
+            .collect();
+        data.extend_from_slice(&sha256(&sequences));
+        data.extend_from_slice(&(self.outputs.len() as u32).to_le_bytes());
+        let outputs: Vec<u8> = self
+            .outputs
+            .iter()
+            .flat_map(|output| output.to_bytes())
+            .collect();
+        data.extend_from_slice(&sha256(&outputs));
+        data.extend_from_slice(&(input_index as u32).to_le_bytes());
+        Ok(sha256(&data))
+    }
+}
+
+/// The bare CTV script `<hash> OP_CHECKTEMPLATEVERIFY`.
+pub fn ctv_script(template_hash: &[u8; 32]) -> Script {
+    let mut script = Script::new(Vec::new());
+    script
+        .push_slice(template_hash)
+        .push_opcode(OP_CHECKTEMPLATEVERIFY);
+    script
+}
@@ -0,0 +1,370 @@
+//! Output script descriptors (BIP380-386): `wpkh`, `sh(wpkh)`,
+//! `wsh(multi)`, key-path-only `tr` and `raw`.
+
+use crate::BitcoinError;
+#[cfg(feature = "secp")]
+use crate::Script;
+use crate::bip32::{KeyScriptType, Xpub, format_step, parse_step};
+use std::fmt;
+use std::str::FromStr;
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+pub use crate::bip32::HARDENED;
+/// Core's limit on keys in `multi()` inside `wsh()`.
+pub const MAX_MULTISIG_KEYS: usize = 20;
+
+/// Fingerprint and path of the key an extended key was derived from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KeyOrigin {
+    pub fingerprint: [u8; 4],
+    pub path: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Wildcard {
+    None,
+    Unhardened,
+    Hardened,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DescriptorKey {
+    /// A hex public key: 33 bytes compressed, or 32 bytes x-only in `tr()`.
+    Single {
+        origin: Option<KeyOrigin>,
+        key: Vec<u8>,
+    },
+    /// A base58 `xpub`/`tpub` with a derivation path below it.
+    Extended {
+        origin: Option<KeyOrigin>,
+        xkey: String,
+        path: Vec<u32>,
+        wildcard: Wildcard,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Descriptor {
+    Wpkh(DescriptorKey),
+    ShWpkh(DescriptorKey),
+    WshMulti(usize, Vec<DescriptorKey>),
+    Tr(DescriptorKey),
+    Raw(Vec<u8>),
+}
+
+impl Descriptor {
+    /// True if any key ends in a `*` wildcard.
+    pub fn has_wildcard(&self) -> bool {
+        self.keys().iter().any(|key| {
+            matches!(key, DescriptorKey::Extended { wildcard, .. } if *wildcard != Wildcard::None)
+        })
+    }
+
+    pub fn keys(&self) -> Vec<&DescriptorKey> {
+        match self {
+            Descriptor::Wpkh(key) | Descriptor::ShWpkh(key) | Descriptor::Tr(key) => vec![key],
+            Descriptor::WshMulti(_, keys) => keys.iter().collect(),
+            Descriptor::Raw(_) => Vec::new(),
+        }
+    }
+
+    /// The descriptor a SLIP-132 extended public key implies: `ypub`/`upub`
+    /// give `sh(wpkh())` and `zpub`/`vpub` give `wpkh()`, with the key
+    /// re-encoded as `xpub`/`tpub`. Plain `xpub`/`tpub` keys fail with
+    /// `Unsupported` since `pkh()` isn't implemented.
+    pub fn from_slip132(
+        xkey: &str,
+        path: Vec<u32>,
+        wildcard: Wildcard,
+    ) -> Result<Descriptor, BitcoinError> {
+        let xpub: Xpub = xkey.parse()?;
+        let key = DescriptorKey::Extended {
+            origin: None,
+            xkey: xpub.with_script_type(KeyScriptType::P2pkh)?.to_string(),
+            path,
+            wildcard,
+        };
+        match xpub.script_type()? {
+            KeyScriptType::P2pkh => Err(BitcoinError::Unsupported),
+            KeyScriptType::P2shP2wpkh => Ok(Descriptor::ShWpkh(key)),
+            KeyScriptType::P2wpkh => Ok(Descriptor::Wpkh(key)),
+        }
+    }
+}
+
+#[cfg(feature = "secp")]
+impl Descriptor {
+    /// The output script at `index`, substituted for any `*` wildcard
+    /// (ignored when there is none). Hardened derivation needs private keys
+    /// and fails with `Unsupported`.
+    pub fn derive_script_pubkey(&self, index: u32) -> Result<Script, BitcoinError> {
+        use crate::hashes::hash160;
+        use crate::opcodes::{OP_CHECKMULTISIG, OP_EQUAL, OP_HASH160};
+        use crate::taproot::TaprootSpendInfo;
+        use crate::witness_program::WitnessProgram;
+
+        match self {
+            Descriptor::Wpkh(key) => {
+                Ok(WitnessProgram::p2wpkh(hash160(&key.derive(index)?)).to_script())
+            }
+            Descriptor::ShWpkh(key) => {
+                let redeem = WitnessProgram::p2wpkh(hash160(&key.derive(index)?)).to_script();
+                let mut script = Script::new(Vec::new());
+                script
+                    .push_opcode(OP_HASH160)
+                    .push_slice(&hash160(&redeem.bytes))
+                    .push_opcode(OP_EQUAL);
+                Ok(script)
+            }
+            Descriptor::WshMulti(k, keys) => {
+                let mut script = Script::new(Vec::new());
+                script.push_int(*k as i64);
+                for key in keys {
+                    script.push_slice(&key.derive(index)?);
+                }
+                script
+                    .push_int(keys.len() as i64)
+                    .push_opcode(OP_CHECKMULTISIG);
+                Ok(script.to_p2wsh())
+            }
+            Descriptor::Tr(key) => {
+                let key = key.derive(index)?;
+                let x_only = key[key.len() - 32..].try_into().unwrap();
+                Ok(TaprootSpendInfo::new(x_only, None)?.script_pubkey())
+            }
+            Descriptor::Raw(script) => Ok(Script::new(script.clone())),
+        }
+    }
+}
+
+#[cfg(feature = "secp")]
+impl DescriptorKey {
+    /// The public key at `index`: compressed, or x-only for a 32-byte
+    /// single key.
+    pub fn derive(&self, index: u32) -> Result<Vec<u8>, BitcoinError> {
+        match self {
+            DescriptorKey::Single { key, .. } => Ok(key.clone()),
+            DescriptorKey::Extended {
+                xkey,
+                path,
+                wildcard,
+                ..
+            } => {
+                let mut xpub = xkey.parse::<crate::bip32::Xpub>()?.derive_path(path)?;
+                match wildcard {
+                    Wildcard::None => {}
+                    Wildcard::Unhardened => xpub = xpub.derive_child(index)?,
+                    Wildcard::Hardened => return Err(BitcoinError::Unsupported),
+                }
+                Ok(xpub.public_key.to_vec())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    /// Writes the descriptor followed by `#` and its checksum.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = match self {
+            Descriptor::Wpkh(key) => format!("wpkh({})", key),
+            Descriptor::ShWpkh(key) => format!("sh(wpkh({}))", key),
+            Descriptor::WshMulti(k, keys) => {
+                let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+                format!("wsh(multi({},{}))", k, keys.join(","))
+            }
+            Descriptor::Tr(key) => format!("tr({})", key),
+            Descriptor::Raw(script) => format!("raw({})", hex::encode(script)),
+        };
+        let checksum = checksum(&body).ok_or(fmt::Error)?;
+        write!(f, "{}#{}", body, checksum)
+    }
+}
+
+impl FromStr for Descriptor {
+    type Err = BitcoinError;
+
+    /// Parses a descriptor, verifying its checksum if one is given.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BitcoinError::InvalidFormat;
+        let body = match s.split_once('#') {
+            Some((body, given)) => {
+                if checksum(body).ok_or_else(invalid)? != given {
+                    return Err(invalid());
+                }
+                body
+            }
+            None => s,
+        };
+
+        let (name, args) = split_call(body)?;
+        match name {
+            "wpkh" => Ok(Descriptor::Wpkh(parse_key(args, 33)?)),
+            "sh" => match split_call(args)? {
+                ("wpkh", key) => Ok(Descriptor::ShWpkh(parse_key(key, 33)?)),
+                _ => Err(invalid()),
+            },
+            "wsh" => match split_call(args)? {
+                ("multi", args) => {
+                    let mut args = args.split(',');
+                    let k: usize = args.next().unwrap().parse().map_err(|_| invalid())?;
+                    let keys = args
+                        .map(|key| parse_key(key, 33))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if k == 0 || k > keys.len() || keys.len() > MAX_MULTISIG_KEYS {
+                        return Err(invalid());
+                    }
+                    Ok(Descriptor::WshMulti(k, keys))
+                }
+                _ => Err(invalid()),
+            },
+            "tr" => Ok(Descriptor::Tr(parse_key(args, 32)?)),
+            "raw" => Ok(Descriptor::Raw(hex::decode(args).map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origin = match self {
+            DescriptorKey::Single { origin, .. } | DescriptorKey::Extended { origin, .. } => origin,
+        };
+        if let Some(origin) = origin {
+            write!(f, "[{}", hex::encode(origin.fingerprint))?;
+            for &step in &origin.path {
+                write!(f, "/{}", format_step(step))?;
+            }
+            write!(f, "]")?;
+        }
+        match self {
+            DescriptorKey::Single { key, .. } => write!(f, "{}", hex::encode(key)),
+            DescriptorKey::Extended {
+                xkey,
+                path,
+                wildcard,
+                ..
+            } => {
+                write!(f, "{}", xkey)?;
+                for &step in path {
+                    write!(f, "/{}", format_step(step))?;
+                }
+                match wildcard {
+                    Wildcard::None => Ok(()),
+                    Wildcard::Unhardened => write!(f, "/*"),
+                    Wildcard::Hardened => write!(f, "/*'"),
+                }
+            }
+        }
+    }
+}
+
+/// The BIP380 checksum of a descriptor (without its `#` suffix), or `None`
+/// if it contains characters outside the descriptor character set.
+pub fn checksum(descriptor: &str) -> Option<String> {
+    let mut c: u64 = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let pos = INPUT_CHARSET.find(ch)? as u64;
+        c = polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Some(
+        (0..8)
+            .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+fn polymod(c: u64, value: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+    let top = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if top >> i & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Splits `name(args)` into its parts.
+fn split_call(s: &str) -> Result<(&str, &str), BitcoinError> {
+    let (name, rest) = s.split_once('(').ok_or(BitcoinError::InvalidFormat)?;
+    let args = rest.strip_suffix(')').ok_or(BitcoinError::InvalidFormat)?;
+    Ok((name, args))
+}
+
+/// Parses a key expression; hex keys must be `single_len` bytes (33, or 32
+/// for x-only), though `tr()` also accepts compressed keys.
+fn parse_key(s: &str, single_len: usize) -> Result<DescriptorKey, BitcoinError> {
+    let invalid = || BitcoinError::InvalidFormat;
+    let (origin, key) = match s.strip_prefix('[') {
+        Some(rest) => {
+            let (origin, key) = rest.split_once(']').ok_or_else(invalid)?;
+            let mut parts = origin.split('/');
+            let mut fingerprint = [0u8; 4];
+            hex::decode_to_slice(parts.next().unwrap(), &mut fingerprint).map_err(|_| invalid())?;
+            let path = parts.map(parse_step).collect::<Result<_, _>>()?;
+            (Some(KeyOrigin { fingerprint, path }), key)
+        }
+        None => (None, s),
+    };
+
+    if let Ok(bytes) = hex::decode(key) {
+        let valid = match bytes.len() {
+            33 => bytes[0] == 0x02 || bytes[0] == 0x03,
+            32 => single_len == 32,
+            _ => false,
+        };
+        if !valid {
+            return Err(invalid());
+        }
+        return Ok(DescriptorKey::Single { origin, key: bytes });
+    }
+
+    let mut parts = key.split('/');
+    let xkey = parts.next().unwrap();
+    if !(xkey.starts_with("xpub") || xkey.starts_with("tpub")) {
+        return Err(invalid());
+    }
+    let mut steps: Vec<&str> = parts.collect();
+    let wildcard = match steps.last() {
+        Some(&"*") => Wildcard::Unhardened,
+        Some(&"*'") | Some(&"*h") => Wildcard::Hardened,
+        _ => Wildcard::None,
+    };
+    if wildcard != Wildcard::None {
+        steps.pop();
+    }
+    Ok(DescriptorKey::Extended {
+        origin,
+        xkey: xkey.to_string(),
+        path: steps
+            .into_iter()
+            .map(parse_step)
+            .collect::<Result<_, _>>()?,
+        wildcard,
+    })
+}
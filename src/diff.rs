@@ -0,0 +1,225 @@
+//! Field-by-field comparison of two transactions, for example one extracted
+//! from a PSBT against the one that confirmed.
+
+use crate::{BitcoinTransaction, OutPoint, Script, hex_codec};
+use std::fmt;
+
+/// One field that differs, with its value in `self` (`left`) and in the
+/// other transaction (`right`). Inputs, outputs and witness items are
+/// compared by index; past the end of the shorter list only the count
+/// difference is reported.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TxDifference {
+    Version {
+        left: u32,
+        right: u32,
+    },
+    LockTime {
+        left: u32,
+        right: u32,
+    },
+    InputCount {
+        left: usize,
+        right: usize,
+    },
+    OutputCount {
+        left: usize,
+        right: usize,
+    },
+    PreviousOutput {
+        input: usize,
+        left: OutPoint,
+        right: OutPoint,
+    },
+    ScriptSig {
+        input: usize,
+        left: Script,
+        right: Script,
+    },
+    Sequence {
+        input: usize,
+        left: u32,
+        right: u32,
+    },
+    WitnessItemCount {
+        input: usize,
+        left: usize,
+        right: usize,
+    },
+    WitnessItem {
+        input: usize,
+        item: usize,
+        left: Vec<u8>,
+        right: Vec<u8>,
+    },
+    Value {
+        output: usize,
+        left: u64,
+        right: u64,
+    },
+    ScriptPubkey {
+        output: usize,
+        left: Script,
+        right: Script,
+    },
+}
+
+impl fmt::Display for TxDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxDifference::Version { left, right } => write!(f, "version: {} != {}", left, right),
+            TxDifference::LockTime { left, right } => {
+                write!(f, "lock_time: {} != {}", left, right)
+            }
+            TxDifference::InputCount { left, right } => {
+                write!(f, "input count: {} != {}", left, right)
+            }
+            TxDifference::OutputCount { left, right } => {
+                write!(f, "output count: {} != {}", left, right)
+            }
+            TxDifference::PreviousOutput { input, left, right } => {
+                write!(
+                    f,
+                    "inputs[{}].previous_output: {} != {}",
+                    input, left, right
+                )
+            }
+            TxDifference::ScriptSig { input, left, right } => write!(
+                f,
+                "inputs[{}].script_sig: {} != {}",
+                input,
+                hex_codec::encode(left),
+                hex_codec::encode(right)
+            ),
+            TxDifference::Sequence { input, left, right } => {
+                write!(f, "inputs[{}].sequence: {:#x} != {:#x}", input, left, right)
+            }
+            TxDifference::WitnessItemCount { input, left, right } => write!(
+                f,
+                "inputs[{}].witness item count: {} != {}",
+                input, left, right
+            ),
+            TxDifference::WitnessItem {
+                input,
+                item,
+                left,
+                right,
+            } => write!(
+                f,
+                "inputs[{}].witness[{}]: {} != {}",
+                input,
+                item,
+                hex_codec::encode(left),
+                hex_codec::encode(right)
+            ),
+            TxDifference::Value {
+                output,
+                left,
+                right,
+            } => write!(f, "outputs[{}].value: {} != {}", output, left, right),
+            TxDifference::ScriptPubkey {
+                output,
+                left,
+                right,
+            } => write!(
+                f,
+                "outputs[{}].script_pubkey: {} != {}",
+                output,
+                hex_codec::encode(left),
+                hex_codec::encode(right)
+            ),
+        }
+    }
+}
+
+impl BitcoinTransaction {
+    /// Every field that differs between `self` and `other`, in
+    /// serialization order; empty if they are equal.
+    pub fn diff(&self, other: &BitcoinTransaction) -> Vec<TxDifference> {
+        let mut diffs = Vec::new();
+        if self.version != other.version {
+            diffs.push(TxDifference::Version {
+                left: self.version,
+                right: other.version,
+            });
+        }
+        if self.inputs.len() != other.inputs.len() {
+            diffs.push(TxDifference::InputCount {
+                left: self.inputs.len(),
+                right: other.inputs.len(),
+            });
+        }
+        for (input, (a, b)) in self.inputs.iter().zip(&other.inputs).enumerate() {
+            if a.previous_output != b.previous_output {
+                diffs.push(TxDifference::PreviousOutput {
+                    input,
+                    left: a.previous_output.clone(),
+                    right: b.previous_output.clone(),
+                });
+            }
+            if a.script_sig != b.script_sig {
+                diffs.push(TxDifference::ScriptSig {
+                    input,
+                    left: a.script_sig.clone(),
+                    right: b.script_sig.clone(),
+                });
+            }
+            if a.sequence != b.sequence {
+                diffs.push(TxDifference::Sequence {
+                    input,
+                    left: a.sequence,
+                    right: b.sequence,
+                });
+            }
+        }
+        if self.outputs.len() != other.outputs.len() {
+            diffs.push(TxDifference::OutputCount {
+                left: self.outputs.len(),
+                right: other.outputs.len(),
+            });
+        }
+        for (output, (a, b)) in self.outputs.iter().zip(&other.outputs).enumerate() {
+            if a.value != b.value {
+                diffs.push(TxDifference::Value {
+                    output,
+                    left: a.value,
+                    right: b.value,
+                });
+            }
+            if a.script_pubkey != b.script_pubkey {
+                diffs.push(TxDifference::ScriptPubkey {
+                    output,
+                    left: a.script_pubkey.clone(),
+                    right: b.script_pubkey.clone(),
+                });
+            }
+        }
+        for (input, (a, b)) in self.inputs.iter().zip(&other.inputs).enumerate() {
+            let (a, b) = (&a.witness.items, &b.witness.items);
+            if a.len() != b.len() {
+                diffs.push(TxDifference::WitnessItemCount {
+                    input,
+                    left: a.len(),
+                    right: b.len(),
+                });
+            }
+            for (item, (a, b)) in a.iter().zip(b).enumerate() {
+                if a != b {
+                    diffs.push(TxDifference::WitnessItem {
+                        input,
+                        item,
+                        left: a.clone(),
+                        right: b.clone(),
+                    });
+                }
+            }
+        }
+        if self.lock_time != other.lock_time {
+            diffs.push(TxDifference::LockTime {
+                left: self.lock_time,
+                right: other.lock_time,
+            });
+        }
+        diffs
+    }
+}
@@ -0,0 +1,81 @@
+//! Graphviz DOT export of the spend graph between transactions.
+//!
+//! Each transaction is a node and each input an edge from the transaction
+//! it spends, labelled with the output index and, when the parent is in the
+//! set, its value. Parents outside the set are drawn dashed; coinbase
+//! inputs have no edge.
+
+use crate::core_json::reversed_hex;
+use crate::{BitcoinTransaction, Txid};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+/// Renders `transactions` as a DOT digraph.
+pub fn to_dot(transactions: &[BitcoinTransaction]) -> String {
+    let mut out = String::new();
+    write_dot(&mut out, transactions).expect("writing to a String can't fail");
+    out
+}
+
+pub fn write_dot<W: Write>(w: &mut W, transactions: &[BitcoinTransaction]) -> fmt::Result {
+    let by_txid: HashMap<Txid, &BitcoinTransaction> =
+        transactions.iter().map(|tx| (tx.txid(), tx)).collect();
+
+    writeln!(w, "digraph transactions {{")?;
+    writeln!(w, "  rankdir=LR;")?;
+    writeln!(w, "  node [shape=box, fontname=monospace];")?;
+    for tx in transactions {
+        let txid = reversed_hex(&tx.txid().0);
+        writeln!(w, "  \"{txid}\" [label=\"{}\"];", short(&txid))?;
+    }
+
+    let mut external = Vec::new();
+    for tx in transactions {
+        for input in tx
+            .inputs
+            .iter()
+            .filter(|input| !input.previous_output.is_null())
+        {
+            let outpoint = &input.previous_output;
+            if !by_txid.contains_key(&outpoint.txid) && !external.contains(&outpoint.txid) {
+                external.push(outpoint.txid.clone());
+            }
+        }
+    }
+    for txid in &external {
+        let txid = reversed_hex(&txid.0);
+        writeln!(
+            w,
+            "  \"{txid}\" [label=\"{}\", style=dashed];",
+            short(&txid)
+        )?;
+    }
+
+    for tx in transactions {
+        let child = reversed_hex(&tx.txid().0);
+        for input in tx
+            .inputs
+            .iter()
+            .filter(|input| !input.previous_output.is_null())
+        {
+            let outpoint = &input.previous_output;
+            let parent = reversed_hex(&outpoint.txid.0);
+            let value = by_txid
+                .get(&outpoint.txid)
+                .and_then(|parent| parent.outputs.get(outpoint.vout as usize))
+                .map(|output| format!(" ({} sat)", output.value))
+                .unwrap_or_default();
+            writeln!(
+                w,
+                "  \"{parent}\" -> \"{child}\" [label=\"{}{value}\"];",
+                outpoint.vout
+            )?;
+        }
+    }
+    writeln!(w, "}}")
+}
+
+// The first and last eight hex digits keep labels readable.
+fn short(txid: &str) -> String {
+    format!("{}…{}", &txid[..8], &txid[txid.len() - 8..])
+}
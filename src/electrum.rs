@@ -0,0 +1,115 @@
+//! Electrum server protocol messages (newline-delimited JSON-RPC 2.0).
+
+use crate::core_json::{hash_from_reversed_hex, reversed_hex};
+use crate::{BitcoinError, BitcoinTransaction, OutPoint, Script, Txid, hashes};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// The Electrum script hash: sha256 of the scriptPubKey, hex encoded in
+/// reversed byte order.
+pub fn script_hash(script: &Script) -> String {
+    reversed_hex(&hashes::sha256(&script.bytes))
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    pub params: Vec<Value>,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Vec<Value>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    /// `blockchain.transaction.get` in its raw-hex (non-verbose) form.
+    pub fn transaction_get(id: u64, txid: &Txid) -> Self {
+        Self::new(
+            id,
+            "blockchain.transaction.get",
+            vec![json!(reversed_hex(&txid.0)), json!(false)],
+        )
+    }
+
+    pub fn scripthash_listunspent(id: u64, script: &Script) -> Self {
+        Self::new(
+            id,
+            "blockchain.scripthash.listunspent",
+            vec![json!(script_hash(script))],
+        )
+    }
+
+    /// The request as sent on the wire, terminated by a newline.
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap();
+        line.push('\n');
+        line
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub id: u64,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+impl Response {
+    pub fn from_line(line: &str) -> Result<Self, BitcoinError> {
+        serde_json::from_str(line.trim()).map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    pub fn into_result(self) -> Result<Value, BitcoinError> {
+        match self.error {
+            Some(error) if !error.is_null() => Err(BitcoinError::Rpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string()),
+            }),
+            _ => Ok(self.result.unwrap_or(Value::Null)),
+        }
+    }
+
+    pub fn into_transaction(self) -> Result<BitcoinTransaction, BitcoinError> {
+        let result = self.into_result()?;
+        let bytes = result
+            .as_str()
+            .and_then(|hex_str| hex::decode(hex_str).ok())
+            .ok_or(BitcoinError::InvalidFormat)?;
+        BitcoinTransaction::from_bytes(&bytes).map(|(tx, _)| tx)
+    }
+
+    pub fn into_unspent(self) -> Result<Vec<UnspentEntry>, BitcoinError> {
+        serde_json::from_value(self.into_result()?).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+/// One element of a `blockchain.scripthash.listunspent` result. `height` is
+/// 0 for mempool outputs.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct UnspentEntry {
+    pub tx_hash: String,
+    pub tx_pos: u32,
+    pub height: u32,
+    pub value: u64,
+}
+
+impl UnspentEntry {
+    pub fn outpoint(&self) -> Result<OutPoint, BitcoinError> {
+        Ok(OutPoint::new(
+            hash_from_reversed_hex(&self.tx_hash)?,
+            self.tx_pos,
+        ))
+    }
+}
@@ -0,0 +1,143 @@
+//! Serializing into reused buffers.
+//!
+//! Each `to_bytes` allocates a fresh `Vec`. Loops that serialize many
+//! values, such as relaying transactions or assembling blocks, can instead
+//! append to a buffer they keep: an [`Encoder`] for one thread, or a
+//! [`BufferPool`] shared between several.
+
+use crate::block::{Block, BlockHeader};
+use crate::{
+    BitcoinTransaction, CompactSize, OutPoint, Script, TransactionInput, TransactionOutput, Witness,
+};
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+/// Types with a consensus encoding that can be appended to a buffer.
+pub trait Encode {
+    /// Appends the bytes `to_bytes` would return.
+    fn encode_to(&self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_encode {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode_to(&self, out: &mut Vec<u8>) {
+                    <$ty>::encode_to(self, out)
+                }
+            }
+        )*
+    };
+}
+
+impl_encode!(
+    CompactSize,
+    OutPoint,
+    Script,
+    TransactionInput,
+    Witness,
+    TransactionOutput,
+    BitcoinTransaction,
+    BlockHeader,
+    Block
+);
+
+/// One scratch buffer, cleared and refilled by each call.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The encoding of `value`, valid until the next call.
+    pub fn encode<T: Encode + ?Sized>(&mut self, value: &T) -> &[u8] {
+        self.buf.clear();
+        value.encode_to(&mut self.buf);
+        &self.buf
+    }
+}
+
+/// Buffers shared between threads. Each one goes back to the pool, emptied
+/// but keeping its capacity, when its [`PooledBuffer`] is dropped.
+#[derive(Debug)]
+pub struct BufferPool {
+    free: Mutex<Vec<Vec<u8>>>,
+    max_free: usize,
+}
+
+impl BufferPool {
+    /// A pool keeping at most `max_free` idle buffers; more are freed.
+    pub fn new(max_free: usize) -> Self {
+        Self {
+            free: Mutex::new(Vec::new()),
+            max_free,
+        }
+    }
+
+    /// An empty buffer, reused if one is idle.
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buf = self.free.lock().unwrap().pop().unwrap_or_default();
+        PooledBuffer { buf, pool: self }
+    }
+
+    pub fn encode<T: Encode + ?Sized>(&self, value: &T) -> PooledBuffer<'_> {
+        let mut buf = self.get();
+        value.encode_to(&mut buf);
+        buf
+    }
+
+    /// How many buffers are idle.
+    pub fn free_len(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+}
+
+#[derive(Debug)]
+pub struct PooledBuffer<'a> {
+    buf: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl PooledBuffer<'_> {
+    /// Keeps the buffer instead of returning it to the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        let mut buf = std::mem::take(&mut self.buf);
+        if buf.capacity() == 0 {
+            return;
+        }
+        buf.clear();
+        let mut free = self.pool.free.lock().unwrap();
+        if free.len() < self.pool.max_free {
+            free.push(buf);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Predicting the weight and fee of a transaction before it is signed.
+//!
+//! Signatures are counted at their largest common size: 72 bytes for
+//! ECDSA (DER plus the sighash byte) and 64 for Schnorr with the default
+//! sighash, so estimates never fall short. Fee rates are in sat/kvB, as in
+//! [`Policy`].
+
+use crate::constants::WITNESS_SCALE_FACTOR;
+use crate::policy::Policy;
+use crate::{BitcoinTransaction, CompactSize, Script, ScriptType, TransactionOutput};
+
+const ECDSA_SIGNATURE_SIZE: usize = 72;
+const SCHNORR_SIGNATURE_SIZE: usize = 64;
+const PUBKEY_SIZE: usize = 33;
+/// Outpoint and sequence.
+const INPUT_BASE_SIZE: usize = 36 + 4;
+
+/// How a planned input will be spent.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum InputType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+    P2trKeyPath,
+    /// Pay-to-anchor, spent with an empty witness.
+    Anchor,
+    /// Bare `OP_CHECKMULTISIG` redeem script of `keys` compressed keys.
+    P2shMultisig {
+        required: u8,
+        keys: u8,
+    },
+    /// The same script as a witness script.
+    P2wshMultisig {
+        required: u8,
+        keys: u8,
+    },
+}
+
+impl InputType {
+    /// The spend of a single-key `script_pubkey`: P2PKH, P2WPKH, a taproot
+    /// key path or an anchor. `None` for script hashes, whose spend depends on
+    /// the script behind them.
+    pub fn from_script_pubkey(script_pubkey: &Script) -> Option<InputType> {
+        match script_pubkey.script_type() {
+            ScriptType::P2pkh => Some(InputType::P2pkh),
+            ScriptType::P2wpkh => Some(InputType::P2wpkh),
+            ScriptType::P2tr => Some(InputType::P2trKeyPath),
+            ScriptType::Anchor => Some(InputType::Anchor),
+            _ => None,
+        }
+    }
+
+    /// Bytes counted at full weight: outpoint, scriptSig with its length
+    /// and sequence.
+    pub fn base_size(&self) -> usize {
+        let script_sig = match *self {
+            InputType::P2pkh => push_size(ECDSA_SIGNATURE_SIZE) + push_size(PUBKEY_SIZE),
+            InputType::P2shP2wpkh => push_size(22),
+            InputType::P2wpkh
+            | InputType::P2trKeyPath
+            | InputType::Anchor
+            | InputType::P2wshMultisig { .. } => 0,
+            InputType::P2shMultisig { required, keys } => {
+                1 + required as usize * push_size(ECDSA_SIGNATURE_SIZE)
+                    + push_size(multisig_script_size(keys))
+            }
+        };
+        INPUT_BASE_SIZE + compact_size_len(script_sig) + script_sig
+    }
+
+    /// Witness bytes, including the item count; zero for inputs with an
+    /// empty witness.
+    pub fn witness_size(&self) -> usize {
+        let items: Vec<usize> = match *self {
+            InputType::P2pkh | InputType::P2shMultisig { .. } | InputType::Anchor => return 0,
+            InputType::P2shP2wpkh | InputType::P2wpkh => vec![ECDSA_SIGNATURE_SIZE, PUBKEY_SIZE],
+            InputType::P2trKeyPath => vec![SCHNORR_SIGNATURE_SIZE],
+            InputType::P2wshMultisig { required, keys } => {
+                let mut items = vec![0];
+                items.extend(std::iter::repeat_n(ECDSA_SIGNATURE_SIZE, required as usize));
+                items.push(multisig_script_size(keys));
+                items
+            }
+        };
+        compact_size_len(items.len())
+            + items
+                .iter()
+                .map(|&len| compact_size_len(len) + len)
+                .sum::<usize>()
+    }
+
+    pub fn is_segwit(&self) -> bool {
+        self.witness_size() > 0
+    }
+}
+
+/// The signed weight of a transaction spending `inputs` to `outputs`.
+pub fn estimate_weight(inputs: &[InputType], outputs: &[TransactionOutput]) -> usize {
+    let segwit = inputs.iter().any(InputType::is_segwit);
+    let base = 4
+        + compact_size_len(inputs.len())
+        + inputs.iter().map(InputType::base_size).sum::<usize>()
+        + compact_size_len(outputs.len())
+        + outputs
+            .iter()
+            .map(|output| output.to_bytes().len())
+            .sum::<usize>()
+        + 4;
+    let witness = if segwit {
+        // The marker and flag, plus an empty stack for each legacy input.
+        2 + inputs
+            .iter()
+            .map(|input| input.witness_size().max(1))
+            .sum::<usize>()
+    } else {
+        0
+    };
+    base * WITNESS_SCALE_FACTOR + witness
+}
+
+/// The fee for `weight` at `fee_rate` sat/kvB, rounding the virtual size
+/// and the fee up.
+pub fn fee_for_weight(weight: usize, fee_rate: u64) -> u64 {
+    (weight.div_ceil(WITNESS_SCALE_FACTOR) as u64 * fee_rate).div_ceil(1000)
+}
+
+/// The fee `inputs` and `outputs` need at `fee_rate` sat/kvB once signed.
+pub fn estimate_fee(inputs: &[InputType], outputs: &[TransactionOutput], fee_rate: u64) -> u64 {
+    fee_for_weight(estimate_weight(inputs, outputs), fee_rate)
+}
+
+/// Change outputs for what `input_value` leaves after `outputs` and the
+/// fee at `fee_rate` sat/kvB, split evenly across `scripts` to blur which
+/// output is change. Trailing scripts are dropped while a share would be
+/// dust, and with none left the rest goes to fees, giving no outputs.
+pub fn change_outputs(
+    input_value: u64,
+    inputs: &[InputType],
+    outputs: &[TransactionOutput],
+    scripts: &[Script],
+    fee_rate: u64,
+) -> Vec<TransactionOutput> {
+    let policy = Policy::default();
+    let spent: u64 = outputs.iter().map(|output| output.value).sum();
+    let Some(excess) = input_value.checked_sub(spent) else {
+        return Vec::new();
+    };
+    for n in (1..=scripts.len()).rev() {
+        let mut change: Vec<TransactionOutput> = scripts[..n]
+            .iter()
+            .map(|script| TransactionOutput::new(0, script.clone()))
+            .collect();
+        let all: Vec<TransactionOutput> = outputs.iter().chain(&change).cloned().collect();
+        let Some(left) = excess.checked_sub(estimate_fee(inputs, &all, fee_rate)) else {
+            continue;
+        };
+        let share = left / n as u64;
+        for output in &mut change {
+            output.value = share;
+        }
+        change[0].value += left % n as u64;
+        if change
+            .iter()
+            .all(|output| output.value >= policy.dust_threshold(output))
+        {
+            return change;
+        }
+    }
+    Vec::new()
+}
+
+impl BitcoinTransaction {
+    /// The signed weight of this unsigned transaction, given how each
+    /// input will be spent, in input order.
+    pub fn estimate_signed_weight(&self, inputs: &[InputType]) -> usize {
+        estimate_weight(inputs, &self.outputs)
+    }
+}
+
+fn compact_size_len(n: usize) -> usize {
+    CompactSize::new(n as u64).to_bytes().len()
+}
+
+// A minimal push of `len` bytes, with its opcode.
+fn push_size(len: usize) -> usize {
+    len + match len {
+        0..=75 => 1,
+        76..=255 => 2,
+        _ => 3,
+    }
+}
+
+// `OP_m <key>... OP_n OP_CHECKMULTISIG`.
+fn multisig_script_size(keys: u8) -> usize {
+    3 + keys as usize * push_size(PUBKEY_SIZE)
+}
@@ -0,0 +1,189 @@
+//! C ABI over transaction decoding; the matching header is `include/btx.h`
+//! (regenerate with `cbindgen --config cbindgen.toml`).
+//!
+//! A decoded transaction lives behind an opaque `BtxTransaction` handle that
+//! must be released with `btx_tx_free`. Accessors return `BTX_OK` or a
+//! negative status and write results through out-pointers.
+
+use crate::BitcoinTransaction;
+use std::ptr;
+
+pub const BTX_OK: i32 = 0;
+pub const BTX_ERR_NULL: i32 = -1;
+pub const BTX_ERR_INDEX: i32 = -2;
+
+/// Opaque handle to a decoded transaction.
+pub struct BtxTransaction(BitcoinTransaction);
+
+/// Decodes `len` bytes at `data`. Returns NULL if they are not exactly one
+/// serialized transaction.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_decode(data: *const u8, len: usize) -> *mut BtxTransaction {
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    match BitcoinTransaction::from_bytes(bytes) {
+        Ok((tx, used)) if used == len => Box::into_raw(Box::new(BtxTransaction(tx))),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `tx` must be NULL or a handle from `btx_tx_decode` not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_free(tx: *mut BtxTransaction) {
+    if !tx.is_null() {
+        drop(unsafe { Box::from_raw(tx) });
+    }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_version(tx: *const BtxTransaction, out: *mut u32) -> i32 {
+    unsafe { with_tx(tx, out, |tx| Some(tx.version)) }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_lock_time(tx: *const BtxTransaction, out: *mut u32) -> i32 {
+    unsafe { with_tx(tx, out, |tx| Some(tx.lock_time)) }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_input_count(tx: *const BtxTransaction, out: *mut usize) -> i32 {
+    unsafe { with_tx(tx, out, |tx| Some(tx.inputs.len())) }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_output_count(tx: *const BtxTransaction, out: *mut usize) -> i32 {
+    unsafe { with_tx(tx, out, |tx| Some(tx.outputs.len())) }
+}
+
+/// Writes the txid in internal (little-endian) byte order.
+///
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must point to 32 writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_txid(tx: *const BtxTransaction, out: *mut [u8; 32]) -> i32 {
+    unsafe { with_tx(tx, out, |tx| Some(tx.txid().0)) }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `txid_out` must point to 32 writable bytes
+/// and `vout_out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_input_prevout(
+    tx: *const BtxTransaction,
+    index: usize,
+    txid_out: *mut [u8; 32],
+    vout_out: *mut u32,
+) -> i32 {
+    let status = unsafe {
+        with_tx(tx, txid_out, |tx| {
+            tx.inputs.get(index).map(|i| i.previous_output.txid.0)
+        })
+    };
+    if status != BTX_OK {
+        return status;
+    }
+    unsafe {
+        with_tx(tx, vout_out, |tx| {
+            Some(tx.inputs[index].previous_output.vout)
+        })
+    }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_input_sequence(
+    tx: *const BtxTransaction,
+    index: usize,
+    out: *mut u32,
+) -> i32 {
+    unsafe { with_tx(tx, out, |tx| tx.inputs.get(index).map(|i| i.sequence)) }
+}
+
+/// # Safety
+///
+/// `tx` must be a live handle; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_output_value(
+    tx: *const BtxTransaction,
+    index: usize,
+    out: *mut u64,
+) -> i32 {
+    unsafe { with_tx(tx, out, |tx| tx.outputs.get(index).map(|o| o.value)) }
+}
+
+/// Borrows an output's scriptPubKey. The pointer stays valid until the
+/// handle is freed.
+///
+/// # Safety
+///
+/// `tx` must be a live handle; `data_out` and `len_out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn btx_tx_output_script(
+    tx: *const BtxTransaction,
+    index: usize,
+    data_out: *mut *const u8,
+    len_out: *mut usize,
+) -> i32 {
+    let status = unsafe {
+        with_tx(tx, data_out, |tx| {
+            tx.outputs
+                .get(index)
+                .map(|o| o.script_pubkey.bytes.as_ptr())
+        })
+    };
+    if status != BTX_OK {
+        return status;
+    }
+    unsafe {
+        with_tx(tx, len_out, |tx| {
+            Some(tx.outputs[index].script_pubkey.len())
+        })
+    }
+}
+
+/// Runs `f` on the handle's transaction and writes its result to `out`,
+/// checking both pointers for NULL first.
+///
+/// # Safety
+///
+/// `tx` must be NULL or a live handle; `out` must be NULL or writable.
+unsafe fn with_tx<T>(
+    tx: *const BtxTransaction,
+    out: *mut T,
+    f: impl FnOnce(&BitcoinTransaction) -> Option<T>,
+) -> i32 {
+    if tx.is_null() || out.is_null() {
+        return BTX_ERR_NULL;
+    }
+    match f(unsafe { &(*tx).0 }) {
+        Some(value) => {
+            unsafe { out.write(value) };
+            BTX_OK
+        }
+        None => BTX_ERR_INDEX,
+    }
+}
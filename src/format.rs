@@ -0,0 +1,406 @@
+//! Configurable text rendering of transactions.
+
+use crate::constants::{COIN, MAX_BIP125_RBF_SEQUENCE, SEQUENCE_FINAL};
+use crate::core_json::reversed_hex;
+use crate::locktime::RelativeLockTime;
+use crate::{BitcoinTransaction, Script, TransactionInput};
+use std::fmt::{self, Write};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ScriptFormat {
+    /// Only the script length, e.g. `25 bytes`.
+    #[default]
+    Length,
+    Hex,
+    Asm,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum AmountUnit {
+    #[default]
+    Sats,
+    Btc,
+}
+
+/// Options for `BitcoinTransaction::format_with`. The default reproduces the
+/// plain `Display` output.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FormatOptions {
+    pub show_txids: bool,
+    pub script_format: ScriptFormat,
+    pub show_witness: bool,
+    pub amount_unit: AmountUnit,
+    /// Spaces per nesting level (inputs and outputs are one level deep).
+    pub indent: usize,
+    /// Wrap txids, amounts and opcodes in ANSI color escapes.
+    #[cfg(feature = "color")]
+    pub color: bool,
+}
+
+impl Default for FormatOptions {
+    /// Txids shown, scripts as lengths and amounts in satoshis.
+    fn default() -> Self {
+        Self {
+            show_txids: true,
+            script_format: ScriptFormat::default(),
+            show_witness: false,
+            amount_unit: AmountUnit::default(),
+            indent: 0,
+            #[cfg(feature = "color")]
+            color: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Everything switched on, with two-space indentation.
+    pub fn verbose() -> Self {
+        Self {
+            show_txids: true,
+            script_format: ScriptFormat::Asm,
+            show_witness: true,
+            amount_unit: AmountUnit::Btc,
+            indent: 2,
+            #[cfg(feature = "color")]
+            color: false,
+        }
+    }
+
+    #[cfg(feature = "color")]
+    fn paint(&self, color: &str, text: String) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text
+        }
+    }
+
+    #[cfg(not(feature = "color"))]
+    fn paint(&self, _color: &str, text: String) -> String {
+        text
+    }
+
+    fn script(&self, script: &Script) -> String {
+        let text = format_script(script, self.script_format);
+        if self.script_format != ScriptFormat::Asm {
+            return text;
+        }
+        text.split(' ')
+            .map(|token| {
+                if token.starts_with("OP_") {
+                    self.paint(OPCODE, token.to_string())
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+const TXID: &str = "\x1b[33m";
+const AMOUNT: &str = "\x1b[32m";
+const OPCODE: &str = "\x1b[36m";
+#[cfg(feature = "color")]
+const RESET: &str = "\x1b[0m";
+
+pub fn format_amount(value: u64, unit: AmountUnit) -> String {
+    match unit {
+        AmountUnit::Sats => value.to_string(),
+        AmountUnit::Btc => format!("{}.{:08} BTC", value / COIN, value % COIN),
+    }
+}
+
+/// What an input's sequence number does in a transaction of `version`:
+/// whether it signals BIP125 replacement and any BIP68 relative lock.
+pub fn describe_sequence(input: &TransactionInput, version: u32) -> String {
+    if input.sequence == SEQUENCE_FINAL {
+        return "final".to_string();
+    }
+    let mut notes = Vec::new();
+    if input.sequence <= MAX_BIP125_RBF_SEQUENCE {
+        notes.push("replaceable".to_string());
+    }
+    match input.relative_lock_time().filter(|_| version >= 2) {
+        Some(RelativeLockTime::Blocks(blocks)) => {
+            notes.push(format!("relative lock {} blocks", blocks))
+        }
+        Some(RelativeLockTime::Time(seconds)) => {
+            notes.push(format!("relative lock {} seconds", seconds))
+        }
+        None => {}
+    }
+    if notes.is_empty() {
+        notes.push("lock time enabled".to_string());
+    }
+    notes.join(", ")
+}
+
+pub fn format_script(script: &Script, format: ScriptFormat) -> String {
+    match format {
+        ScriptFormat::Length => format!("{} bytes", script.len()),
+        ScriptFormat::Hex => hex::encode(&script.bytes),
+        ScriptFormat::Asm => script.to_asm(),
+    }
+}
+
+impl BitcoinTransaction {
+    pub fn format_with(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, options).unwrap();
+        out
+    }
+
+    pub fn write_with<W: Write>(&self, w: &mut W, options: &FormatOptions) -> fmt::Result {
+        let pad = |level: usize| " ".repeat(options.indent * level);
+        if options.show_txids {
+            let txid = options.paint(TXID, reversed_hex(&self.txid().0));
+            writeln!(w, "Txid: {}", txid)?;
+        }
+        writeln!(w, "Version: {}", self.version)?;
+        for input in &self.inputs {
+            if options.show_txids {
+                writeln!(
+                    w,
+                    "{}Previous Output Txid: {}",
+                    pad(1),
+                    options.paint(TXID, reversed_hex(&input.previous_output.txid.0))
+                )?;
+            }
+            writeln!(
+                w,
+                "{}Previous Output Vout: {}",
+                pad(1),
+                input.previous_output.vout
+            )?;
+            writeln!(
+                w,
+                "{}ScriptSig: {}",
+                pad(1),
+                options.script(&input.script_sig)
+            )?;
+            writeln!(
+                w,
+                "{}Sequence: {} ({})",
+                pad(1),
+                input.sequence,
+                describe_sequence(input, self.version)
+            )?;
+            if options.show_witness && !input.witness.is_empty() {
+                writeln!(w, "{}Witness: {} items", pad(1), input.witness.len())?;
+                for item in input.witness.iter() {
+                    writeln!(w, "{}{}", pad(2), hex::encode(item))?;
+                }
+            }
+        }
+        for output in &self.outputs {
+            writeln!(
+                w,
+                "{}Output Value: {}",
+                pad(1),
+                options.paint(AMOUNT, format_amount(output.value, options.amount_unit))
+            )?;
+            writeln!(
+                w,
+                "{}ScriptPubKey: {}",
+                pad(1),
+                options.script(&output.script_pubkey)
+            )?;
+            writeln!(
+                w,
+                "{}Script Type: {}",
+                pad(1),
+                output.script_pubkey.script_type()
+            )?;
+        }
+        writeln!(w, "Lock Time: {}", self.lock_time)
+    }
+}
+
+/// One annotated field of a serialization.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DumpRow {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    pub field: String,
+    pub value: String,
+}
+
+/// An annotated hex dump, as rendered by the alternate (`{:#}`) form of
+/// `Display` on transactions, scripts and outpoints.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct HexDump {
+    pub rows: Vec<DumpRow>,
+    offset: usize,
+}
+
+const DUMP_WIDTH: usize = 16;
+
+impl HexDump {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, field: impl Into<String>, bytes: &[u8], value: impl Into<String>) {
+        self.rows.push(DumpRow {
+            offset: self.offset,
+            bytes: bytes.to_vec(),
+            field: field.into(),
+            value: value.into(),
+        });
+        self.offset += bytes.len();
+    }
+
+    fn push_script(&mut self, field: &str, script: &Script) {
+        let prefix = crate::CompactSize::new(script.len() as u64).to_bytes();
+        self.push(
+            format!("{} length", field),
+            &prefix,
+            script.len().to_string(),
+        );
+        if !script.is_empty() {
+            self.push(field, &script.bytes, script.to_asm());
+        }
+    }
+}
+
+impl fmt::Display for HexDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            let mut chunks = row.bytes.chunks(DUMP_WIDTH);
+            let first = chunks.next().unwrap_or(&[]);
+            write!(
+                f,
+                "{:06x}  {:<width$}  {}",
+                row.offset,
+                hex::encode(first),
+                row.field,
+                width = DUMP_WIDTH * 2
+            )?;
+            if row.value.is_empty() {
+                writeln!(f)?;
+            } else {
+                writeln!(f, ": {}", row.value)?;
+            }
+            for (i, chunk) in chunks.enumerate() {
+                writeln!(
+                    f,
+                    "{:06x}  {}",
+                    row.offset + (i + 1) * DUMP_WIDTH,
+                    hex::encode(chunk)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BitcoinTransaction {
+    pub fn hex_dump(&self) -> HexDump {
+        let mut dump = HexDump::new();
+        dump.push(
+            "version",
+            &self.version.to_le_bytes(),
+            self.version.to_string(),
+        );
+        let segwit = self.has_witness();
+        if segwit {
+            dump.push("marker, flag", &[0x00, 0x01], "segwit");
+        }
+        let count = crate::CompactSize::new(self.inputs.len() as u64);
+        dump.push("input count", &count.to_bytes(), count.value.to_string());
+        for (i, input) in self.inputs.iter().enumerate() {
+            let outpoint = &input.previous_output;
+            dump.push(
+                format!("input[{}] txid", i),
+                &outpoint.txid.0,
+                reversed_hex(&outpoint.txid.0),
+            );
+            dump.push(
+                format!("input[{}] vout", i),
+                &outpoint.vout.to_le_bytes(),
+                outpoint.vout.to_string(),
+            );
+            dump.push_script(&format!("input[{}] scriptSig", i), &input.script_sig);
+            dump.push(
+                format!("input[{}] sequence", i),
+                &input.sequence.to_le_bytes(),
+                format!("0x{:08x}", input.sequence),
+            );
+        }
+        let count = crate::CompactSize::new(self.outputs.len() as u64);
+        dump.push("output count", &count.to_bytes(), count.value.to_string());
+        for (i, output) in self.outputs.iter().enumerate() {
+            dump.push(
+                format!("output[{}] value", i),
+                &output.value.to_le_bytes(),
+                format_amount(output.value, AmountUnit::Btc),
+            );
+            dump.push_script(
+                &format!("output[{}] scriptPubKey", i),
+                &output.script_pubkey,
+            );
+        }
+        if segwit {
+            for (i, input) in self.inputs.iter().enumerate() {
+                let count = crate::CompactSize::new(input.witness.len() as u64);
+                dump.push(
+                    format!("witness[{}] items", i),
+                    &count.to_bytes(),
+                    count.value.to_string(),
+                );
+                for (j, item) in input.witness.iter().enumerate() {
+                    let len = crate::CompactSize::new(item.len() as u64);
+                    dump.push(
+                        format!("witness[{}][{}] length", i, j),
+                        &len.to_bytes(),
+                        len.value.to_string(),
+                    );
+                    if !item.is_empty() {
+                        dump.push(format!("witness[{}][{}]", i, j), item, "");
+                    }
+                }
+            }
+        }
+        dump.push(
+            "lock time",
+            &self.lock_time.to_le_bytes(),
+            self.lock_time.to_string(),
+        );
+        dump
+    }
+}
+
+impl Script {
+    /// Dumps the length-prefixed script one instruction per row.
+    pub fn hex_dump(&self) -> HexDump {
+        let mut dump = HexDump::new();
+        let prefix = crate::CompactSize::new(self.len() as u64).to_bytes();
+        dump.push("length", &prefix, self.len().to_string());
+        let mut pos = 0;
+        for instruction in self.instructions() {
+            let (end, value) = match instruction {
+                Ok(crate::script::Instruction::PushBytes(data)) => {
+                    let end = data.as_ptr() as usize - self.bytes.as_ptr() as usize + data.len();
+                    (end, format!("push {} bytes", data.len()))
+                }
+                Ok(crate::script::Instruction::Op(opcode)) => {
+                    (pos + 1, crate::opcodes::name(opcode).to_string())
+                }
+                Err(_) => (self.len(), "[error]".to_string()),
+            };
+            dump.push("script", &self.bytes[pos..end], value);
+            pos = end;
+        }
+        dump
+    }
+}
+
+impl crate::OutPoint {
+    pub fn hex_dump(&self) -> HexDump {
+        let mut dump = HexDump::new();
+        dump.push("txid", &self.txid.0, reversed_hex(&self.txid.0));
+        dump.push("vout", &self.vout.to_le_bytes(), self.vout.to_string());
+        dump
+    }
+}
@@ -0,0 +1,260 @@
+//! Golomb-Rice coded sets, as used by BIP158 compact block filters.
+
+use crate::hash_types::FilterHash;
+use crate::{BitcoinError, CompactSize};
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GcsParams {
+    /// Number of bits in the Golomb-Rice remainder.
+    pub p: u8,
+    /// Inverse false-positive rate; items are hashed into `[0, n * m)`.
+    pub m: u64,
+}
+
+/// Parameters of the BIP158 "basic" filter type.
+pub const BASIC_FILTER_PARAMS: GcsParams = GcsParams { p: 19, m: 784931 };
+
+impl GcsParams {
+    /// Fails with `InvalidFormat` unless `p` is below 64, the most a
+    /// remainder can hold.
+    pub fn new(p: u8, m: u64) -> Result<Self, BitcoinError> {
+        let params = Self { p, m };
+        params.check()?;
+        Ok(params)
+    }
+
+    fn check(&self) -> Result<(), BitcoinError> {
+        if self.p >= 64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(())
+    }
+
+    /// The hash range `[0, n * m)` for a set of `n` items.
+    fn range(&self, n: u64) -> Result<u64, BitcoinError> {
+        n.checked_mul(self.m).ok_or(BitcoinError::InvalidFormat)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GcsFilter {
+    pub params: GcsParams,
+    pub key: [u8; 16],
+    pub n: u64,
+    pub data: Vec<u8>,
+}
+
+impl GcsFilter {
+    /// Fails with `InvalidFormat` if `params` has `p` of 64 or more or
+    /// `items.len() * m` overflows.
+    pub fn new<T: AsRef<[u8]>>(
+        params: GcsParams,
+        key: [u8; 16],
+        items: &[T],
+    ) -> Result<Self, BitcoinError> {
+        params.check()?;
+        let n = items.len() as u64;
+        let range = params.range(n)?;
+        let mut values: Vec<u64> = items
+            .iter()
+            .map(|item| hash_to_range(&key, range, item.as_ref()))
+            .collect();
+        values.sort_unstable();
+
+        let mut writer = BitWriter::new();
+        let mut last = 0;
+        for value in values {
+            golomb_encode(&mut writer, value - last, params.p);
+            last = value;
+        }
+
+        Ok(Self {
+            params,
+            key,
+            n,
+            data: writer.finish(),
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = CompactSize::new(self.n).to_bytes();
+        v.extend_from_slice(&self.data);
+        v
+    }
+
+    /// Double SHA256 of the serialized filter, as chained into BIP157
+    /// filter headers.
+    pub fn filter_hash(&self) -> FilterHash {
+        FilterHash::hash(&self.to_bytes())
+    }
+
+    // The encoded set carries no length of its own, so the whole of `bytes`
+    // is taken as the filter.
+    pub fn from_bytes(
+        params: GcsParams,
+        key: [u8; 16],
+        bytes: &[u8],
+    ) -> Result<(Self, usize), BitcoinError> {
+        let (n, consumed) = CompactSize::from_bytes(bytes)?;
+        let filter = Self {
+            params,
+            key,
+            n: n.value,
+            data: bytes[consumed..].to_vec(),
+        };
+        Ok((filter, bytes.len()))
+    }
+
+    /// Decodes the sorted hashed values stored in the filter.
+    pub fn decode_values(&self) -> Result<Vec<u64>, BitcoinError> {
+        let mut reader = BitReader::new(&self.data);
+        let mut values = Vec::with_capacity(self.n.min(1 << 16) as usize);
+        let mut last: u64 = 0;
+        for _ in 0..self.n {
+            let delta = golomb_decode(&mut reader, self.params.p)?;
+            last = last.checked_add(delta).ok_or(BitcoinError::InvalidFormat)?;
+            values.push(last);
+        }
+        Ok(values)
+    }
+
+    pub fn matches(&self, item: &[u8]) -> Result<bool, BitcoinError> {
+        self.matches_any(&[item])
+    }
+
+    pub fn matches_any<T: AsRef<[u8]>>(&self, items: &[T]) -> Result<bool, BitcoinError> {
+        if self.n == 0 || items.is_empty() {
+            return Ok(false);
+        }
+        self.params.check()?;
+        let range = self.params.range(self.n)?;
+        let mut queries: Vec<u64> = items
+            .iter()
+            .map(|item| hash_to_range(&self.key, range, item.as_ref()))
+            .collect();
+        queries.sort_unstable();
+
+        // Walk both sorted sequences together, decoding lazily.
+        let mut queries = queries.into_iter().peekable();
+        let mut reader = BitReader::new(&self.data);
+        let mut value: u64 = 0;
+        for _ in 0..self.n {
+            let delta = golomb_decode(&mut reader, self.params.p)?;
+            value = value
+                .checked_add(delta)
+                .ok_or(BitcoinError::InvalidFormat)?;
+            while queries.next_if(|&query| query < value).is_some() {}
+            match queries.peek() {
+                None => return Ok(false),
+                Some(&query) if query == value => return Ok(true),
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+}
+
+pub fn hash_to_range(key: &[u8; 16], range: u64, item: &[u8]) -> u64 {
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(item);
+    ((hasher.finish() as u128 * range as u128) >> 64) as u64
+}
+
+fn golomb_encode(writer: &mut BitWriter, value: u64, p: u8) {
+    let mut quotient = value >> p;
+    while quotient > 0 {
+        writer.write_bit(true);
+        quotient -= 1;
+    }
+    writer.write_bit(false);
+    writer.write_bits(value, p);
+}
+
+fn golomb_decode(reader: &mut BitReader, p: u8) -> Result<u64, BitcoinError> {
+    let mut quotient: u64 = 0;
+    while reader.read_bit()? {
+        quotient += 1;
+    }
+    let remainder = reader.read_bits(p)?;
+    quotient
+        .checked_shl(p as u32)
+        .filter(|q| q >> p == quotient)
+        .map(|q| q + remainder)
+        .ok_or(BitcoinError::InvalidFormat)
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    used: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            used: 8,
+        }
+    }
+
+    fn write_bit(&mut self, bit: bool) {
+        if self.used == 8 {
+            self.bytes.push(0);
+            self.used = 0;
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> self.used;
+        }
+        self.used += 1;
+    }
+
+    fn write_bits(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> Result<bool, BitcoinError> {
+        let byte = self
+            .bytes
+            .get(self.position / 8)
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    fn read_bits(&mut self, count: u8) -> Result<u64, BitcoinError> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Ok(value)
+    }
+}
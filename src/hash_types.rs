@@ -0,0 +1,239 @@
+//! Typed hashes, so a block hash can't be passed where a wtxid is expected.
+//!
+//! Each type holds its bytes in internal order. `Display`, `FromStr` and
+//! human-readable serde use the conventional hex: byte-reversed for block,
+//! transaction and filter hashes, as stored for script and key hashes.
+//! `Txid` predates these and keeps its own serde form.
+//!
+//! A [`BlockHash`] always displays as 64 hex digits, most significant byte
+//! first, which is where a valid block's run of leading zeros shows up.
+//! Hex-encoding the raw bytes of
+//! [`BlockHeader::block_hash`](crate::BlockHeader::block_hash) instead puts
+//! them at the end.
+
+use crate::BitcoinError;
+use crate::hashes::{hash160, sha256, sha256d};
+use crate::hex_codec;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! hash_newtype {
+    ($(#[$attr:meta])* $name:ident, $len:expr, $hash:path, $reversed:expr) => {
+        $(#[$attr])*
+        #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+        pub struct $name(pub [u8; $len]);
+
+        impl $name {
+            pub const LEN: usize = $len;
+
+            /// Hashes `data` with this type's hash function.
+            pub fn hash(data: &[u8]) -> Self {
+                $name($hash(data))
+            }
+
+            pub fn from_slice(bytes: &[u8]) -> Result<Self, BitcoinError> {
+                let array = bytes.try_into().map_err(|_| BitcoinError::InvalidFormat)?;
+                Ok($name(array))
+            }
+
+            pub fn to_byte_array(self) -> [u8; $len] {
+                self.0
+            }
+
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if $reversed {
+                    f.write_str(&hex_codec::encode_reversed(&self.0))
+                } else {
+                    f.write_str(&hex_codec::encode(&self.0))
+                }
+            }
+        }
+
+        /// Shows the hex `Display` uses, so debug output reads in the
+        /// same byte order as explorers and RPC.
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = BitcoinError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut bytes = [0u8; $len];
+                hex_codec::decode_to_slice(s, &mut bytes)?;
+                if $reversed {
+                    bytes.reverse();
+                }
+                Ok($name(bytes))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    serializer.serialize_bytes(&self.0)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                if deserializer.is_human_readable() {
+                    let s = String::deserialize(deserializer)?;
+                    s.parse().map_err(serde::de::Error::custom)
+                } else {
+                    let bytes = <Vec<u8>>::deserialize(deserializer)?;
+                    $name::from_slice(&bytes).map_err(serde::de::Error::custom)
+                }
+            }
+        }
+    };
+}
+
+hash_newtype!(
+    /// Double SHA256 of a block header.
+    BlockHash, 32, sha256d, true
+);
+hash_newtype!(
+    /// Double SHA256 of a transaction including its witness.
+    Wtxid, 32, sha256d, true
+);
+hash_newtype!(
+    /// Double SHA256 of a transaction with its scriptSigs and witnesses
+    /// emptied, which third parties can't malleate.
+    Ntxid, 32, sha256d, true
+);
+hash_newtype!(
+    /// A node of a block's transaction merkle tree.
+    TxMerkleNode, 32, sha256d, true
+);
+hash_newtype!(
+    /// Double SHA256 of a serialized BIP158 filter.
+    FilterHash, 32, sha256d, true
+);
+hash_newtype!(
+    /// A BIP157 filter header, chaining filter hashes.
+    FilterHeader, 32, sha256d, true
+);
+hash_newtype!(
+    /// HASH160 of a P2SH redeem script.
+    ScriptHash, 20, hash160, false
+);
+hash_newtype!(
+    /// SHA256 of a P2WSH witness script.
+    WScriptHash, 32, sha256, false
+);
+hash_newtype!(
+    /// HASH160 of a public key.
+    PubkeyHash, 20, hash160, false
+);
+
+impl FilterHash {
+    /// The BIP157 header for this filter following `prev`.
+    pub fn filter_header(&self, prev: &FilterHeader) -> FilterHeader {
+        let mut data = self.0.to_vec();
+        data.extend_from_slice(&prev.0);
+        FilterHeader::hash(&data)
+    }
+}
+
+impl BlockHash {
+    /// Zero bits at the most significant end of the hash, as counted from
+    /// the left of its displayed hex.
+    pub fn leading_zero_bits(&self) -> u32 {
+        let mut bits = 0;
+        for &byte in self.0.iter().rev() {
+            bits += byte.leading_zeros();
+            if byte != 0 {
+                break;
+            }
+        }
+        bits
+    }
+
+    /// Hashes expected to find one at or below this one, `2^256 / (hash +
+    /// 1)`, as a little-endian 256-bit number saturating for the all-zero
+    /// hash. Compare
+    /// [`BlockHeader::work`](crate::BlockHeader::work), which uses the
+    /// target instead.
+    pub fn work(&self) -> [u8; 32] {
+        work(&self.0)
+    }
+}
+
+/// `2^256 / (x + 1)` for a little-endian 256-bit `x`, computed as
+/// `!x / (x + 1) + 1` like Core's `GetBlockProof` so nothing overflows.
+pub(crate) fn work(x: &[u8; 32]) -> [u8; 32] {
+    let x = to_limbs(x);
+    let Some(divisor) = add_one(x) else {
+        return from_limbs([1, 0, 0, 0]);
+    };
+    let dividend = x.map(|limb| !limb);
+    let mut quotient = [0u64; 4];
+    let mut remainder = [0u64; 4];
+    for bit in (0..256).rev() {
+        let carry = remainder[3] >> 63;
+        for i in (1..4).rev() {
+            remainder[i] = remainder[i] << 1 | remainder[i - 1] >> 63;
+        }
+        remainder[0] = remainder[0] << 1 | dividend[bit / 64] >> (bit % 64) & 1;
+        if carry == 1 || remainder.iter().rev().ge(divisor.iter().rev()) {
+            let mut borrow = false;
+            for i in 0..4 {
+                let (diff, b1) = remainder[i].overflowing_sub(divisor[i]);
+                let (diff, b2) = diff.overflowing_sub(borrow as u64);
+                remainder[i] = diff;
+                borrow = b1 || b2;
+            }
+            quotient[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    // Only x = 0 overflows, as 2^256 itself; saturate.
+    from_limbs(add_one(quotient).unwrap_or([u64::MAX; 4]))
+}
+
+fn to_limbs(bytes: &[u8; 32]) -> [u64; 4] {
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+fn from_limbs(limbs: [u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+fn add_one(mut limbs: [u64; 4]) -> Option<[u64; 4]> {
+    for limb in &mut limbs {
+        let (sum, carry) = limb.overflowing_add(1);
+        *limb = sum;
+        if !carry {
+            return Some(limbs);
+        }
+    }
+    None
+}
@@ -0,0 +1,25 @@
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+/// RIPEMD160 of SHA256, as used for P2PKH and P2SH.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(sha256(data)).into()
+}
+
+/// BIP340 tagged hash: `sha256(sha256(tag) || sha256(tag) || data)`.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag = sha256(tag.as_bytes());
+    let mut engine = Sha256::new();
+    engine.update(tag);
+    engine.update(tag);
+    engine.update(data);
+    engine.finalize().into()
+}
@@ -0,0 +1,82 @@
+//! Table-driven hex, for the paths where JSON export spends its time.
+//!
+//! Encoding looks up both digits of a byte at once; decoding looks up each
+//! digit and checks for invalid ones once per string rather than per byte.
+
+use crate::BitcoinError;
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+const INVALID: u8 = 0xff;
+
+static ENCODE: [[u8; 2]; 256] = {
+    let mut table = [[0; 2]; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = [DIGITS[i >> 4], DIGITS[i & 0xf]];
+        i += 1;
+    }
+    table
+};
+
+static DECODE: [u8; 256] = {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < 10 {
+        table[b'0' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        table[b'a' as usize + i] = 10 + i as u8;
+        table[b'A' as usize + i] = 10 + i as u8;
+        i += 1;
+    }
+    table
+};
+
+/// Lowercase hex of `bytes`.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = Vec::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.extend_from_slice(&ENCODE[b as usize]);
+    }
+    String::from_utf8(out).expect("hex digits are ascii")
+}
+
+/// Lowercase hex of `bytes` in reverse order, as hashes are displayed.
+pub fn encode_reversed(bytes: &[u8]) -> String {
+    let mut out = Vec::with_capacity(bytes.len() * 2);
+    for &b in bytes.iter().rev() {
+        out.extend_from_slice(&ENCODE[b as usize]);
+    }
+    String::from_utf8(out).expect("hex digits are ascii")
+}
+
+/// Decodes `s` into `out`, which must be exactly half its length. Either
+/// case is accepted.
+pub fn decode_to_slice(s: &str, out: &mut [u8]) -> Result<(), BitcoinError> {
+    let digits = s.as_bytes();
+    if digits.len() != out.len() * 2 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let mut invalid = 0;
+    for (byte, pair) in out.iter_mut().zip(digits.chunks_exact(2)) {
+        let (hi, lo) = (DECODE[pair[0] as usize], DECODE[pair[1] as usize]);
+        invalid |= hi | lo;
+        *byte = hi << 4 | lo;
+    }
+    if invalid == INVALID {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(())
+}
+
+/// Decodes a hex string of any even length.
+pub fn decode(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    if !s.len().is_multiple_of(2) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let mut out = vec![0; s.len() / 2];
+    decode_to_slice(s, &mut out)?;
+    Ok(out)
+}
@@ -0,0 +1,183 @@
+//! A scriptPubKey → outputs index over a block stream, the core of an
+//! explorer backend.
+//!
+//! Entries live in any [`KvStore`] under these keys:
+//!
+//! - `'O' || sha256(script) || outpoint`: value, height and, once spent,
+//!   the spending txid, input index and height;
+//! - `'S' || outpoint`: `sha256(script)`, to find the entry when spent;
+//! - `'T'`: the height of the last indexed block.
+//!
+//! Blocks must be indexed in order. Reorgs aren't handled; rebuild the
+//! index from the fork point instead.
+
+use crate::hashes::sha256;
+use crate::store::KvStore;
+use crate::{BitcoinError, Block, OutPoint, Script, Txid};
+
+/// The storage types moved to [`store`](crate::store); these names remain
+/// for existing users.
+pub use crate::store::{Entry, KvStore as IndexStore, MemoryStore};
+
+const OUTPUT_PREFIX: u8 = b'O';
+const SCRIPT_PREFIX: u8 = b'S';
+const TIP_KEY: &[u8] = b"T";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spend {
+    pub txid: Txid,
+    pub input: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexedOutput {
+    pub outpoint: OutPoint,
+    pub value: u64,
+    pub height: u32,
+    pub spent_by: Option<Spend>,
+}
+
+impl IndexedOutput {
+    fn value_bytes(&self) -> Vec<u8> {
+        let mut v = self.value.to_le_bytes().to_vec();
+        v.extend_from_slice(&self.height.to_le_bytes());
+        if let Some(spend) = &self.spent_by {
+            v.extend_from_slice(&spend.txid.0);
+            v.extend_from_slice(&spend.input.to_le_bytes());
+            v.extend_from_slice(&spend.height.to_le_bytes());
+        }
+        v
+    }
+
+    fn from_value_bytes(outpoint: OutPoint, bytes: &[u8]) -> Result<Self, BitcoinError> {
+        let spent_by = match bytes.len() {
+            12 => None,
+            52 => Some(Spend {
+                txid: Txid(bytes[12..44].try_into().unwrap()),
+                input: u32::from_le_bytes(bytes[44..48].try_into().unwrap()),
+                height: u32::from_le_bytes(bytes[48..52].try_into().unwrap()),
+            }),
+            _ => return Err(BitcoinError::InvalidFormat),
+        };
+        Ok(IndexedOutput {
+            outpoint,
+            value: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            height: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            spent_by,
+        })
+    }
+}
+
+pub struct ScriptIndex<S> {
+    store: S,
+}
+
+impl<S: KvStore> ScriptIndex<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// The height of the last indexed block, if any.
+    pub fn tip_height(&self) -> Result<Option<u32>, BitcoinError> {
+        self.store
+            .get(TIP_KEY)?
+            .map(|bytes| {
+                bytes
+                    .try_into()
+                    .map(u32::from_le_bytes)
+                    .map_err(|_| BitcoinError::InvalidFormat)
+            })
+            .transpose()
+    }
+
+    /// Records the outputs `block` creates and marks the indexed ones it
+    /// spends. `height` must follow the tip; the first block may be at any
+    /// height, and spends of outputs from before it are ignored.
+    pub fn index_block(&mut self, block: &Block, height: u32) -> Result<(), BitcoinError> {
+        if let Some(tip) = self.tip_height()?
+            && tip.checked_add(1) != Some(height)
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        for tx in &block.transactions {
+            let txid = tx.txid();
+            for (input, spent) in (0..).zip(&tx.inputs) {
+                let outpoint = &spent.previous_output;
+                if outpoint.is_null() {
+                    continue;
+                }
+                let Some(script_hash) = self.store.get(&script_key(outpoint))? else {
+                    continue;
+                };
+                let key = output_key(&script_hash, outpoint);
+                let value = self.store.get(&key)?.ok_or(BitcoinError::InvalidFormat)?;
+                let mut output = IndexedOutput::from_value_bytes(outpoint.clone(), &value)?;
+                output.spent_by = Some(Spend {
+                    txid: txid.clone(),
+                    input,
+                    height,
+                });
+                self.store.put(&key, &output.value_bytes())?;
+            }
+            for (vout, created) in (0..).zip(&tx.outputs) {
+                let outpoint = OutPoint::new(txid.0, vout);
+                let script_hash = sha256(&created.script_pubkey.bytes);
+                let output = IndexedOutput {
+                    outpoint: outpoint.clone(),
+                    value: created.value,
+                    height,
+                    spent_by: None,
+                };
+                self.store
+                    .put(&output_key(&script_hash, &outpoint), &output.value_bytes())?;
+                self.store.put(&script_key(&outpoint), &script_hash)?;
+            }
+        }
+        self.store.put(TIP_KEY, &height.to_le_bytes())
+    }
+
+    /// Every indexed output paying to `script`, spent or not, in outpoint
+    /// order.
+    pub fn outputs(&self, script: &Script) -> Result<Vec<IndexedOutput>, BitcoinError> {
+        let prefix = [&[OUTPUT_PREFIX][..], &sha256(&script.bytes)].concat();
+        self.store
+            .scan_prefix(&prefix)?
+            .into_iter()
+            .map(|(key, value)| {
+                let (outpoint, _) = OutPoint::from_bytes(&key[prefix.len()..])?;
+                IndexedOutput::from_value_bytes(outpoint, &value)
+            })
+            .collect()
+    }
+
+    pub fn unspent(&self, script: &Script) -> Result<Vec<IndexedOutput>, BitcoinError> {
+        let mut outputs = self.outputs(script)?;
+        outputs.retain(|output| output.spent_by.is_none());
+        Ok(outputs)
+    }
+
+    pub fn balance(&self, script: &Script) -> Result<u64, BitcoinError> {
+        Ok(self
+            .unspent(script)?
+            .iter()
+            .map(|output| output.value)
+            .sum())
+    }
+}
+
+fn output_key(script_hash: &[u8], outpoint: &OutPoint) -> Vec<u8> {
+    [&[OUTPUT_PREFIX][..], script_hash, &outpoint.to_bytes()].concat()
+}
+
+fn script_key(outpoint: &OutPoint) -> Vec<u8> {
+    [&[SCRIPT_PREFIX][..], &outpoint.to_bytes()].concat()
+}
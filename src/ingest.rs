@@ -0,0 +1,182 @@
+//! Pipelined reading of block files: one thread reads records, a pool of
+//! threads decodes them and hashes their transactions, and the caller's
+//! thread runs the callback, all at once. Bounded channels between the
+//! stages keep memory flat when the callback is the slowest part.
+//!
+//! Blocks reach the callback in file order, as [`BlockFileReader`] would
+//! yield them.
+
+use crate::blockfile::BlockFileReader;
+use crate::cancel::CancellationToken;
+use crate::progress::{ProgressObserver, ProgressTracker};
+use crate::{BitcoinError, Block, Network, Txid};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IngestOptions {
+    /// Threads decoding blocks and computing txids.
+    pub decode_threads: usize,
+    /// Blocks buffered between each pair of stages.
+    pub queue_len: usize,
+    /// Checked before reading and before delivering each block.
+    pub cancel: CancellationToken,
+}
+
+impl Default for IngestOptions {
+    /// One decoding thread per core and a queue of 16 blocks.
+    fn default() -> Self {
+        Self {
+            decode_threads: thread::available_parallelism().map_or(4, |n| n.get()),
+            queue_len: 16,
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+/// A decoded block with its transactions' txids, in block order.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IngestedBlock {
+    /// Index of the block in the whole run, counting from zero.
+    pub index: u64,
+    pub block: Block,
+    pub txids: Vec<Txid>,
+}
+
+type Sequenced<T> = (u64, Result<T, BitcoinError>);
+
+// Records and blocks travel with the run's byte position after them.
+type Positioned<T> = Sequenced<(T, u64)>;
+
+/// Feeds every block in `paths` to `callback`. Stops at the first read,
+/// decode or callback error, or on cancellation, and returns it after the
+/// other threads have wound down.
+pub fn ingest<P, F>(
+    paths: &[P],
+    network: Network,
+    options: &IngestOptions,
+    callback: F,
+) -> Result<(), BitcoinError>
+where
+    P: AsRef<Path> + Sync,
+    F: FnMut(IngestedBlock) -> Result<(), BitcoinError>,
+{
+    ingest_with_progress(paths, network, options, (), callback)
+}
+
+/// [`ingest`], reporting to `observer` as each block reaches the callback.
+/// The total is the combined size of `paths`, if they all exist.
+pub fn ingest_with_progress<P, O, F>(
+    paths: &[P],
+    network: Network,
+    options: &IngestOptions,
+    mut observer: O,
+    mut callback: F,
+) -> Result<(), BitcoinError>
+where
+    P: AsRef<Path> + Sync,
+    O: ProgressObserver,
+    F: FnMut(IngestedBlock) -> Result<(), BitcoinError>,
+{
+    // A missing file surfaces as an error in its turn, not up front.
+    let mut tracker =
+        ProgressTracker::for_files(paths).unwrap_or_else(|_| ProgressTracker::new(None));
+    let queue_len = options.queue_len.max(1);
+    let (raw_tx, raw_rx) = sync_channel::<Positioned<Vec<u8>>>(queue_len);
+    let (decoded_tx, decoded_rx) = sync_channel::<Positioned<IngestedBlock>>(queue_len);
+    let cancel = &options.cancel;
+    let raw_rx = Arc::new(Mutex::new(raw_rx));
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut index = 0;
+            let mut done_bytes = 0;
+            for path in paths {
+                let mut reader = match BlockFileReader::open(path, network) {
+                    Ok(reader) => reader,
+                    Err(err) => {
+                        let _ = raw_tx.send((index, Err(err)));
+                        return;
+                    }
+                };
+                loop {
+                    if cancel.is_cancelled() {
+                        let _ = raw_tx.send((index, Err(BitcoinError::Cancelled)));
+                        return;
+                    }
+                    let record = match reader.next_raw() {
+                        Ok(Some(raw)) => Ok((raw, done_bytes + reader.position())),
+                        Ok(None) => break,
+                        Err(err) => Err(err),
+                    };
+                    let failed = record.is_err();
+                    // A closed channel means the run was stopped.
+                    if raw_tx.send((index, record)).is_err() || failed {
+                        return;
+                    }
+                    index += 1;
+                }
+                done_bytes += reader.position();
+            }
+        });
+
+        for _ in 0..options.decode_threads.max(1) {
+            let raw_rx = Arc::clone(&raw_rx);
+            let decoded_tx = decoded_tx.clone();
+            scope.spawn(move || {
+                loop {
+                    let next = raw_rx.lock().expect("no panics holding the lock").recv();
+                    let Ok((index, record)) = next else {
+                        return;
+                    };
+                    let decoded = record.and_then(|(raw, position)| {
+                        let (block, _) = Block::from_bytes(&raw)?;
+                        let txids = block.transactions.iter().map(|tx| tx.txid()).collect();
+                        let ingested = IngestedBlock {
+                            index,
+                            block,
+                            txids,
+                        };
+                        Ok((ingested, position))
+                    });
+                    if decoded_tx.send((index, decoded)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop((raw_rx, decoded_tx));
+
+        deliver_in_order(decoded_rx, |ingested, position| {
+            cancel.check()?;
+            callback(ingested)?;
+            observer.on_progress(&tracker.block_parsed(position));
+            Ok(())
+        })
+    })
+}
+
+/// Runs `callback` on results in index order. Returning drops `results`,
+/// which stops the decoding threads and, through them, the reader.
+fn deliver_in_order<F>(
+    results: Receiver<Positioned<IngestedBlock>>,
+    mut callback: F,
+) -> Result<(), BitcoinError>
+where
+    F: FnMut(IngestedBlock, u64) -> Result<(), BitcoinError>,
+{
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (index, result) in results {
+        pending.insert(index, result);
+        while let Some(result) = pending.remove(&next) {
+            let (ingested, position) = result?;
+            callback(ingested, position)?;
+            next += 1;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! Best-effort decoding for corrupt dumps: instead of failing at the first
+//! error, keep whatever decoded and record where and why decoding stopped.
+//!
+//! Lengths aren't self-synchronizing, so most errors end the parse; only
+//! problems that leave the position known, such as a bad segwit flag, are
+//! skipped over.
+
+use crate::block::BlockHeader;
+use crate::{
+    BitcoinError, BitcoinTransaction, Block, CompactSize, TransactionInput, TransactionOutput,
+    Witness,
+};
+
+/// One problem found while decoding.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    /// Byte offset of the field into the input.
+    pub offset: usize,
+    /// The field being decoded, such as `transactions[2].inputs[0]`.
+    pub field: String,
+    pub reason: BitcoinError,
+}
+
+/// The decoded value, with any field that couldn't be decoded left empty
+/// or zeroed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PartialParse<T> {
+    pub value: T,
+    /// Bytes decoded before the parse stopped, or all of them if it didn't.
+    pub consumed: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T> PartialParse<T> {
+    /// True if nothing went wrong, in which case `value` is what
+    /// `from_bytes` would have returned.
+    pub fn is_complete(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Reader<'_> {
+    fn note(&mut self, field: String, reason: BitcoinError) {
+        self.diagnostics.push(Diagnostic {
+            offset: self.cursor,
+            field,
+            reason,
+        });
+    }
+
+    /// Decodes with `decode` at the cursor, advancing past it on success
+    /// and noting `field` on failure.
+    fn read<T>(
+        &mut self,
+        field: impl FnOnce() -> String,
+        decode: impl FnOnce(&[u8]) -> Result<(T, usize), BitcoinError>,
+    ) -> Option<T> {
+        match decode(&self.bytes[self.cursor..]) {
+            Ok((value, used)) => {
+                self.cursor += used;
+                Some(value)
+            }
+            Err(reason) => {
+                self.note(field(), reason);
+                None
+            }
+        }
+    }
+
+    fn read_u32(&mut self, field: impl FnOnce() -> String) -> Option<u32> {
+        self.read(field, |bytes| match bytes.first_chunk::<4>() {
+            Some(word) => Ok((u32::from_le_bytes(*word), 4)),
+            None => Err(BitcoinError::InsufficientBytes),
+        })
+    }
+
+    /// Fills `tx` field by field, returning false if it had to stop early.
+    fn transaction(&mut self, prefix: &str, tx: &mut BitcoinTransaction) -> bool {
+        let Some(version) = self.read_u32(|| format!("{prefix}version")) else {
+            return false;
+        };
+        tx.version = version;
+        let Some(mut input_count) =
+            self.read(|| format!("{prefix}input_count"), CompactSize::from_bytes)
+        else {
+            return false;
+        };
+        let segwit = input_count.value == 0 && self.bytes.get(self.cursor).is_some_and(|&b| b != 0);
+        if segwit {
+            if self.bytes[self.cursor] != 0x01 {
+                self.note(format!("{prefix}flag"), BitcoinError::InvalidFormat);
+            }
+            self.cursor += 1;
+            match self.read(|| format!("{prefix}input_count"), CompactSize::from_bytes) {
+                Some(count) => input_count = count,
+                None => return false,
+            }
+        }
+        for i in 0..input_count.value {
+            match self.read(
+                || format!("{prefix}inputs[{i}]"),
+                TransactionInput::from_bytes,
+            ) {
+                Some(input) => tx.inputs.push(input),
+                None => return false,
+            }
+        }
+        let Some(output_count) =
+            self.read(|| format!("{prefix}output_count"), CompactSize::from_bytes)
+        else {
+            return false;
+        };
+        for i in 0..output_count.value {
+            match self.read(
+                || format!("{prefix}outputs[{i}]"),
+                TransactionOutput::from_bytes,
+            ) {
+                Some(output) => tx.outputs.push(output),
+                None => return false,
+            }
+        }
+        if segwit {
+            for (i, input) in tx.inputs.iter_mut().enumerate() {
+                match self.read(
+                    || format!("{prefix}inputs[{i}].witness"),
+                    Witness::from_bytes,
+                ) {
+                    Some(witness) => input.witness = witness,
+                    None => return false,
+                }
+            }
+        }
+        let Some(lock_time) = self.read_u32(|| format!("{prefix}lock_time")) else {
+            return false;
+        };
+        tx.lock_time = lock_time;
+        true
+    }
+}
+
+impl BitcoinTransaction {
+    /// Decodes as much of a transaction as `bytes` allows, like
+    /// [`from_bytes`](Self::from_bytes) but reporting errors as diagnostics
+    /// alongside the fields read before them.
+    pub fn parse_lenient(bytes: &[u8]) -> PartialParse<BitcoinTransaction> {
+        let mut reader = Reader {
+            bytes,
+            cursor: 0,
+            diagnostics: Vec::new(),
+        };
+        let mut tx = BitcoinTransaction::new(0, Vec::new(), 0);
+        reader.transaction("", &mut tx);
+        PartialParse {
+            value: tx,
+            consumed: reader.cursor,
+            diagnostics: reader.diagnostics,
+        }
+    }
+}
+
+impl Block {
+    /// Decodes as much of a block as `bytes` allows. A transaction cut
+    /// short is kept with the fields it got, if any; the header is zeroed
+    /// if it is incomplete.
+    pub fn parse_lenient(bytes: &[u8]) -> PartialParse<Block> {
+        let mut reader = Reader {
+            bytes,
+            cursor: 0,
+            diagnostics: Vec::new(),
+        };
+        let mut block = Block::new(BlockHeader::new(0, [0; 32], [0; 32], 0, 0, 0), Vec::new());
+        if let Some(header) = reader.read(|| "header".to_string(), BlockHeader::from_bytes) {
+            block.header = header;
+            if let Some(count) = reader.read(|| "tx_count".to_string(), CompactSize::from_bytes) {
+                for i in 0..count.value {
+                    let start = reader.cursor;
+                    let mut tx = BitcoinTransaction::new(0, Vec::new(), 0);
+                    let complete = reader.transaction(&format!("transactions[{i}]."), &mut tx);
+                    if reader.cursor > start {
+                        block.transactions.push(tx);
+                    }
+                    if !complete {
+                        break;
+                    }
+                }
+            }
+        }
+        PartialParse {
+            value: block,
+            consumed: reader.cursor,
+            diagnostics: reader.diagnostics,
+        }
+    }
+}
@@ -1,16 +1,166 @@
+pub mod address;
+pub mod base58;
+pub mod bech32;
+pub mod bip32;
+pub mod bip39;
+pub mod block;
+pub mod blockfile;
+pub mod builder;
+pub mod cache;
+pub mod cancel;
+pub mod chainstate;
+pub mod change;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod compress;
+pub mod consensus;
+pub mod constants;
+pub mod core_json;
+pub mod cpfp;
+pub mod csv;
+pub mod ctv;
+pub mod descriptor;
+pub mod diff;
+pub mod dot;
+pub mod electrum;
+pub mod encode;
+pub mod fee;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod gcs;
+pub mod hash_types;
+pub mod hashes;
+mod hex_codec;
+pub mod index;
+pub mod ingest;
+pub mod lenient;
+pub mod locktime;
+pub mod mining;
+pub mod miniscript;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod muhash;
+pub mod network;
+pub mod opcodes;
+pub mod policy;
+pub mod progress;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod psbt;
+pub mod rbf;
+pub mod rescan;
+#[cfg(feature = "http")]
+pub mod rest;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "schemars")]
+pub mod schema;
+pub mod script;
+pub mod sighash;
+pub mod signet;
+#[cfg(feature = "secp")]
+pub mod sigverify;
+#[cfg(feature = "secp")]
+pub mod silent_payments;
+pub mod size;
+pub mod snapshot;
+pub mod spv;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod sv2;
+pub mod taproot;
+pub mod undo;
+#[cfg(feature = "vectors")]
+pub mod vectors;
+pub mod versionbits;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod witness_commitment;
+pub mod witness_program;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+pub use block::{Block, BlockHeader, BlockView, TransactionView};
+pub use builder::TransactionBuilder;
+pub use hash_types::{BlockHash, Ntxid, Wtxid};
+pub use network::Network;
+
+use constants::{SEQUENCE_FINAL, WITNESS_SCALE_FACTOR};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::Deref;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct CompactSize {
     pub value: u64,
 }
 
+/// Core's MSB base-128 `VARINT`, used in the chainstate database and undo
+/// files. Not interchangeable with [`CompactSize`], the length prefix used
+/// in blocks and transactions.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct VarInt {
+    pub value: u64,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    Io(std::io::ErrorKind),
+    Unsupported,
+    InvalidTransaction(&'static str),
+    Http(String),
+    Rpc {
+        code: i64,
+        message: String,
+    },
+    MissingPrevout(OutPoint),
+    Address(address::AddressError),
+    /// A complete value was decoded with this many bytes left over.
+    TrailingBytes(usize),
+    Database(String),
+    /// Stopped through a [`cancel::CancellationToken`].
+    Cancelled,
+    /// A decoded length doesn't fit in `usize` on this target.
+    NumericOverflow,
+}
+
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinError::InsufficientBytes => write!(f, "insufficient bytes"),
+            BitcoinError::InvalidFormat => write!(f, "invalid format"),
+            BitcoinError::Io(kind) => write!(f, "i/o error: {}", kind),
+            BitcoinError::Unsupported => write!(f, "unsupported (missing crate feature?)"),
+            BitcoinError::InvalidTransaction(reason) => {
+                write!(f, "invalid transaction: {}", reason)
+            }
+            BitcoinError::Http(message) => write!(f, "http error: {}", message),
+            BitcoinError::Rpc { code, message } => write!(f, "rpc error {}: {}", code, message),
+            BitcoinError::MissingPrevout(outpoint) => {
+                write!(f, "missing previous output {}", outpoint)
+            }
+            BitcoinError::Address(err) => write!(f, "invalid address: {}", err),
+            BitcoinError::TrailingBytes(count) => write!(f, "{} trailing bytes", count),
+            BitcoinError::Database(message) => write!(f, "database error: {}", message),
+            BitcoinError::Cancelled => write!(f, "cancelled"),
+            BitcoinError::NumericOverflow => write!(f, "numeric overflow"),
+        }
+    }
+}
+
+impl std::error::Error for BitcoinError {}
+
+impl From<std::io::Error> for BitcoinError {
+    fn from(err: std::io::Error) -> Self {
+        BitcoinError::Io(err.kind())
+    }
 }
 
 impl CompactSize {
@@ -18,23 +168,32 @@ impl CompactSize {
         Self { value }
     }
 
+    /// The value as a length, failing rather than truncating where `usize`
+    /// is narrower than 64 bits, as on wasm32.
+    pub fn to_usize(&self) -> Result<usize, BitcoinError> {
+        usize::try_from(self.value).map_err(|_| BitcoinError::NumericOverflow)
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(9);
+        self.encode_to(&mut v);
+        v
+    }
+
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
         match self.value {
-            0..=252 => vec![self.value as u8],
+            0..=252 => out.push(self.value as u8),
             253..=0xffff => {
-                let mut v = vec![0xfd];
-                v.extend_from_slice(&(self.value as u16).to_le_bytes());
-                v
+                out.push(0xfd);
+                out.extend_from_slice(&(self.value as u16).to_le_bytes());
             }
             0x10000..=0xffff_ffff => {
-                let mut v = vec![0xfe];
-                v.extend_from_slice(&(self.value as u32).to_le_bytes());
-                v
+                out.push(0xfe);
+                out.extend_from_slice(&(self.value as u32).to_le_bytes());
             }
             _ => {
-                let mut v = vec![0xff];
-                v.extend_from_slice(&(self.value as u64).to_le_bytes());
-                v
+                out.push(0xff);
+                out.extend_from_slice(&self.value.to_le_bytes());
             }
         }
     }
@@ -73,7 +232,42 @@ impl CompactSize {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl VarInt {
+    pub fn new(value: u64) -> Self {
+        Self { value }
+    }
+
+    /// Seven bits per byte, most significant group first, with the high bit
+    /// marking a continuation. Each continuation also adds one, so every
+    /// value has exactly one encoding.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut n = self.value;
+        let mut v = vec![(n & 0x7f) as u8];
+        while n > 0x7f {
+            n = (n >> 7) - 1;
+            v.push((n & 0x7f) as u8 | 0x80);
+        }
+        v.reverse();
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut n: u64 = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            if n > u64::MAX >> 7 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            n = (n << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                return Ok((VarInt::new(n), i + 1));
+            }
+            n = n.checked_add(1).ok_or(BitcoinError::InvalidFormat)?;
+        }
+        Err(BitcoinError::InsufficientBytes)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Txid(pub [u8; 32]);
 
 impl Serialize for Txid {
@@ -81,7 +275,7 @@ impl Serialize for Txid {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&hex::encode(&self.0))
+        serializer.serialize_str(&hex_codec::encode(&self.0))
     }
 }
 
@@ -91,17 +285,17 @@ impl<'de> Deserialize<'de> for Txid {
         D: serde::Deserializer<'de>,
     {
         let hex_str = String::deserialize(deserializer)?;
-        let bytes = hex::decode(&hex_str).map_err(serde::de::Error::custom)?;
-        if bytes.len() != 32 {
+        if hex_str.len() != 64 {
             return Err(serde::de::Error::custom("Txid must be 32 bytes"));
         }
         let mut arr = [0u8; 32];
-        arr.copy_from_slice(&bytes);
+        hex_codec::decode_to_slice(&hex_str, &mut arr).map_err(serde::de::Error::custom)?;
         Ok(Txid(arr))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -115,12 +309,26 @@ impl OutPoint {
         }
     }
 
+    /// The all-zero outpoint with index `0xFFFFFFFF` spent by coinbases.
+    pub fn null() -> Self {
+        Self::new([0; 32], u32::MAX)
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.txid.0 == [0; 32] && self.vout == u32::MAX
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.txid.0.to_vec();
-        v.extend_from_slice(&self.vout.to_le_bytes());
+        let mut v = Vec::with_capacity(36);
+        self.encode_to(&mut v);
         v
     }
 
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.txid.0);
+        out.extend_from_slice(&self.vout.to_le_bytes());
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         if bytes.len() < 36 {
             return Err(BitcoinError::InsufficientBytes);
@@ -132,7 +340,16 @@ impl OutPoint {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}", self.hex_dump());
+        }
+        write!(f, "{}:{}", core_json::reversed_hex(&self.txid.0), self.vout)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Script {
     pub bytes: Vec<u8>,
 }
@@ -143,20 +360,102 @@ impl Script {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        v.extend_from_slice(&self.bytes);
+        let mut v = Vec::new();
+        self.encode_to(&mut v);
         v
     }
 
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        CompactSize::new(self.bytes.len() as u64).encode_to(out);
+        out.extend_from_slice(&self.bytes);
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         let (len_prefix, consumed) = CompactSize::from_bytes(bytes)?;
-        let len = len_prefix.value as usize;
-        if bytes.len() < consumed + len {
+        let len = len_prefix.to_usize()?;
+        if bytes.len() - consumed < len {
             return Err(BitcoinError::InsufficientBytes);
         }
         let data = bytes[consumed..consumed + len].to_vec();
         Ok((Script::new(data), consumed + len))
     }
+
+    pub fn script_type(&self) -> ScriptType {
+        if let Some(program) = self.witness_program() {
+            return if program.is_p2wpkh() {
+                ScriptType::P2wpkh
+            } else if program.is_p2wsh() {
+                ScriptType::P2wsh
+            } else if program.is_p2tr() {
+                ScriptType::P2tr
+            } else if program.is_p2a() {
+                ScriptType::Anchor
+            } else {
+                ScriptType::WitnessUnknown
+            };
+        }
+        let b = &self.bytes[..];
+        match b {
+            [0x76, 0xa9, 0x14, .., 0x88, 0xac] if b.len() == 25 => ScriptType::P2pkh,
+            [0xa9, 0x14, .., 0x87] if b.len() == 23 => ScriptType::P2sh,
+            [0x21, 0x02 | 0x03, .., 0xac] if b.len() == 35 => ScriptType::P2pk,
+            [0x41, 0x04, .., 0xac] if b.len() == 67 => ScriptType::P2pk,
+            // As Core's `Solver`, only pushes may follow the OP_RETURN.
+            [0x6a, rest @ ..] if Script::new(rest.to_vec()).is_push_only() => ScriptType::NullData,
+            _ if self.parse_multisig().is_some() => ScriptType::Multisig,
+            _ => ScriptType::NonStandard,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    /// Pay-to-anchor, `OP_1 <0x4e73>`.
+    Anchor,
+    WitnessUnknown,
+    Multisig,
+    NullData,
+    NonStandard,
+}
+
+impl ScriptType {
+    /// The name Bitcoin Core uses in RPC `scriptPubKey.type` fields.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptType::P2pk => "pubkey",
+            ScriptType::P2pkh => "pubkeyhash",
+            ScriptType::P2sh => "scripthash",
+            ScriptType::P2wpkh => "witness_v0_keyhash",
+            ScriptType::P2wsh => "witness_v0_scripthash",
+            ScriptType::P2tr => "witness_v1_taproot",
+            ScriptType::Anchor => "anchor",
+            ScriptType::WitnessUnknown => "witness_unknown",
+            ScriptType::Multisig => "multisig",
+            ScriptType::NullData => "nulldata",
+            ScriptType::NonStandard => "nonstandard",
+        }
+    }
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}", self.hex_dump());
+        }
+        f.write_str(&self.to_asm())
+    }
 }
 
 impl Deref for Script {
@@ -166,11 +465,199 @@ impl Deref for Script {
     }
 }
 
+/// A hex string in human-readable formats, as in Core's JSON; raw bytes
+/// otherwise.
+impl Serialize for Script {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex_codec::encode(&self.bytes))
+        } else {
+            serializer.serialize_bytes(&self.bytes)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Script {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            hex_codec::decode(&s)
+                .map(Script::new)
+                .map_err(serde::de::Error::custom)
+        } else {
+            <Vec<u8>>::deserialize(deserializer).map(Script::new)
+        }
+    }
+}
+
+/// A script behind a reference count, so clones share the bytes. For
+/// long-lived copies, such as mempool entries, where deep-copying every
+/// [`Script`] adds up.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct SharedScript(Arc<[u8]>);
+
+impl SharedScript {
+    pub fn to_script(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        self.to_script().script_type()
+    }
+}
+
+impl Deref for SharedScript {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SharedScript {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Script> for SharedScript {
+    fn from(script: Script) -> Self {
+        SharedScript(script.bytes.into())
+    }
+}
+
+impl From<&Script> for SharedScript {
+    fn from(script: &Script) -> Self {
+        SharedScript(script.bytes.as_slice().into())
+    }
+}
+
+impl From<SharedScript> for Script {
+    fn from(script: SharedScript) -> Self {
+        script.to_script()
+    }
+}
+
+impl PartialEq<Script> for SharedScript {
+    fn eq(&self, other: &Script) -> bool {
+        self[..] == other.bytes[..]
+    }
+}
+
+impl fmt::Display for SharedScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_script(), f)
+    }
+}
+
+impl Serialize for SharedScript {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex_codec::encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedScript {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Script::deserialize(deserializer).map(SharedScript::from)
+    }
+}
+
+/// A script stored inline when it is at most 34 bytes, as every standard
+/// output but bare P2PK and multisig is, saving an allocation per output
+/// for code that keeps many short scripts. Converts to and from [`Script`].
+#[cfg(feature = "smallvec")]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Default)]
+pub struct InlineScript(smallvec::SmallVec<[u8; 34]>);
+
+#[cfg(feature = "smallvec")]
+impl InlineScript {
+    pub fn new(bytes: &[u8]) -> Self {
+        InlineScript(smallvec::SmallVec::from_slice(bytes))
+    }
+
+    /// Parses a length-prefixed script, as [`Script::from_bytes`] does.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (len_prefix, consumed) = CompactSize::from_bytes(bytes)?;
+        let len = len_prefix.to_usize()?;
+        if bytes.len() - consumed < len {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        Ok((Self::new(&bytes[consumed..consumed + len]), consumed + len))
+    }
+
+    /// True if the bytes live inline rather than on the heap.
+    pub fn is_inline(&self) -> bool {
+        !self.0.spilled()
+    }
+
+    pub fn to_script(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        self.to_script().script_type()
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl Deref for InlineScript {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl AsRef<[u8]> for InlineScript {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl From<&Script> for InlineScript {
+    fn from(script: &Script) -> Self {
+        InlineScript::new(&script.bytes)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl From<InlineScript> for Script {
+    fn from(script: InlineScript) -> Self {
+        Script::new(script.0.into_vec())
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl PartialEq<Script> for InlineScript {
+    fn eq(&self, other: &Script) -> bool {
+        self[..] == other.bytes[..]
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TransactionInput {
     pub previous_output: OutPoint,
     pub script_sig: Script,
     pub sequence: u32,
+    #[serde(default)]
+    pub witness: Witness,
 }
 
 impl TransactionInput {
@@ -179,16 +666,43 @@ impl TransactionInput {
             previous_output,
             script_sig,
             sequence,
+            witness: Witness::default(),
         }
     }
 
+    /// A coinbase input: the null outpoint and a final sequence.
+    pub fn coinbase(script_sig: Script) -> Self {
+        Self::new(OutPoint::null(), script_sig, SEQUENCE_FINAL)
+    }
+
+    pub fn with_witness(mut self, witness: Witness) -> Self {
+        self.witness = witness;
+        self
+    }
+
+    pub fn set_witness(&mut self, witness: Witness) {
+        self.witness = witness;
+    }
+
+    pub fn has_witness(&self) -> bool {
+        !self.witness.is_empty()
+    }
+
+    // The witness is not part of the input's own encoding; it is written
+    // after all outputs by `BitcoinTransaction::to_bytes`.
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.previous_output.to_bytes();
-        v.extend(self.script_sig.to_bytes());
-        v.extend_from_slice(&self.sequence.to_le_bytes());
+        let mut v = Vec::new();
+        self.encode_to(&mut v);
         v
     }
 
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        self.previous_output.encode_to(out);
+        self.script_sig.encode_to(out);
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         // TODO: Deserialize in order:
         let (outpoint, used1) = OutPoint::from_bytes(bytes)?;
@@ -209,10 +723,102 @@ impl TransactionInput {
     }
 }
 
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Witness {
+    pub items: Vec<Vec<u8>>,
+}
+
+impl Witness {
+    pub fn new(items: Vec<Vec<u8>>) -> Self {
+        Self { items }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::new();
+        self.encode_to(&mut v);
+        v
+    }
+
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        CompactSize::new(self.items.len() as u64).encode_to(out);
+        for item in &self.items {
+            CompactSize::new(item.len() as u64).encode_to(out);
+            out.extend_from_slice(item);
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (count, mut cursor) = CompactSize::from_bytes(bytes)?;
+        let mut items = Vec::new();
+        for _ in 0..count.value {
+            let (len_prefix, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+            let len = len_prefix.to_usize()?;
+            cursor += consumed;
+            if bytes.len() - cursor < len {
+                return Err(BitcoinError::InsufficientBytes);
+            }
+            items.push(bytes[cursor..cursor + len].to_vec());
+            cursor += len;
+        }
+        Ok((Witness::new(items), cursor))
+    }
+}
+
+impl Deref for Witness {
+    type Target = Vec<Vec<u8>>;
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::new();
+        self.encode_to(&mut v);
+        v
+    }
+
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.value.to_le_bytes());
+        self.script_pubkey.encode_to(out);
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 8 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&bytes[..8]);
+        let (script_pubkey, used) = Script::from_bytes(&bytes[8..])?;
+        Ok((
+            TransactionOutput::new(u64::from_le_bytes(value), script_pubkey),
+            8 + used,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    #[serde(default)]
+    pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
 }
 
@@ -221,33 +827,178 @@ impl BitcoinTransaction {
         Self {
             version,
             inputs,
+            outputs: Vec::new(),
             lock_time,
         }
     }
 
+    /// A transaction with no inputs or outputs and a zero lock time, to
+    /// fill in field by field.
+    pub fn empty(version: u32) -> Self {
+        Self::new(version, Vec::new(), 0)
+    }
+
+    pub fn with_outputs(mut self, outputs: Vec<TransactionOutput>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    /// True if any input has a witness; `to_bytes` then uses the segwit
+    /// encoding.
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(TransactionInput::has_witness)
+    }
+
+    /// Replaces the witness of input `index`.
+    pub fn set_witness(&mut self, index: usize, witness: Witness) -> Result<(), BitcoinError> {
+        self.inputs
+            .get_mut(index)
+            .ok_or(BitcoinError::InvalidFormat)?
+            .set_witness(witness);
+        Ok(())
+    }
+
+    /// Empties every witness, leaving a transaction that serializes in the
+    /// legacy format.
+    pub fn clear_witnesses(&mut self) {
+        for input in &mut self.inputs {
+            input.witness = Witness::default();
+        }
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    // Uses the BIP144 marker/flag encoding when any input carries a witness.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.version.to_le_bytes().to_vec();
-        v.extend(CompactSize::new(self.inputs.len() as u64).to_bytes());
-        for input in &self.inputs {
-            v.extend(input.to_bytes());
+        self.serialize(self.has_witness())
+    }
+
+    pub fn to_bytes_without_witness(&self) -> Vec<u8> {
+        self.serialize(false)
+    }
+
+    /// Appends the encoding `to_bytes` returns.
+    pub fn encode_to(&self, out: &mut Vec<u8>) {
+        self.encode_with(out, self.has_witness());
+    }
+
+    pub fn encode_without_witness_to(&self, out: &mut Vec<u8>) {
+        self.encode_with(out, false);
+    }
+
+    pub fn txid(&self) -> Txid {
+        Txid(hashes::sha256d(&self.to_bytes_without_witness()))
+    }
+
+    pub fn wtxid(&self) -> Wtxid {
+        Wtxid::hash(&self.to_bytes())
+    }
+
+    /// The normalized txid Core once used to spot malleated copies of a
+    /// transaction: equal for any two versions differing only in
+    /// signatures and other unlocking data.
+    pub fn ntxid(&self) -> Ntxid {
+        let mut normalized = self.clone();
+        for input in &mut normalized.inputs {
+            input.script_sig = Script::new(Vec::new());
         }
-        v.extend(&self.lock_time.to_le_bytes());
+        Ntxid::hash(&normalized.to_bytes_without_witness())
+    }
+
+    pub fn weight(&self) -> usize {
+        self.to_bytes_without_witness().len() * (WITNESS_SCALE_FACTOR - 1) + self.to_bytes().len()
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    fn serialize(&self, segwit: bool) -> Vec<u8> {
+        let mut v = Vec::new();
+        self.encode_with(&mut v, segwit);
         v
     }
 
+    fn encode_with(&self, out: &mut Vec<u8>, segwit: bool) {
+        out.extend_from_slice(&self.version.to_le_bytes());
+        if segwit {
+            out.extend_from_slice(&[0x00, 0x01]);
+        }
+        CompactSize::new(self.inputs.len() as u64).encode_to(out);
+        for input in &self.inputs {
+            input.encode_to(out);
+        }
+        CompactSize::new(self.outputs.len() as u64).encode_to(out);
+        for output in &self.outputs {
+            output.encode_to(out);
+        }
+        if segwit {
+            for input in &self.inputs {
+                input.witness.encode_to(out);
+            }
+        }
+        out.extend_from_slice(&self.lock_time.to_le_bytes());
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::decode(bytes, true)
+    }
+
+    /// Decodes the legacy encoding only, as BIP174 stores the unsigned
+    /// transaction: an empty input list is never read as a segwit marker.
+    pub fn from_bytes_without_witness(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::decode(bytes, false)
+    }
+
+    fn decode(bytes: &[u8], allow_witness: bool) -> Result<(Self, usize), BitcoinError> {
         if bytes.len() < 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
         let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        let (input_count, offset) = CompactSize::from_bytes(&bytes[4..])?;
+        let mut cursor = 4;
+        let (mut input_count, offset) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += offset;
+        // An empty input list followed by a non-zero byte is the segwit
+        // marker and flag rather than a transaction without inputs.
+        let segwit =
+            allow_witness && input_count.value == 0 && bytes.get(cursor).is_some_and(|&b| b != 0);
+        if segwit {
+            if bytes[cursor] != 0x01 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            cursor += 1;
+            let (count, offset) = CompactSize::from_bytes(&bytes[cursor..])?;
+            input_count = count;
+            cursor += offset;
+        }
         let mut inputs = Vec::new();
-        let mut cursor = 4 + offset;
         for _ in 0..input_count.value {
             let (input, used) = TransactionInput::from_bytes(&bytes[cursor..])?;
             inputs.push(input);
             cursor += used;
         }
+        let (output_count, offset) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += offset;
+        let mut outputs = Vec::new();
+        for _ in 0..output_count.value {
+            let (output, used) = TransactionOutput::from_bytes(&bytes[cursor..])?;
+            outputs.push(output);
+            cursor += used;
+        }
+        if segwit {
+            for input in &mut inputs {
+                let (witness, used) = Witness::from_bytes(&bytes[cursor..])?;
+                input.witness = witness;
+                cursor += used;
+            }
+            // Core rejects a superfluous witness record; it would also not
+            // survive re-serialization, which writes the legacy encoding.
+            if !inputs.iter().any(TransactionInput::has_witness) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
         if bytes.len() < cursor + 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
@@ -257,20 +1008,48 @@ impl BitcoinTransaction {
             bytes[cursor + 2],
             bytes[cursor + 3],
         ]);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            size = cursor + 4,
+            inputs = inputs.len(),
+            outputs = outputs.len(),
+            segwit,
+            "decoded transaction"
+        );
         Ok((
-            BitcoinTransaction::new(version, inputs, lock_time),
+            BitcoinTransaction::new(version, inputs, lock_time).with_outputs(outputs),
             cursor + 4,
         ))
     }
+
+    /// Like `from_bytes`, but `bytes` must hold exactly one transaction.
+    pub fn from_bytes_exact(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        exact(bytes, Self::from_bytes)
+    }
+
+    /// Decodes a hex string holding exactly one transaction.
+    pub fn from_hex_exact(hex_str: &str) -> Result<Self, BitcoinError> {
+        Self::from_bytes_exact(&hex_codec::decode(hex_str)?)
+    }
+}
+
+/// Runs `decode` on `bytes`, failing if it doesn't use all of them.
+pub(crate) fn exact<T>(
+    bytes: &[u8],
+    decode: impl FnOnce(&[u8]) -> Result<(T, usize), BitcoinError>,
+) -> Result<T, BitcoinError> {
+    let (value, used) = decode(bytes)?;
+    match bytes.len() - used {
+        0 => Ok(value),
+        leftover => Err(BitcoinError::TrailingBytes(leftover)),
+    }
 }
 
 impl fmt::Display for BitcoinTransaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Version: {}", self.version)?;
-        for input in &self.inputs {
-            writeln!(f, "Previous Output Vout: {}", input.previous_output.vout)?;
-            writeln!(f, "ScriptSig: {} bytes", input.script_sig.bytes.len())?;
+        if f.alternate() {
+            return write!(f, "{}", self.hex_dump());
         }
-        writeln!(f, "Lock Time: {}", self.lock_time)
+        self.write_with(f, &format::FormatOptions::default())
     }
 }
@@ -0,0 +1,153 @@
+//! Absolute (`nLockTime`) and BIP68 relative lock time evaluation.
+
+use crate::{BitcoinTransaction, TransactionInput};
+
+pub use crate::constants::{
+    LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG,
+    SEQUENCE_LOCKTIME_GRANULARITY, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    /// In seconds, always a multiple of 512.
+    Time(u32),
+}
+
+impl RelativeLockTime {
+    /// Whether an input confirmed at `coin_height`, whose block's parent had
+    /// median time past `coin_mtp`, may be spent in a block at `height`
+    /// whose parent has median time past `mtp`.
+    pub fn is_satisfied(&self, coin_height: u32, coin_mtp: u32, height: u32, mtp: u32) -> bool {
+        match *self {
+            RelativeLockTime::Blocks(blocks) => height as u64 >= coin_height as u64 + blocks as u64,
+            RelativeLockTime::Time(seconds) => mtp as u64 >= coin_mtp as u64 + seconds as u64,
+        }
+    }
+}
+
+impl TransactionInput {
+    /// The BIP68 lock encoded in the sequence number, if not disabled.
+    /// Only meaningful in transactions with version 2 or higher.
+    pub fn relative_lock_time(&self) -> Option<RelativeLockTime> {
+        if self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = self.sequence & SEQUENCE_LOCKTIME_MASK;
+        Some(if self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            RelativeLockTime::Time(value << SEQUENCE_LOCKTIME_GRANULARITY)
+        } else {
+            RelativeLockTime::Blocks(value as u16)
+        })
+    }
+}
+
+/// The last height and median time past at which a transaction is still
+/// locked, as Core's `CalculateSequenceLocks`; -1 means unconstrained.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SequenceLocks {
+    pub min_height: i64,
+    pub min_time: i64,
+}
+
+impl SequenceLocks {
+    /// True if the transaction may be included in a block at `height` whose
+    /// parent has median time past `mtp`.
+    pub fn is_satisfied(&self, height: u32, mtp: u32) -> bool {
+        self.min_height < height as i64 && self.min_time < mtp as i64
+    }
+}
+
+/// Why a transaction can't be mined yet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LockReason {
+    /// `nLockTime` hasn't passed; holds the lock time.
+    LockTime(u32),
+    /// A BIP68 block lock; holds the first height allowed.
+    RelativeHeight(u32),
+    /// A BIP68 time lock; holds the first parent median time past allowed.
+    RelativeTime(u32),
+    /// Fewer confirmation heights were given than there are inputs.
+    MissingConfirmationHeight,
+}
+
+impl BitcoinTransaction {
+    /// Core's `IsFinalTx`: the lock time has passed for a block at `height`
+    /// with time `mtp`, or every input opts out with a final sequence.
+    pub fn is_final(&self, height: u32, mtp: u32) -> bool {
+        if self.lock_time == 0 {
+            return true;
+        }
+        let cutoff = if self.lock_time < LOCKTIME_THRESHOLD {
+            height
+        } else {
+            mtp
+        };
+        self.lock_time < cutoff
+            || self
+                .inputs
+                .iter()
+                .all(|input| input.sequence == SEQUENCE_FINAL)
+    }
+
+    /// BIP68 locks given the height each input's coin confirmed at, in input
+    /// order. `mtp_at(h)` returns the median time past of the block at
+    /// height `h`.
+    pub fn sequence_locks<F>(&self, prev_heights: &[u32], mtp_at: F) -> SequenceLocks
+    where
+        F: Fn(u32) -> u32,
+    {
+        let mut locks = SequenceLocks {
+            min_height: -1,
+            min_time: -1,
+        };
+        if self.version < 2 {
+            return locks;
+        }
+        for (input, &coin_height) in self.inputs.iter().zip(prev_heights) {
+            match input.relative_lock_time() {
+                None => {}
+                Some(RelativeLockTime::Blocks(blocks)) => {
+                    let min_height = coin_height as i64 + blocks as i64 - 1;
+                    locks.min_height = locks.min_height.max(min_height);
+                }
+                Some(RelativeLockTime::Time(seconds)) => {
+                    // Measured from the block before the one that confirmed the coin.
+                    let coin_time = mtp_at(coin_height.saturating_sub(1)) as i64;
+                    locks.min_time = locks.min_time.max(coin_time + seconds as i64 - 1);
+                }
+            }
+        }
+        locks
+    }
+
+    /// Whether the transaction may go in a block at `height` whose parent
+    /// has median time past `mtp`, checking both `nLockTime` and BIP68.
+    /// `input_confirmation_heights` and `mtp_at` are as for
+    /// [`sequence_locks`](Self::sequence_locks).
+    pub fn is_mineable_at<F>(
+        &self,
+        height: u32,
+        mtp: u32,
+        input_confirmation_heights: &[u32],
+        mtp_at: F,
+    ) -> Result<(), LockReason>
+    where
+        F: Fn(u32) -> u32,
+    {
+        if !self.is_final(height, mtp) {
+            return Err(LockReason::LockTime(self.lock_time));
+        }
+        if input_confirmation_heights.len() < self.inputs.len() {
+            return Err(LockReason::MissingConfirmationHeight);
+        }
+        let locks = self.sequence_locks(input_confirmation_heights, mtp_at);
+        if locks.min_height >= height as i64 {
+            return Err(LockReason::RelativeHeight(locks.min_height as u32 + 1));
+        }
+        if locks.min_time >= mtp as i64 {
+            return Err(LockReason::RelativeTime(locks.min_time as u32 + 1));
+        }
+        Ok(())
+    }
+}
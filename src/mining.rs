@@ -0,0 +1,96 @@
+//! Splitting a coinbase around its extranonce, as Stratum mining proxies
+//! do: the pool sends `coinb1` and `coinb2`, and each miner rebuilds the
+//! coinbase with its own extranonce in between.
+
+use crate::{BitcoinError, BitcoinTransaction, CompactSize, Txid, hashes};
+use std::ops::Range;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CoinbaseSplit {
+    /// Serialized coinbase (without witness) up to the extranonce.
+    pub coinb1: Vec<u8>,
+    /// Serialized coinbase after the extranonce.
+    pub coinb2: Vec<u8>,
+    pub extranonce_len: usize,
+}
+
+impl CoinbaseSplit {
+    /// Splits a coinbase serialized without witness around `extranonce`, a
+    /// byte range that must lie inside the scriptSig.
+    pub fn new(serialized: &[u8], extranonce: Range<usize>) -> Result<Self, BitcoinError> {
+        let (tx, used) = BitcoinTransaction::from_bytes(serialized)?;
+        if used != serialized.len() || !tx.is_coinbase() || tx.has_witness() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let script_sig = script_sig_range(&tx);
+        if extranonce.start > extranonce.end
+            || extranonce.start < script_sig.start
+            || extranonce.end > script_sig.end
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Self {
+            coinb1: serialized[..extranonce.start].to_vec(),
+            coinb2: serialized[extranonce.end..].to_vec(),
+            extranonce_len: extranonce.len(),
+        })
+    }
+
+    /// Splits `coinbase` around `extranonce`, a range of its scriptSig
+    /// bytes holding placeholder data.
+    pub fn from_transaction(
+        coinbase: &BitcoinTransaction,
+        extranonce: Range<usize>,
+    ) -> Result<Self, BitcoinError> {
+        let mut coinbase = coinbase.clone();
+        coinbase.clear_witnesses();
+        let offset = script_sig_range(&coinbase).start;
+        Self::new(
+            &coinbase.to_bytes(),
+            offset + extranonce.start..offset + extranonce.end,
+        )
+    }
+
+    /// The serialized coinbase with `extranonce` filled in.
+    pub fn assemble(&self, extranonce: &[u8]) -> Result<Vec<u8>, BitcoinError> {
+        if extranonce.len() != self.extranonce_len {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok([&self.coinb1[..], extranonce, &self.coinb2[..]].concat())
+    }
+
+    pub fn coinbase(&self, extranonce: &[u8]) -> Result<BitcoinTransaction, BitcoinError> {
+        let raw = self.assemble(extranonce)?;
+        Ok(BitcoinTransaction::from_bytes(&raw)?.0)
+    }
+
+    pub fn txid(&self, extranonce: &[u8]) -> Result<Txid, BitcoinError> {
+        Ok(Txid(hashes::sha256d(&self.assemble(extranonce)?)))
+    }
+
+    /// The merkle root for `extranonce`, folding in the coinbase's merkle
+    /// branch from the leaves up.
+    pub fn merkle_root(
+        &self,
+        extranonce: &[u8],
+        branch: &[[u8; 32]],
+    ) -> Result<[u8; 32], BitcoinError> {
+        let txid = self.txid(extranonce)?;
+        Ok(branch.iter().fold(txid.0, |node, sibling| {
+            hashes::sha256d(&[node, *sibling].concat())
+        }))
+    }
+}
+
+// Byte range of the coinbase scriptSig in its serialization without
+// witness: after the version, input count, outpoint and script length.
+fn script_sig_range(coinbase: &BitcoinTransaction) -> Range<usize> {
+    let script_sig = &coinbase.inputs[0].script_sig;
+    let start = 4
+        + CompactSize::new(coinbase.inputs.len() as u64)
+            .to_bytes()
+            .len()
+        + 36
+        + CompactSize::new(script_sig.len() as u64).to_bytes().len();
+    start..start + script_sig.len()
+}
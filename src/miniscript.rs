@@ -0,0 +1,319 @@
+//! A subset of Miniscript for P2WSH: `pk`, `pkh`, `older`, `after`,
+//! `and_v`, `or_d`, `thresh` and the `v:`, `s:` and `a:` wrappers.
+//!
+//! Keys are hex-encoded compressed public keys. Only the basic type system
+//! (B, V, K, W) is checked; the `d`/`u`/`o` modifiers are not.
+
+use crate::hashes::hash160;
+use crate::opcodes::{
+    OP_ADD, OP_CHECKLOCKTIMEVERIFY, OP_CHECKSEQUENCEVERIFY, OP_CHECKSIG, OP_DUP, OP_ENDIF,
+    OP_EQUAL, OP_EQUALVERIFY, OP_FROMALTSTACK, OP_HASH160, OP_IFDUP, OP_NOTIF, OP_SWAP,
+    OP_TOALTSTACK, OP_VERIFY,
+};
+use crate::{BitcoinError, Script};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest DER signature plus sighash byte.
+const MAX_SIG_SIZE: usize = 73;
+const PUBKEY_SIZE: usize = 33;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Miniscript {
+    Pk([u8; 33]),
+    Pkh([u8; 33]),
+    Older(u32),
+    After(u32),
+    AndV(Box<Miniscript>, Box<Miniscript>),
+    OrD(Box<Miniscript>, Box<Miniscript>),
+    Thresh(usize, Vec<Miniscript>),
+    /// `v:`
+    Verify(Box<Miniscript>),
+    /// `s:`
+    Swap(Box<Miniscript>),
+    /// `a:`
+    Alt(Box<Miniscript>),
+}
+
+/// Miniscript's basic types.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BasicType {
+    /// Pushes nonzero on success, zero on dissatisfaction.
+    B,
+    /// Pushes nothing; fails or continues.
+    V,
+    /// Pushes a key for a following `CHECKSIG`.
+    K,
+    /// Like B, but takes its input from one below the top of the stack.
+    W,
+}
+
+impl Miniscript {
+    /// The basic type, or `InvalidFormat` if a fragment's arguments have the
+    /// wrong types.
+    pub fn basic_type(&self) -> Result<BasicType, BitcoinError> {
+        use BasicType::*;
+        let invalid = Err(BitcoinError::InvalidFormat);
+        match self {
+            Miniscript::Pk(_) | Miniscript::Pkh(_) => Ok(B),
+            Miniscript::Older(n) | Miniscript::After(n) => {
+                if (1..0x80000000).contains(n) {
+                    Ok(B)
+                } else {
+                    invalid
+                }
+            }
+            Miniscript::AndV(x, y) => match (x.basic_type()?, y.basic_type()?) {
+                (V, t @ (B | V | K)) => Ok(t),
+                _ => invalid,
+            },
+            Miniscript::OrD(x, z) => match (x.basic_type()?, z.basic_type()?) {
+                (B, B) => Ok(B),
+                _ => invalid,
+            },
+            Miniscript::Thresh(k, subs) => {
+                if *k == 0 || *k > subs.len() || subs[0].basic_type()? != B {
+                    return invalid;
+                }
+                for sub in &subs[1..] {
+                    if sub.basic_type()? != W {
+                        return invalid;
+                    }
+                }
+                Ok(B)
+            }
+            Miniscript::Verify(x) => match x.basic_type()? {
+                B => Ok(V),
+                _ => invalid,
+            },
+            Miniscript::Swap(x) | Miniscript::Alt(x) => match x.basic_type()? {
+                B => Ok(W),
+                _ => invalid,
+            },
+        }
+    }
+
+    /// The witness script this fragment compiles to.
+    pub fn compile(&self) -> Script {
+        let mut script = Script::new(Vec::new());
+        self.encode(&mut script);
+        script
+    }
+
+    fn encode(&self, script: &mut Script) {
+        match self {
+            Miniscript::Pk(key) => {
+                script.push_slice(key).push_opcode(OP_CHECKSIG);
+            }
+            Miniscript::Pkh(key) => {
+                script
+                    .push_opcode(OP_DUP)
+                    .push_opcode(OP_HASH160)
+                    .push_slice(&hash160(key))
+                    .push_opcode(OP_EQUALVERIFY)
+                    .push_opcode(OP_CHECKSIG);
+            }
+            Miniscript::Older(n) => {
+                script
+                    .push_int(*n as i64)
+                    .push_opcode(OP_CHECKSEQUENCEVERIFY);
+            }
+            Miniscript::After(n) => {
+                script
+                    .push_int(*n as i64)
+                    .push_opcode(OP_CHECKLOCKTIMEVERIFY);
+            }
+            Miniscript::AndV(x, y) => {
+                x.encode(script);
+                y.encode(script);
+            }
+            Miniscript::OrD(x, z) => {
+                x.encode(script);
+                script.push_opcode(OP_IFDUP).push_opcode(OP_NOTIF);
+                z.encode(script);
+                script.push_opcode(OP_ENDIF);
+            }
+            Miniscript::Thresh(k, subs) => {
+                for (i, sub) in subs.iter().enumerate() {
+                    sub.encode(script);
+                    if i > 0 {
+                        script.push_opcode(OP_ADD);
+                    }
+                }
+                script.push_int(*k as i64).push_opcode(OP_EQUAL);
+            }
+            Miniscript::Verify(x) => {
+                x.encode(script);
+                // Fold the VERIFY into the last opcode where one exists.
+                match script.bytes.last_mut() {
+                    Some(op @ (&mut OP_CHECKSIG | &mut OP_EQUAL)) => *op += 1,
+                    _ => {
+                        script.push_opcode(OP_VERIFY);
+                    }
+                }
+            }
+            Miniscript::Swap(x) => {
+                script.push_opcode(OP_SWAP);
+                x.encode(script);
+            }
+            Miniscript::Alt(x) => {
+                script.push_opcode(OP_TOALTSTACK);
+                x.encode(script);
+                script.push_opcode(OP_FROMALTSTACK);
+            }
+        }
+    }
+
+    /// Largest witness (every element with its length prefix, excluding the
+    /// witness script) that satisfies this fragment, or `None` if it can't
+    /// be satisfied.
+    pub fn max_satisfaction_size(&self) -> Option<usize> {
+        self.sizes().0
+    }
+
+    // (satisfaction, dissatisfaction) witness sizes.
+    fn sizes(&self) -> (Option<usize>, Option<usize>) {
+        match self {
+            Miniscript::Pk(_) => (Some(1 + MAX_SIG_SIZE), Some(1)),
+            Miniscript::Pkh(_) => (
+                Some(1 + MAX_SIG_SIZE + 1 + PUBKEY_SIZE),
+                Some(1 + 1 + PUBKEY_SIZE),
+            ),
+            Miniscript::Older(_) | Miniscript::After(_) => (Some(0), None),
+            Miniscript::AndV(x, y) => {
+                let sat = x.sizes().0.zip(y.sizes().0).map(|(a, b)| a + b);
+                (sat, None)
+            }
+            Miniscript::OrD(x, z) => {
+                let (sat_x, dsat_x) = x.sizes();
+                let (sat_z, dsat_z) = z.sizes();
+                let via_z = sat_z.zip(dsat_x).map(|(a, b)| a + b);
+                (sat_x.max(via_z), dsat_z.zip(dsat_x).map(|(a, b)| a + b))
+            }
+            Miniscript::Thresh(k, subs) => {
+                let sizes: Vec<_> = subs.iter().map(|sub| sub.sizes()).collect();
+                let Some(dsat) = sizes.iter().map(|s| s.1).sum::<Option<usize>>() else {
+                    return (None, None);
+                };
+                // Satisfy the k subs that add the most over dissatisfying them.
+                let mut extra: Vec<usize> = sizes
+                    .iter()
+                    .filter_map(|(sat, dsat)| Some(sat.as_ref()?.saturating_sub(dsat.unwrap())))
+                    .collect();
+                if extra.len() < *k {
+                    return (None, Some(dsat));
+                }
+                extra.sort_unstable_by(|a, b| b.cmp(a));
+                (Some(dsat + extra[..*k].iter().sum::<usize>()), Some(dsat))
+            }
+            Miniscript::Verify(x) => (x.sizes().0, None),
+            Miniscript::Swap(x) | Miniscript::Alt(x) => x.sizes(),
+        }
+    }
+}
+
+impl fmt::Display for Miniscript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Miniscript::Pk(key) => write!(f, "pk({})", hex::encode(key)),
+            Miniscript::Pkh(key) => write!(f, "pkh({})", hex::encode(key)),
+            Miniscript::Older(n) => write!(f, "older({})", n),
+            Miniscript::After(n) => write!(f, "after({})", n),
+            Miniscript::AndV(x, y) => write!(f, "and_v({},{})", x, y),
+            Miniscript::OrD(x, z) => write!(f, "or_d({},{})", x, z),
+            Miniscript::Thresh(k, subs) => {
+                write!(f, "thresh({}", k)?;
+                for sub in subs {
+                    write!(f, ",{}", sub)?;
+                }
+                write!(f, ")")
+            }
+            Miniscript::Verify(x) => write!(f, "v:{}", x),
+            Miniscript::Swap(x) => write!(f, "s:{}", x),
+            Miniscript::Alt(x) => write!(f, "a:{}", x),
+        }
+    }
+}
+
+impl FromStr for Miniscript {
+    type Err = BitcoinError;
+
+    /// Parses and type-checks a top-level (type B) expression.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ms = parse(s.trim())?;
+        if ms.basic_type()? != BasicType::B {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(ms)
+    }
+}
+
+fn parse(s: &str) -> Result<Miniscript, BitcoinError> {
+    let invalid = || BitcoinError::InvalidFormat;
+    let open = s.find('(').ok_or_else(invalid)?;
+    let (name, wrappers) = match s[..open].split_once(':') {
+        Some((wrappers, name)) => (name, wrappers),
+        None => (&s[..open], ""),
+    };
+    let inner = s[open + 1..].strip_suffix(')').ok_or_else(invalid)?;
+    let args = split_args(inner)?;
+
+    let mut ms = match (name, args.as_slice()) {
+        ("pk", [key]) => Miniscript::Pk(parse_key(key)?),
+        ("pkh", [key]) => Miniscript::Pkh(parse_key(key)?),
+        ("older", [n]) => Miniscript::Older(n.parse().map_err(|_| invalid())?),
+        ("after", [n]) => Miniscript::After(n.parse().map_err(|_| invalid())?),
+        ("and_v", [x, y]) => Miniscript::AndV(Box::new(parse(x)?), Box::new(parse(y)?)),
+        ("or_d", [x, z]) => Miniscript::OrD(Box::new(parse(x)?), Box::new(parse(z)?)),
+        ("thresh", [k, subs @ ..]) if !subs.is_empty() => Miniscript::Thresh(
+            k.parse().map_err(|_| invalid())?,
+            subs.iter()
+                .map(|sub| parse(sub))
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => return Err(invalid()),
+    };
+    // Wrappers apply right to left: `sv:X` is `s:(v:X)`.
+    for wrapper in wrappers.chars().rev() {
+        ms = match wrapper {
+            'v' => Miniscript::Verify(Box::new(ms)),
+            's' => Miniscript::Swap(Box::new(ms)),
+            'a' => Miniscript::Alt(Box::new(ms)),
+            _ => return Err(invalid()),
+        };
+    }
+    ms.basic_type()?;
+    Ok(ms)
+}
+
+/// Splits on the commas at nesting depth zero.
+fn split_args(s: &str) -> Result<Vec<&str>, BitcoinError> {
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or(BitcoinError::InvalidFormat)?,
+            ',' if depth == 0 => {
+                args.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    args.push(&s[start..]);
+    Ok(args)
+}
+
+fn parse_key(s: &str) -> Result<[u8; 33], BitcoinError> {
+    let mut key = [0u8; 33];
+    hex::decode_to_slice(s, &mut key).map_err(|_| BitcoinError::InvalidFormat)?;
+    if key[0] != 0x02 && key[0] != 0x03 {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(key)
+}
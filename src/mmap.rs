@@ -0,0 +1,57 @@
+//! Zero-copy parsing of memory-mapped block files.
+
+use crate::blockfile::{block_records, read_xor_key, xor_in_place};
+use crate::{BitcoinError, BlockView, Network};
+use memmap2::{Mmap, MmapMut, MmapOptions};
+use std::fs::File;
+use std::path::Path;
+
+enum Mapping {
+    Shared(Mmap),
+    // Obfuscated files are mapped copy-on-write and de-obfuscated in place,
+    // which touches every page; use the streaming reader if that matters.
+    Private(MmapMut),
+}
+
+/// A block file mapped into memory. Every `BlockView` handed out borrows
+/// from the mapping, so views cannot outlive it.
+pub struct MappedBlockFile {
+    mapping: Mapping,
+    network: Network,
+}
+
+impl MappedBlockFile {
+    /// Maps a block file, de-obfuscating it with the `xor.dat` key found next
+    /// to it.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or modified while mapped. Core only
+    /// appends to the newest `blk*.dat`, so map older files or stop the node.
+    pub unsafe fn open<P: AsRef<Path>>(path: P, network: Network) -> Result<Self, BitcoinError> {
+        let path = path.as_ref();
+        let key = read_xor_key(path.parent().unwrap_or(Path::new(".")))?;
+        let file = File::open(path)?;
+        let mapping = if key == [0u8; 8] {
+            Mapping::Shared(unsafe { Mmap::map(&file)? })
+        } else {
+            let mut map = unsafe { MmapOptions::new().map_copy(&file)? };
+            xor_in_place(&mut map, key, 0);
+            Mapping::Private(map)
+        };
+        Ok(Self { mapping, network })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.mapping {
+            Mapping::Shared(map) => map,
+            Mapping::Private(map) => map,
+        }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = Result<BlockView<'_>, BitcoinError>> {
+        block_records(self.as_bytes(), self.network)
+            .into_iter()
+            .map(BlockView::new)
+    }
+}
@@ -0,0 +1,220 @@
+//! MuHash3072, the rolling UTXO set hash behind Core's
+//! `gettxoutsetinfo muhash`.
+//!
+//! Each element is hashed to a number modulo the prime `2^3072 - 1103717`
+//! (SHA256, then 384 bytes of ChaCha20 keystream). The set hash is the
+//! product of its elements, so coins can be added and removed in any
+//! order; removals are tracked in a separate denominator and divided out
+//! when finalizing.
+
+use crate::undo::Coin;
+use crate::{OutPoint, hashes};
+
+const LIMBS: usize = 48;
+/// The prime is `2^3072 - PRIME_DIFF`.
+const PRIME_DIFF: u64 = 1103717;
+
+/// A number below `2^3072`, as little-endian 64-bit limbs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Num3072([u64; LIMBS]);
+
+impl Num3072 {
+    const ONE: Num3072 = {
+        let mut limbs = [0; LIMBS];
+        limbs[0] = 1;
+        Num3072(limbs)
+    };
+
+    fn from_bytes(bytes: &[u8; LIMBS * 8]) -> Self {
+        let mut limbs = [0; LIMBS];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Num3072(limbs)
+    }
+
+    fn to_bytes(self) -> [u8; LIMBS * 8] {
+        let mut bytes = [0; LIMBS * 8];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn is_overflow(&self) -> bool {
+        self.0[0] > u64::MAX - PRIME_DIFF && self.0[1..].iter().all(|&limb| limb == u64::MAX)
+    }
+
+    // Adds `n * 2^3072 mod p`, i.e. `n * PRIME_DIFF`, returning the carry
+    // out of the top limb.
+    fn add_folded(&mut self, n: u64) -> bool {
+        let mut carry = n as u128 * PRIME_DIFF as u128;
+        for limb in &mut self.0 {
+            if carry == 0 {
+                return false;
+            }
+            let sum = *limb as u128 + carry;
+            *limb = sum as u64;
+            carry = sum >> 64;
+        }
+        carry != 0
+    }
+
+    /// The product modulo the prime, fully reduced.
+    fn mul(&self, other: &Num3072) -> Num3072 {
+        let mut wide = [0u64; 2 * LIMBS];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.0.iter().enumerate() {
+                let t = wide[i + j] as u128 + a as u128 * b as u128 + carry;
+                wide[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            wide[i + LIMBS] = carry as u64;
+        }
+        // Fold the high half down, since 2^3072 = PRIME_DIFF (mod p).
+        let mut out = [0u64; LIMBS];
+        let mut carry = 0u128;
+        for i in 0..LIMBS {
+            let t = wide[i] as u128 + wide[i + LIMBS] as u128 * PRIME_DIFF as u128 + carry;
+            out[i] = t as u64;
+            carry = t >> 64;
+        }
+        let mut out = Num3072(out);
+        if out.add_folded(carry as u64) {
+            out.add_folded(1);
+        }
+        if out.is_overflow() {
+            out.add_folded(1);
+        }
+        out
+    }
+
+    /// The inverse by Fermat's little theorem, `self^(p - 2)`, four
+    /// exponent bits at a time.
+    fn inverse(&self) -> Num3072 {
+        let mut exponent = [u64::MAX; LIMBS];
+        exponent[0] = u64::MAX - PRIME_DIFF - 1;
+        let mut powers = [Num3072::ONE; 16];
+        for i in 1..16 {
+            powers[i] = powers[i - 1].mul(self);
+        }
+        let mut out = Num3072::ONE;
+        for limb in exponent.iter().rev() {
+            for shift in (0..64).step_by(4).rev() {
+                for _ in 0..4 {
+                    out = out.mul(&out);
+                }
+                out = out.mul(&powers[(limb >> shift & 0xf) as usize]);
+            }
+        }
+        out
+    }
+}
+
+/// A multiset hash of byte strings.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MuHash3072 {
+    numerator: Num3072,
+    denominator: Num3072,
+}
+
+impl Default for MuHash3072 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MuHash3072 {
+    /// The hash of the empty set.
+    pub fn new() -> Self {
+        Self {
+            numerator: Num3072::ONE,
+            denominator: Num3072::ONE,
+        }
+    }
+
+    pub fn insert(&mut self, data: &[u8]) {
+        self.numerator = self.numerator.mul(&to_num3072(data));
+    }
+
+    pub fn remove(&mut self, data: &[u8]) {
+        self.denominator = self.denominator.mul(&to_num3072(data));
+    }
+
+    /// Adds every element of `other`.
+    pub fn combine(&mut self, other: &MuHash3072) {
+        self.numerator = self.numerator.mul(&other.numerator);
+        self.denominator = self.denominator.mul(&other.denominator);
+    }
+
+    pub fn insert_coin(&mut self, outpoint: &OutPoint, coin: &Coin) {
+        self.insert(&coin_bytes(outpoint, coin));
+    }
+
+    pub fn remove_coin(&mut self, outpoint: &OutPoint, coin: &Coin) {
+        self.remove(&coin_bytes(outpoint, coin));
+    }
+
+    /// The 32-byte set hash. Core displays it byte-reversed, like other
+    /// hashes.
+    pub fn finalize(&self) -> [u8; 32] {
+        let value = self.numerator.mul(&self.denominator.inverse());
+        hashes::sha256(&value.to_bytes())
+    }
+}
+
+/// A coin as Core hashes it into the set: outpoint, height and coinbase
+/// flag as a `u32`, then the output.
+pub fn coin_bytes(outpoint: &OutPoint, coin: &Coin) -> Vec<u8> {
+    let mut v = outpoint.to_bytes();
+    v.extend_from_slice(&(coin.height << 1 | coin.coinbase as u32).to_le_bytes());
+    v.extend(coin.output.to_bytes());
+    v
+}
+
+fn to_num3072(data: &[u8]) -> Num3072 {
+    let key = hashes::sha256(data);
+    let mut bytes = [0u8; LIMBS * 8];
+    for (counter, block) in bytes.chunks_exact_mut(64).enumerate() {
+        block.copy_from_slice(&chacha20_block(&key, counter as u32));
+    }
+    Num3072::from_bytes(&bytes)
+}
+
+// One block of ChaCha20 keystream with an all-zero nonce (RFC 8439).
+fn chacha20_block(key: &[u8; 32], counter: u32) -> [u8; 64] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for (word, chunk) in state[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    state[12] = counter;
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+    let mut out = [0u8; 64];
+    for (i, chunk) in out.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&working[i].wrapping_add(state[i]).to_le_bytes());
+    }
+    out
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
@@ -0,0 +1,259 @@
+//! Script opcodes and their names as printed by Bitcoin Core.
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_RESERVED: u8 = 0x50;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_NOP: u8 = 0x61;
+pub const OP_IF: u8 = 0x63;
+pub const OP_NOTIF: u8 = 0x64;
+pub const OP_ELSE: u8 = 0x67;
+pub const OP_ENDIF: u8 = 0x68;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_TOALTSTACK: u8 = 0x6b;
+pub const OP_FROMALTSTACK: u8 = 0x6c;
+pub const OP_IFDUP: u8 = 0x73;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_SWAP: u8 = 0x7c;
+pub const OP_SIZE: u8 = 0x82;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_0NOTEQUAL: u8 = 0x92;
+pub const OP_ADD: u8 = 0x93;
+pub const OP_BOOLAND: u8 = 0x9a;
+pub const OP_BOOLOR: u8 = 0x9b;
+pub const OP_NUMEQUAL: u8 = 0x9c;
+pub const OP_NUMEQUALVERIFY: u8 = 0x9d;
+pub const OP_RIPEMD160: u8 = 0xa6;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_HASH256: u8 = 0xaa;
+pub const OP_CODESEPARATOR: u8 = 0xab;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+pub const OP_CHECKSIGADD: u8 = 0xba;
+
+const NAMES: [&str; 187] = [
+    "0",
+    "OP_PUSHBYTES_1",
+    "OP_PUSHBYTES_2",
+    "OP_PUSHBYTES_3",
+    "OP_PUSHBYTES_4",
+    "OP_PUSHBYTES_5",
+    "OP_PUSHBYTES_6",
+    "OP_PUSHBYTES_7",
+    "OP_PUSHBYTES_8",
+    "OP_PUSHBYTES_9",
+    "OP_PUSHBYTES_10",
+    "OP_PUSHBYTES_11",
+    "OP_PUSHBYTES_12",
+    "OP_PUSHBYTES_13",
+    "OP_PUSHBYTES_14",
+    "OP_PUSHBYTES_15",
+    "OP_PUSHBYTES_16",
+    "OP_PUSHBYTES_17",
+    "OP_PUSHBYTES_18",
+    "OP_PUSHBYTES_19",
+    "OP_PUSHBYTES_20",
+    "OP_PUSHBYTES_21",
+    "OP_PUSHBYTES_22",
+    "OP_PUSHBYTES_23",
+    "OP_PUSHBYTES_24",
+    "OP_PUSHBYTES_25",
+    "OP_PUSHBYTES_26",
+    "OP_PUSHBYTES_27",
+    "OP_PUSHBYTES_28",
+    "OP_PUSHBYTES_29",
+    "OP_PUSHBYTES_30",
+    "OP_PUSHBYTES_31",
+    "OP_PUSHBYTES_32",
+    "OP_PUSHBYTES_33",
+    "OP_PUSHBYTES_34",
+    "OP_PUSHBYTES_35",
+    "OP_PUSHBYTES_36",
+    "OP_PUSHBYTES_37",
+    "OP_PUSHBYTES_38",
+    "OP_PUSHBYTES_39",
+    "OP_PUSHBYTES_40",
+    "OP_PUSHBYTES_41",
+    "OP_PUSHBYTES_42",
+    "OP_PUSHBYTES_43",
+    "OP_PUSHBYTES_44",
+    "OP_PUSHBYTES_45",
+    "OP_PUSHBYTES_46",
+    "OP_PUSHBYTES_47",
+    "OP_PUSHBYTES_48",
+    "OP_PUSHBYTES_49",
+    "OP_PUSHBYTES_50",
+    "OP_PUSHBYTES_51",
+    "OP_PUSHBYTES_52",
+    "OP_PUSHBYTES_53",
+    "OP_PUSHBYTES_54",
+    "OP_PUSHBYTES_55",
+    "OP_PUSHBYTES_56",
+    "OP_PUSHBYTES_57",
+    "OP_PUSHBYTES_58",
+    "OP_PUSHBYTES_59",
+    "OP_PUSHBYTES_60",
+    "OP_PUSHBYTES_61",
+    "OP_PUSHBYTES_62",
+    "OP_PUSHBYTES_63",
+    "OP_PUSHBYTES_64",
+    "OP_PUSHBYTES_65",
+    "OP_PUSHBYTES_66",
+    "OP_PUSHBYTES_67",
+    "OP_PUSHBYTES_68",
+    "OP_PUSHBYTES_69",
+    "OP_PUSHBYTES_70",
+    "OP_PUSHBYTES_71",
+    "OP_PUSHBYTES_72",
+    "OP_PUSHBYTES_73",
+    "OP_PUSHBYTES_74",
+    "OP_PUSHBYTES_75",
+    "OP_PUSHDATA1",
+    "OP_PUSHDATA2",
+    "OP_PUSHDATA4",
+    "-1",
+    "OP_RESERVED",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+    "9",
+    "10",
+    "11",
+    "12",
+    "13",
+    "14",
+    "15",
+    "16",
+    "OP_NOP",
+    "OP_VER",
+    "OP_IF",
+    "OP_NOTIF",
+    "OP_VERIF",
+    "OP_VERNOTIF",
+    "OP_ELSE",
+    "OP_ENDIF",
+    "OP_VERIFY",
+    "OP_RETURN",
+    "OP_TOALTSTACK",
+    "OP_FROMALTSTACK",
+    "OP_2DROP",
+    "OP_2DUP",
+    "OP_3DUP",
+    "OP_2OVER",
+    "OP_2ROT",
+    "OP_2SWAP",
+    "OP_IFDUP",
+    "OP_DEPTH",
+    "OP_DROP",
+    "OP_DUP",
+    "OP_NIP",
+    "OP_OVER",
+    "OP_PICK",
+    "OP_ROLL",
+    "OP_ROT",
+    "OP_SWAP",
+    "OP_TUCK",
+    "OP_CAT",
+    "OP_SUBSTR",
+    "OP_LEFT",
+    "OP_RIGHT",
+    "OP_SIZE",
+    "OP_INVERT",
+    "OP_AND",
+    "OP_OR",
+    "OP_XOR",
+    "OP_EQUAL",
+    "OP_EQUALVERIFY",
+    "OP_RESERVED1",
+    "OP_RESERVED2",
+    "OP_1ADD",
+    "OP_1SUB",
+    "OP_2MUL",
+    "OP_2DIV",
+    "OP_NEGATE",
+    "OP_ABS",
+    "OP_NOT",
+    "OP_0NOTEQUAL",
+    "OP_ADD",
+    "OP_SUB",
+    "OP_MUL",
+    "OP_DIV",
+    "OP_MOD",
+    "OP_LSHIFT",
+    "OP_RSHIFT",
+    "OP_BOOLAND",
+    "OP_BOOLOR",
+    "OP_NUMEQUAL",
+    "OP_NUMEQUALVERIFY",
+    "OP_NUMNOTEQUAL",
+    "OP_LESSTHAN",
+    "OP_GREATERTHAN",
+    "OP_LESSTHANOREQUAL",
+    "OP_GREATERTHANOREQUAL",
+    "OP_MIN",
+    "OP_MAX",
+    "OP_WITHIN",
+    "OP_RIPEMD160",
+    "OP_SHA1",
+    "OP_SHA256",
+    "OP_HASH160",
+    "OP_HASH256",
+    "OP_CODESEPARATOR",
+    "OP_CHECKSIG",
+    "OP_CHECKSIGVERIFY",
+    "OP_CHECKMULTISIG",
+    "OP_CHECKMULTISIGVERIFY",
+    "OP_NOP1",
+    "OP_CHECKLOCKTIMEVERIFY",
+    "OP_CHECKSEQUENCEVERIFY",
+    "OP_NOP4",
+    "OP_NOP5",
+    "OP_NOP6",
+    "OP_NOP7",
+    "OP_NOP8",
+    "OP_NOP9",
+    "OP_NOP10",
+    "OP_CHECKSIGADD",
+];
+
+pub fn name(opcode: u8) -> &'static str {
+    NAMES.get(opcode as usize).copied().unwrap_or("OP_UNKNOWN")
+}
+
+/// Looks an opcode up by name, with or without the `OP_` prefix.
+pub fn from_name(name: &str) -> Option<u8> {
+    let upper = name.to_ascii_uppercase();
+    let bare = upper.strip_prefix("OP_").unwrap_or(&upper);
+    match bare {
+        "0" | "FALSE" => return Some(OP_0),
+        "TRUE" => return Some(OP_1),
+        "1NEGATE" | "-1" => return Some(OP_1NEGATE),
+        "CLTV" => return Some(OP_CHECKLOCKTIMEVERIFY),
+        "CSV" => return Some(OP_CHECKSEQUENCEVERIFY),
+        _ => {}
+    }
+    if let Ok(n) = bare.parse::<u8>() {
+        return (1..=16).contains(&n).then(|| OP_1 + n - 1);
+    }
+    NAMES
+        .iter()
+        .position(|candidate| candidate.strip_prefix("OP_") == Some(bare))
+        .map(|opcode| opcode as u8)
+}
@@ -0,0 +1,225 @@
+//! Relay policy checks, following Core's `IsStandardTx`,
+//! `AreInputsStandard` and the BIP431 TRUC rules.
+
+use crate::constants::{MAX_SCRIPT_SIZE, WITNESS_SCALE_FACTOR};
+use crate::{BitcoinTransaction, ScriptType, TransactionOutput};
+use std::collections::HashSet;
+use std::fmt;
+
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+pub const TRUC_MAX_VSIZE: usize = 10_000;
+pub const TRUC_CHILD_MAX_VSIZE: usize = 1_000;
+
+/// A transaction's `nVersion`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Version(pub u32);
+
+impl Version {
+    pub const ONE: Version = Version(1);
+    /// Enables BIP68 relative lock times.
+    pub const TWO: Version = Version(2);
+    /// Opts in to the BIP431 TRUC rules.
+    pub const THREE: Version = Version(3);
+
+    /// Versions 1 to 3 are relayed; consensus allows any.
+    pub fn is_standard(self) -> bool {
+        (Version::ONE..=Version::THREE).contains(&self)
+    }
+
+    pub fn is_truc(self) -> bool {
+        self == Version::THREE
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The ways a transaction can break the BIP431 topology rules.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TrucViolation {
+    /// A TRUC transaction spends an unconfirmed non-TRUC one.
+    SpendsNonTruc,
+    /// A non-TRUC transaction spends an unconfirmed TRUC one.
+    NonTrucSpendsTruc,
+    /// Over `TRUC_MAX_VSIZE`.
+    TooBig,
+    /// More than one unconfirmed ancestor.
+    TooManyAncestors,
+    /// A child of an unconfirmed parent over `TRUC_CHILD_MAX_VSIZE`.
+    ChildTooBig,
+    /// The parent already has an unconfirmed child.
+    TooManyDescendants,
+}
+
+/// An in-mempool transaction that a candidate spends from.
+#[derive(Debug, Clone, Copy)]
+pub struct UnconfirmedParent<'a> {
+    pub tx: &'a BitcoinTransaction,
+    /// Whether the parent itself spends unconfirmed outputs.
+    pub has_unconfirmed_parents: bool,
+    /// Children already in the mempool, not counting the candidate.
+    pub child_count: usize,
+}
+
+/// Node settings that standardness depends on. The default matches Core's.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Policy {
+    pub permit_bare_multisig: bool,
+    /// Fee rate in sat/kvB used to derive the dust threshold.
+    pub dust_relay_fee: u64,
+    /// Largest standard `OP_RETURN` output script, in bytes.
+    pub max_datacarrier_bytes: usize,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            permit_bare_multisig: true,
+            dust_relay_fee: DUST_RELAY_TX_FEE,
+            max_datacarrier_bytes: MAX_OP_RETURN_RELAY,
+        }
+    }
+}
+
+impl Policy {
+    /// The smallest standard value for `output`, as Core's `GetDustThreshold`:
+    /// the cost of creating and later spending it at `dust_relay_fee`.
+    pub fn dust_threshold(&self, output: &TransactionOutput) -> u64 {
+        let script = &output.script_pubkey;
+        if script.first() == Some(&0x6a) || script.len() > MAX_SCRIPT_SIZE {
+            return 0;
+        }
+        let mut size = output.to_bytes().len() as u64;
+        size += match script.script_type() {
+            // outpoint, empty scriptSig, sequence and a discounted witness
+            ScriptType::P2wpkh
+            | ScriptType::P2wsh
+            | ScriptType::P2tr
+            | ScriptType::Anchor
+            | ScriptType::WitnessUnknown => 32 + 4 + 1 + 107 / WITNESS_SCALE_FACTOR as u64 + 4,
+            _ => 32 + 4 + 1 + 107 + 4,
+        };
+        size * self.dust_relay_fee / 1000
+    }
+}
+
+impl BitcoinTransaction {
+    pub fn tx_version(&self) -> Version {
+        Version(self.version)
+    }
+
+    /// The TRUC (v3) rules this transaction breaks given its unconfirmed
+    /// parents, empty if it may enter the mempool. Replacing an existing
+    /// child (sibling eviction) isn't considered.
+    pub fn check_truc(&self, parents: &[UnconfirmedParent]) -> Vec<TrucViolation> {
+        let mut violations = Vec::new();
+        let truc = self.tx_version().is_truc();
+        for parent in parents {
+            match (truc, parent.tx.tx_version().is_truc()) {
+                (true, false) => violations.push(TrucViolation::SpendsNonTruc),
+                (false, true) => violations.push(TrucViolation::NonTrucSpendsTruc),
+                _ => {}
+            }
+        }
+        if !truc {
+            return violations;
+        }
+
+        let vsize = self.vsize();
+        if vsize > TRUC_MAX_VSIZE {
+            violations.push(TrucViolation::TooBig);
+        }
+        if parents.len() > 1 || parents.iter().any(|p| p.has_unconfirmed_parents) {
+            violations.push(TrucViolation::TooManyAncestors);
+        }
+        if !parents.is_empty() {
+            if vsize > TRUC_CHILD_MAX_VSIZE {
+                violations.push(TrucViolation::ChildTooBig);
+            }
+            if parents.iter().any(|p| p.child_count > 0) {
+                violations.push(TrucViolation::TooManyDescendants);
+            }
+        }
+        violations
+    }
+
+    /// Policy violations under Core's default settings; see `check_standard_with`.
+    pub fn check_standard(&self, prevouts: &[TransactionOutput]) -> Vec<&'static str> {
+        self.check_standard_with(prevouts, &Policy::default())
+    }
+
+    /// Returns Core's reject reasons for every rule the transaction breaks,
+    /// or an empty list if it would be relayed. `prevouts` are the outputs
+    /// being spent, in input order.
+    pub fn check_standard_with(
+        &self,
+        prevouts: &[TransactionOutput],
+        policy: &Policy,
+    ) -> Vec<&'static str> {
+        let mut violations = Vec::new();
+        if !self.tx_version().is_standard() {
+            violations.push("version");
+        }
+        if self.weight() > MAX_STANDARD_TX_WEIGHT {
+            violations.push("tx-size");
+        }
+        for input in &self.inputs {
+            if input.script_sig.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
+                violations.push("scriptsig-size");
+            }
+            if !input.script_sig.is_push_only() {
+                violations.push("scriptsig-not-pushonly");
+            }
+        }
+
+        let mut data_outputs = 0;
+        for output in &self.outputs {
+            let script = &output.script_pubkey;
+            match script.script_type() {
+                ScriptType::NonStandard => violations.push("scriptpubkey"),
+                ScriptType::NullData => {
+                    if script.len() > policy.max_datacarrier_bytes {
+                        violations.push("scriptpubkey");
+                    }
+                    data_outputs += 1;
+                }
+                ScriptType::Multisig => match script.parse_multisig() {
+                    Some(multisig) if (1..=3).contains(&multisig.keys.len()) => {
+                        if !policy.permit_bare_multisig {
+                            violations.push("bare-multisig");
+                        }
+                    }
+                    _ => violations.push("scriptpubkey"),
+                },
+                _ => {}
+            }
+            if output.value < policy.dust_threshold(output) {
+                violations.push("dust");
+            }
+        }
+        if data_outputs > 1 {
+            violations.push("multi-op-return");
+        }
+
+        if prevouts.len() != self.inputs.len() {
+            violations.push("bad-txns-inputs-missingorspent");
+        } else if prevouts.iter().any(|prevout| {
+            matches!(
+                prevout.script_pubkey.script_type(),
+                ScriptType::NonStandard | ScriptType::WitnessUnknown
+            )
+        }) {
+            violations.push("bad-txns-nonstandard-inputs");
+        }
+
+        let mut seen = HashSet::new();
+        violations.retain(|reason| seen.insert(*reason));
+        violations
+    }
+}
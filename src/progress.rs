@@ -0,0 +1,107 @@
+//! Progress reporting for long scans over block files, so a CLI can draw a
+//! progress bar instead of going quiet for an hour.
+//!
+//! [`BlockFileReader::with_progress`](crate::blockfile::BlockFileReader::with_progress)
+//! and [`ingest_with_progress`](crate::ingest::ingest_with_progress) report
+//! after every block. The indexers take their blocks one at a time from the
+//! caller, who can drive a [`ProgressTracker`] from the same loop.
+
+use crate::BitcoinError;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Progress {
+    /// Bytes read so far, including any garbage skipped between records.
+    pub bytes_processed: u64,
+    /// Bytes to read in all, when known up front.
+    pub total_bytes: Option<u64>,
+    pub blocks_parsed: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// How far along the scan is, from 0 to 1.
+    pub fn fraction(&self) -> Option<f64> {
+        let total = self.total_bytes?;
+        if total == 0 {
+            return Some(1.0);
+        }
+        Some((self.bytes_processed as f64 / total as f64).min(1.0))
+    }
+
+    /// Time left at the average rate so far. `None` until the first bytes
+    /// are in or when the total isn't known.
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total_bytes?;
+        if self.bytes_processed == 0 {
+            return None;
+        }
+        let remaining = total.saturating_sub(self.bytes_processed);
+        Some(
+            self.elapsed
+                .mul_f64(remaining as f64 / self.bytes_processed as f64),
+        )
+    }
+}
+
+pub trait ProgressObserver {
+    fn on_progress(&mut self, progress: &Progress);
+}
+
+impl<F: FnMut(&Progress)> ProgressObserver for F {
+    fn on_progress(&mut self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Ignores all progress.
+impl ProgressObserver for () {
+    fn on_progress(&mut self, _: &Progress) {}
+}
+
+/// Counts blocks and bytes and times the run from its creation.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    progress: Progress,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub fn new(total_bytes: Option<u64>) -> Self {
+        Self {
+            progress: Progress {
+                bytes_processed: 0,
+                total_bytes,
+                blocks_parsed: 0,
+                elapsed: Duration::ZERO,
+            },
+            started: Instant::now(),
+        }
+    }
+
+    /// A tracker whose total is the combined size of `paths`.
+    pub fn for_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, BitcoinError> {
+        let mut total = 0;
+        for path in paths {
+            total += fs::metadata(path)?.len();
+        }
+        Ok(Self::new(Some(total)))
+    }
+
+    /// Records a block ending `bytes_processed` bytes into the run and
+    /// returns the updated progress.
+    pub fn block_parsed(&mut self, bytes_processed: u64) -> Progress {
+        self.progress.bytes_processed = bytes_processed;
+        self.progress.blocks_parsed += 1;
+        self.progress()
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            elapsed: self.started.elapsed(),
+            ..self.progress
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Protobuf messages for streaming parsed transactions and blocks, as
+//! defined in `proto/bitcoin.proto`, with conversions to and from the
+//! crate's types.
+//!
+//! The messages are derived by hand rather than generated, so building
+//! doesn't need `protoc`; keep them in step with the `.proto` file.
+//! Converting back fails with [`BitcoinError::InvalidFormat`] on a hash
+//! that isn't 32 bytes or a missing outpoint or header.
+
+use crate::block::BlockHeader as CrateBlockHeader;
+use crate::{
+    BitcoinError, BitcoinTransaction, Block as CrateBlock, OutPoint as CrateOutPoint, Script,
+    TransactionInput, TransactionOutput, Txid, Witness,
+};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OutPoint {
+    #[prost(bytes = "vec", tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub vout: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TxIn {
+    #[prost(message, optional, tag = "1")]
+    pub previous_output: Option<OutPoint>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub script_sig: Vec<u8>,
+    #[prost(uint32, tag = "3")]
+    pub sequence: u32,
+    #[prost(bytes = "vec", repeated, tag = "4")]
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TxOut {
+    #[prost(uint64, tag = "1")]
+    pub value: u64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Transaction {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(message, repeated, tag = "2")]
+    pub inputs: Vec<TxIn>,
+    #[prost(message, repeated, tag = "3")]
+    pub outputs: Vec<TxOut>,
+    #[prost(uint32, tag = "4")]
+    pub lock_time: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlockHeader {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub prev_blockhash: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub merkle_root: Vec<u8>,
+    #[prost(uint32, tag = "4")]
+    pub time: u32,
+    #[prost(uint32, tag = "5")]
+    pub bits: u32,
+    #[prost(uint32, tag = "6")]
+    pub nonce: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Block {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<BlockHeader>,
+    #[prost(message, repeated, tag = "2")]
+    pub transactions: Vec<Transaction>,
+}
+
+fn hash(bytes: &[u8]) -> Result<[u8; 32], BitcoinError> {
+    bytes.try_into().map_err(|_| BitcoinError::InvalidFormat)
+}
+
+impl From<&CrateOutPoint> for OutPoint {
+    fn from(outpoint: &CrateOutPoint) -> Self {
+        OutPoint {
+            txid: outpoint.txid.0.to_vec(),
+            vout: outpoint.vout,
+        }
+    }
+}
+
+impl TryFrom<OutPoint> for CrateOutPoint {
+    type Error = BitcoinError;
+
+    fn try_from(outpoint: OutPoint) -> Result<Self, Self::Error> {
+        Ok(CrateOutPoint {
+            txid: Txid(hash(&outpoint.txid)?),
+            vout: outpoint.vout,
+        })
+    }
+}
+
+impl From<&TransactionInput> for TxIn {
+    fn from(input: &TransactionInput) -> Self {
+        TxIn {
+            previous_output: Some((&input.previous_output).into()),
+            script_sig: input.script_sig.to_vec(),
+            sequence: input.sequence,
+            witness: input.witness.items.clone(),
+        }
+    }
+}
+
+impl TryFrom<TxIn> for TransactionInput {
+    type Error = BitcoinError;
+
+    fn try_from(input: TxIn) -> Result<Self, Self::Error> {
+        let previous_output = input
+            .previous_output
+            .ok_or(BitcoinError::InvalidFormat)?
+            .try_into()?;
+        Ok(TransactionInput::new(
+            previous_output,
+            Script::new(input.script_sig),
+            input.sequence,
+        )
+        .with_witness(Witness::new(input.witness)))
+    }
+}
+
+impl From<&TransactionOutput> for TxOut {
+    fn from(output: &TransactionOutput) -> Self {
+        TxOut {
+            value: output.value,
+            script_pubkey: output.script_pubkey.to_vec(),
+        }
+    }
+}
+
+impl From<TxOut> for TransactionOutput {
+    fn from(output: TxOut) -> Self {
+        TransactionOutput::new(output.value, Script::new(output.script_pubkey))
+    }
+}
+
+impl From<&BitcoinTransaction> for Transaction {
+    fn from(tx: &BitcoinTransaction) -> Self {
+        Transaction {
+            version: tx.version,
+            inputs: tx.inputs.iter().map(TxIn::from).collect(),
+            outputs: tx.outputs.iter().map(TxOut::from).collect(),
+            lock_time: tx.lock_time,
+        }
+    }
+}
+
+impl TryFrom<Transaction> for BitcoinTransaction {
+    type Error = BitcoinError;
+
+    fn try_from(tx: Transaction) -> Result<Self, Self::Error> {
+        let inputs = tx
+            .inputs
+            .into_iter()
+            .map(TransactionInput::try_from)
+            .collect::<Result<_, _>>()?;
+        let outputs = tx
+            .outputs
+            .into_iter()
+            .map(TransactionOutput::from)
+            .collect();
+        Ok(BitcoinTransaction::new(tx.version, inputs, tx.lock_time).with_outputs(outputs))
+    }
+}
+
+impl From<&CrateBlockHeader> for BlockHeader {
+    fn from(header: &CrateBlockHeader) -> Self {
+        BlockHeader {
+            version: header.version,
+            prev_blockhash: header.prev_blockhash.to_vec(),
+            merkle_root: header.merkle_root.to_vec(),
+            time: header.time,
+            bits: header.bits,
+            nonce: header.nonce,
+        }
+    }
+}
+
+impl TryFrom<BlockHeader> for CrateBlockHeader {
+    type Error = BitcoinError;
+
+    fn try_from(header: BlockHeader) -> Result<Self, Self::Error> {
+        Ok(CrateBlockHeader::new(
+            header.version,
+            hash(&header.prev_blockhash)?,
+            hash(&header.merkle_root)?,
+            header.time,
+            header.bits,
+            header.nonce,
+        ))
+    }
+}
+
+impl From<&CrateBlock> for Block {
+    fn from(block: &CrateBlock) -> Self {
+        Block {
+            header: Some((&block.header).into()),
+            transactions: block.transactions.iter().map(Transaction::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<Block> for CrateBlock {
+    type Error = BitcoinError;
+
+    fn try_from(block: Block) -> Result<Self, Self::Error> {
+        let header = block
+            .header
+            .ok_or(BitcoinError::InvalidFormat)?
+            .try_into()?;
+        let transactions = block
+            .transactions
+            .into_iter()
+            .map(BitcoinTransaction::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(CrateBlock::new(header, transactions))
+    }
+}
@@ -0,0 +1,148 @@
+//! Partially signed transactions (BIP174, version 0).
+//!
+//! Only the unsigned transaction is interpreted; every other key-value pair
+//! is kept verbatim so a PSBT survives a round trip through this crate.
+
+use crate::{BitcoinError, BitcoinTransaction, CompactSize, exact};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+pub const PSBT_MAGIC: [u8; 5] = *b"psbt\xff";
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+
+pub type KeyValueMap = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Psbt {
+    pub unsigned_tx: BitcoinTransaction,
+    pub global: KeyValueMap,
+    pub inputs: Vec<KeyValueMap>,
+    pub outputs: Vec<KeyValueMap>,
+}
+
+impl Psbt {
+    pub fn from_unsigned_tx(unsigned_tx: BitcoinTransaction) -> Result<Self, BitcoinError> {
+        check_unsigned(&unsigned_tx)?;
+        Ok(Self {
+            inputs: vec![Vec::new(); unsigned_tx.inputs.len()],
+            outputs: vec![Vec::new(); unsigned_tx.outputs.len()],
+            global: Vec::new(),
+            unsigned_tx,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = PSBT_MAGIC.to_vec();
+        write_pair(
+            &mut v,
+            &[PSBT_GLOBAL_UNSIGNED_TX],
+            &self.unsigned_tx.to_bytes(),
+        );
+        write_map(&mut v, &self.global);
+        for map in self.inputs.iter().chain(&self.outputs) {
+            write_map(&mut v, map);
+        }
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if !bytes.starts_with(&PSBT_MAGIC) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut cursor = PSBT_MAGIC.len();
+        let (mut global, used) = read_map(&bytes[cursor..])?;
+        cursor += used;
+        let position = global
+            .iter()
+            .position(|(key, _)| key.as_slice() == [PSBT_GLOBAL_UNSIGNED_TX])
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let (_, raw_tx) = global.remove(position);
+        // BIP174: the legacy encoding, filling the whole value, unsigned.
+        let unsigned_tx = exact(&raw_tx, BitcoinTransaction::from_bytes_without_witness)?;
+        check_unsigned(&unsigned_tx)?;
+
+        let mut inputs = Vec::new();
+        for _ in 0..unsigned_tx.inputs.len() {
+            let (map, used) = read_map(&bytes[cursor..])?;
+            inputs.push(map);
+            cursor += used;
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..unsigned_tx.outputs.len() {
+            let (map, used) = read_map(&bytes[cursor..])?;
+            outputs.push(map);
+            cursor += used;
+        }
+        let psbt = Psbt {
+            unsigned_tx,
+            global,
+            inputs,
+            outputs,
+        };
+        Ok((psbt, cursor))
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.to_bytes())
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self, BitcoinError> {
+        let bytes = STANDARD
+            .decode(text.trim())
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        Psbt::from_bytes(&bytes).map(|(psbt, _)| psbt)
+    }
+}
+
+fn check_unsigned(tx: &BitcoinTransaction) -> Result<(), BitcoinError> {
+    let signed = tx
+        .inputs
+        .iter()
+        .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty());
+    if signed {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(())
+}
+
+fn write_pair(v: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    v.extend(CompactSize::new(key.len() as u64).to_bytes());
+    v.extend_from_slice(key);
+    v.extend(CompactSize::new(value.len() as u64).to_bytes());
+    v.extend_from_slice(value);
+}
+
+fn write_map(v: &mut Vec<u8>, map: &KeyValueMap) {
+    for (key, value) in map {
+        write_pair(v, key, value);
+    }
+    v.push(0x00);
+}
+
+fn read_map(bytes: &[u8]) -> Result<(KeyValueMap, usize), BitcoinError> {
+    let mut map = Vec::new();
+    let mut cursor = 0;
+    loop {
+        let (key_len, used) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        if key_len.value == 0 {
+            return Ok((map, cursor));
+        }
+        let key = take(bytes, &mut cursor, key_len.value)?;
+        let (value_len, used) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        let value = take(bytes, &mut cursor, value_len.value)?;
+        map.push((key, value));
+    }
+}
+
+fn take(bytes: &[u8], cursor: &mut usize, len: u64) -> Result<Vec<u8>, BitcoinError> {
+    let len = usize::try_from(len).map_err(|_| BitcoinError::NumericOverflow)?;
+    let end = cursor
+        .checked_add(len)
+        .filter(|&end| end <= bytes.len())
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    let data = bytes[*cursor..end].to_vec();
+    *cursor = end;
+    Ok(data)
+}
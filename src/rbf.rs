@@ -0,0 +1,182 @@
+//! BIP125 replace-by-fee rules.
+
+use crate::constants::MAX_BIP125_RBF_SEQUENCE;
+use crate::fee::{InputType, estimate_weight, fee_for_weight};
+use crate::policy::Policy;
+use crate::{
+    BitcoinError, BitcoinTransaction, OutPoint, Script, TransactionInput, TransactionOutput,
+};
+
+pub const MAX_REPLACEMENT_CANDIDATES: usize = 100;
+/// Core's default `-incrementalrelayfee`, in sat/kvB.
+pub const DEFAULT_INCREMENTAL_RELAY_FEE: u64 = 1000;
+
+/// The BIP125 rules, numbered as in the BIP.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum RbfRule {
+    /// 1: the original signals replaceability.
+    Signaling,
+    /// 2: the replacement spends no unconfirmed outputs the original didn't.
+    NewUnconfirmedInputs,
+    /// 3: the replacement pays at least the fees of everything it evicts.
+    AbsoluteFee,
+    /// 4: the extra fee pays for the replacement's own relay bandwidth.
+    RelayFee,
+    /// 5: at most 100 transactions are evicted.
+    TooManyReplacements,
+}
+
+/// The mempool state a replacement is judged against.
+#[derive(Debug, Clone)]
+pub struct Replacement<'a> {
+    pub original: &'a BitcoinTransaction,
+    pub original_fee: u64,
+    /// Total fees and count of the original's in-mempool descendants, which
+    /// are evicted along with it.
+    pub descendant_fees: u64,
+    pub descendant_count: usize,
+    pub replacement: &'a BitcoinTransaction,
+    pub replacement_fee: u64,
+    /// Outputs currently unconfirmed in the mempool.
+    pub unconfirmed: &'a [OutPoint],
+    pub incremental_relay_fee: u64,
+}
+
+impl Replacement<'_> {
+    /// The rules the replacement breaks, empty if it would be accepted.
+    pub fn check(&self) -> Vec<RbfRule> {
+        let mut failed = Vec::new();
+        if !signals_rbf(self.original) {
+            failed.push(RbfRule::Signaling);
+        }
+
+        let spent_before = |outpoint: &OutPoint| {
+            self.original
+                .inputs
+                .iter()
+                .any(|input| &input.previous_output == outpoint)
+        };
+        if self.replacement.inputs.iter().any(|input| {
+            let outpoint = &input.previous_output;
+            self.unconfirmed.contains(outpoint) && !spent_before(outpoint)
+        }) {
+            failed.push(RbfRule::NewUnconfirmedInputs);
+        }
+
+        let evicted_fees = self.original_fee.saturating_add(self.descendant_fees);
+        match self.replacement_fee.checked_sub(evicted_fees) {
+            None => failed.push(RbfRule::AbsoluteFee),
+            Some(extra) => {
+                let relay_fee = self.replacement.vsize() as u64 * self.incremental_relay_fee / 1000;
+                if extra < relay_fee {
+                    failed.push(RbfRule::RelayFee);
+                }
+            }
+        }
+
+        if 1 + self.descendant_count > MAX_REPLACEMENT_CANDIDATES {
+            failed.push(RbfRule::TooManyReplacements);
+        }
+        failed
+    }
+}
+
+/// True if any input has a sequence of at most `MAX_BIP125_RBF_SEQUENCE`.
+pub fn signals_rbf(tx: &BitcoinTransaction) -> bool {
+    tx.inputs
+        .iter()
+        .any(|input| input.sequence <= MAX_BIP125_RBF_SEQUENCE)
+}
+
+/// An unsigned replacement for `original` paying `new_fee_rate` sat/kvB,
+/// and enough over the original's fee for rule 4 at the default
+/// incremental relay fee.
+///
+/// The fee comes out of output `change` first, which is dropped if it would
+/// fall below dust. If that isn't enough, the largest of `coins` are added
+/// as inputs until it is. Inputs keep their sequences, and added ones take
+/// the first input's, so signaling is preserved. Every spent output must
+/// be P2PKH, P2WPKH or taproot so the signed weight can be estimated;
+/// others are `Unsupported`.
+pub fn bump_fee<F>(
+    original: &BitcoinTransaction,
+    prevout: F,
+    new_fee_rate: u64,
+    change: u32,
+    coins: &[(OutPoint, TransactionOutput)],
+) -> Result<BitcoinTransaction, BitcoinError>
+where
+    F: Fn(&OutPoint) -> Option<TransactionOutput>,
+{
+    let original_fee = original.fee(&prevout)?;
+    let change = change as usize;
+    let change_output = original
+        .outputs
+        .get(change)
+        .ok_or(BitcoinError::InvalidFormat)?;
+    let sequence = original
+        .inputs
+        .first()
+        .ok_or(BitcoinError::InvalidFormat)?
+        .sequence;
+
+    let mut types = Vec::new();
+    let mut available = original_fee + change_output.value;
+    for input in &original.inputs {
+        let spent = prevout(&input.previous_output)
+            .ok_or_else(|| BitcoinError::MissingPrevout(input.previous_output.clone()))?;
+        types.push(
+            InputType::from_script_pubkey(&spent.script_pubkey).ok_or(BitcoinError::Unsupported)?,
+        );
+    }
+    let mut coins: Vec<&(OutPoint, TransactionOutput)> = coins
+        .iter()
+        .filter(|(outpoint, _)| {
+            !original
+                .inputs
+                .iter()
+                .any(|input| &input.previous_output == outpoint)
+        })
+        .collect();
+    coins.sort_by_key(|(_, coin)| std::cmp::Reverse(coin.value));
+    let mut coins = coins.into_iter();
+
+    let mut replacement = original.clone();
+    replacement.clear_witnesses();
+    for input in &mut replacement.inputs {
+        input.script_sig = Script::new(vec![]);
+    }
+    let required = |weight| {
+        fee_for_weight(weight, new_fee_rate)
+            .max(original_fee + fee_for_weight(weight, DEFAULT_INCREMENTAL_RELAY_FEE))
+    };
+    let dust = Policy::default().dust_threshold(change_output);
+    loop {
+        let fee = required(estimate_weight(&types, &replacement.outputs));
+        if let Some(value) = available.checked_sub(fee).filter(|&value| value >= dust) {
+            replacement.outputs[change].value = value;
+            return Ok(replacement);
+        }
+        let mut without_change = replacement.outputs.clone();
+        without_change.remove(change);
+        if !without_change.is_empty()
+            && available >= required(estimate_weight(&types, &without_change))
+        {
+            replacement.outputs = without_change;
+            return Ok(replacement);
+        }
+
+        let (outpoint, coin) = coins
+            .next()
+            .ok_or(BitcoinError::InvalidTransaction("insufficient-funds"))?;
+        types.push(
+            InputType::from_script_pubkey(&coin.script_pubkey).ok_or(BitcoinError::Unsupported)?,
+        );
+        available += coin.value;
+        replacement.inputs.push(TransactionInput::new(
+            outpoint.clone(),
+            Script::new(vec![]),
+            sequence,
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+//! Matching blocks against a watch-only wallet's scripts and outpoints,
+//! for rescans.
+//!
+//! Scripts are keyed by a 64-bit hash computed once when added, so each
+//! output costs one hash of its script and a lookup. Outputs that match
+//! are watched from then on, so later blocks spending them match too.
+
+use crate::cancel::CancellationToken;
+use crate::{BitcoinError, BitcoinTransaction, Block, OutPoint, Script};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptSet {
+    state: RandomState,
+    /// Scripts by hash; collisions share a bucket.
+    scripts: HashMap<u64, Vec<Script>>,
+    outpoints: HashSet<OutPoint>,
+}
+
+/// A transaction relevant to a [`ScriptSet`], with the indexes of its
+/// matching inputs and outputs.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RelevantTransaction<'a> {
+    /// Position in the block.
+    pub index: usize,
+    pub tx: &'a BitcoinTransaction,
+    pub inputs: Vec<u32>,
+    pub outputs: Vec<u32>,
+}
+
+impl ScriptSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watches `script`; returns false if it already was.
+    pub fn insert_script(&mut self, script: Script) -> bool {
+        let bucket = self
+            .scripts
+            .entry(self.state.hash_one(&script.bytes))
+            .or_default();
+        if bucket.contains(&script) {
+            return false;
+        }
+        bucket.push(script);
+        true
+    }
+
+    /// Watches `outpoint` for spends; returns false if it already was.
+    pub fn insert_outpoint(&mut self, outpoint: OutPoint) -> bool {
+        self.outpoints.insert(outpoint)
+    }
+
+    pub fn contains_script(&self, script: &Script) -> bool {
+        self.scripts
+            .get(&self.state.hash_one(&script.bytes))
+            .is_some_and(|bucket| bucket.contains(script))
+    }
+
+    pub fn contains_outpoint(&self, outpoint: &OutPoint) -> bool {
+        self.outpoints.contains(outpoint)
+    }
+
+    /// The transactions of `block` spending a watched outpoint or paying a
+    /// watched script, in block order. Matching outputs are watched from
+    /// then on, including by later transactions in the same block.
+    pub fn match_block<'a>(&mut self, block: &'a Block) -> Vec<RelevantTransaction<'a>> {
+        let mut relevant = Vec::new();
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let inputs: Vec<u32> = (0..)
+                .zip(&tx.inputs)
+                .filter(|(_, input)| self.contains_outpoint(&input.previous_output))
+                .map(|(i, _)| i)
+                .collect();
+            let outputs: Vec<u32> = (0..)
+                .zip(&tx.outputs)
+                .filter(|(_, output)| self.contains_script(&output.script_pubkey))
+                .map(|(vout, _)| vout)
+                .collect();
+            if inputs.is_empty() && outputs.is_empty() {
+                continue;
+            }
+            let txid = tx.txid();
+            for &vout in &outputs {
+                self.outpoints.insert(OutPoint::new(txid.0, vout));
+            }
+            relevant.push(RelevantTransaction {
+                index,
+                tx,
+                inputs,
+                outputs,
+            });
+        }
+        relevant
+    }
+    /// Runs [`match_block`](ScriptSet::match_block) over `blocks` in order,
+    /// passing each block with relevant transactions to `found`, and
+    /// returns the number of blocks scanned. `cancel` is checked before each
+    /// block; a cancelled or failed rescan keeps everything watched so far,
+    /// so it can resume from the block it stopped at.
+    pub fn rescan<I, F>(
+        &mut self,
+        blocks: I,
+        cancel: &CancellationToken,
+        mut found: F,
+    ) -> Result<u64, BitcoinError>
+    where
+        I: IntoIterator<Item = Result<Block, BitcoinError>>,
+        F: FnMut(&Block, Vec<RelevantTransaction<'_>>),
+    {
+        let mut blocks = blocks.into_iter();
+        let mut scanned = 0;
+        loop {
+            cancel.check()?;
+            let Some(block) = blocks.next() else {
+                break;
+            };
+            let block = block?;
+            let relevant = self.match_block(&block);
+            if !relevant.is_empty() {
+                found(&block, relevant);
+            }
+            scanned += 1;
+        }
+        Ok(scanned)
+    }
+}
@@ -0,0 +1,118 @@
+//! Fetch-and-parse helpers for Esplora and Bitcoin Core REST endpoints.
+
+use crate::core_json::{hash_from_reversed_hex, reversed_hex};
+use crate::{BitcoinError, BitcoinTransaction, Block, Network, Txid};
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Backend {
+    Esplora,
+    /// Core's `-rest` interface, with a base URL ending in `/rest`.
+    CoreRest,
+}
+
+pub struct RestClient {
+    base_url: String,
+    backend: Backend,
+    agent: ureq::Agent,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl RestClient {
+    pub fn new(base_url: &str, backend: Backend) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            backend,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+
+    /// A public Esplora instance for the network; regtest assumes a local
+    /// electrs on its default HTTP port.
+    pub fn esplora(network: Network) -> Self {
+        let url = match network {
+            Network::Mainnet => "https://blockstream.info/api",
+            Network::Testnet => "https://blockstream.info/testnet/api",
+            Network::Signet => "https://mempool.space/signet/api",
+            Network::Regtest => "http://127.0.0.1:3002",
+        };
+        Self::new(url, Backend::Esplora)
+    }
+
+    pub fn core_rest(base_url: &str) -> Self {
+        Self::new(base_url, Backend::CoreRest)
+    }
+
+    /// Retries transport failures, `429` and `5xx` responses up to `retries`
+    /// times, doubling `delay` after each attempt.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn get_transaction(&self, txid: &Txid) -> Result<BitcoinTransaction, BitcoinError> {
+        let path = match self.backend {
+            Backend::Esplora => format!("/tx/{}/raw", reversed_hex(&txid.0)),
+            Backend::CoreRest => format!("/tx/{}.bin", reversed_hex(&txid.0)),
+        };
+        let bytes = self.get(&path)?;
+        BitcoinTransaction::from_bytes(&bytes).map(|(tx, _)| tx)
+    }
+
+    pub fn get_block(&self, hash: &[u8; 32]) -> Result<Block, BitcoinError> {
+        let path = match self.backend {
+            Backend::Esplora => format!("/block/{}/raw", reversed_hex(hash)),
+            Backend::CoreRest => format!("/block/{}.bin", reversed_hex(hash)),
+        };
+        let bytes = self.get(&path)?;
+        Block::from_bytes(&bytes).map(|(block, _)| block)
+    }
+
+    pub fn get_block_hash(&self, height: u64) -> Result<[u8; 32], BitcoinError> {
+        match self.backend {
+            Backend::Esplora => {
+                let bytes = self.get(&format!("/block-height/{}", height))?;
+                let text = String::from_utf8(bytes).map_err(|_| BitcoinError::InvalidFormat)?;
+                hash_from_reversed_hex(text.trim())
+            }
+            // The binary form is the hash in internal byte order.
+            Backend::CoreRest => self
+                .get(&format!("/blockhashbyheight/{}.bin", height))?
+                .try_into()
+                .map_err(|_| BitcoinError::InvalidFormat),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Vec<u8>, BitcoinError> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let error = match self.agent.get(&url).call() {
+                Ok(response) => {
+                    let mut bytes = Vec::new();
+                    response.into_reader().read_to_end(&mut bytes)?;
+                    return Ok(bytes);
+                }
+                Err(ureq::Error::Status(code, _)) if code != 429 && code < 500 => {
+                    return Err(BitcoinError::Http(format!("{} returned {}", url, code)));
+                }
+                Err(err) => err,
+            };
+            if attempt >= self.retries {
+                return Err(BitcoinError::Http(error.to_string()));
+            }
+            attempt += 1;
+            thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+}
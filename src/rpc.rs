@@ -0,0 +1,98 @@
+//! Thin Bitcoin Core JSON-RPC client returning this crate's types.
+
+use crate::core_json::{hash_from_reversed_hex, reversed_hex};
+use crate::{BitcoinError, BitcoinTransaction, Block, Txid};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::{Value, json};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct RpcClient {
+    url: String,
+    auth: String,
+    agent: ureq::Agent,
+    next_id: AtomicU64,
+}
+
+impl RpcClient {
+    pub fn new(url: &str, user: &str, password: &str) -> Self {
+        let auth = format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", user, password))
+        );
+        Self {
+            url: url.to_string(),
+            auth,
+            agent: ureq::AgentBuilder::new().build(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Authenticates with the `.cookie` file Core writes into its datadir.
+    pub fn with_cookie_file<P: AsRef<Path>>(url: &str, path: P) -> Result<Self, BitcoinError> {
+        let cookie = std::fs::read_to_string(path)?;
+        let (user, password) = cookie
+            .trim()
+            .split_once(':')
+            .ok_or(BitcoinError::InvalidFormat)?;
+        Ok(Self::new(url, user, password))
+    }
+
+    pub fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, BitcoinError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({ "jsonrpc": "1.0", "id": id, "method": method, "params": params });
+        // Core reports RPC failures with a non-2xx status and a JSON body.
+        let response = match self
+            .agent
+            .post(&self.url)
+            .set("Authorization", &self.auth)
+            .send_json(request)
+        {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => return Err(BitcoinError::Http(err.to_string())),
+        };
+        let status = response.status();
+        let body: Value = response
+            .into_json()
+            .map_err(|_| BitcoinError::Http(format!("status {} without JSON body", status)))?;
+        match body.get("error") {
+            Some(error) if !error.is_null() => Err(BitcoinError::Rpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            }),
+            _ => Ok(body["result"].clone()),
+        }
+    }
+
+    fn call_hex(&self, method: &str, params: Vec<Value>) -> Result<Vec<u8>, BitcoinError> {
+        let result = self.call(method, params)?;
+        let hex_str = result.as_str().ok_or(BitcoinError::InvalidFormat)?;
+        hex::decode(hex_str).map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    pub fn get_raw_transaction(&self, txid: &Txid) -> Result<BitcoinTransaction, BitcoinError> {
+        let bytes = self.call_hex("getrawtransaction", vec![json!(reversed_hex(&txid.0))])?;
+        BitcoinTransaction::from_bytes(&bytes).map(|(tx, _)| tx)
+    }
+
+    pub fn get_block_hash(&self, height: u64) -> Result<[u8; 32], BitcoinError> {
+        let result = self.call("getblockhash", vec![json!(height)])?;
+        hash_from_reversed_hex(result.as_str().ok_or(BitcoinError::InvalidFormat)?)
+    }
+
+    /// `getblock <hash> 0`, i.e. the raw serialized block.
+    pub fn get_block(&self, hash: &[u8; 32]) -> Result<Block, BitcoinError> {
+        let bytes = self.call_hex("getblock", vec![json!(reversed_hex(hash)), json!(0)])?;
+        Block::from_bytes(&bytes).map(|(block, _)| block)
+    }
+
+    pub fn send_raw_transaction(&self, tx: &BitcoinTransaction) -> Result<Txid, BitcoinError> {
+        let result = self.call(
+            "sendrawtransaction",
+            vec![json!(hex::encode(tx.to_bytes()))],
+        )?;
+        let txid = hash_from_reversed_hex(result.as_str().ok_or(BitcoinError::InvalidFormat)?)?;
+        Ok(Txid(txid))
+    }
+}
@@ -0,0 +1,159 @@
+//! JSON Schemas for the crate's serde forms and for the Core-shaped JSON
+//! from [`core_json`](crate::core_json), for services that validate API
+//! payloads. The serde types implement [`JsonSchema`], so
+//! `schemars::schema_for!(BitcoinTransaction)` works directly; Core's forms
+//! are built from `serde_json::Value`, so their schemas are here.
+
+use crate::{Script, Txid};
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaGenerator;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, StringValidation};
+
+fn hex_string(pattern: &str, description: &str) -> Schema {
+    let mut schema = SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern.to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    schema.metadata().description = Some(description.to_string());
+    schema.into()
+}
+
+impl JsonSchema for Txid {
+    fn schema_name() -> String {
+        "Txid".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            "^[0-9a-fA-F]{64}$",
+            "32-byte hash as hex, in internal byte order",
+        )
+    }
+}
+
+/// Scripts are hex in human-readable formats such as JSON.
+impl JsonSchema for Script {
+    fn schema_name() -> String {
+        "Script".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string("^([0-9a-fA-F]{2})*$", "script bytes as hex")
+    }
+}
+
+/// A hash in RPC byte order.
+struct ReversedHash;
+
+impl JsonSchema for ReversedHash {
+    fn schema_name() -> String {
+        "ReversedHash".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            "^[0-9a-fA-F]{64}$",
+            "32-byte hash as hex, in RPC (reversed) byte order",
+        )
+    }
+}
+
+/// Hex of arbitrary bytes.
+struct Hex;
+
+impl JsonSchema for Hex {
+    fn schema_name() -> String {
+        "Hex".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string("^([0-9a-fA-F]{2})*$", "bytes as hex")
+    }
+}
+
+// The structs below only describe `core_json`'s output and are never built.
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreScriptSig {
+    hex: Hex,
+}
+
+/// A coinbase input has `coinbase` in place of `txid`, `vout` and
+/// `scriptSig`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreInput {
+    coinbase: Option<Hex>,
+    txid: Option<ReversedHash>,
+    vout: Option<u32>,
+    #[serde(rename = "scriptSig")]
+    script_sig: Option<CoreScriptSig>,
+    txinwitness: Option<Vec<Hex>>,
+    sequence: u32,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreScriptPubKey {
+    hex: Hex,
+    r#type: String,
+}
+
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreOutput {
+    /// Amount in BTC.
+    value: f64,
+    n: usize,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: CoreScriptPubKey,
+}
+
+/// `decoderawtransaction`'s result.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreTransaction {
+    txid: ReversedHash,
+    hash: ReversedHash,
+    version: u32,
+    size: usize,
+    vsize: usize,
+    weight: usize,
+    locktime: u32,
+    vin: Vec<CoreInput>,
+    vout: Vec<CoreOutput>,
+}
+
+/// `getblock <hash> 2`'s result.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct CoreBlock {
+    hash: ReversedHash,
+    version: u32,
+    merkleroot: ReversedHash,
+    time: u32,
+    nonce: u32,
+    /// Compact target as 8 hex digits.
+    bits: String,
+    #[serde(rename = "nTx")]
+    n_tx: usize,
+    size: usize,
+    /// Absent for the genesis block.
+    previousblockhash: Option<ReversedHash>,
+    tx: Vec<CoreTransaction>,
+}
+
+/// Schema for [`transaction_to_json`](crate::core_json::transaction_to_json).
+pub fn core_transaction_schema() -> RootSchema {
+    schemars::schema_for!(CoreTransaction)
+}
+
+/// Schema for [`block_to_json`](crate::core_json::block_to_json).
+pub fn core_block_schema() -> RootSchema {
+    schemars::schema_for!(CoreBlock)
+}
@@ -0,0 +1,299 @@
+//! Parsing scripts into instructions, and Core-style ASM.
+
+use crate::opcodes::{self, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4};
+use crate::{BitcoinError, Script, TransactionInput};
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    /// A data push, whether by a direct length opcode or an `OP_PUSHDATA*`.
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opcode = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        let (len_size, len) = match opcode {
+            0x00..=0x4b => (0, opcode as usize),
+            OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => {
+                let len_size = match opcode {
+                    OP_PUSHDATA1 => 1,
+                    OP_PUSHDATA2 => 2,
+                    _ => 4,
+                };
+                let Some(len_bytes) = self.bytes.get(self.pos..self.pos + len_size) else {
+                    self.pos = self.bytes.len();
+                    return Some(Err(BitcoinError::InsufficientBytes));
+                };
+                let mut le = [0u8; 4];
+                le[..len_size].copy_from_slice(len_bytes);
+                (len_size, u32::from_le_bytes(le) as usize)
+            }
+            _ => return Some(Ok(Instruction::Op(opcode))),
+        };
+        let start = self.pos + len_size;
+        match start
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+        {
+            Some(end) => {
+                self.pos = end;
+                Some(Ok(Instruction::PushBytes(&self.bytes[start..end])))
+            }
+            None => {
+                self.pos = self.bytes.len();
+                Some(Err(BitcoinError::InsufficientBytes))
+            }
+        }
+    }
+}
+
+impl Script {
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            bytes: &self.bytes,
+            pos: 0,
+        }
+    }
+
+    /// Renders the script the way Core's `scriptPubKey.asm` does: pushes of
+    /// up to four bytes as numbers, longer ones as hex.
+    pub fn to_asm(&self) -> String {
+        let mut parts = Vec::new();
+        for instruction in self.instructions() {
+            match instruction {
+                Ok(Instruction::PushBytes(data)) if data.len() <= 4 => {
+                    parts.push(script_num(data).to_string())
+                }
+                Ok(Instruction::PushBytes(data)) => parts.push(hex::encode(data)),
+                Ok(Instruction::Op(opcode)) => parts.push(opcodes::name(opcode).to_string()),
+                Err(_) => {
+                    parts.push("[error]".to_string());
+                    break;
+                }
+            }
+        }
+        parts.join(" ")
+    }
+
+    pub fn push_opcode(&mut self, opcode: u8) -> &mut Self {
+        self.bytes.push(opcode);
+        self
+    }
+
+    /// Appends a minimal push of `data`.
+    pub fn push_slice(&mut self, data: &[u8]) -> &mut Self {
+        match data.len() {
+            0..=0x4b => self.bytes.push(data.len() as u8),
+            0x4c..=0xff => self.bytes.extend([OP_PUSHDATA1, data.len() as u8]),
+            0x100..=0xffff => {
+                self.bytes.push(OP_PUSHDATA2);
+                self.bytes.extend((data.len() as u16).to_le_bytes());
+            }
+            _ => {
+                self.bytes.push(OP_PUSHDATA4);
+                self.bytes.extend((data.len() as u32).to_le_bytes());
+            }
+        }
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    /// Appends `n` as `OP_0`, `OP_1NEGATE`, `OP_1`..`OP_16`, or a script
+    /// number push.
+    pub fn push_int(&mut self, n: i64) -> &mut Self {
+        match n {
+            0 => self.push_opcode(opcodes::OP_0),
+            -1 => self.push_opcode(opcodes::OP_1NEGATE),
+            1..=16 => self.push_opcode(opcodes::OP_1 + n as u8 - 1),
+            _ => self.push_slice(&encode_script_num(n)),
+        }
+    }
+
+    /// True if the script only pushes data (opcodes up to `OP_16`), as Core
+    /// requires of standard scriptSigs.
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(Instruction::PushBytes(_)) => true,
+            Ok(Instruction::Op(opcode)) => opcode <= opcodes::OP_16,
+            Err(_) => false,
+        })
+    }
+
+    /// Parses `OP_m <key>... OP_n OP_CHECKMULTISIG` with 33- or 65-byte
+    /// keys. Counts above 16 may be pushed as script numbers, up to
+    /// Core's limit of 20 keys.
+    pub fn parse_multisig(&self) -> Option<Multisig> {
+        let instructions: Vec<Instruction> = self.instructions().collect::<Result<_, _>>().ok()?;
+        let [
+            first,
+            middle @ ..,
+            last,
+            Instruction::Op(opcodes::OP_CHECKMULTISIG),
+        ] = &instructions[..]
+        else {
+            return None;
+        };
+        let (required, total) = (multisig_count(first)?, multisig_count(last)?);
+        let keys = middle
+            .iter()
+            .map(|instruction| match *instruction {
+                Instruction::PushBytes(key) if matches!(key.len(), 33 | 65) => Some(key.to_vec()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        (required >= 1 && required <= total && keys.len() == total as usize)
+            .then_some(Multisig { required, keys })
+    }
+
+    /// True if the script's instructions match `template` one for one.
+    pub fn matches_template(&self, template: &ScriptTemplate) -> bool {
+        template.captures(self).is_some()
+    }
+}
+
+/// Core's `MAX_PUBKEYS_PER_MULTISIG`.
+pub const MAX_MULTISIG_KEYS: u8 = 20;
+
+/// An `m`-of-`n` `OP_CHECKMULTISIG` script's threshold and keys.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Multisig {
+    pub required: u8,
+    pub keys: Vec<Vec<u8>>,
+}
+
+fn multisig_count(instruction: &Instruction) -> Option<u8> {
+    let count = match *instruction {
+        Instruction::Op(opcode @ opcodes::OP_1..=opcodes::OP_16) => opcode - opcodes::OP_1 + 1,
+        // Smaller counts have to use OP_1 to OP_16.
+        Instruction::PushBytes(&[count]) if count > 16 => count,
+        _ => return None,
+    };
+    (count <= MAX_MULTISIG_KEYS).then_some(count)
+}
+
+impl TransactionInput {
+    /// The multisig script this input spends: the witness script for P2WSH
+    /// (native or nested), otherwise the P2SH redeem script.
+    pub fn multisig(&self) -> Option<Multisig> {
+        let script = match self.witness.last() {
+            Some(witness_script) => Script::new(witness_script.clone()),
+            None => match self.script_sig.instructions().last()?.ok()? {
+                Instruction::PushBytes(redeem_script) => Script::new(redeem_script.to_vec()),
+                Instruction::Op(_) => return None,
+            },
+        };
+        script.parse_multisig()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum TemplateToken {
+    Op(u8),
+    /// A push of exactly this many bytes.
+    Push(usize),
+    AnyPush,
+    AnyInstruction,
+}
+
+/// A script pattern such as `OP_DUP OP_HASH160 <20> OP_EQUALVERIFY
+/// OP_CHECKSIG`. Tokens are opcode names (with or without `OP_`, numbers
+/// 0 to 16 included), `<n>` for a push of exactly `n` bytes, `<*>` for any
+/// push and `*` for any single instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScriptTemplate {
+    tokens: Vec<TemplateToken>,
+}
+
+impl ScriptTemplate {
+    /// The data of each `<n>` and `<*>` push (and pushes matched by `*`)
+    /// if the script matches.
+    pub fn captures<'a>(&self, script: &'a Script) -> Option<Vec<&'a [u8]>> {
+        let mut instructions = script.instructions();
+        let mut captures = Vec::new();
+        for token in &self.tokens {
+            let instruction = instructions.next()?.ok()?;
+            match (token, instruction) {
+                // OP_0 parses as an empty push.
+                (TemplateToken::Op(opcodes::OP_0), Instruction::PushBytes([])) => {}
+                (TemplateToken::Op(expected), Instruction::Op(opcode)) if *expected == opcode => {}
+                (TemplateToken::Push(len), Instruction::PushBytes(data)) if data.len() == *len => {
+                    captures.push(data)
+                }
+                (
+                    TemplateToken::AnyPush | TemplateToken::AnyInstruction,
+                    Instruction::PushBytes(data),
+                ) => captures.push(data),
+                (TemplateToken::AnyInstruction, Instruction::Op(_)) => {}
+                _ => return None,
+            }
+        }
+        instructions.next().is_none().then_some(captures)
+    }
+}
+
+impl FromStr for ScriptTemplate {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = s
+            .split_whitespace()
+            .map(|token| match token {
+                "*" => Ok(TemplateToken::AnyInstruction),
+                "<*>" => Ok(TemplateToken::AnyPush),
+                _ => match token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+                    Some(len) => len
+                        .parse()
+                        .map(TemplateToken::Push)
+                        .map_err(|_| BitcoinError::InvalidFormat),
+                    None => opcodes::from_name(token)
+                        .map(TemplateToken::Op)
+                        .ok_or(BitcoinError::InvalidFormat),
+                },
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ScriptTemplate { tokens })
+    }
+}
+
+/// Decodes a minimal little-endian sign-magnitude script number.
+pub fn script_num(data: &[u8]) -> i64 {
+    let Some(&last) = data.last() else {
+        return 0;
+    };
+    let mut value: i64 = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        value |= (byte as i64) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        -(value & !(0x80 << (8 * (data.len() - 1))))
+    } else {
+        value
+    }
+}
+
+/// Encodes `n` as a minimal script number, the inverse of `script_num`.
+pub fn encode_script_num(n: i64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut magnitude = n.unsigned_abs();
+    while magnitude > 0 {
+        bytes.push(magnitude as u8);
+        magnitude >>= 8;
+    }
+    // The top bit of the last byte is the sign, so add a byte if it's taken.
+    match bytes.last_mut() {
+        Some(last) if *last & 0x80 != 0 => bytes.push(if n < 0 { 0x80 } else { 0 }),
+        Some(last) if n < 0 => *last |= 0x80,
+        _ => {}
+    }
+    bytes
+}
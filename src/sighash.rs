@@ -0,0 +1,204 @@
+//! Sighash flags, and reading signatures and their flags back out of
+//! existing inputs.
+
+use crate::TransactionInput;
+use crate::script::Instruction;
+use crate::taproot::{ControlBlock, TAPROOT_LEAF_TAPSCRIPT};
+use std::fmt;
+
+/// The byte appended to a signature, selecting what it commits to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct SighashType(pub u8);
+
+impl SighashType {
+    /// Taproot only: a 64-byte signature, committing like `ALL`.
+    pub const DEFAULT: SighashType = SighashType(0x00);
+    pub const ALL: SighashType = SighashType(0x01);
+    pub const NONE: SighashType = SighashType(0x02);
+    pub const SINGLE: SighashType = SighashType(0x03);
+    pub const ANYONECANPAY: u8 = 0x80;
+
+    /// The output mode, with `DEFAULT` counting as `ALL`.
+    pub fn base(self) -> SighashType {
+        match self.0 & 0x1f {
+            0 => SighashType::ALL,
+            base => SighashType(base),
+        }
+    }
+
+    /// Only the signed input is committed to; others can be added.
+    pub fn anyone_can_pay(self) -> bool {
+        self.0 & Self::ANYONECANPAY != 0
+    }
+
+    /// No outputs are committed to, so they can be changed at will.
+    pub fn is_none(self) -> bool {
+        self.base() == SighashType::NONE
+    }
+
+    pub fn is_single(self) -> bool {
+        self.base() == SighashType::SINGLE
+    }
+
+    /// `ALL`, `NONE` or `SINGLE`, with or without `ANYONECANPAY`, or
+    /// taproot's `DEFAULT`.
+    pub fn is_defined(self) -> bool {
+        matches!(self.0 & !Self::ANYONECANPAY, 0x01..=0x03) || self == Self::DEFAULT
+    }
+}
+
+impl fmt::Display for SighashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_defined() {
+            return write!(f, "{:#04x}", self.0);
+        }
+        let base = match self.0 & 0x1f {
+            0x00 => "DEFAULT",
+            0x01 => "ALL",
+            0x02 => "NONE",
+            _ => "SINGLE",
+        };
+        write!(f, "{}", base)?;
+        if self.anyone_can_pay() {
+            write!(f, "|ANYONECANPAY")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where in the input a signature was found.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SignaturePosition {
+    /// Index among the scriptSig's pushes.
+    ScriptSig(usize),
+    /// Index in the witness stack.
+    Witness(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Signature {
+    /// DER-encoded, without the sighash byte.
+    Ecdsa(Vec<u8>),
+    Schnorr([u8; 64]),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExtractedSignature {
+    pub signature: Signature,
+    pub sighash_type: SighashType,
+    /// Serialized key for ECDSA; `None` for taproot key-path spends, whose
+    /// key is in the spent output.
+    pub public_key: Option<Vec<u8>>,
+    pub position: SignaturePosition,
+}
+
+/// BIP66 strict DER encoding followed by a sighash byte, as Core's
+/// `IsValidSignatureEncoding` checks.
+pub fn is_ecdsa_signature(sig: &[u8]) -> bool {
+    if !(9..=73).contains(&sig.len()) || sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+    let valid_integer = |start: usize, len: usize| {
+        sig[start - 2] == 0x02
+            && len != 0
+            && sig[start] & 0x80 == 0
+            && !(len > 1 && sig[start] == 0 && sig[start + 1] & 0x80 == 0)
+    };
+    valid_integer(4, len_r) && valid_integer(6 + len_r, len_s)
+}
+
+impl TransactionInput {
+    /// The sighash types of the signatures in this input, in order: DER
+    /// signatures pushed by the scriptSig or in the witness, then Schnorr
+    /// signatures of taproot key-path (a lone 64- or 65-byte element) and
+    /// tapscript spends. Found by shape alone, without the spent output.
+    pub fn sighash_types(&self) -> Vec<SighashType> {
+        let pushes = self
+            .script_sig
+            .instructions()
+            .filter_map(|instruction| match instruction {
+                Ok(Instruction::PushBytes(data)) => Some(data),
+                _ => None,
+            });
+        let mut types: Vec<SighashType> = pushes
+            .chain(self.witness.iter().map(Vec::as_slice))
+            .filter(|item| is_ecdsa_signature(item))
+            .map(|sig| SighashType(sig[sig.len() - 1]))
+            .collect();
+
+        let items = self.witness.without_annex();
+        let schnorr = match items {
+            [_] => items,
+            [stack @ .., _, control]
+                if ControlBlock::from_bytes(control)
+                    .is_ok_and(|(control, _)| control.leaf_version == TAPROOT_LEAF_TAPSCRIPT) =>
+            {
+                stack
+            }
+            _ => &[],
+        };
+        types.extend(schnorr.iter().filter_map(|sig| match sig.len() {
+            64 => Some(SighashType::DEFAULT),
+            65 => Some(SighashType(sig[64])),
+            _ => None,
+        }));
+        types
+    }
+
+    /// The signature and key of a P2PKH, P2WPKH (native or nested in
+    /// P2SH) or taproot key-path spend, recognized by the input's shape.
+    /// Empty for other inputs.
+    pub fn extract_signatures(&self) -> Vec<ExtractedSignature> {
+        let pushes: Option<Vec<&[u8]>> = self
+            .script_sig
+            .instructions()
+            .map(|instruction| match instruction {
+                Ok(Instruction::PushBytes(data)) => Some(data),
+                _ => None,
+            })
+            .collect();
+        let Some(pushes) = pushes else {
+            return Vec::new();
+        };
+        let ecdsa = |sig: &[u8], key: &[u8], position| {
+            let key_valid = matches!(
+                (key.len(), key.first()),
+                (33, Some(0x02 | 0x03)) | (65, Some(0x04))
+            );
+            (is_ecdsa_signature(sig) && key_valid).then(|| ExtractedSignature {
+                signature: Signature::Ecdsa(sig[..sig.len() - 1].to_vec()),
+                sighash_type: SighashType(sig[sig.len() - 1]),
+                public_key: Some(key.to_vec()),
+                position,
+            })
+        };
+        let nested_p2wpkh = match &pushes[..] {
+            [program] => program.len() == 22 && program.starts_with(&[0x00, 0x14]),
+            _ => false,
+        };
+        let found = match (&pushes[..], self.witness.without_annex()) {
+            ([sig, key], []) => ecdsa(sig, key, SignaturePosition::ScriptSig(0)),
+            (_, [sig, key]) if pushes.is_empty() || nested_p2wpkh => {
+                ecdsa(sig, key, SignaturePosition::Witness(0))
+            }
+            ([], [sig]) if sig.len() == 64 || sig.len() == 65 => Some(ExtractedSignature {
+                signature: Signature::Schnorr(sig[..64].try_into().unwrap()),
+                sighash_type: sig
+                    .get(64)
+                    .map_or(SighashType::DEFAULT, |&b| SighashType(b)),
+                public_key: None,
+                position: SignaturePosition::Witness(0),
+            }),
+            _ => None,
+        };
+        found.into_iter().collect()
+    }
+}
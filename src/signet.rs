@@ -0,0 +1,243 @@
+//! Signet block solutions (BIP325).
+//!
+//! A signet block carries a solution to the network's challenge script in
+//! its coinbase witness commitment output, as a push starting with
+//! [`SIGNET_HEADER`]. The solution spends a virtual `to_spend` transaction
+//! that commits to the block with that push cleared.
+//!
+//! There is no script interpreter, so only bare `OP_CHECKMULTISIG`
+//! challenges (what Core's signet tooling sets up) and `OP_TRUE` are
+//! evaluated, with `SIGHASH_ALL` signatures. Other challenges fail with
+//! `Unsupported`, as does everything without the `secp` feature.
+
+use crate::opcodes::{OP_0, OP_RETURN};
+use crate::script::Instruction;
+use crate::{
+    BitcoinError, BitcoinTransaction, Block, OutPoint, Script, TransactionInput, TransactionOutput,
+    Witness, block, hashes,
+};
+
+pub const SIGNET_HEADER: [u8; 4] = [0xec, 0xc7, 0xda, 0xa2];
+
+const SIGHASH_ALL: u8 = 0x01;
+
+/// The scriptSig and witness spending the challenge.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignetSolution {
+    pub script_sig: Script,
+    pub witness: Witness,
+}
+
+impl SignetSolution {
+    pub fn new(script_sig: Script, witness: Witness) -> Self {
+        Self {
+            script_sig,
+            witness,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = self.script_sig.to_bytes();
+        v.extend(self.witness.to_bytes());
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (script_sig, used) = Script::from_bytes(bytes)?;
+        let (witness, used2) = Witness::from_bytes(&bytes[used..])?;
+        Ok((SignetSolution::new(script_sig, witness), used + used2))
+    }
+}
+
+impl Block {
+    /// The solution carried by the coinbase, if its witness commitment has
+    /// a signet section.
+    pub fn signet_solution(&self) -> Result<Option<SignetSolution>, BitcoinError> {
+        let Some(index) = self.witness_commitment_index() else {
+            return Ok(None);
+        };
+        let script = &self.transactions[0].outputs[index].script_pubkey;
+        match split_commitment(script) {
+            Some((_, section)) => parse_solution(&section).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// BIP325's `to_spend` and `to_sign` transactions for `challenge`.
+    /// `to_sign` carries the block's solution, or an empty one if it has
+    /// none. Blocks without a witness commitment have no signet data and
+    /// are rejected.
+    pub fn signet_transactions(
+        &self,
+        challenge: &Script,
+    ) -> Result<(BitcoinTransaction, BitcoinTransaction), BitcoinError> {
+        let reject = || BitcoinError::InvalidTransaction("bad-signet-blksig");
+        let index = self.witness_commitment_index().ok_or_else(reject)?;
+        let mut coinbase = self.transactions[0].clone();
+        let mut solution = SignetSolution::new(Script::new(Vec::new()), Witness::default());
+        let commitment = &mut coinbase.outputs[index].script_pubkey;
+        if let Some((cleared, section)) = split_commitment(commitment) {
+            *commitment = cleared;
+            solution = parse_solution(&section).map_err(|_| reject())?;
+        }
+
+        let mut txids: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.txid().0).collect();
+        txids[0] = coinbase.txid().0;
+        let mut block_data = self.header.version.to_le_bytes().to_vec();
+        block_data.extend_from_slice(&self.header.prev_blockhash);
+        block_data.extend_from_slice(&block::merkle_root(&txids));
+        block_data.extend_from_slice(&self.header.time.to_le_bytes());
+
+        let mut script_sig = Script::new(Vec::new());
+        script_sig.push_opcode(OP_0).push_slice(&block_data);
+        let to_spend = BitcoinTransaction::new(
+            0,
+            vec![TransactionInput::new(OutPoint::null(), script_sig, 0)],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(0, challenge.clone())]);
+
+        let mut input =
+            TransactionInput::new(OutPoint::new(to_spend.txid().0, 0), solution.script_sig, 0);
+        input.witness = solution.witness;
+        let to_sign =
+            BitcoinTransaction::new(0, vec![input], 0).with_outputs(vec![TransactionOutput::new(
+                0,
+                Script::new(vec![OP_RETURN]),
+            )]);
+        Ok((to_spend, to_sign))
+    }
+
+    /// The legacy `SIGHASH_ALL` digest that signers of a bare challenge
+    /// sign.
+    pub fn signet_sighash(&self, challenge: &Script) -> Result<[u8; 32], BitcoinError> {
+        let (_, mut to_sign) = self.signet_transactions(challenge)?;
+        to_sign.inputs[0].script_sig = challenge.clone();
+        to_sign.clear_witnesses();
+        let mut data = to_sign.to_bytes_without_witness();
+        data.extend_from_slice(&(SIGHASH_ALL as u32).to_le_bytes());
+        Ok(hashes::sha256d(&data))
+    }
+
+    /// Checks the block's solution against `challenge`, failing with
+    /// `bad-signet-blksig` if it doesn't satisfy it. The genesis block
+    /// needs no solution.
+    pub fn check_signet_solution(&self, challenge: &Script) -> Result<(), BitcoinError> {
+        if self.header.prev_blockhash == [0; 32] {
+            return Ok(());
+        }
+        let (_, to_sign) = self.signet_transactions(challenge)?;
+        if satisfies(self, challenge, &to_sign.inputs[0])? {
+            Ok(())
+        } else {
+            Err(BitcoinError::InvalidTransaction("bad-signet-blksig"))
+        }
+    }
+}
+
+// Splits the first push that starts with the signet header and has data
+// after it, returning the script with that push cut down to the header.
+fn split_commitment(script: &Script) -> Option<(Script, Vec<u8>)> {
+    let mut cleared = Script::new(Vec::new());
+    let mut section = None;
+    for instruction in script.instructions() {
+        match instruction {
+            Ok(Instruction::PushBytes(data))
+                if section.is_none()
+                    && data.len() > SIGNET_HEADER.len()
+                    && data.starts_with(&SIGNET_HEADER) =>
+            {
+                section = Some(data[SIGNET_HEADER.len()..].to_vec());
+                cleared.push_slice(&SIGNET_HEADER);
+            }
+            Ok(Instruction::PushBytes(data)) => {
+                cleared.push_slice(data);
+            }
+            Ok(Instruction::Op(opcode)) => {
+                cleared.push_opcode(opcode);
+            }
+            Err(_) => break,
+        }
+    }
+    section.map(|section| (cleared, section))
+}
+
+// The section must hold exactly one solution.
+fn parse_solution(section: &[u8]) -> Result<SignetSolution, BitcoinError> {
+    let (solution, used) = SignetSolution::from_bytes(section)?;
+    if used != section.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(solution)
+}
+
+#[cfg(feature = "secp")]
+fn satisfies(
+    block: &Block,
+    challenge: &Script,
+    input: &TransactionInput,
+) -> Result<bool, BitcoinError> {
+    use crate::opcodes::OP_1;
+    use crate::sigverify::SignatureCheck;
+
+    // Only witness programs may be spent with a witness.
+    if !input.witness.is_empty() || !input.script_sig.is_push_only() {
+        return Ok(false);
+    }
+    if challenge.bytes == [OP_1] {
+        return Ok(true);
+    }
+    let multisig = challenge
+        .parse_multisig()
+        .ok_or(BitcoinError::Unsupported)?;
+    let required = multisig.required as usize;
+    // The dummy and signatures are data pushes; small number opcodes
+    // aren't worth evaluating.
+    let mut stack = Vec::new();
+    for instruction in input.script_sig.instructions() {
+        match instruction? {
+            Instruction::PushBytes(data) => stack.push(data),
+            Instruction::Op(_) => return Ok(false),
+        }
+    }
+    // The dummy element popped by OP_CHECKMULTISIG must be empty
+    // (BIP147), followed by the signatures in key order.
+    let Some(start) = stack.len().checked_sub(required + 1) else {
+        return Ok(false);
+    };
+    if !stack[start].is_empty() {
+        return Ok(false);
+    }
+    let sighash = block.signet_sighash(challenge)?;
+    let secp = secp256k1::Secp256k1::verification_only();
+    let mut keys = multisig.keys.iter();
+    for signature in &stack[start + 1..] {
+        let Some((&hash_type, der)) = signature.split_last() else {
+            return Ok(false);
+        };
+        if hash_type != SIGHASH_ALL {
+            return Err(BitcoinError::Unsupported);
+        }
+        let found = keys.by_ref().any(|key| {
+            SignatureCheck::Ecdsa {
+                sighash,
+                signature: der.to_vec(),
+                public_key: key.clone(),
+            }
+            .verify(&secp)
+        });
+        if !found {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(not(feature = "secp"))]
+fn satisfies(
+    _block: &Block,
+    _challenge: &Script,
+    _input: &TransactionInput,
+) -> Result<bool, BitcoinError> {
+    Err(BitcoinError::Unsupported)
+}
@@ -0,0 +1,127 @@
+//! Batched ECDSA and Schnorr signature checks.
+//!
+//! libsecp256k1 has no batch verification API, so a batch is checked in
+//! parallel on the rayon thread pool when the `rayon` feature is enabled,
+//! and sequentially otherwise.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use secp256k1::{Message, PublicKey, Secp256k1, VerifyOnly, XOnlyPublicKey, ecdsa, schnorr};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SignatureCheck {
+    /// A DER signature (without the sighash byte) against a serialized
+    /// public key. High-S signatures are accepted, as by consensus.
+    Ecdsa {
+        sighash: [u8; 32],
+        signature: Vec<u8>,
+        public_key: Vec<u8>,
+    },
+    /// A BIP340 signature against an x-only key.
+    Schnorr {
+        sighash: [u8; 32],
+        signature: [u8; 64],
+        public_key: [u8; 32],
+    },
+}
+
+impl SignatureCheck {
+    pub(crate) fn verify(&self, secp: &Secp256k1<VerifyOnly>) -> bool {
+        match self {
+            SignatureCheck::Ecdsa {
+                sighash,
+                signature,
+                public_key,
+            } => {
+                let (Ok(mut signature), Ok(key)) = (
+                    ecdsa::Signature::from_der_lax(signature),
+                    PublicKey::from_slice(public_key),
+                ) else {
+                    return false;
+                };
+                signature.normalize_s();
+                let msg = Message::from_digest(*sighash);
+                secp.verify_ecdsa(&msg, &signature, &key).is_ok()
+            }
+            SignatureCheck::Schnorr {
+                sighash,
+                signature,
+                public_key,
+            } => {
+                let (Ok(signature), Ok(key)) = (
+                    schnorr::Signature::from_slice(signature),
+                    XOnlyPublicKey::from_slice(public_key),
+                ) else {
+                    return false;
+                };
+                let msg = Message::from_digest(*sighash);
+                secp.verify_schnorr(&signature, &msg, &key).is_ok()
+            }
+        }
+    }
+}
+
+/// Signature checks gathered from many inputs, verified together.
+#[derive(Debug, Default, Clone)]
+pub struct BatchVerifier {
+    checks: Vec<SignatureCheck>,
+}
+
+impl BatchVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, check: SignatureCheck) {
+        self.checks.push(check);
+    }
+
+    pub fn push_ecdsa(&mut self, sighash: [u8; 32], signature: &[u8], public_key: &[u8]) {
+        self.push(SignatureCheck::Ecdsa {
+            sighash,
+            signature: signature.to_vec(),
+            public_key: public_key.to_vec(),
+        });
+    }
+
+    pub fn push_schnorr(&mut self, sighash: [u8; 32], signature: [u8; 64], public_key: [u8; 32]) {
+        self.push(SignatureCheck::Schnorr {
+            sighash,
+            signature,
+            public_key,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.checks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// The indexes, in push order, of the checks that failed; empty if every
+    /// signature is valid.
+    pub fn verify(&self) -> Vec<usize> {
+        let secp = Secp256k1::verification_only();
+        #[cfg(feature = "rayon")]
+        let checks = self.checks.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let checks = self.checks.iter();
+        checks
+            .enumerate()
+            .filter(|(_, check)| !check.verify(&secp))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// True if every signature is valid.
+    pub fn verify_all(&self) -> bool {
+        #[cfg(feature = "rayon")]
+        let checks = self.checks.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let mut checks = self.checks.iter();
+        let secp = Secp256k1::verification_only();
+        checks.all(|check| check.verify(&secp))
+    }
+}
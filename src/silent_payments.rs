@@ -0,0 +1,251 @@
+//! BIP352 silent payments: sending to and scanning for outputs derived
+//! from a recipient's scan and spend keys. Labels are not supported.
+
+use crate::hashes::{hash160, tagged_hash};
+use crate::script::Instruction;
+use crate::taproot::ControlBlock;
+use crate::{
+    BitcoinError, BitcoinTransaction, OutPoint, Script, TransactionInput, TransactionOutput,
+};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+
+/// The BIP341 NUMS point `H`; script-path spends with this internal key
+/// contribute no input key.
+pub const NUMS_H: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// A recipient's compressed scan and spend public keys, as carried in an
+/// `sp1` address.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SilentPaymentAddress {
+    pub scan_key: [u8; 33],
+    pub spend_key: [u8; 33],
+}
+
+impl SilentPaymentAddress {
+    /// The x-only key of the `k`th output to this address, given the
+    /// secret key of every eligible input (with whether it spends a P2TR
+    /// output) and the outpoints of all inputs.
+    pub fn output_key(
+        &self,
+        input_secrets: &[([u8; 32], bool)],
+        outpoints: &[OutPoint],
+        k: u32,
+    ) -> Result<[u8; 32], BitcoinError> {
+        let secp = Secp256k1::new();
+        let invalid = |_| BitcoinError::InvalidFormat;
+        let mut sum: Option<SecretKey> = None;
+        for (secret, taproot) in input_secrets {
+            let mut key = SecretKey::from_slice(secret).map_err(invalid)?;
+            // Taproot keys are used with even y.
+            if *taproot && key.x_only_public_key(&secp).1 == secp256k1::Parity::Odd {
+                key = key.negate();
+            }
+            sum = Some(match sum {
+                None => key,
+                Some(sum) => sum.add_tweak(&Scalar::from(key)).map_err(invalid)?,
+            });
+        }
+        let sum = sum.ok_or(BitcoinError::InvalidFormat)?;
+        let input_hash = input_hash(outpoints, &PublicKey::from_secret_key(&secp, &sum))?;
+        let shared = PublicKey::from_slice(&self.scan_key)
+            .and_then(|scan| scan.mul_tweak(&secp, &Scalar::from(sum)))
+            .and_then(|point| point.mul_tweak(&secp, &input_hash))
+            .map_err(invalid)?;
+        output_key(&shared, &self.spend_key, k).map(|(key, _)| key)
+    }
+}
+
+/// An output found by [`Receiver::scan`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SilentPaymentOutput {
+    pub vout: u32,
+    pub output_key: [u8; 32],
+    /// Added to the spend secret key to get the output's secret key.
+    pub tweak: [u8; 32],
+}
+
+/// The scan secret and spend public key needed to detect payments.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Receiver {
+    pub scan_secret: [u8; 32],
+    pub spend_key: [u8; 33],
+}
+
+impl Receiver {
+    /// The outputs of `tx` paying this receiver; `prevouts` are the outputs
+    /// its inputs spend, in input order.
+    pub fn scan(
+        &self,
+        tx: &BitcoinTransaction,
+        prevouts: &[TransactionOutput],
+    ) -> Result<Vec<SilentPaymentOutput>, BitcoinError> {
+        let Some(tweak) = tweak_data(tx, prevouts)? else {
+            return Ok(Vec::new());
+        };
+        let secp = Secp256k1::verification_only();
+        let scan =
+            SecretKey::from_slice(&self.scan_secret).map_err(|_| BitcoinError::InvalidFormat)?;
+        let shared = PublicKey::from_slice(&tweak)
+            .and_then(|point| point.mul_tweak(&secp, &Scalar::from(scan)))
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+
+        let mut found = Vec::new();
+        for k in 0.. {
+            let (output_key, tweak) = output_key(&shared, &self.spend_key, k)?;
+            let vout = tx.outputs.iter().position(|output| {
+                output
+                    .script_pubkey
+                    .witness_program()
+                    .is_some_and(|program| program.is_p2tr() && program.program == output_key)
+            });
+            match vout {
+                Some(vout) => found.push(SilentPaymentOutput {
+                    vout: vout as u32,
+                    output_key,
+                    tweak,
+                }),
+                None => break,
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// `input_hash·A`, the per-transaction point a receiver multiplies by its
+/// scan key. `None` if the transaction can't contain silent payments: it
+/// has no P2TR output or no eligible input, or spends a witness version
+/// above 1.
+pub fn tweak_data(
+    tx: &BitcoinTransaction,
+    prevouts: &[TransactionOutput],
+) -> Result<Option<[u8; 33]>, BitcoinError> {
+    if prevouts.len() != tx.inputs.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let has_taproot_output = tx.outputs.iter().any(|output| {
+        output
+            .script_pubkey
+            .witness_program()
+            .is_some_and(|program| program.is_p2tr())
+    });
+    if !has_taproot_output {
+        return Ok(None);
+    }
+    let mut keys = Vec::new();
+    for (input, prevout) in tx.inputs.iter().zip(prevouts) {
+        if let Some(program) = prevout.script_pubkey.witness_program()
+            && program.version.to_u8() > 1
+        {
+            return Ok(None);
+        }
+        if let Some(key) = input_public_key(input, &prevout.script_pubkey) {
+            keys.push(PublicKey::from_slice(&key).map_err(|_| BitcoinError::InvalidFormat)?);
+        }
+    }
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    let refs: Vec<&PublicKey> = keys.iter().collect();
+    let Ok(sum) = PublicKey::combine_keys(&refs) else {
+        // The keys cancelled out.
+        return Ok(None);
+    };
+    let outpoints: Vec<OutPoint> = tx
+        .inputs
+        .iter()
+        .map(|input| input.previous_output.clone())
+        .collect();
+    let tweak = sum
+        .mul_tweak(
+            &Secp256k1::verification_only(),
+            &input_hash(&outpoints, &sum)?,
+        )
+        .map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok(Some(tweak.serialize()))
+}
+
+/// The compressed public key an input contributes, if it spends an
+/// eligible P2PKH, P2SH-P2WPKH, P2WPKH or P2TR output.
+pub fn input_public_key(input: &TransactionInput, prevout: &Script) -> Option<[u8; 33]> {
+    let compressed = |item: &[u8]| -> Option<[u8; 33]> {
+        match item.first() {
+            Some(0x02 | 0x03) => item.try_into().ok(),
+            _ => None,
+        }
+    };
+    if let Some(program) = prevout.witness_program() {
+        if program.is_p2wpkh() {
+            return compressed(input.witness.last()?);
+        }
+        if program.is_p2tr() {
+            if let Some(Ok((_, control))) = ControlBlock::from_witness(&input.witness)
+                && control.internal_key == NUMS_H
+            {
+                return None;
+            }
+            let mut key = [0x02; 33];
+            key[1..].copy_from_slice(&program.program);
+            return Some(key);
+        }
+        return None;
+    }
+    let b = &prevout.bytes[..];
+    match b {
+        // P2SH-P2WPKH: the scriptSig pushes the witness program.
+        [0xa9, 0x14, .., 0x87] if b.len() == 23 => {
+            let redeem = &input.script_sig.bytes;
+            if redeem.len() == 23 && redeem[..3] == [0x16, 0x00, 0x14] {
+                return compressed(input.witness.last()?);
+            }
+            None
+        }
+        [0x76, 0xa9, 0x14, .., 0x88, 0xac] if b.len() == 25 => {
+            let pushes: Vec<&[u8]> = input
+                .script_sig
+                .instructions()
+                .filter_map(|instruction| match instruction {
+                    Ok(Instruction::PushBytes(data)) => Some(data),
+                    _ => None,
+                })
+                .collect();
+            pushes
+                .into_iter()
+                .rev()
+                .filter_map(compressed)
+                .find(|key| hash160(key)[..] == b[3..23])
+        }
+        _ => None,
+    }
+}
+
+/// `hash_BIP0352/Inputs(outpoint_L || A)` over the smallest serialized
+/// outpoint.
+fn input_hash(outpoints: &[OutPoint], sum: &PublicKey) -> Result<Scalar, BitcoinError> {
+    let mut data = outpoints
+        .iter()
+        .map(OutPoint::to_bytes)
+        .min()
+        .ok_or(BitcoinError::InvalidFormat)?;
+    data.extend_from_slice(&sum.serialize());
+    Scalar::from_be_bytes(tagged_hash("BIP0352/Inputs", &data))
+        .map_err(|_| BitcoinError::InvalidFormat)
+}
+
+/// `B_spend + t_k·G` and `t_k` for the shared secret `ecdh`.
+fn output_key(
+    ecdh: &PublicKey,
+    spend_key: &[u8; 33],
+    k: u32,
+) -> Result<([u8; 32], [u8; 32]), BitcoinError> {
+    let mut data = ecdh.serialize().to_vec();
+    data.extend_from_slice(&k.to_be_bytes());
+    let t = tagged_hash("BIP0352/SharedSecret", &data);
+    let tweak = Scalar::from_be_bytes(t).map_err(|_| BitcoinError::InvalidFormat)?;
+    let key = PublicKey::from_slice(spend_key)
+        .and_then(|key| key.add_exp_tweak(&Secp256k1::verification_only(), &tweak))
+        .map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok((key.x_only_public_key().0.serialize(), t))
+}
@@ -0,0 +1,149 @@
+//! Where a transaction's bytes and weight go, field by field.
+
+use crate::constants::WITNESS_SCALE_FACTOR;
+use crate::{BitcoinTransaction, CompactSize};
+use std::iter::Sum;
+use std::ops::Add;
+
+/// Bytes counted at full weight (`base`) and at the witness discount.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ByteCount {
+    pub base: usize,
+    pub witness: usize,
+}
+
+impl ByteCount {
+    pub fn total(&self) -> usize {
+        self.base + self.witness
+    }
+
+    pub fn weight(&self) -> usize {
+        self.base * WITNESS_SCALE_FACTOR + self.witness
+    }
+}
+
+impl Add for ByteCount {
+    type Output = ByteCount;
+
+    fn add(self, other: ByteCount) -> ByteCount {
+        ByteCount {
+            base: self.base + other.base,
+            witness: self.witness + other.witness,
+        }
+    }
+}
+
+impl Sum for ByteCount {
+    fn sum<I: Iterator<Item = ByteCount>>(iter: I) -> ByteCount {
+        iter.fold(ByteCount::default(), Add::add)
+    }
+}
+
+/// Byte counts of one input. Script and witness sizes include their length
+/// prefixes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InputSize {
+    pub outpoint: usize,
+    pub script_sig: usize,
+    pub sequence: usize,
+    /// Zero in transactions without witnesses; otherwise at least the
+    /// one-byte item count.
+    pub witness: usize,
+}
+
+impl InputSize {
+    pub fn bytes(&self) -> ByteCount {
+        ByteCount {
+            base: self.outpoint + self.script_sig + self.sequence,
+            witness: self.witness,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OutputSize {
+    pub value: usize,
+    pub script_pubkey: usize,
+}
+
+impl OutputSize {
+    pub fn bytes(&self) -> ByteCount {
+        ByteCount {
+            base: self.value + self.script_pubkey,
+            witness: 0,
+        }
+    }
+}
+
+/// Byte counts of each part of a serialized transaction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SizeBreakdown {
+    pub version: usize,
+    /// The segwit marker and flag, witness-discounted.
+    pub marker_flag: usize,
+    pub input_count: usize,
+    pub inputs: Vec<InputSize>,
+    pub output_count: usize,
+    pub outputs: Vec<OutputSize>,
+    pub lock_time: usize,
+}
+
+impl SizeBreakdown {
+    /// Everything outside the inputs and outputs.
+    pub fn overhead(&self) -> ByteCount {
+        ByteCount {
+            base: self.version + self.input_count + self.output_count + self.lock_time,
+            witness: self.marker_flag,
+        }
+    }
+
+    pub fn total(&self) -> ByteCount {
+        self.overhead()
+            + self.inputs.iter().map(InputSize::bytes).sum()
+            + self.outputs.iter().map(OutputSize::bytes).sum()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.total().weight()
+    }
+}
+
+impl BitcoinTransaction {
+    /// Breaks the serialized size down by field; totals match
+    /// `to_bytes().len()` and `weight()`.
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let compact_len = |n: usize| CompactSize::new(n as u64).to_bytes().len();
+        let segwit = self.has_witness();
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| InputSize {
+                outpoint: 36,
+                script_sig: compact_len(input.script_sig.len()) + input.script_sig.len(),
+                sequence: 4,
+                witness: if segwit {
+                    input.witness.to_bytes().len()
+                } else {
+                    0
+                },
+            })
+            .collect();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| OutputSize {
+                value: 8,
+                script_pubkey: compact_len(output.script_pubkey.len()) + output.script_pubkey.len(),
+            })
+            .collect();
+        SizeBreakdown {
+            version: 4,
+            marker_flag: if segwit { 2 } else { 0 },
+            input_count: compact_len(self.inputs.len()),
+            inputs,
+            output_count: compact_len(self.outputs.len()),
+            outputs,
+            lock_time: 4,
+        }
+    }
+}
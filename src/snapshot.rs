@@ -0,0 +1,170 @@
+//! Reader for the UTXO set snapshots written by Core's `dumptxoutset` and
+//! loaded by assumeutxo (format version 2, Core 28 and later).
+//!
+//! After a header, coins are grouped by txid: each group is the txid, a
+//! `CompactSize` coin count, then a `CompactSize` vout and a chainstate
+//! encoded [`Coin`] per output.
+
+use crate::constants::MAX_SCRIPT_SIZE;
+use crate::hash_types::BlockHash;
+use crate::undo::Coin;
+use crate::{BitcoinError, CompactSize, Network, OutPoint, VarInt};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+pub const SNAPSHOT_MAGIC: [u8; 5] = *b"utxo\xff";
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SnapshotMetadata {
+    pub network: Network,
+    /// The block whose UTXO set the snapshot holds.
+    pub base_blockhash: BlockHash,
+    pub coins_count: u64,
+}
+
+impl SnapshotMetadata {
+    pub const SIZE: usize = 5 + 2 + 4 + 32 + 8;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = SNAPSHOT_MAGIC.to_vec();
+        v.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        v.extend_from_slice(&self.network.magic());
+        v.extend_from_slice(&self.base_blockhash.0);
+        v.extend_from_slice(&self.coins_count.to_le_bytes());
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < Self::SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        if bytes[..5] != SNAPSHOT_MAGIC {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        if u16::from_le_bytes([bytes[5], bytes[6]]) != SNAPSHOT_VERSION {
+            return Err(BitcoinError::Unsupported);
+        }
+        let network = Network::from_magic(bytes[7..11].try_into().unwrap())
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let metadata = SnapshotMetadata {
+            network,
+            base_blockhash: BlockHash(bytes[11..43].try_into().unwrap()),
+            coins_count: u64::from_le_bytes(bytes[43..51].try_into().unwrap()),
+        };
+        Ok((metadata, Self::SIZE))
+    }
+}
+
+/// Streams `(OutPoint, Coin)` entries from a snapshot, stopping after the
+/// number of coins the header announces.
+pub struct SnapshotReader<R> {
+    reader: R,
+    pub metadata: SnapshotMetadata,
+    coins_read: u64,
+    txid: [u8; 32],
+    group_left: u64,
+    done: bool,
+}
+
+impl SnapshotReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BitcoinError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Reads the header; coins are read as the iterator advances.
+    pub fn new(mut reader: R) -> Result<Self, BitcoinError> {
+        let mut header = [0u8; SnapshotMetadata::SIZE];
+        reader.read_exact(&mut header)?;
+        let (metadata, _) = SnapshotMetadata::from_bytes(&header)?;
+        Ok(Self {
+            reader,
+            metadata,
+            coins_read: 0,
+            txid: [0; 32],
+            group_left: 0,
+            done: false,
+        })
+    }
+
+    fn read_entry(&mut self) -> Result<(OutPoint, Coin), BitcoinError> {
+        if self.group_left == 0 {
+            self.reader.read_exact(&mut self.txid)?;
+            self.group_left = self.read_compact_size()?;
+            if self.group_left == 0 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        let vout =
+            u32::try_from(self.read_compact_size()?).map_err(|_| BitcoinError::InvalidFormat)?;
+
+        // Code, amount and script size are VarInts; the size says how much
+        // script data follows.
+        let mut raw = Vec::new();
+        self.read_varint_into(&mut raw)?;
+        self.read_varint_into(&mut raw)?;
+        let size_start = raw.len();
+        self.read_varint_into(&mut raw)?;
+        let (size, _) = VarInt::from_bytes(&raw[size_start..])?;
+        let data_len = match size.value {
+            0 | 1 => 20,
+            2..=5 => 32,
+            n => usize::try_from(n - 6).map_err(|_| BitcoinError::NumericOverflow)?,
+        };
+        if data_len > MAX_SCRIPT_SIZE {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let start = raw.len();
+        raw.resize(start + data_len, 0);
+        self.reader.read_exact(&mut raw[start..])?;
+        let (coin, _) = Coin::from_bytes(&raw)?;
+
+        self.group_left -= 1;
+        self.coins_read += 1;
+        Ok((OutPoint::new(self.txid, vout), coin))
+    }
+
+    fn read_compact_size(&mut self) -> Result<u64, BitcoinError> {
+        let mut bytes = [0u8; 9];
+        self.reader.read_exact(&mut bytes[..1])?;
+        let len = match bytes[0] {
+            0xfd => 3,
+            0xfe => 5,
+            0xff => 9,
+            _ => 1,
+        };
+        self.reader.read_exact(&mut bytes[1..len])?;
+        Ok(CompactSize::from_bytes(&bytes[..len])?.0.value)
+    }
+
+    fn read_varint_into(&mut self, raw: &mut Vec<u8>) -> Result<(), BitcoinError> {
+        // A u64 VarInt is at most ten bytes.
+        for _ in 0..10 {
+            let mut byte = [0u8; 1];
+            self.reader.read_exact(&mut byte)?;
+            raw.push(byte[0]);
+            if byte[0] & 0x80 == 0 {
+                return Ok(());
+            }
+        }
+        Err(BitcoinError::InvalidFormat)
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = Result<(OutPoint, Coin), BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.coins_read == self.metadata.coins_count {
+            return None;
+        }
+        let entry = self.read_entry();
+        if entry.is_err() {
+            self.done = true;
+        }
+        Some(entry)
+    }
+}
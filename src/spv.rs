@@ -0,0 +1,313 @@
+//! Simplified payment verification: BIP37 `merkleblock` messages, header
+//! chain checks, and [`SpvProof`] tying them together.
+//!
+//! Header checks cover linkage and proof of work against each header's own
+//! `bits`; difficulty retargeting isn't checked, so callers should anchor
+//! the chain at a header they trust.
+
+use crate::block::{MerkleBranch, verify_merkle_branch};
+use crate::constants::MAX_BLOCK_WEIGHT;
+use crate::{BitcoinError, Block, BlockHeader, CompactSize, Txid, hashes};
+
+/// No block can hold more transactions than this (60 weight units each).
+const MAX_TRANSACTIONS: u32 = (MAX_BLOCK_WEIGHT / 60) as u32;
+
+/// The merkle root a partial tree commits to, and its matched transactions
+/// with their branches.
+pub type ExtractedMatches = ([u8; 32], Vec<(Txid, MerkleBranch)>);
+
+/// A block header with a partial merkle tree proving some of its
+/// transactions.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MerkleBlock {
+    pub header: BlockHeader,
+    pub total_transactions: u32,
+    pub hashes: Vec<[u8; 32]>,
+    /// Traversal flag bits, least significant bit first.
+    pub flags: Vec<u8>,
+}
+
+impl MerkleBlock {
+    /// Builds the proof for the transactions of `block` in `txids`, the way
+    /// Core answers a filtered block request.
+    pub fn from_block(block: &Block, txids: &[Txid]) -> Self {
+        let leaves: Vec<[u8; 32]> = block.transactions.iter().map(|tx| tx.txid().0).collect();
+        let matches: Vec<bool> = leaves
+            .iter()
+            .map(|leaf| txids.iter().any(|txid| txid.0 == *leaf))
+            .collect();
+        let mut builder = TreeBuilder {
+            leaves: &leaves,
+            matches: &matches,
+            hashes: Vec::new(),
+            bits: Vec::new(),
+        };
+        builder.build(tree_height(leaves.len() as u32), 0);
+        let mut flags = vec![0u8; builder.bits.len().div_ceil(8)];
+        for (i, bit) in builder.bits.iter().enumerate() {
+            flags[i / 8] |= (*bit as u8) << (i % 8);
+        }
+        MerkleBlock {
+            header: block.header.clone(),
+            total_transactions: leaves.len() as u32,
+            hashes: builder.hashes,
+            flags,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = self.header.to_bytes();
+        v.extend_from_slice(&self.total_transactions.to_le_bytes());
+        v.extend(CompactSize::new(self.hashes.len() as u64).to_bytes());
+        for hash in &self.hashes {
+            v.extend_from_slice(hash);
+        }
+        v.extend(CompactSize::new(self.flags.len() as u64).to_bytes());
+        v.extend_from_slice(&self.flags);
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (header, mut cursor) = BlockHeader::from_bytes(bytes)?;
+        let total = bytes
+            .get(cursor..cursor + 4)
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        let total_transactions = u32::from_le_bytes(total.try_into().unwrap());
+        cursor += 4;
+        let (count, used) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        let mut hashes = Vec::new();
+        for _ in 0..count.value {
+            let hash = bytes
+                .get(cursor..cursor + 32)
+                .ok_or(BitcoinError::InsufficientBytes)?;
+            hashes.push(hash.try_into().unwrap());
+            cursor += 32;
+        }
+        let (flag_count, used) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        let flag_len = flag_count.to_usize()?;
+        let flags = cursor
+            .checked_add(flag_len)
+            .and_then(|end| bytes.get(cursor..end))
+            .ok_or(BitcoinError::InsufficientBytes)?
+            .to_vec();
+        cursor += flags.len();
+        let merkle_block = MerkleBlock {
+            header,
+            total_transactions,
+            hashes,
+            flags,
+        };
+        Ok((merkle_block, cursor))
+    }
+
+    /// Walks the partial tree, returning the merkle root it commits to and
+    /// each matched transaction with its branch. Fails on malformed trees,
+    /// including ones using Core's duplicate-subtree trick (CVE-2012-2459).
+    pub fn extract_matches(&self) -> Result<ExtractedMatches, BitcoinError> {
+        let total = self.total_transactions;
+        if total == 0 || total > MAX_TRANSACTIONS || self.hashes.len() > total as usize {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        if self.flags.len() * 8 < self.hashes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut walker = TreeWalker {
+            total,
+            hashes: &self.hashes,
+            flags: &self.flags,
+            hashes_used: 0,
+            bits_used: 0,
+        };
+        let (root, matches) = walker.walk(tree_height(total), 0)?;
+        // Every hash must be used, and only the last flag byte padded.
+        if walker.hashes_used != self.hashes.len()
+            || walker.bits_used.div_ceil(8) != self.flags.len()
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let matches = matches
+            .into_iter()
+            .map(|(txid, index, hashes)| (txid, MerkleBranch { index, hashes }))
+            .collect();
+        Ok((root, matches))
+    }
+}
+
+/// Checks that `headers` link up in order and each meets its own proof of
+/// work target.
+pub fn validate_header_chain(headers: &[BlockHeader]) -> Result<(), BitcoinError> {
+    for (i, header) in headers.iter().enumerate() {
+        if !header.check_pow() {
+            return Err(BitcoinError::InvalidTransaction("high-hash"));
+        }
+        if i > 0 && header.prev_blockhash != headers[i - 1].block_hash() {
+            return Err(BitcoinError::InvalidTransaction("bad-prevblk"));
+        }
+    }
+    Ok(())
+}
+
+/// Proof that a transaction is confirmed in the block with `header`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpvProof {
+    pub header: BlockHeader,
+    pub txid: Txid,
+    pub branch: MerkleBranch,
+}
+
+impl SpvProof {
+    /// Proves `txid` from a full block, or `None` if it isn't in it.
+    pub fn from_block(block: &Block, txid: Txid) -> Option<Self> {
+        let branch = block.merkle_branch(&txid)?;
+        Some(SpvProof {
+            header: block.header.clone(),
+            txid,
+            branch,
+        })
+    }
+
+    /// Proves `txid` from a `merkleblock`, checking the partial tree leads
+    /// to the header's merkle root.
+    pub fn from_merkle_block(merkle_block: &MerkleBlock, txid: Txid) -> Result<Self, BitcoinError> {
+        let (root, matches) = merkle_block.extract_matches()?;
+        if root != merkle_block.header.merkle_root {
+            return Err(BitcoinError::InvalidTransaction("bad-txnmrklroot"));
+        }
+        let (_, branch) = matches
+            .into_iter()
+            .find(|(matched, _)| *matched == txid)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        Ok(SpvProof {
+            header: merkle_block.header.clone(),
+            txid,
+            branch,
+        })
+    }
+
+    /// Verifies the proof against a header chain, returning the number of
+    /// confirmations: 1 if the block is the last header.
+    pub fn verify(&self, headers: &[BlockHeader]) -> Result<u32, BitcoinError> {
+        validate_header_chain(headers)?;
+        if !verify_merkle_branch(&self.txid, &self.branch, &self.header.merkle_root) {
+            return Err(BitcoinError::InvalidTransaction("bad-txnmrklroot"));
+        }
+        let position = headers
+            .iter()
+            .position(|header| *header == self.header)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        Ok((headers.len() - position) as u32)
+    }
+}
+
+fn tree_height(leaves: u32) -> u32 {
+    let mut height = 0;
+    while tree_width(leaves, height) > 1 {
+        height += 1;
+    }
+    height
+}
+
+fn tree_width(leaves: u32, height: u32) -> u32 {
+    (leaves + (1 << height) - 1) >> height
+}
+
+struct TreeBuilder<'a> {
+    leaves: &'a [[u8; 32]],
+    matches: &'a [bool],
+    hashes: Vec<[u8; 32]>,
+    bits: Vec<bool>,
+}
+
+impl TreeBuilder<'_> {
+    fn subtree_hash(&self, height: u32, pos: u32) -> [u8; 32] {
+        if height == 0 {
+            return self.leaves[pos as usize];
+        }
+        let left = self.subtree_hash(height - 1, pos * 2);
+        let right = if pos * 2 + 1 < tree_width(self.leaves.len() as u32, height - 1) {
+            self.subtree_hash(height - 1, pos * 2 + 1)
+        } else {
+            left
+        };
+        hashes::sha256d(&[left, right].concat())
+    }
+
+    fn build(&mut self, height: u32, pos: u32) {
+        let start = (pos << height) as usize;
+        let end = (((pos + 1) << height) as usize).min(self.leaves.len());
+        let matched = self.matches[start..end].iter().any(|&m| m);
+        self.bits.push(matched);
+        if height == 0 || !matched {
+            self.hashes.push(self.subtree_hash(height, pos));
+        } else {
+            self.build(height - 1, pos * 2);
+            if pos * 2 + 1 < tree_width(self.leaves.len() as u32, height - 1) {
+                self.build(height - 1, pos * 2 + 1);
+            }
+        }
+    }
+}
+
+// A matched txid, its position and its siblings so far, from the leaf up.
+type Match = (Txid, u32, Vec<[u8; 32]>);
+
+struct TreeWalker<'a> {
+    total: u32,
+    hashes: &'a [[u8; 32]],
+    flags: &'a [u8],
+    hashes_used: usize,
+    bits_used: usize,
+}
+
+impl TreeWalker<'_> {
+    fn walk(&mut self, height: u32, pos: u32) -> Result<([u8; 32], Vec<Match>), BitcoinError> {
+        let flag = *self
+            .flags
+            .get(self.bits_used / 8)
+            .ok_or(BitcoinError::InvalidFormat)?
+            >> (self.bits_used % 8)
+            & 1
+            == 1;
+        self.bits_used += 1;
+        if height == 0 || !flag {
+            let hash = *self
+                .hashes
+                .get(self.hashes_used)
+                .ok_or(BitcoinError::InvalidFormat)?;
+            self.hashes_used += 1;
+            let matches = if height == 0 && flag {
+                vec![(Txid(hash), pos, Vec::new())]
+            } else {
+                Vec::new()
+            };
+            return Ok((hash, matches));
+        }
+        let (left, mut matches) = self.walk(height - 1, pos * 2)?;
+        let right = if pos * 2 + 1 < tree_width(self.total, height - 1) {
+            let (right, right_matches) = self.walk(height - 1, pos * 2 + 1)?;
+            if right == left {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            for (_, _, siblings) in &mut matches {
+                siblings.push(right);
+            }
+            matches.extend(
+                right_matches
+                    .into_iter()
+                    .map(|(txid, index, mut siblings)| {
+                        siblings.push(left);
+                        (txid, index, siblings)
+                    }),
+            );
+            right
+        } else {
+            for (_, _, siblings) in &mut matches {
+                siblings.push(left);
+            }
+            left
+        };
+        Ok((hashes::sha256d(&[left, right].concat()), matches))
+    }
+}
@@ -0,0 +1,245 @@
+//! A small SQLite block indexer: headers, transactions, outputs and the
+//! inputs spending them, in one file.
+//!
+//! Each block is written in a single SQLite transaction along with the new
+//! tip, so after a crash the database holds whole blocks only; resume by
+//! feeding blocks from [`tip`](SqliteIndex::tip) + 1. As with
+//! [`ScriptIndex`](crate::index::ScriptIndex), blocks must come in order
+//! and reorgs aren't handled.
+
+use crate::{BitcoinError, Block, OutPoint, Script, Txid};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS headers (
+        height INTEGER PRIMARY KEY,
+        hash BLOB NOT NULL UNIQUE,
+        prev_hash BLOB NOT NULL,
+        merkle_root BLOB NOT NULL,
+        version INTEGER NOT NULL,
+        time INTEGER NOT NULL,
+        bits INTEGER NOT NULL,
+        nonce INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS txs (
+        txid BLOB PRIMARY KEY,
+        height INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        lock_time INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        weight INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS outputs (
+        txid BLOB NOT NULL,
+        vout INTEGER NOT NULL,
+        value INTEGER NOT NULL,
+        script_pubkey BLOB NOT NULL,
+        PRIMARY KEY (txid, vout)
+    );
+    CREATE INDEX IF NOT EXISTS outputs_by_script ON outputs (script_pubkey);
+    CREATE TABLE IF NOT EXISTS spends (
+        txid BLOB NOT NULL,
+        vout INTEGER NOT NULL,
+        spending_txid BLOB NOT NULL,
+        vin INTEGER NOT NULL,
+        height INTEGER NOT NULL,
+        PRIMARY KEY (txid, vout)
+    );
+";
+
+fn db_error(err: rusqlite::Error) -> BitcoinError {
+    BitcoinError::Database(err.to_string())
+}
+
+fn txid_from(bytes: Vec<u8>) -> Result<Txid, BitcoinError> {
+    bytes
+        .try_into()
+        .map(Txid)
+        .map_err(|_| BitcoinError::InvalidFormat)
+}
+
+pub struct SqliteIndex {
+    conn: Connection,
+}
+
+impl SqliteIndex {
+    /// Opens or creates the index at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BitcoinError> {
+        Self::with_connection(Connection::open(path).map_err(db_error)?)
+    }
+
+    pub fn in_memory() -> Result<Self, BitcoinError> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, BitcoinError> {
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// The height and hash of the last indexed block, if any.
+    pub fn tip(&self) -> Result<Option<(u32, [u8; 32])>, BitcoinError> {
+        self.conn
+            .query_row(
+                "SELECT height, hash FROM headers ORDER BY height DESC LIMIT 1",
+                [],
+                |row| Ok((row.get::<_, u32>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()
+            .map_err(db_error)?
+            .map(|(height, hash)| {
+                let hash = hash.try_into().map_err(|_| BitcoinError::InvalidFormat)?;
+                Ok((height, hash))
+            })
+            .transpose()
+    }
+
+    /// Indexes `block` at `height`, which must follow the tip, with the
+    /// block building on the tip's hash. The first block may be at any
+    /// height; spends of outputs from before it are still recorded.
+    pub fn index_block(&mut self, block: &Block, height: u32) -> Result<(), BitcoinError> {
+        if let Some((tip, hash)) = self.tip()?
+            && (tip.checked_add(1) != Some(height) || block.header.prev_blockhash != hash)
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let db = self.conn.transaction().map_err(db_error)?;
+        let header = &block.header;
+        db.execute(
+            "INSERT INTO headers VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                height,
+                &block.block_hash()[..],
+                &header.prev_blockhash[..],
+                &header.merkle_root[..],
+                header.version,
+                header.time,
+                header.bits,
+                header.nonce,
+            ],
+        )
+        .map_err(db_error)?;
+        {
+            let mut insert_tx = db
+                .prepare("INSERT OR REPLACE INTO txs VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")
+                .map_err(db_error)?;
+            let mut insert_output = db
+                .prepare("INSERT OR REPLACE INTO outputs VALUES (?1, ?2, ?3, ?4)")
+                .map_err(db_error)?;
+            let mut insert_spend = db
+                .prepare("INSERT OR REPLACE INTO spends VALUES (?1, ?2, ?3, ?4, ?5)")
+                .map_err(db_error)?;
+            for (position, tx) in block.transactions.iter().enumerate() {
+                let txid = tx.txid();
+                insert_tx
+                    .execute(params![
+                        &txid.0[..],
+                        height,
+                        position as u32,
+                        tx.version,
+                        tx.lock_time,
+                        tx.to_bytes().len() as u64,
+                        tx.weight() as u64,
+                    ])
+                    .map_err(db_error)?;
+                for (vin, input) in tx.inputs.iter().enumerate() {
+                    let outpoint = &input.previous_output;
+                    if outpoint.is_null() {
+                        continue;
+                    }
+                    insert_spend
+                        .execute(params![
+                            &outpoint.txid.0[..],
+                            outpoint.vout,
+                            &txid.0[..],
+                            vin as u32,
+                            height,
+                        ])
+                        .map_err(db_error)?;
+                }
+                for (vout, output) in tx.outputs.iter().enumerate() {
+                    insert_output
+                        .execute(params![
+                            &txid.0[..],
+                            vout as u32,
+                            output.value as i64,
+                            &output.script_pubkey[..],
+                        ])
+                        .map_err(db_error)?;
+                }
+            }
+        }
+        db.commit().map_err(db_error)
+    }
+
+    /// The hash of the block indexed at `height`.
+    pub fn block_hash(&self, height: u32) -> Result<Option<[u8; 32]>, BitcoinError> {
+        self.conn
+            .query_row(
+                "SELECT hash FROM headers WHERE height = ?1",
+                [height],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()
+            .map_err(db_error)?
+            .map(|hash| hash.try_into().map_err(|_| BitcoinError::InvalidFormat))
+            .transpose()
+    }
+
+    /// The height of the block containing `txid` and the transaction's
+    /// position in it.
+    pub fn transaction_location(&self, txid: &Txid) -> Result<Option<(u32, u32)>, BitcoinError> {
+        self.conn
+            .query_row(
+                "SELECT height, position FROM txs WHERE txid = ?1",
+                [&txid.0[..]],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// The transaction and input index spending `outpoint`, if indexed.
+    pub fn spent_by(&self, outpoint: &OutPoint) -> Result<Option<(Txid, u32)>, BitcoinError> {
+        self.conn
+            .query_row(
+                "SELECT spending_txid, vin FROM spends WHERE txid = ?1 AND vout = ?2",
+                params![&outpoint.txid.0[..], outpoint.vout],
+                |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, u32>(1)?)),
+            )
+            .optional()
+            .map_err(db_error)?
+            .map(|(txid, vin)| Ok((txid_from(txid)?, vin)))
+            .transpose()
+    }
+
+    /// Unspent outputs paying to `script` and their values, in outpoint
+    /// order.
+    pub fn unspent(&self, script: &Script) -> Result<Vec<(OutPoint, u64)>, BitcoinError> {
+        let mut query = self
+            .conn
+            .prepare(
+                "SELECT o.txid, o.vout, o.value FROM outputs o
+                 LEFT JOIN spends s ON s.txid = o.txid AND s.vout = o.vout
+                 WHERE o.script_pubkey = ?1 AND s.txid IS NULL
+                 ORDER BY o.txid, o.vout",
+            )
+            .map_err(db_error)?;
+        let rows = query
+            .query_map([&script[..]], |row| {
+                Ok((
+                    row.get::<_, Vec<u8>>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(db_error)?;
+        rows.map(|row| {
+            let (txid, vout, value) = row.map_err(db_error)?;
+            Ok((OutPoint::new(txid_from(txid)?.0, vout), value as u64))
+        })
+        .collect()
+    }
+}
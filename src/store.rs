@@ -0,0 +1,267 @@
+//! Key-value storage behind the crate's indexes: the UTXO set, the
+//! [`ScriptIndex`](crate::index::ScriptIndex) address index and the header
+//! chain.
+//!
+//! [`MemoryStore`] is provided. A disk backend such as RocksDB or sled
+//! only needs to implement [`KvStore`], overriding
+//! [`write_batch`](KvStore::write_batch) with the database's own atomic
+//! batch; the default applies the operations one by one.
+
+use crate::block::BlockHeader;
+use crate::chainstate::coin_key;
+use crate::undo::Coin;
+use crate::{BitcoinError, Block, OutPoint, TransactionOutput, VarInt};
+use std::collections::BTreeMap;
+
+/// A key and its value.
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BatchOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// Writes to apply together.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct WriteBatch {
+    pub ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.ops.push(BatchOp::Put(key.to_vec(), value.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: &[u8]) {
+        self.ops.push(BatchOp::Delete(key.to_vec()));
+    }
+}
+
+pub trait KvStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BitcoinError>;
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), BitcoinError>;
+    fn delete(&mut self, key: &[u8]) -> Result<(), BitcoinError>;
+    /// Entries whose keys start with `prefix`, in key order.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<Entry>, BitcoinError>;
+
+    /// Applies `batch` in order. Backends with atomic batches should
+    /// override this so a crash can't leave half of one written.
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), BitcoinError> {
+        for op in batch.ops {
+            match op {
+                BatchOp::Put(key, value) => self.put(&key, &value)?,
+                BatchOp::Delete(key) => self.delete(&key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryStore {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BitcoinError> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), BitcoinError> {
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), BitcoinError> {
+        self.entries.remove(key);
+        Ok(())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<Entry>, BitcoinError> {
+        Ok(self
+            .entries
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// Unspent outputs, keyed as in Core's chainstate by [`coin_key`]. Values
+/// hold the height and coinbase flag as Core does but the output
+/// uncompressed, so any script can be stored without the `secp` feature.
+pub struct UtxoSet<S> {
+    store: S,
+}
+
+impl<S: KvStore> UtxoSet<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Result<Option<Coin>, BitcoinError> {
+        self.store
+            .get(&coin_key(outpoint))?
+            .map(|bytes| decode_coin(&bytes))
+            .transpose()
+    }
+
+    /// Spends the block's inputs and adds its outputs in one batch,
+    /// returning the spent coins in input order, as undo data lists them.
+    /// Provably unspendable `OP_RETURN` outputs are skipped. Fails with
+    /// [`BitcoinError::MissingPrevout`], writing nothing, if an input's
+    /// coin isn't in the set.
+    pub fn connect_block(&mut self, block: &Block, height: u32) -> Result<Vec<Coin>, BitcoinError> {
+        let mut batch = WriteBatch::new();
+        let mut created = BTreeMap::new();
+        let mut spent = Vec::new();
+        for tx in &block.transactions {
+            let txid = tx.txid();
+            if !tx.is_coinbase() {
+                for input in &tx.inputs {
+                    let key = coin_key(&input.previous_output);
+                    let coin = match created.remove(&key) {
+                        Some(coin) => coin,
+                        None => self.get(&input.previous_output)?.ok_or_else(|| {
+                            BitcoinError::MissingPrevout(input.previous_output.clone())
+                        })?,
+                    };
+                    batch.delete(&key);
+                    spent.push(coin);
+                }
+            }
+            for (vout, output) in (0..).zip(&tx.outputs) {
+                if output.script_pubkey.first() == Some(&0x6a) {
+                    continue;
+                }
+                let coin = Coin::new(height, tx.is_coinbase(), output.clone());
+                let key = coin_key(&OutPoint::new(txid.0, vout));
+                batch.put(&key, &encode_coin(&coin));
+                created.insert(key, coin);
+            }
+        }
+        self.store.write_batch(batch)?;
+        Ok(spent)
+    }
+}
+
+fn encode_coin(coin: &Coin) -> Vec<u8> {
+    let code = (coin.height as u64) << 1 | coin.coinbase as u64;
+    let mut v = VarInt::new(code).to_bytes();
+    coin.output.encode_to(&mut v);
+    v
+}
+
+fn decode_coin(bytes: &[u8]) -> Result<Coin, BitcoinError> {
+    let (code, used) = VarInt::from_bytes(bytes)?;
+    let height = u32::try_from(code.value >> 1).map_err(|_| BitcoinError::InvalidFormat)?;
+    let (output, _) = TransactionOutput::from_bytes(&bytes[used..])?;
+    Ok(Coin::new(height, code.value & 1 == 1, output))
+}
+
+const HEADER_PREFIX: u8 = b'H';
+const HEIGHT_PREFIX: u8 = b'h';
+const TIP_KEY: &[u8] = b"t";
+
+/// Block headers by height and height by hash, growing from a first
+/// header at height zero. Reorgs aren't handled.
+pub struct HeaderChain<S> {
+    store: S,
+}
+
+impl<S: KvStore> HeaderChain<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn get(&self, height: u32) -> Result<Option<BlockHeader>, BitcoinError> {
+        self.store
+            .get(&header_key(height))?
+            .map(|bytes| BlockHeader::from_bytes(&bytes).map(|(header, _)| header))
+            .transpose()
+    }
+
+    pub fn height_of(&self, hash: &[u8; 32]) -> Result<Option<u32>, BitcoinError> {
+        self.store
+            .get(&height_key(hash))?
+            .map(|bytes| {
+                bytes
+                    .try_into()
+                    .map(u32::from_be_bytes)
+                    .map_err(|_| BitcoinError::InvalidFormat)
+            })
+            .transpose()
+    }
+
+    /// The height and header of the last header.
+    pub fn tip(&self) -> Result<Option<(u32, BlockHeader)>, BitcoinError> {
+        let Some(bytes) = self.store.get(TIP_KEY)? else {
+            return Ok(None);
+        };
+        let height = bytes
+            .try_into()
+            .map(u32::from_be_bytes)
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        let header = self.get(height)?.ok_or(BitcoinError::InvalidFormat)?;
+        Ok(Some((height, header)))
+    }
+
+    /// Appends `header`, which must build on the tip and meet its own
+    /// target; returns its height.
+    pub fn push(&mut self, header: &BlockHeader) -> Result<u32, BitcoinError> {
+        let height = match self.tip()? {
+            Some((tip, last)) if header.prev_blockhash == last.block_hash() => tip + 1,
+            Some(_) => return Err(BitcoinError::InvalidFormat),
+            None => 0,
+        };
+        if !header.check_pow() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut batch = WriteBatch::new();
+        batch.put(&header_key(height), &header.to_bytes());
+        batch.put(&height_key(&header.block_hash()), &height.to_be_bytes());
+        batch.put(TIP_KEY, &height.to_be_bytes());
+        self.store.write_batch(batch)?;
+        Ok(height)
+    }
+}
+
+// Big-endian so headers sort in height order.
+fn header_key(height: u32) -> Vec<u8> {
+    [&[HEADER_PREFIX][..], &height.to_be_bytes()].concat()
+}
+
+fn height_key(hash: &[u8; 32]) -> Vec<u8> {
+    [&[HEIGHT_PREFIX][..], hash].concat()
+}
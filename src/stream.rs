@@ -0,0 +1,106 @@
+//! Async [`Stream`]s of transactions for tokio consumers.
+//!
+//! The sources in this crate block: block files are read with `std::io`
+//! and [`ZmqSubscriber`](crate::zmq::ZmqSubscriber) sits on a plain
+//! `TcpStream`. Each stream therefore drives its source on a thread of its
+//! own, which hands transactions over through a bounded channel. Once
+//! `buffer` transactions are waiting the thread blocks, so a slow consumer
+//! slows the source down instead of growing a queue. Dropping the stream
+//! stops the thread at its next send.
+//!
+//! Streams end after yielding their first error.
+
+use crate::blockfile::BlockFileReader;
+use crate::{BitcoinError, BitcoinTransaction, Network};
+use futures_core::Stream;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use tokio::sync::mpsc::{Receiver, channel};
+
+type Item = Result<BitcoinTransaction, BitcoinError>;
+
+#[derive(Debug)]
+pub struct TxStream {
+    receiver: Receiver<Item>,
+}
+
+impl TxStream {
+    /// Drains `source` on a new thread, holding at most `buffer` (at least
+    /// one) transactions ahead of the consumer. Any blocking source of
+    /// transactions can be adapted this way.
+    pub fn from_iter<I>(source: I, buffer: usize) -> Self
+    where
+        I: IntoIterator<Item = Item>,
+        I::IntoIter: Send + 'static,
+    {
+        let (sender, receiver) = channel(buffer.max(1));
+        let source = source.into_iter();
+        thread::spawn(move || {
+            for item in source {
+                let failed = item.is_err();
+                // A closed channel means the stream was dropped.
+                if sender.blocking_send(item).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Self { receiver }
+    }
+
+    /// Every transaction in the blocks of `paths`, in file order.
+    pub fn from_block_files<P: Into<PathBuf>>(
+        paths: impl IntoIterator<Item = P>,
+        network: Network,
+        buffer: usize,
+    ) -> Self {
+        let paths: Vec<PathBuf> = paths.into_iter().map(Into::into).collect();
+        let source = paths.into_iter().flat_map(move |path| {
+            let blocks: Box<dyn Iterator<Item = _> + Send> =
+                match BlockFileReader::open(path, network) {
+                    Ok(reader) => Box::new(reader),
+                    Err(err) => Box::new(std::iter::once(Err(err))),
+                };
+            blocks.flat_map(|block| -> Vec<Item> {
+                match block {
+                    Ok(block) => block.transactions.into_iter().map(Ok).collect(),
+                    Err(err) => vec![Err(err)],
+                }
+            })
+        });
+        Self::from_iter(source, buffer)
+    }
+
+    /// Transactions from `rawtx` notifications, and those inside `rawblock`
+    /// ones, in the order Core publishes them. Other topics are skipped.
+    #[cfg(feature = "zmq")]
+    pub fn from_zmq(subscriber: crate::zmq::ZmqSubscriber, buffer: usize) -> Self {
+        use crate::zmq::Notification;
+
+        let source = subscriber.flat_map(|notification| -> Vec<Item> {
+            match notification {
+                Ok(Notification::RawTx { tx, .. }) => vec![Ok(tx)],
+                Ok(Notification::RawBlock { block, .. }) => {
+                    block.transactions.into_iter().map(Ok).collect()
+                }
+                Ok(Notification::Other { .. }) => Vec::new(),
+                Err(err) => vec![Err(err)],
+            }
+        });
+        Self::from_iter(source, buffer)
+    }
+
+    /// The next transaction, or `None` once the source is exhausted.
+    pub async fn next(&mut self) -> Option<Item> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for TxStream {
+    type Item = Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
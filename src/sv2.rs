@@ -0,0 +1,348 @@
+//! Stratum V2 Template Distribution messages: `NewTemplate`,
+//! `SetNewPrevHash` and `SubmitSolution`.
+//!
+//! Fields use SV2's binary encoding: little-endian integers, 32-byte
+//! `U256`s, and byte strings and sequences prefixed with a one-byte
+//! (`B0_255`, `SEQ0_255`) or two-byte (`B0_64K`) length. Frames add a
+//! six-byte header of extension type, message type and a 24-bit payload
+//! length; this module doesn't handle the Noise encryption around them.
+
+use crate::hash_types::BlockHash;
+use crate::{
+    BitcoinError, BitcoinTransaction, BlockHeader, OutPoint, Script, TransactionInput,
+    TransactionOutput, hashes,
+};
+
+pub const MSG_NEW_TEMPLATE: u8 = 0x71;
+pub const MSG_SET_NEW_PREV_HASH: u8 = 0x72;
+pub const MSG_SUBMIT_SOLUTION: u8 = 0x76;
+
+/// A block template without the previous block hash, which follows in a
+/// `SetNewPrevHash` (immediately, unless `future_template`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NewTemplate {
+    pub template_id: u64,
+    pub future_template: bool,
+    pub version: u32,
+    pub coinbase_tx_version: u32,
+    /// The start of the coinbase scriptSig; the rest is up to the miner.
+    pub coinbase_prefix: Vec<u8>,
+    pub coinbase_tx_input_sequence: u32,
+    /// Value left for the miner's own coinbase outputs.
+    pub coinbase_tx_value_remaining: u64,
+    pub coinbase_tx_outputs_count: u32,
+    /// Serialized outputs the coinbase must include.
+    pub coinbase_tx_outputs: Vec<u8>,
+    pub coinbase_tx_locktime: u32,
+    /// The coinbase's merkle branch, from the leaves up.
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+impl NewTemplate {
+    /// Fails if a field is too long for its length prefix.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BitcoinError> {
+        let mut v = self.template_id.to_le_bytes().to_vec();
+        v.push(self.future_template as u8);
+        v.extend_from_slice(&self.version.to_le_bytes());
+        v.extend_from_slice(&self.coinbase_tx_version.to_le_bytes());
+        write_b0_255(&mut v, &self.coinbase_prefix)?;
+        v.extend_from_slice(&self.coinbase_tx_input_sequence.to_le_bytes());
+        v.extend_from_slice(&self.coinbase_tx_value_remaining.to_le_bytes());
+        v.extend_from_slice(&self.coinbase_tx_outputs_count.to_le_bytes());
+        write_b0_64k(&mut v, &self.coinbase_tx_outputs)?;
+        v.extend_from_slice(&self.coinbase_tx_locktime.to_le_bytes());
+        let path_len =
+            u8::try_from(self.merkle_path.len()).map_err(|_| BitcoinError::InvalidFormat)?;
+        v.push(path_len);
+        for hash in &self.merkle_path {
+            v.extend_from_slice(hash);
+        }
+        Ok(v)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut r = Reader { bytes, pos: 0 };
+        let template_id = r.u64()?;
+        let future_template = match r.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(BitcoinError::InvalidFormat),
+        };
+        let version = r.u32()?;
+        let coinbase_tx_version = r.u32()?;
+        let prefix_len = r.u8()? as usize;
+        let coinbase_prefix = r.take(prefix_len)?.to_vec();
+        let coinbase_tx_input_sequence = r.u32()?;
+        let coinbase_tx_value_remaining = r.u64()?;
+        let coinbase_tx_outputs_count = r.u32()?;
+        let outputs_len = u16::from_le_bytes(r.array()?) as usize;
+        let coinbase_tx_outputs = r.take(outputs_len)?.to_vec();
+        let coinbase_tx_locktime = r.u32()?;
+        let mut merkle_path = Vec::new();
+        for _ in 0..r.u8()? {
+            merkle_path.push(r.array()?);
+        }
+        let template = NewTemplate {
+            template_id,
+            future_template,
+            version,
+            coinbase_tx_version,
+            coinbase_prefix,
+            coinbase_tx_input_sequence,
+            coinbase_tx_value_remaining,
+            coinbase_tx_outputs_count,
+            coinbase_tx_outputs,
+            coinbase_tx_locktime,
+            merkle_path,
+        };
+        Ok((template, r.pos))
+    }
+
+    /// Decodes the outputs the coinbase must include.
+    pub fn coinbase_outputs(&self) -> Result<Vec<TransactionOutput>, BitcoinError> {
+        let mut outputs = Vec::new();
+        let mut cursor = 0;
+        for _ in 0..self.coinbase_tx_outputs_count {
+            let (output, used) =
+                TransactionOutput::from_bytes(&self.coinbase_tx_outputs[cursor..])?;
+            outputs.push(output);
+            cursor += used;
+        }
+        if cursor != self.coinbase_tx_outputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(outputs)
+    }
+
+    /// Builds the coinbase: the prefix followed by `script_sig_suffix`
+    /// (extranonce and the like), the miner's `outputs` and then the
+    /// template's.
+    pub fn coinbase(
+        &self,
+        script_sig_suffix: &[u8],
+        outputs: Vec<TransactionOutput>,
+    ) -> Result<BitcoinTransaction, BitcoinError> {
+        let script_sig = Script::new([&self.coinbase_prefix[..], script_sig_suffix].concat());
+        let input = TransactionInput::new(
+            OutPoint::null(),
+            script_sig,
+            self.coinbase_tx_input_sequence,
+        );
+        let mut all_outputs = outputs;
+        all_outputs.extend(self.coinbase_outputs()?);
+        Ok(BitcoinTransaction::new(
+            self.coinbase_tx_version,
+            vec![input],
+            self.coinbase_tx_locktime,
+        )
+        .with_outputs(all_outputs))
+    }
+
+    /// The block's merkle root with `coinbase` as its first transaction.
+    pub fn merkle_root(&self, coinbase: &BitcoinTransaction) -> [u8; 32] {
+        self.merkle_path
+            .iter()
+            .fold(coinbase.txid().0, |node, sibling| {
+                hashes::sha256d(&[node, *sibling].concat())
+            })
+    }
+}
+
+/// The chain tip a template (or a future template) builds on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SetNewPrevHash {
+    pub template_id: u64,
+    pub prev_hash: BlockHash,
+    pub header_timestamp: u32,
+    pub n_bits: u32,
+    /// The full target as a little-endian 256-bit number.
+    pub target: [u8; 32],
+}
+
+impl SetNewPrevHash {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = self.template_id.to_le_bytes().to_vec();
+        v.extend_from_slice(&self.prev_hash.0);
+        v.extend_from_slice(&self.header_timestamp.to_le_bytes());
+        v.extend_from_slice(&self.n_bits.to_le_bytes());
+        v.extend_from_slice(&self.target);
+        v
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut r = Reader { bytes, pos: 0 };
+        let message = SetNewPrevHash {
+            template_id: r.u64()?,
+            prev_hash: BlockHash(r.array()?),
+            header_timestamp: r.u32()?,
+            n_bits: r.u32()?,
+            target: r.array()?,
+        };
+        Ok((message, r.pos))
+    }
+}
+
+/// A solved block, sent back to the template provider.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SubmitSolution {
+    pub template_id: u64,
+    pub version: u32,
+    pub header_timestamp: u32,
+    pub header_nonce: u32,
+    pub coinbase_tx: BitcoinTransaction,
+}
+
+impl SubmitSolution {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BitcoinError> {
+        let mut v = self.template_id.to_le_bytes().to_vec();
+        v.extend_from_slice(&self.version.to_le_bytes());
+        v.extend_from_slice(&self.header_timestamp.to_le_bytes());
+        v.extend_from_slice(&self.header_nonce.to_le_bytes());
+        write_b0_64k(&mut v, &self.coinbase_tx.to_bytes())?;
+        Ok(v)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut r = Reader { bytes, pos: 0 };
+        let template_id = r.u64()?;
+        let version = r.u32()?;
+        let header_timestamp = r.u32()?;
+        let header_nonce = r.u32()?;
+        let len = u16::from_le_bytes(r.array()?) as usize;
+        let raw = r.take(len)?;
+        let (coinbase_tx, used) = BitcoinTransaction::from_bytes(raw)?;
+        if used != raw.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let solution = SubmitSolution {
+            template_id,
+            version,
+            header_timestamp,
+            header_nonce,
+            coinbase_tx,
+        };
+        Ok((solution, r.pos))
+    }
+
+    /// The solved block's header.
+    pub fn block_header(&self, template: &NewTemplate, prev: &SetNewPrevHash) -> BlockHeader {
+        BlockHeader::new(
+            self.version,
+            prev.prev_hash.0,
+            template.merkle_root(&self.coinbase_tx),
+            self.header_timestamp,
+            prev.n_bits,
+            self.header_nonce,
+        )
+    }
+}
+
+/// A framed Template Distribution Protocol message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Message {
+    NewTemplate(NewTemplate),
+    SetNewPrevHash(SetNewPrevHash),
+    SubmitSolution(SubmitSolution),
+}
+
+impl Message {
+    pub fn msg_type(&self) -> u8 {
+        match self {
+            Message::NewTemplate(_) => MSG_NEW_TEMPLATE,
+            Message::SetNewPrevHash(_) => MSG_SET_NEW_PREV_HASH,
+            Message::SubmitSolution(_) => MSG_SUBMIT_SOLUTION,
+        }
+    }
+
+    pub fn to_frame(&self) -> Result<Vec<u8>, BitcoinError> {
+        let payload = match self {
+            Message::NewTemplate(message) => message.to_bytes()?,
+            Message::SetNewPrevHash(message) => message.to_bytes(),
+            Message::SubmitSolution(message) => message.to_bytes()?,
+        };
+        // Extension type 0 (core protocol), not a channel message.
+        let mut v = vec![0, 0, self.msg_type()];
+        v.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..3]);
+        v.extend(payload);
+        Ok(v)
+    }
+
+    pub fn from_frame(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 6 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        if u16::from_le_bytes([bytes[0], bytes[1]]) & 0x7fff != 0 {
+            return Err(BitcoinError::Unsupported);
+        }
+        let len = u32::from_le_bytes([bytes[3], bytes[4], bytes[5], 0]) as usize;
+        let payload = bytes
+            .get(6..6 + len)
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        let (message, used) = match bytes[2] {
+            MSG_NEW_TEMPLATE => {
+                let (message, used) = NewTemplate::from_bytes(payload)?;
+                (Message::NewTemplate(message), used)
+            }
+            MSG_SET_NEW_PREV_HASH => {
+                let (message, used) = SetNewPrevHash::from_bytes(payload)?;
+                (Message::SetNewPrevHash(message), used)
+            }
+            MSG_SUBMIT_SOLUTION => {
+                let (message, used) = SubmitSolution::from_bytes(payload)?;
+                (Message::SubmitSolution(message), used)
+            }
+            _ => return Err(BitcoinError::Unsupported),
+        };
+        if used != len {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((message, 6 + len))
+    }
+}
+
+fn write_b0_255(v: &mut Vec<u8>, data: &[u8]) -> Result<(), BitcoinError> {
+    let len = u8::try_from(data.len()).map_err(|_| BitcoinError::InvalidFormat)?;
+    v.push(len);
+    v.extend_from_slice(data);
+    Ok(())
+}
+
+fn write_b0_64k(v: &mut Vec<u8>, data: &[u8]) -> Result<(), BitcoinError> {
+    let len = u16::try_from(data.len()).map_err(|_| BitcoinError::InvalidFormat)?;
+    v.extend_from_slice(&len.to_le_bytes());
+    v.extend_from_slice(data);
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BitcoinError> {
+        let data = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, BitcoinError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, BitcoinError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, BitcoinError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
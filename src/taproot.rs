@@ -0,0 +1,322 @@
+//! BIP341 script-path structures.
+
+use crate::hashes::tagged_hash;
+use crate::{BitcoinError, CompactSize, Script, Witness};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+pub const TAPROOT_ANNEX_PREFIX: u8 = 0x50;
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// The last witness element of a script-path spend.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ControlBlock {
+    pub leaf_version: u8,
+    /// Whether the output key has an odd y coordinate.
+    pub output_key_parity: bool,
+    /// X-only internal key.
+    pub internal_key: [u8; 32],
+    /// Sibling hashes from the leaf up to the root.
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = vec![self.leaf_version | self.output_key_parity as u8];
+        v.extend_from_slice(&self.internal_key);
+        for node in &self.merkle_branch {
+            v.extend_from_slice(node);
+        }
+        v
+    }
+
+    // Like `GcsFilter`, a control block has no length of its own and takes
+    // up the whole of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < TAPROOT_CONTROL_BASE_SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let path = &bytes[TAPROOT_CONTROL_BASE_SIZE..];
+        if !path.len().is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+            || path.len() / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let control = Self {
+            leaf_version: bytes[0] & 0xfe,
+            output_key_parity: bytes[0] & 1 == 1,
+            internal_key: bytes[1..33].try_into().unwrap(),
+            merkle_branch: path
+                .chunks(TAPROOT_CONTROL_NODE_SIZE)
+                .map(|node| node.try_into().unwrap())
+                .collect(),
+        };
+        Ok((control, bytes.len()))
+    }
+
+    /// Splits a script-path witness into the leaf script and its control
+    /// block, ignoring any annex. `None` for key-path spends.
+    pub fn from_witness(witness: &Witness) -> Option<Result<(Script, Self), BitcoinError>> {
+        let items = witness.without_annex();
+        if items.len() < 2 {
+            return None;
+        }
+        let script = Script::new(items[items.len() - 2].clone());
+        Some(Self::from_bytes(&items[items.len() - 1]).map(|(control, _)| (script, control)))
+    }
+
+    /// The merkle root committed to by `script` with this control block's path.
+    pub fn merkle_root(&self, script: &Script) -> [u8; 32] {
+        self.merkle_branch
+            .iter()
+            .fold(tapleaf_hash(self.leaf_version, script), |node, sibling| {
+                tapbranch_hash(&node, sibling)
+            })
+    }
+
+    /// Checks that `output_key` (x-only, from the P2TR scriptPubKey) commits
+    /// to `script` through this control block. Needs the `secp` feature.
+    pub fn verify_taproot_commitment(
+        &self,
+        output_key: &[u8; 32],
+        script: &Script,
+    ) -> Result<bool, BitcoinError> {
+        let tweak = taptweak_hash(&self.internal_key, Some(&self.merkle_root(script)));
+        check_tweak(
+            &self.internal_key,
+            output_key,
+            self.output_key_parity,
+            &tweak,
+        )
+    }
+}
+
+impl Witness {
+    /// The BIP341 annex: a last element starting with `0x50`, when there
+    /// are at least two elements. Only meaningful for taproot spends.
+    pub fn taproot_annex(&self) -> Option<&[u8]> {
+        match &self[..] {
+            [_, .., last] if last.first() == Some(&TAPROOT_ANNEX_PREFIX) => Some(last),
+            _ => None,
+        }
+    }
+
+    /// The witness stack with any annex removed.
+    pub fn without_annex(&self) -> &[Vec<u8>] {
+        match self.taproot_annex() {
+            Some(_) => &self[..self.len() - 1],
+            None => &self[..],
+        }
+    }
+}
+
+pub fn tapleaf_hash(leaf_version: u8, script: &Script) -> [u8; 32] {
+    let mut data = vec![leaf_version];
+    data.extend(CompactSize::new(script.len() as u64).to_bytes());
+    data.extend_from_slice(&script.bytes);
+    tagged_hash("TapLeaf", &data)
+}
+
+/// Hashes two child nodes in lexicographic order.
+pub fn tapbranch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    tagged_hash("TapBranch", &[&left[..], &right[..]].concat())
+}
+
+pub fn taptweak_hash(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = internal_key.to_vec();
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    tagged_hash("TapTweak", &data)
+}
+
+#[cfg(feature = "secp")]
+fn check_tweak(
+    internal_key: &[u8; 32],
+    output_key: &[u8; 32],
+    parity: bool,
+    tweak: &[u8; 32],
+) -> Result<bool, BitcoinError> {
+    use secp256k1::{Parity, Scalar, Secp256k1, XOnlyPublicKey};
+
+    let internal =
+        XOnlyPublicKey::from_slice(internal_key).map_err(|_| BitcoinError::InvalidFormat)?;
+    let output = XOnlyPublicKey::from_slice(output_key).map_err(|_| BitcoinError::InvalidFormat)?;
+    let tweak = Scalar::from_be_bytes(*tweak).map_err(|_| BitcoinError::InvalidFormat)?;
+    let parity = if parity { Parity::Odd } else { Parity::Even };
+    Ok(internal.tweak_add_check(&Secp256k1::verification_only(), &output, parity, tweak))
+}
+
+// Adding the tweak to the internal key needs curve math.
+#[cfg(not(feature = "secp"))]
+fn check_tweak(
+    _internal_key: &[u8; 32],
+    _output_key: &[u8; 32],
+    _parity: bool,
+    _tweak: &[u8; 32],
+) -> Result<bool, BitcoinError> {
+    Err(BitcoinError::Unsupported)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TapLeaf {
+    pub leaf_version: u8,
+    pub script: Script,
+    /// Sibling hashes from this leaf up to the root.
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+impl TapLeaf {
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        tapleaf_hash(self.leaf_version, &self.script)
+    }
+}
+
+/// A finished script tree: its root and every leaf with its path.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TapTree {
+    pub merkle_root: [u8; 32],
+    /// In the order the leaves were added.
+    pub leaves: Vec<TapLeaf>,
+}
+
+impl TapTree {
+    /// The control block spending leaf `index`; `output_key_parity` comes
+    /// from tweaking `internal_key` with this tree's root.
+    pub fn control_block(
+        &self,
+        index: usize,
+        internal_key: [u8; 32],
+        output_key_parity: bool,
+    ) -> Option<ControlBlock> {
+        let leaf = self.leaves.get(index)?;
+        Some(ControlBlock {
+            leaf_version: leaf.leaf_version,
+            output_key_parity,
+            internal_key,
+            merkle_branch: leaf.merkle_branch.clone(),
+        })
+    }
+}
+
+/// Builds a script tree from weighted leaves, pairing the two lightest
+/// subtrees first (a Huffman tree) so likely leaves get short paths.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TaprootBuilder {
+    leaves: Vec<(u32, u8, Script)>,
+}
+
+impl TaprootBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_leaf(self, weight: u32, script: Script) -> Self {
+        self.add_leaf_with_version(weight, TAPROOT_LEAF_TAPSCRIPT, script)
+    }
+
+    pub fn add_leaf_with_version(mut self, weight: u32, leaf_version: u8, script: Script) -> Self {
+        self.leaves.push((weight, leaf_version, script));
+        self
+    }
+
+    /// Fails with `InvalidFormat` for an empty tree or one deeper than 128.
+    pub fn finalize(self) -> Result<TapTree, BitcoinError> {
+        let mut leaves: Vec<TapLeaf> = self
+            .leaves
+            .iter()
+            .map(|(_, leaf_version, script)| TapLeaf {
+                leaf_version: *leaf_version,
+                script: script.clone(),
+                merkle_branch: Vec::new(),
+            })
+            .collect();
+
+        // (weight, insertion sequence) keeps ties deterministic.
+        let mut queue = BinaryHeap::new();
+        let mut subtrees = Vec::new();
+        for (i, (weight, _, _)) in self.leaves.iter().enumerate() {
+            queue.push(Reverse((*weight as u64, i)));
+            subtrees.push((leaves[i].leaf_hash(), vec![i]));
+        }
+        while queue.len() > 1 {
+            let Reverse((weight_a, a)) = queue.pop().unwrap();
+            let Reverse((weight_b, b)) = queue.pop().unwrap();
+            let (hash_a, members_a) = std::mem::take(&mut subtrees[a]);
+            let (hash_b, members_b) = std::mem::take(&mut subtrees[b]);
+            for &i in &members_a {
+                leaves[i].merkle_branch.push(hash_b);
+            }
+            for &i in &members_b {
+                leaves[i].merkle_branch.push(hash_a);
+            }
+            let members = [members_a, members_b].concat();
+            queue.push(Reverse((weight_a + weight_b, subtrees.len())));
+            subtrees.push((tapbranch_hash(&hash_a, &hash_b), members));
+        }
+        let Reverse((_, root)) = queue.pop().ok_or(BitcoinError::InvalidFormat)?;
+        if leaves
+            .iter()
+            .any(|leaf| leaf.merkle_branch.len() > TAPROOT_CONTROL_MAX_NODE_COUNT)
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(TapTree {
+            merkle_root: subtrees[root].0,
+            leaves,
+        })
+    }
+}
+
+/// A P2TR output derived from an internal key and optional script tree.
+#[cfg(feature = "secp")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TaprootSpendInfo {
+    pub internal_key: [u8; 32],
+    pub tree: Option<TapTree>,
+    pub output_key: [u8; 32],
+    pub output_key_parity: bool,
+}
+
+#[cfg(feature = "secp")]
+impl TaprootSpendInfo {
+    /// Tweaks `internal_key` (x-only) with the tree's merkle root, or with
+    /// no root for a key-path-only output.
+    pub fn new(internal_key: [u8; 32], tree: Option<TapTree>) -> Result<Self, BitcoinError> {
+        use secp256k1::{Parity, Scalar, Secp256k1, XOnlyPublicKey};
+
+        let tweak = taptweak_hash(&internal_key, tree.as_ref().map(|t| &t.merkle_root));
+        let tweak = Scalar::from_be_bytes(tweak).map_err(|_| BitcoinError::InvalidFormat)?;
+        let (output_key, parity) = XOnlyPublicKey::from_slice(&internal_key)
+            .and_then(|key| key.add_tweak(&Secp256k1::verification_only(), &tweak))
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Self {
+            internal_key,
+            tree,
+            output_key: output_key.serialize(),
+            output_key_parity: parity == Parity::Odd,
+        })
+    }
+
+    pub fn merkle_root(&self) -> Option<[u8; 32]> {
+        self.tree.as_ref().map(|tree| tree.merkle_root)
+    }
+
+    /// `OP_1 <output key>`.
+    pub fn script_pubkey(&self) -> Script {
+        let mut bytes = vec![0x51, 0x20];
+        bytes.extend_from_slice(&self.output_key);
+        Script::new(bytes)
+    }
+
+    /// The control block for spending leaf `index` of the tree.
+    pub fn control_block(&self, index: usize) -> Option<ControlBlock> {
+        self.tree
+            .as_ref()?
+            .control_block(index, self.internal_key, self.output_key_parity)
+    }
+}
@@ -0,0 +1,180 @@
+//! Block undo data, as stored in Bitcoin Core's `blocks/rev*.dat` files.
+//!
+//! For every non-coinbase transaction in a block, the undo record lists the
+//! coins its inputs spent, in input order. This is the only place the chain
+//! keeps prevout amounts and scripts once they are spent.
+
+use crate::blockfile::{XorReader, read_record, read_xor_key};
+use crate::compress::{compress_amount, compress_script, decompress_amount, decompress_script};
+use crate::constants::MAX_SIZE;
+use crate::{BitcoinError, CompactSize, Network, TransactionOutput, VarInt, hashes};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Coin {
+    pub height: u32,
+    pub coinbase: bool,
+    pub output: TransactionOutput,
+}
+
+impl Coin {
+    pub fn new(height: u32, coinbase: bool, output: TransactionOutput) -> Self {
+        Self {
+            height,
+            coinbase,
+            output,
+        }
+    }
+
+    /// The chainstate database and UTXO snapshot encoding. Fails only where
+    /// [`compress_script`] does.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BitcoinError> {
+        let code = (self.height as u64) << 1 | self.coinbase as u64;
+        let mut v = VarInt::new(code).to_bytes();
+        v.extend(VarInt::new(compress_amount(self.output.value)).to_bytes());
+        v.extend(compress_script(&self.output.script_pubkey)?);
+        Ok(v)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (code, mut cursor) = VarInt::from_bytes(bytes)?;
+        let height = u32::try_from(code.value >> 1).map_err(|_| BitcoinError::InvalidFormat)?;
+        let (amount, used) = VarInt::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        let (script, used) = decompress_script(&bytes[cursor..])?;
+        cursor += used;
+        let output = TransactionOutput::new(decompress_amount(amount.value), script);
+        Ok((Coin::new(height, code.value & 1 == 1, output), cursor))
+    }
+
+    /// Undo data additionally stores a transaction version for coins above
+    /// height zero.
+    pub fn from_undo_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (code, mut cursor) = VarInt::from_bytes(bytes)?;
+        let height = u32::try_from(code.value >> 1).map_err(|_| BitcoinError::InvalidFormat)?;
+        if height > 0 {
+            // Unused transaction version kept for format compatibility.
+            let (_, used) = VarInt::from_bytes(&bytes[cursor..])?;
+            cursor += used;
+        }
+        let (amount, used) = VarInt::from_bytes(&bytes[cursor..])?;
+        cursor += used;
+        let (script, used) = decompress_script(&bytes[cursor..])?;
+        cursor += used;
+        let output = TransactionOutput::new(decompress_amount(amount.value), script);
+        Ok((Coin::new(height, code.value & 1 == 1, output), cursor))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TxUndo {
+    pub spent: Vec<Coin>,
+}
+
+impl TxUndo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (count, mut cursor) = CompactSize::from_bytes(bytes)?;
+        let mut spent = Vec::new();
+        for _ in 0..count.value {
+            let (coin, used) = Coin::from_undo_bytes(&bytes[cursor..])?;
+            spent.push(coin);
+            cursor += used;
+        }
+        Ok((TxUndo { spent }, cursor))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BlockUndo {
+    /// One entry per transaction, skipping the coinbase.
+    pub txs: Vec<TxUndo>,
+}
+
+impl BlockUndo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (count, mut cursor) = CompactSize::from_bytes(bytes)?;
+        let mut txs = Vec::new();
+        for _ in 0..count.value {
+            let (tx_undo, used) = TxUndo::from_bytes(&bytes[cursor..])?;
+            txs.push(tx_undo);
+            cursor += used;
+        }
+        Ok((BlockUndo { txs }, cursor))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UndoRecord {
+    pub block_undo: BlockUndo,
+    pub checksum: [u8; 32],
+    raw: Vec<u8>,
+}
+
+impl UndoRecord {
+    /// Checks the record against the hash of the block *before* the one it
+    /// undoes, which Core mixes into the checksum.
+    pub fn verify_checksum(&self, prev_blockhash: &[u8; 32]) -> bool {
+        let mut data = prev_blockhash.to_vec();
+        data.extend_from_slice(&self.raw);
+        hashes::sha256d(&data) == self.checksum
+    }
+}
+
+pub struct UndoFileReader<R> {
+    reader: R,
+    magic: [u8; 4],
+    done: bool,
+}
+
+impl UndoFileReader<BufReader<XorReader<File>>> {
+    pub fn open<P: AsRef<Path>>(path: P, network: Network) -> Result<Self, BitcoinError> {
+        let path = path.as_ref();
+        let key = read_xor_key(path.parent().unwrap_or(Path::new(".")))?;
+        let file = File::open(path)?;
+        Ok(Self::new(
+            BufReader::new(XorReader::new(file, key)),
+            network,
+        ))
+    }
+}
+
+impl<R: Read> UndoFileReader<R> {
+    pub fn new(reader: R, network: Network) -> Self {
+        Self {
+            reader,
+            magic: network.magic(),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for UndoFileReader<R> {
+    type Item = Result<UndoRecord, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match read_record(&mut self.reader, self.magic, 1..=MAX_SIZE, 32) {
+            Ok(Some((raw, trailer))) => {
+                Some(
+                    BlockUndo::from_bytes(&raw).map(|(block_undo, _)| UndoRecord {
+                        block_undo,
+                        checksum: trailer.try_into().unwrap(),
+                        raw,
+                    }),
+                )
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
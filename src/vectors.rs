@@ -0,0 +1,184 @@
+//! Runs Bitcoin Core's JSON test vectors (`tx_valid.json`,
+//! `tx_invalid.json` and `script_tests.json` from `src/test/data`) through
+//! the crate.
+//!
+//! There is no script interpreter yet, so script outcomes aren't checked.
+//! Transaction vectors are decoded, re-encoded and run through
+//! `check_transaction`; script vectors only have their scripts parsed.
+//! Comment entries (a single string) are skipped.
+
+use crate::opcodes;
+use crate::{BitcoinError, BitcoinTransaction, OutPoint, Script};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TxVectors {
+    /// `tx_valid.json`: every transaction must decode and pass.
+    Valid,
+    /// `tx_invalid.json`: transactions that decode and pass the checks we
+    /// have are skipped, as they need script evaluation to reject.
+    Invalid,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct VectorReport {
+    pub passed: usize,
+    pub skipped: usize,
+    /// Index into the JSON array and what went wrong.
+    pub failures: Vec<(usize, String)>,
+}
+
+impl VectorReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+pub fn run_tx_vector_file<P: AsRef<Path>>(
+    path: P,
+    kind: TxVectors,
+) -> Result<VectorReport, BitcoinError> {
+    run_tx_vectors(&fs::read_to_string(path)?, kind)
+}
+
+/// Entries are `[[[prevout hash, prevout index, prevout scriptPubKey,
+/// amount?], ...], serialized tx, excluded flags]`.
+pub fn run_tx_vectors(json: &str, kind: TxVectors) -> Result<VectorReport, BitcoinError> {
+    let mut report = VectorReport::default();
+    for (index, entry) in parse_array(json)?.iter().enumerate() {
+        let Some(fields) = entry.as_array().filter(|fields| fields.len() >= 2) else {
+            report.skipped += 1;
+            continue;
+        };
+        if !fields[0].is_array() {
+            report.skipped += 1;
+            continue;
+        }
+        match (check_tx_vector(fields), kind) {
+            (Ok(()), TxVectors::Valid) | (Err(Outcome::Rejected(_)), TxVectors::Invalid) => {
+                report.passed += 1
+            }
+            (Ok(()), TxVectors::Invalid) => report.skipped += 1,
+            (Err(Outcome::Rejected(reason)), TxVectors::Valid)
+            | (Err(Outcome::Malformed(reason)), _) => report.failures.push((index, reason)),
+        }
+    }
+    Ok(report)
+}
+
+pub fn run_script_vector_file<P: AsRef<Path>>(path: P) -> Result<VectorReport, BitcoinError> {
+    run_script_vectors(&fs::read_to_string(path)?)
+}
+
+/// Entries are `[[witness..., amount]?, scriptSig, scriptPubKey, flags,
+/// expected result, comment?]`.
+pub fn run_script_vectors(json: &str) -> Result<VectorReport, BitcoinError> {
+    let mut report = VectorReport::default();
+    for (index, entry) in parse_array(json)?.iter().enumerate() {
+        let Some(fields) = entry.as_array().filter(|fields| fields.len() >= 4) else {
+            report.skipped += 1;
+            continue;
+        };
+        let scripts = if fields[0].is_array() {
+            &fields[1..3]
+        } else {
+            &fields[0..2]
+        };
+        let parsed = scripts.iter().try_for_each(|script| {
+            let asm = script.as_str().ok_or(BitcoinError::InvalidFormat)?;
+            parse_core_script(asm).map(|_| ())
+        });
+        match parsed {
+            Ok(()) => report.passed += 1,
+            Err(err) => report.failures.push((index, err.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Parses the script notation of Core's test data: decimal numbers, `0x`
+/// prefixed raw bytes inserted as-is, `'quoted'` strings pushed as data,
+/// and opcode names with or without `OP_`.
+pub fn parse_core_script(s: &str) -> Result<Script, BitcoinError> {
+    let mut script = Script::new(Vec::new());
+    for token in s.split_whitespace() {
+        if let Some(hex_bytes) = token.strip_prefix("0x") {
+            let bytes = hex::decode(hex_bytes).map_err(|_| BitcoinError::InvalidFormat)?;
+            script.bytes.extend_from_slice(&bytes);
+        } else if let Some(text) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            script.push_slice(text.as_bytes());
+        } else if let Ok(n) = token.parse::<i64>() {
+            script.push_int(n);
+        } else {
+            let opcode = opcodes::from_name(token).ok_or(BitcoinError::InvalidFormat)?;
+            script.push_opcode(opcode);
+        }
+    }
+    Ok(script)
+}
+
+// Whether a vector was malformed (always a failure) or the transaction was
+// rejected (a failure only for valid vectors).
+enum Outcome {
+    Malformed(String),
+    Rejected(String),
+}
+
+fn check_tx_vector(fields: &[Value]) -> Result<(), Outcome> {
+    let prevouts = parse_prevouts(&fields[0]).map_err(Outcome::Malformed)?;
+    let raw = fields[1]
+        .as_str()
+        .and_then(|raw| hex::decode(raw).ok())
+        .ok_or_else(|| Outcome::Malformed("bad transaction hex".to_string()))?;
+    let (tx, used) =
+        BitcoinTransaction::from_bytes(&raw).map_err(|err| Outcome::Rejected(err.to_string()))?;
+    if used != raw.len() || tx.to_bytes() != raw {
+        return Err(Outcome::Rejected(
+            "does not re-encode to the same bytes".to_string(),
+        ));
+    }
+    if let Some(input) = tx
+        .inputs
+        .iter()
+        .find(|input| !prevouts.contains_key(&input.previous_output))
+    {
+        return Err(Outcome::Malformed(format!(
+            "no prevout for input {}",
+            input.previous_output
+        )));
+    }
+    tx.check_transaction()
+        .map_err(|err| Outcome::Rejected(err.to_string()))
+}
+
+fn parse_prevouts(value: &Value) -> Result<HashMap<OutPoint, Script>, String> {
+    let mut prevouts = HashMap::new();
+    for prevout in value.as_array().into_iter().flatten() {
+        let fields = prevout.as_array().ok_or("prevout is not an array")?;
+        let (Some(hash), Some(index), Some(script)) = (
+            fields.first().and_then(Value::as_str),
+            fields.get(1).and_then(Value::as_i64),
+            fields.get(2).and_then(Value::as_str),
+        ) else {
+            return Err("bad prevout".to_string());
+        };
+        let mut txid = [0u8; 32];
+        hex::decode_to_slice(hash, &mut txid).map_err(|_| "bad prevout hash")?;
+        txid.reverse();
+        // -1 stands for the null outpoint's index.
+        let outpoint = OutPoint::new(txid, index as u32);
+        let script = parse_core_script(script).map_err(|err| err.to_string())?;
+        prevouts.insert(outpoint, script);
+    }
+    Ok(prevouts)
+}
+
+fn parse_array(json: &str) -> Result<Vec<Value>, BitcoinError> {
+    match serde_json::from_str(json) {
+        Ok(Value::Array(entries)) => Ok(entries),
+        _ => Err(BitcoinError::InvalidFormat),
+    }
+}
@@ -0,0 +1,103 @@
+//! BIP9 version bits signaling in block header versions.
+
+use crate::{BlockHeader, Network};
+
+/// The top three version bits of a signaling header must be `001`.
+pub const VERSIONBITS_TOP_BITS: u32 = 0x2000_0000;
+pub const VERSIONBITS_TOP_MASK: u32 = 0xE000_0000;
+/// Bits 0 to 28 can each signal a deployment.
+pub const VERSIONBITS_NUM_BITS: u8 = 29;
+
+impl Network {
+    /// Blocks per signaling period (the retarget window, shorter on
+    /// regtest).
+    pub fn miner_confirmation_window(&self) -> u32 {
+        match self {
+            Network::Regtest => 144,
+            _ => 2016,
+        }
+    }
+
+    /// Signaling blocks needed in a period to lock in a deployment.
+    pub fn rule_change_activation_threshold(&self) -> u32 {
+        match self {
+            Network::Mainnet | Network::Signet => 1815,
+            Network::Testnet => 1512,
+            Network::Regtest => 108,
+        }
+    }
+}
+
+impl BlockHeader {
+    /// True if the version has the BIP9 top bits pattern.
+    pub fn uses_version_bits(&self) -> bool {
+        self.version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS
+    }
+
+    /// True if the header signals for `bit`; headers without the top bits
+    /// pattern signal nothing.
+    pub fn signals(&self, bit: u8) -> bool {
+        bit < VERSIONBITS_NUM_BITS && self.uses_version_bits() && self.version & (1 << bit) != 0
+    }
+
+    /// The bits the header signals for, in ascending order.
+    pub fn signaling_bits(&self) -> Vec<u8> {
+        (0..VERSIONBITS_NUM_BITS)
+            .filter(|&bit| self.signals(bit))
+            .collect()
+    }
+}
+
+/// Signaling counts per bit over a run of headers, typically one
+/// confirmation window.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignalTally {
+    pub blocks: u32,
+    pub counts: [u32; VERSIONBITS_NUM_BITS as usize],
+}
+
+impl Default for SignalTally {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalTally {
+    pub fn new() -> Self {
+        Self {
+            blocks: 0,
+            counts: [0; VERSIONBITS_NUM_BITS as usize],
+        }
+    }
+
+    pub fn add(&mut self, header: &BlockHeader) {
+        self.blocks += 1;
+        for bit in header.signaling_bits() {
+            self.counts[bit as usize] += 1;
+        }
+    }
+
+    pub fn count(&self, bit: u8) -> u32 {
+        self.counts.get(bit as usize).copied().unwrap_or(0)
+    }
+
+    /// True if `bit` reached `network`'s activation threshold.
+    pub fn threshold_reached(&self, bit: u8, network: Network) -> bool {
+        self.count(bit) >= network.rule_change_activation_threshold()
+    }
+}
+
+impl<'a> FromIterator<&'a BlockHeader> for SignalTally {
+    fn from_iter<I: IntoIterator<Item = &'a BlockHeader>>(headers: I) -> Self {
+        let mut tally = SignalTally::new();
+        for header in headers {
+            tally.add(header);
+        }
+        tally
+    }
+}
+
+/// The first height of the signaling period containing `height`.
+pub fn period_start(height: u32, network: Network) -> u32 {
+    height - height % network.miner_confirmation_window()
+}
@@ -0,0 +1,49 @@
+//! `wasm-bindgen` exports for browser explorers and teaching tools.
+//!
+//! Everything crosses the boundary as strings: hex for raw bytes and the
+//! Core-style JSON of `core_json` for decoded transactions.
+
+use crate::{BitcoinError, BitcoinTransaction, Script, core_json};
+use wasm_bindgen::prelude::*;
+
+fn decode_hex(hex_str: &str) -> Result<Vec<u8>, BitcoinError> {
+    hex::decode(hex_str.trim()).map_err(|_| BitcoinError::InvalidFormat)
+}
+
+fn parse_transaction(hex_str: &str) -> Result<BitcoinTransaction, BitcoinError> {
+    let bytes = decode_hex(hex_str)?;
+    let (tx, used) = BitcoinTransaction::from_bytes(&bytes)?;
+    if used != bytes.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(tx)
+}
+
+fn js_error(err: BitcoinError) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[wasm_bindgen(js_name = decodeTransaction)]
+pub fn decode_transaction(hex_str: &str) -> Result<String, JsError> {
+    let tx = parse_transaction(hex_str).map_err(js_error)?;
+    Ok(core_json::to_string(&core_json::transaction_to_json(&tx)))
+}
+
+/// Serializes a transaction given in this crate's serde JSON form.
+#[wasm_bindgen(js_name = encodeTransaction)]
+pub fn encode_transaction(json: &str) -> Result<String, JsError> {
+    let tx: BitcoinTransaction = serde_json::from_str(json)?;
+    Ok(hex::encode(tx.to_bytes()))
+}
+
+#[wasm_bindgen(js_name = computeTxid)]
+pub fn compute_txid(hex_str: &str) -> Result<String, JsError> {
+    let tx = parse_transaction(hex_str).map_err(js_error)?;
+    Ok(core_json::reversed_hex(&tx.txid().0))
+}
+
+#[wasm_bindgen(js_name = classifyScript)]
+pub fn classify_script(hex_str: &str) -> Result<String, JsError> {
+    let script = Script::new(decode_hex(hex_str).map_err(js_error)?);
+    Ok(script.script_type().as_str().to_string())
+}
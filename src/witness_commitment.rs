@@ -0,0 +1,110 @@
+//! BIP141 witness commitments and the coinbase witness reserved value.
+//!
+//! The coinbase commits to `SHA256d(witness root || reserved value)` in an
+//! output starting with [`WITNESS_COMMITMENT_PREFIX`]. The reserved value
+//! is the single 32-byte item of the coinbase input's witness, so assembly
+//! and validation both go through the accessors here.
+
+use crate::{
+    BitcoinError, BitcoinTransaction, Block, Script, TransactionOutput, Witness, block, hashes,
+};
+
+/// `OP_RETURN`, a 36-byte push and the BIP141 commitment header.
+pub const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+impl BitcoinTransaction {
+    /// The coinbase input's witness reserved value, if its witness is a
+    /// single 32-byte item.
+    pub fn witness_reserved_value(&self) -> Option<[u8; 32]> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        match &self.inputs[0].witness.items[..] {
+            [value] => value.as_slice().try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Makes `value` the coinbase input's whole witness.
+    pub fn set_witness_reserved_value(&mut self, value: [u8; 32]) -> Result<(), BitcoinError> {
+        if !self.is_coinbase() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        self.inputs[0].set_witness(Witness::new(vec![value.to_vec()]));
+        Ok(())
+    }
+}
+
+impl Block {
+    /// The coinbase output holding the segwit commitment: the last one
+    /// starting with the commitment header.
+    pub fn witness_commitment_index(&self) -> Option<usize> {
+        let coinbase = self.transactions.first()?;
+        coinbase.outputs.iter().rposition(|output| {
+            output.script_pubkey.len() >= 38
+                && output.script_pubkey.starts_with(&WITNESS_COMMITMENT_PREFIX)
+        })
+    }
+
+    /// The merkle root of the wtxids, with the coinbase's taken as zero.
+    pub fn witness_root(&self) -> [u8; 32] {
+        let wtxids: Vec<[u8; 32]> = (0..)
+            .zip(&self.transactions)
+            .map(|(i, tx)| if i == 0 { [0; 32] } else { tx.wtxid().0 })
+            .collect();
+        block::merkle_root(&wtxids)
+    }
+
+    pub fn compute_witness_commitment(&self, reserved_value: &[u8; 32]) -> [u8; 32] {
+        hashes::sha256d(&[self.witness_root(), *reserved_value].concat())
+    }
+
+    /// Sets the coinbase's reserved value and commits to the witnesses,
+    /// updating an existing commitment output (keeping any data after the
+    /// hash) or appending one. Recomputes the header's merkle root, so the
+    /// block must be mined afterwards.
+    pub fn add_witness_commitment(&mut self, reserved_value: [u8; 32]) -> Result<(), BitcoinError> {
+        let commitment = self.compute_witness_commitment(&reserved_value);
+        let index = self.witness_commitment_index();
+        let coinbase = self
+            .transactions
+            .first_mut()
+            .ok_or(BitcoinError::InvalidFormat)?;
+        coinbase.set_witness_reserved_value(reserved_value)?;
+        match index {
+            Some(index) => {
+                coinbase.outputs[index].script_pubkey.bytes[6..38].copy_from_slice(&commitment)
+            }
+            None => coinbase.outputs.push(TransactionOutput::new(
+                0,
+                Script::new([&WITNESS_COMMITMENT_PREFIX[..], &commitment].concat()),
+            )),
+        }
+        self.header.merkle_root = self.compute_merkle_root();
+        Ok(())
+    }
+
+    /// Core's witness malleation check: with a commitment, the coinbase
+    /// must carry a reserved value and the commitment must match; without
+    /// one, no transaction may have a witness.
+    pub fn check_witness_commitment(&self) -> Result<(), BitcoinError> {
+        let Some(index) = self.witness_commitment_index() else {
+            if self
+                .transactions
+                .iter()
+                .any(BitcoinTransaction::has_witness)
+            {
+                return Err(BitcoinError::InvalidTransaction("unexpected-witness"));
+            }
+            return Ok(());
+        };
+        let reserved_value = self.transactions[0]
+            .witness_reserved_value()
+            .ok_or(BitcoinError::InvalidTransaction("bad-witness-nonce-size"))?;
+        let script = &self.transactions[0].outputs[index].script_pubkey;
+        if script.bytes[6..38] != self.compute_witness_commitment(&reserved_value) {
+            return Err(BitcoinError::InvalidTransaction("bad-witness-merkle-match"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Segwit output programs (BIP141, BIP341).
+
+use crate::hashes::sha256;
+use crate::taproot::{TAPROOT_LEAF_TAPSCRIPT, tapleaf_hash};
+use crate::{BitcoinError, BitcoinTransaction, Script, TransactionOutput};
+use std::fmt;
+
+/// The v1 program of a pay-to-anchor output.
+pub const P2A_PROGRAM: [u8; 2] = [0x4e, 0x73];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct WitnessVersion(u8);
+
+impl WitnessVersion {
+    pub const V0: WitnessVersion = WitnessVersion(0);
+    pub const V1: WitnessVersion = WitnessVersion(1);
+
+    pub fn new(version: u8) -> Result<Self, BitcoinError> {
+        if version > 16 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Self(version))
+    }
+
+    pub fn to_u8(self) -> u8 {
+        self.0
+    }
+
+    /// `OP_0` for v0, `OP_1`..`OP_16` otherwise.
+    pub fn opcode(self) -> u8 {
+        match self.0 {
+            0 => 0x00,
+            v => 0x50 + v,
+        }
+    }
+
+    pub fn from_opcode(opcode: u8) -> Result<Self, BitcoinError> {
+        match opcode {
+            0x00 => Ok(Self(0)),
+            0x51..=0x60 => Ok(Self(opcode - 0x50)),
+            _ => Err(BitcoinError::InvalidFormat),
+        }
+    }
+}
+
+impl fmt::Display for WitnessVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct WitnessProgram {
+    pub version: WitnessVersion,
+    pub program: Vec<u8>,
+}
+
+impl WitnessProgram {
+    /// Checks the lengths consensus allows: 2 to 40 bytes, and exactly 20
+    /// or 32 for v0. Other v1 lengths are valid (if unencumbered) programs,
+    /// so only `is_p2tr` insists on 32.
+    pub fn new(version: WitnessVersion, program: Vec<u8>) -> Result<Self, BitcoinError> {
+        let valid = match version {
+            WitnessVersion::V0 => program.len() == 20 || program.len() == 32,
+            _ => (2..=40).contains(&program.len()),
+        };
+        if !valid {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Self { version, program })
+    }
+
+    pub fn p2wpkh(hash: [u8; 20]) -> Self {
+        Self {
+            version: WitnessVersion::V0,
+            program: hash.to_vec(),
+        }
+    }
+
+    pub fn p2wsh(hash: [u8; 32]) -> Self {
+        Self {
+            version: WitnessVersion::V0,
+            program: hash.to_vec(),
+        }
+    }
+
+    pub fn p2tr(output_key: [u8; 32]) -> Self {
+        Self {
+            version: WitnessVersion::V1,
+            program: output_key.to_vec(),
+        }
+    }
+
+    /// Pay-to-anchor: a keyless output anyone can spend, used to attach
+    /// CPFP fee bumps.
+    pub fn p2a() -> Self {
+        Self {
+            version: WitnessVersion::V1,
+            program: P2A_PROGRAM.to_vec(),
+        }
+    }
+
+    pub fn is_p2wpkh(&self) -> bool {
+        self.version == WitnessVersion::V0 && self.program.len() == 20
+    }
+
+    pub fn is_p2wsh(&self) -> bool {
+        self.version == WitnessVersion::V0 && self.program.len() == 32
+    }
+
+    pub fn is_p2tr(&self) -> bool {
+        self.version == WitnessVersion::V1 && self.program.len() == 32
+    }
+
+    pub fn is_p2a(&self) -> bool {
+        self.version == WitnessVersion::V1 && self.program == P2A_PROGRAM
+    }
+
+    /// `<version opcode> <push program>`.
+    pub fn to_script(&self) -> Script {
+        let mut bytes = vec![self.version.opcode(), self.program.len() as u8];
+        bytes.extend_from_slice(&self.program);
+        Script::new(bytes)
+    }
+
+    pub fn from_script(script: &Script) -> Result<Self, BitcoinError> {
+        match &script.bytes[..] {
+            [opcode, len, program @ ..] if *len as usize == program.len() => {
+                Self::new(WitnessVersion::from_opcode(*opcode)?, program.to_vec())
+            }
+            _ => Err(BitcoinError::InvalidFormat),
+        }
+    }
+}
+
+impl Script {
+    /// The witness program, if this is a segwit output script.
+    pub fn witness_program(&self) -> Option<WitnessProgram> {
+        WitnessProgram::from_script(self).ok()
+    }
+
+    /// SHA256 of the script, as committed to by a P2WSH output.
+    pub fn wscript_hash(&self) -> [u8; 32] {
+        sha256(&self.bytes)
+    }
+
+    /// The P2WSH output paying to this witness script.
+    pub fn to_p2wsh(&self) -> Script {
+        WitnessProgram::p2wsh(self.wscript_hash()).to_script()
+    }
+
+    /// The BIP341 leaf hash of this script as a tapscript (leaf version 0xc0).
+    pub fn tapleaf_hash(&self) -> [u8; 32] {
+        tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, self)
+    }
+}
+
+impl TransactionOutput {
+    pub fn is_anchor(&self) -> bool {
+        self.script_pubkey
+            .witness_program()
+            .is_some_and(|program| program.is_p2a())
+    }
+
+    /// A zero-value anchor, which must be spent in the same package.
+    pub fn is_ephemeral_anchor(&self) -> bool {
+        self.value == 0 && self.is_anchor()
+    }
+}
+
+impl BitcoinTransaction {
+    /// Indexes of the P2A outputs.
+    pub fn anchor_outputs(&self) -> Vec<u32> {
+        (0..)
+            .zip(&self.outputs)
+            .filter(|(_, output)| output.is_anchor())
+            .map(|(vout, _)| vout)
+            .collect()
+    }
+}
@@ -0,0 +1,178 @@
+//! Subscriber for Bitcoin Core's `-zmqpub*` notifications.
+//!
+//! Speaks just enough ZMTP 3.0 (NULL security, SUB socket) over a plain
+//! `TcpStream` to receive `rawtx`/`rawblock` messages, so no libzmq is
+//! needed. Core sends each notification as three frames: the topic, the
+//! body and a little-endian `u32` sequence number.
+
+use crate::constants::MAX_BLOCK_SERIALIZED_SIZE;
+use crate::{BitcoinError, BitcoinTransaction, Block};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+const FLAG_MORE: u8 = 0x01;
+const FLAG_LONG: u8 = 0x02;
+const FLAG_COMMAND: u8 = 0x04;
+/// Largest frame accepted: a maximal block with room to spare.
+const MAX_FRAME_SIZE: usize = MAX_BLOCK_SERIALIZED_SIZE as usize + 4096;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Notification {
+    RawTx {
+        tx: BitcoinTransaction,
+        sequence: u32,
+    },
+    RawBlock {
+        block: Block,
+        sequence: u32,
+    },
+    /// Any other topic (`hashtx`, `hashblock`, `sequence`), left undecoded.
+    Other {
+        topic: String,
+        body: Vec<u8>,
+        sequence: u32,
+    },
+}
+
+pub struct ZmqSubscriber {
+    stream: TcpStream,
+    // Set once a frame read fails, leaving the stream mid-frame.
+    done: bool,
+}
+
+impl ZmqSubscriber {
+    pub fn connect<A: ToSocketAddrs>(addr: A, topics: &[&str]) -> Result<Self, BitcoinError> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(&greeting())?;
+        let mut peer_greeting = [0u8; 64];
+        stream.read_exact(&mut peer_greeting)?;
+        if peer_greeting[0] != 0xff || peer_greeting[9] != 0x7f || peer_greeting[10] < 3 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let mut ready = vec![5];
+        ready.extend_from_slice(b"READY");
+        ready.push(11);
+        ready.extend_from_slice(b"Socket-Type");
+        ready.extend_from_slice(&3u32.to_be_bytes());
+        ready.extend_from_slice(b"SUB");
+        write_frame(&mut stream, FLAG_COMMAND, &ready)?;
+
+        let mut subscriber = Self {
+            stream,
+            done: false,
+        };
+        let (flags, _) = subscriber.read_frame()?;
+        if flags & FLAG_COMMAND == 0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        // ZMTP 3.0 subscriptions are ordinary messages prefixed with 0x01.
+        for topic in topics {
+            let mut body = vec![0x01];
+            body.extend_from_slice(topic.as_bytes());
+            write_frame(&mut subscriber.stream, 0, &body)?;
+        }
+        Ok(subscriber)
+    }
+
+    /// Reads the next message as its raw frames, skipping commands.
+    pub fn recv_multipart(&mut self) -> Result<Vec<Vec<u8>>, BitcoinError> {
+        let mut frames = Vec::new();
+        loop {
+            let (flags, body) = self.read_frame()?;
+            if flags & FLAG_COMMAND != 0 {
+                continue;
+            }
+            frames.push(body);
+            if flags & FLAG_MORE == 0 {
+                return Ok(frames);
+            }
+        }
+    }
+
+    pub fn recv(&mut self) -> Result<Notification, BitcoinError> {
+        let frames = self.recv_multipart()?;
+        let [topic, body, sequence] =
+            <[Vec<u8>; 3]>::try_from(frames).map_err(|_| BitcoinError::InvalidFormat)?;
+        let sequence = u32::from_le_bytes(
+            sequence
+                .try_into()
+                .map_err(|_| BitcoinError::InvalidFormat)?,
+        );
+        match topic.as_slice() {
+            b"rawtx" => Ok(Notification::RawTx {
+                tx: BitcoinTransaction::from_bytes(&body)?.0,
+                sequence,
+            }),
+            b"rawblock" => Ok(Notification::RawBlock {
+                block: Block::from_bytes(&body)?.0,
+                sequence,
+            }),
+            _ => Ok(Notification::Other {
+                topic: String::from_utf8_lossy(&topic).into_owned(),
+                body,
+                sequence,
+            }),
+        }
+    }
+
+    fn read_frame(&mut self) -> Result<(u8, Vec<u8>), BitcoinError> {
+        let frame = self.read_frame_inner();
+        self.done |= frame.is_err();
+        frame
+    }
+
+    fn read_frame_inner(&mut self) -> Result<(u8, Vec<u8>), BitcoinError> {
+        let mut flags = [0u8; 1];
+        self.stream.read_exact(&mut flags)?;
+        let len = if flags[0] & FLAG_LONG != 0 {
+            let mut len = [0u8; 8];
+            self.stream.read_exact(&mut len)?;
+            usize::try_from(u64::from_be_bytes(len)).map_err(|_| BitcoinError::NumericOverflow)?
+        } else {
+            let mut len = [0u8; 1];
+            self.stream.read_exact(&mut len)?;
+            len[0] as usize
+        };
+        if len > MAX_FRAME_SIZE {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut body = vec![0u8; len];
+        self.stream.read_exact(&mut body)?;
+        Ok((flags[0], body))
+    }
+}
+
+/// Blocks until the next notification. Ends after the error from a failed
+/// read, such as the publisher disconnecting; notifications that fail to
+/// decode are yielded as errors and iteration goes on.
+impl Iterator for ZmqSubscriber {
+    type Item = Result<Notification, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        Some(self.recv())
+    }
+}
+
+pub fn greeting() -> [u8; 64] {
+    let mut greeting = [0u8; 64];
+    greeting[0] = 0xff;
+    greeting[9] = 0x7f;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    greeting
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, flags: u8, body: &[u8]) -> Result<(), BitcoinError> {
+    if body.len() > 255 {
+        writer.write_all(&[flags | FLAG_LONG])?;
+        writer.write_all(&(body.len() as u64).to_be_bytes())?;
+    } else {
+        writer.write_all(&[flags, body.len() as u8])?;
+    }
+    writer.write_all(body)?;
+    Ok(())
+}
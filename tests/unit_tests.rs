@@ -4,6 +4,53 @@ use rust_week_3_exercises::*;
 mod tests {
     use super::*;
 
+    const GENESIS_BLOCK_HEX: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    /// Serves one canned HTTP response per connection and returns the request
+    /// heads and bodies it saw.
+    #[cfg(any(feature = "rpc", feature = "http"))]
+    fn fake_http_server(
+        responses: Vec<(u16, Vec<u8>)>,
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut request_body = vec![0u8; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+                request.push_str(&String::from_utf8(request_body).unwrap());
+                requests.push(request);
+                let head = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                reader.get_mut().write_all(head.as_bytes()).unwrap();
+                reader.get_mut().write_all(&body).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
     fn dummy_txid(val: u8) -> [u8; 32] {
         let mut txid = [0u8; 32];
         txid[31] = val;
@@ -34,6 +81,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_varint_serialization() {
+        // Vectors from Core's serialize_tests.
+        let tests = [
+            (0u64, "00"),
+            (0x7f, "7f"),
+            (0x80, "8000"),
+            (0x1234, "a334"),
+            (0xffff, "82fe7f"),
+            (0x123456, "c7e756"),
+            (0x80123456, "86ffc7e756"),
+            (0xffffffff, "8efefefe7f"),
+            (u64::MAX, "80fefefefefefefefe7f"),
+        ];
+        for (value, hex_bytes) in tests {
+            let bytes = hex::decode(hex_bytes).unwrap();
+            assert_eq!(VarInt::new(value).to_bytes(), bytes);
+            assert_eq!(
+                VarInt::from_bytes(&bytes),
+                Ok((VarInt::new(value), bytes.len()))
+            );
+        }
+        assert_eq!(
+            VarInt::from_bytes(&[0x80]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            VarInt::from_bytes(&hex::decode("80fefefefefefefefeff00").unwrap()),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
     #[test]
     fn test_outpoint_roundtrip() {
         let txid = dummy_txid(0xCC);
@@ -95,6 +174,9 @@ mod tests {
 
         assert!(json.contains("\"version\": 1"));
         assert!(json.contains("\"lock_time\": 999"));
+        assert!(json.contains("\"script_sig\": \"deadbeef\""));
+        assert!(serde_json::from_str::<Script>("\"dead\"").is_ok());
+        assert!(serde_json::from_str::<Script>("\"xyz\"").is_err());
     }
 
     #[test]
@@ -110,4 +192,5036 @@ mod tests {
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
     }
+
+    #[test]
+    fn test_gcs_basic_filter_genesis_vector() {
+        // BIP158 test vector: basic filter of the testnet3 genesis block.
+        let block_hash =
+            hex::decode("43497fd7f826957108f4a30fd9cec3aeba79972084e90ead01ea330900000000")
+                .unwrap();
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&block_hash[..16]);
+        let script = hex::decode(
+            "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac",
+        )
+        .unwrap();
+
+        let filter = gcs::GcsFilter::new(gcs::BASIC_FILTER_PARAMS, key, &[&script]).unwrap();
+        assert_eq!(hex::encode(filter.to_bytes()), "019dfca8");
+
+        let (parsed, consumed) =
+            gcs::GcsFilter::from_bytes(gcs::BASIC_FILTER_PARAMS, key, &filter.to_bytes()).unwrap();
+        assert_eq!(parsed, filter);
+        assert_eq!(consumed, 4);
+        assert!(parsed.matches(&script).unwrap());
+        assert!(!parsed.matches(b"not in the set").unwrap());
+    }
+
+    #[test]
+    fn test_gcs_custom_params_roundtrip() {
+        let params = gcs::GcsParams::new(10, 1 << 10).unwrap();
+        let items: Vec<Vec<u8>> = (0u32..200).map(|i| i.to_le_bytes().to_vec()).collect();
+        let filter = gcs::GcsFilter::new(params, [7u8; 16], &items).unwrap();
+
+        let values = filter.decode_values().unwrap();
+        assert_eq!(values.len(), 200);
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert!(items.iter().all(|item| filter.matches(item).unwrap()));
+        assert!(
+            filter
+                .matches_any(&[b"absent".as_slice(), &items[42]])
+                .unwrap()
+        );
+
+        let empty = gcs::GcsFilter::new::<&[u8]>(params, [0u8; 16], &[]).unwrap();
+        assert!(empty.is_empty());
+        assert!(!empty.matches(b"anything").unwrap());
+
+        // Parameters and counts that would overflow are rejected.
+        assert_eq!(gcs::GcsParams::new(64, 1), Err(BitcoinError::InvalidFormat));
+        let huge_m = gcs::GcsParams::new(10, u64::MAX).unwrap();
+        assert_eq!(
+            gcs::GcsFilter::new(huge_m, [0u8; 16], &items),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut crafted = CompactSize::new(u64::MAX).to_bytes();
+        crafted.extend_from_slice(&[0u8; 8]);
+        let (crafted, _) = gcs::GcsFilter::from_bytes(params, [0u8; 16], &crafted).unwrap();
+        assert_eq!(
+            crafted.matches(b"anything"),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_segwit_tx_roundtrip() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(2), 1),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        input.witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let tx =
+            BitcoinTransaction::new(2, vec![input], 0).with_outputs(vec![TransactionOutput::new(
+                50_000,
+                Script::new(vec![0x00, 0x14, 0xAA]),
+            )]);
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+
+        // Clearing every witness falls back to the legacy encoding.
+        let mut legacy = tx.clone();
+        legacy.clear_witnesses();
+        assert!(!legacy.has_witness());
+        assert_eq!(legacy.to_bytes(), tx.to_bytes_without_witness());
+        assert_eq!(legacy.txid(), tx.txid());
+        legacy
+            .set_witness(0, Witness::new(vec![vec![0x01]]))
+            .unwrap();
+        assert!(legacy.inputs[0].has_witness());
+        assert_eq!(&legacy.to_bytes()[4..6], &[0x00, 0x01]);
+        assert!(legacy.set_witness(1, Witness::default()).is_err());
+    }
+
+    #[test]
+    fn test_block_file_reader() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let (block, consumed) = Block::from_bytes(&genesis).unwrap();
+        assert_eq!(consumed, genesis.len());
+        assert_eq!(block.to_bytes(), genesis);
+        assert_eq!(block.transactions[0].outputs[0].value, 50 * 100_000_000);
+
+        let magic = Network::Mainnet.magic();
+        let mut file = vec![0u8; 3];
+        for _ in 0..2 {
+            file.extend_from_slice(&magic);
+            file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+            file.extend_from_slice(&genesis);
+        }
+        // A record cut short by a crash, followed by pre-allocated zeros.
+        file.extend_from_slice(&magic);
+        file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+        file.extend_from_slice(&genesis[..100]);
+        file.extend_from_slice(&[0u8; 64]);
+
+        let reader = blockfile::BlockFileReader::new(std::io::Cursor::new(file), Network::Mainnet);
+        let blocks: Vec<Block> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(blocks, vec![block.clone(), block]);
+    }
+
+    #[test]
+    fn test_block_file_reader_xor_obfuscated() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut file = Network::Mainnet.magic().to_vec();
+        file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+        file.extend_from_slice(&genesis);
+        let key = [0x8f, 0x12, 0x00, 0xa5, 0x33, 0x01, 0xfe, 0x7c];
+        blockfile::xor_in_place(&mut file, key, 0);
+
+        let dir = std::env::temp_dir().join(format!("blk-xor-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("xor.dat"), key).unwrap();
+        std::fs::write(dir.join("blk00000.dat"), &file).unwrap();
+
+        assert_eq!(
+            blockfile::blk_files(&dir).unwrap(),
+            vec![dir.join("blk00000.dat")]
+        );
+        let reader =
+            blockfile::BlockFileReader::open(dir.join("blk00000.dat"), Network::Mainnet).unwrap();
+        let blocks: Vec<Block> = reader.map(|b| b.unwrap()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].to_bytes(), genesis);
+    }
+
+    #[test]
+    fn test_block_records_split() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut file = vec![0u8; 5];
+        for _ in 0..3 {
+            file.extend_from_slice(&Network::Regtest.magic());
+            file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+            file.extend_from_slice(&genesis);
+        }
+        file.extend_from_slice(&Network::Regtest.magic());
+        file.extend_from_slice(&[0xff, 0xff]);
+
+        let records = blockfile::block_records(&file, Network::Regtest);
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| *r == genesis.as_slice()));
+        assert!(blockfile::block_records(&file, Network::Mainnet).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parse_blocks_parallel() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let dir = std::env::temp_dir().join(format!("blk-par-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut paths = Vec::new();
+        for (i, count) in [2usize, 0, 3].into_iter().enumerate() {
+            let mut file = Vec::new();
+            for _ in 0..count {
+                file.extend_from_slice(&Network::Mainnet.magic());
+                file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+                file.extend_from_slice(&genesis);
+            }
+            let path = dir.join(format!("blk{:05}.dat", i));
+            std::fs::write(&path, file).unwrap();
+            paths.push(path);
+        }
+
+        let blocks = blockfile::parse_blocks_parallel(&paths, Network::Mainnet).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(blocks.len(), 5);
+        assert!(blocks.iter().all(|b| b.to_bytes() == genesis));
+    }
+
+    #[test]
+    fn test_block_view_zero_copy() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let view = BlockView::new(&genesis).unwrap();
+        assert_eq!(view.header().time, 1231006505);
+
+        let txs: Vec<TransactionView> = view.transactions().map(|t| t.unwrap()).collect();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].as_bytes(), &genesis[81..]);
+        assert_eq!(txs[0].as_bytes().as_ptr(), genesis[81..].as_ptr());
+        assert_eq!(
+            view.to_block().unwrap().transactions[0],
+            txs[0].parse().unwrap()
+        );
+
+        assert_eq!(
+            BlockView::new(&genesis[..60]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        let truncated = BlockView::new(&genesis[..120]).unwrap();
+        assert_eq!(
+            truncated.transactions().next(),
+            Some(Err(BitcoinError::InsufficientBytes))
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped_block_file() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut file = Vec::new();
+        for _ in 0..2 {
+            file.extend_from_slice(&Network::Mainnet.magic());
+            file.extend_from_slice(&(genesis.len() as u32).to_le_bytes());
+            file.extend_from_slice(&genesis);
+        }
+        let dir = std::env::temp_dir().join(format!("blk-mmap-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("blk00000.dat"), &file).unwrap();
+
+        let mapped =
+            unsafe { mmap::MappedBlockFile::open(dir.join("blk00000.dat"), Network::Mainnet) }
+                .unwrap();
+        let views: Vec<BlockView> = mapped.blocks().map(|b| b.unwrap()).collect();
+        assert_eq!(views.len(), 2);
+        assert!(views.iter().all(|v| v.as_bytes() == genesis.as_slice()));
+        drop(mapped);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_block_transaction_offsets() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let (block, _) = Block::from_bytes(&genesis).unwrap();
+        // The only transaction's txid is the genesis merkle root.
+        let expected = vec![(Txid(block.header.merkle_root), 81, genesis.len() - 81)];
+        assert_eq!(block.transaction_offsets(), expected);
+        assert_eq!(
+            BlockView::new(&genesis)
+                .unwrap()
+                .transaction_offsets()
+                .unwrap(),
+            expected
+        );
+
+        let (_, start, len) = expected[0];
+        let (tx, _) = BitcoinTransaction::from_bytes(&genesis[start..start + len]).unwrap();
+        assert_eq!(tx, block.transactions[0]);
+
+        // A segwit marker with every witness empty would re-serialize two
+        // bytes (plus a byte per input) shorter, so it is refused.
+        let legacy = block.transactions[0].to_bytes();
+        let mut superfluous = legacy[..4].to_vec();
+        superfluous.extend([0x00, 0x01]);
+        superfluous.extend_from_slice(&legacy[4..legacy.len() - 4]);
+        superfluous.push(0x00);
+        superfluous.extend_from_slice(&legacy[legacy.len() - 4..]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&superfluous),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut block_bytes = genesis[..81].to_vec();
+        block_bytes.extend(superfluous);
+        assert_eq!(
+            Block::from_bytes(&block_bytes).map(|_| ()),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_undo_file_reader() {
+        let pkh = [0x11u8; 20];
+        let mut payload = vec![0x01, 0x02];
+        // Height 100, not coinbase, 50 BTC to a P2PKH script.
+        payload.extend_from_slice(&[0x80, 0x48, 0x00, 0x32, 0x00]);
+        payload.extend_from_slice(&pkh);
+        // Height 1 coinbase, zero value, two-byte raw script.
+        payload.extend_from_slice(&[0x03, 0x00, 0x00, 0x08, 0x51, 0x52]);
+
+        let prev_blockhash = [0x42u8; 32];
+        let checksum = hashes::sha256d(&[&prev_blockhash[..], &payload].concat());
+        // A corrupt length above MAX_SIZE is skipped, not allocated.
+        let mut file = Network::Mainnet.magic().to_vec();
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(&Network::Mainnet.magic());
+        file.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        file.extend_from_slice(&payload);
+        file.extend_from_slice(&checksum);
+        file.extend_from_slice(&[0u8; 16]);
+
+        let records: Vec<undo::UndoRecord> =
+            undo::UndoFileReader::new(std::io::Cursor::new(file), Network::Mainnet)
+                .map(|r| r.unwrap())
+                .collect();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].verify_checksum(&prev_blockhash));
+        assert!(!records[0].verify_checksum(&[0u8; 32]));
+
+        let spent = &records[0].block_undo.txs[0].spent;
+        let p2pkh = [&[0x76, 0xa9, 0x14][..], &pkh, &[0x88, 0xac]].concat();
+        assert_eq!(
+            spent[0],
+            undo::Coin::new(
+                100,
+                false,
+                TransactionOutput::new(5_000_000_000, Script::new(p2pkh))
+            )
+        );
+        assert_eq!(
+            spent[1],
+            undo::Coin::new(
+                1,
+                true,
+                TransactionOutput::new(0, Script::new(vec![0x51, 0x52]))
+            )
+        );
+    }
+
+    #[test]
+    fn test_core_json_and_btx_decode() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let (block, _) = Block::from_bytes(&genesis).unwrap();
+        let json = core_json::block_to_json(&block);
+        assert_eq!(
+            json["hash"],
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        let tx = &json["tx"][0];
+        assert_eq!(
+            tx["txid"],
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(tx["vout"][0]["value"], 50.0);
+        assert_eq!(tx["vin"][0]["sequence"], 0xFFFFFFFFu32);
+        assert!(tx["vin"][0]["coinbase"].is_string());
+
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_btx"))
+            .args(["decode", &GENESIS_BLOCK_HEX[162..]])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let decoded: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(&decoded, tx);
+    }
+
+    #[test]
+    fn test_transaction_builder_validation() {
+        let outpoint = OutPoint::new(dummy_txid(9), 0);
+        let p2wpkh = Script::new([&[0x00, 0x14][..], &[0xAB; 20]].concat());
+        let tx = TransactionBuilder::new()
+            .lock_time(800_000)
+            .add_input(outpoint.clone(), 0xFFFFFFFD)
+            .add_output(10_000, p2wpkh.clone())
+            .build()
+            .unwrap();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.lock_time, 800_000);
+        assert_eq!(tx.outputs[0].value, 10_000);
+
+        assert_eq!(
+            TransactionBuilder::new()
+                .add_output(1, p2wpkh.clone())
+                .build(),
+            Err(BitcoinError::InvalidTransaction("bad-txns-vin-empty"))
+        );
+        assert_eq!(
+            TransactionBuilder::new()
+                .add_input(outpoint.clone(), 0)
+                .add_input(outpoint.clone(), 1)
+                .add_output(1, p2wpkh.clone())
+                .build(),
+            Err(BitcoinError::InvalidTransaction(
+                "bad-txns-inputs-duplicate"
+            ))
+        );
+        assert_eq!(
+            TransactionBuilder::new()
+                .add_input(outpoint, 0)
+                .add_output(builder::MAX_MONEY, p2wpkh.clone())
+                .add_output(1, p2wpkh)
+                .build(),
+            Err(BitcoinError::InvalidTransaction(
+                "bad-txns-txouttotal-toolarge"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_psbt_roundtrip_and_btx_create() {
+        let spec = r#"{
+            "locktime": 42,
+            "inputs": [{ "txid": "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", "vout": 0 }],
+            "outputs": [{ "value": 5000, "script_pubkey": "51" }]
+        }"#;
+        let path = std::env::temp_dir().join(format!("btx-spec-{}.json", std::process::id()));
+        std::fs::write(&path, spec).unwrap();
+        let run = |extra: &[&str]| {
+            let output = std::process::Command::new(env!("CARGO_BIN_EXE_btx"))
+                .arg("create")
+                .args(extra)
+                .arg(&path)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        let raw = run(&[]);
+        let encoded = run(&["--psbt"]);
+        std::fs::remove_file(&path).unwrap();
+
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(raw).unwrap()).unwrap();
+        assert_eq!(tx.lock_time, 42);
+        assert_eq!(tx.inputs[0].sequence, 0xFFFFFFFD);
+        assert_eq!(
+            core_json::reversed_hex(&tx.inputs[0].previous_output.txid.0),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+
+        let mut psbt = psbt::Psbt::from_base64(&encoded).unwrap();
+        assert_eq!(psbt.unsigned_tx, tx);
+        psbt.inputs[0].push((vec![0x01], vec![0xAA; 8]));
+        psbt.global.push((vec![0xFB], vec![0, 0, 0, 0]));
+        let (parsed, consumed) = psbt::Psbt::from_bytes(&psbt.to_bytes()).unwrap();
+        assert_eq!(parsed, psbt);
+        assert_eq!(consumed, psbt.to_bytes().len());
+
+        // A PSBT whose global unsigned transaction value is `raw`.
+        let with_tx = |raw: &[u8]| {
+            let mut v = psbt::PSBT_MAGIC.to_vec();
+            v.extend([0x01, 0x00]);
+            v.extend(CompactSize::new(raw.len() as u64).to_bytes());
+            v.extend_from_slice(raw);
+            v.push(0x00);
+            v.extend(vec![0x00; tx.inputs.len() + tx.outputs.len()]);
+            v
+        };
+        assert!(psbt::Psbt::from_bytes(&with_tx(&tx.to_bytes())).is_ok());
+        assert_eq!(
+            psbt::Psbt::from_bytes(&with_tx(&[tx.to_bytes(), vec![0xAB]].concat())),
+            Err(BitcoinError::TrailingBytes(1))
+        );
+        let mut signed = tx.clone();
+        signed.inputs[0].script_sig = Script::new(vec![0x51]);
+        assert_eq!(
+            psbt::Psbt::from_bytes(&with_tx(&signed.to_bytes())),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut witnessed = tx.clone();
+        witnessed.inputs[0].witness = Witness::new(vec![vec![0x01]]);
+        assert!(psbt::Psbt::from_bytes(&with_tx(&witnessed.to_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_script_type_classification() {
+        let cases = vec![
+            (
+                "76a914".to_string() + &"11".repeat(20) + "88ac",
+                ScriptType::P2pkh,
+            ),
+            (
+                "a914".to_string() + &"22".repeat(20) + "87",
+                ScriptType::P2sh,
+            ),
+            ("0014".to_string() + &"33".repeat(20), ScriptType::P2wpkh),
+            ("0020".to_string() + &"44".repeat(32), ScriptType::P2wsh),
+            ("5120".to_string() + &"55".repeat(32), ScriptType::P2tr),
+            ("5202".to_string() + "abcd", ScriptType::WitnessUnknown),
+            ("21".to_string() + &"02".repeat(33) + "ac", ScriptType::P2pk),
+            (
+                "5121".to_string() + &"02".repeat(33) + "21" + &"03".repeat(33) + "52ae",
+                ScriptType::Multisig,
+            ),
+            ("6a0568656c6c6f".to_string(), ScriptType::NullData),
+            ("6a".to_string(), ScriptType::NullData),
+            ("".to_string(), ScriptType::NonStandard),
+            // Shapes the loose byte patterns used to accept.
+            ("5151ae".to_string(), ScriptType::NonStandard),
+            ("52ffff51ae".to_string(), ScriptType::NonStandard),
+            (
+                "5120".to_string() + &"02".repeat(32) + "51ae",
+                ScriptType::NonStandard,
+            ),
+            ("6a0568656c6c6fac".to_string(), ScriptType::NonStandard),
+            (
+                "21".to_string() + &"05".repeat(33) + "ac",
+                ScriptType::NonStandard,
+            ),
+            (
+                "41".to_string() + &"02".repeat(65) + "ac",
+                ScriptType::NonStandard,
+            ),
+        ];
+        for (script_hex, expected) in cases {
+            let script = Script::new(hex::decode(&script_hex).unwrap());
+            assert_eq!(script.script_type(), expected, "{}", script_hex);
+        }
+        assert_eq!(ScriptType::P2wpkh.to_string(), "witness_v0_keyhash");
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi_handle_accessors() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let raw = &genesis[81..];
+        unsafe {
+            let tx = ffi::btx_tx_decode(raw.as_ptr(), raw.len());
+            assert!(!tx.is_null());
+
+            let mut count = 0usize;
+            assert_eq!(ffi::btx_tx_output_count(tx, &mut count), ffi::BTX_OK);
+            assert_eq!(count, 1);
+            let mut value = 0u64;
+            assert_eq!(ffi::btx_tx_output_value(tx, 0, &mut value), ffi::BTX_OK);
+            assert_eq!(value, 5_000_000_000);
+            assert_eq!(
+                ffi::btx_tx_output_value(tx, 1, &mut value),
+                ffi::BTX_ERR_INDEX
+            );
+
+            let mut txid = [0u8; 32];
+            assert_eq!(ffi::btx_tx_txid(tx, &mut txid), ffi::BTX_OK);
+            assert_eq!(txid, genesis[36..68]);
+
+            let mut data = std::ptr::null();
+            let mut len = 0usize;
+            assert_eq!(
+                ffi::btx_tx_output_script(tx, 0, &mut data, &mut len),
+                ffi::BTX_OK
+            );
+            assert_eq!(
+                std::slice::from_raw_parts(data, len),
+                &raw[raw.len() - 71..raw.len() - 4]
+            );
+
+            ffi::btx_tx_free(tx);
+            assert!(ffi::btx_tx_decode(raw.as_ptr(), raw.len() - 1).is_null());
+            assert_eq!(
+                ffi::btx_tx_version(std::ptr::null(), &mut 0),
+                ffi::BTX_ERR_NULL
+            );
+        }
+    }
+
+    #[cfg(feature = "zmq")]
+    #[test]
+    fn test_zmq_subscriber_against_fake_publisher() {
+        use std::io::{Read, Write};
+
+        fn handshake(stream: &mut std::net::TcpStream) {
+            stream.write_all(&zmq::greeting()).unwrap();
+            let mut buf = [0u8; 64];
+            stream.read_exact(&mut buf).unwrap();
+            let ready = b"\x05READY\x0bSocket-Type\x00\x00\x00\x03PUB";
+            zmq::write_frame(stream, 0x04, ready).unwrap();
+            // Subscriber READY command, then its "rawtx" subscription.
+            let mut header = [0u8; 2];
+            stream.read_exact(&mut header).unwrap();
+            stream
+                .read_exact(&mut vec![0u8; header[1] as usize])
+                .unwrap();
+            stream.read_exact(&mut header).unwrap();
+            let mut subscription = vec![0u8; header[1] as usize];
+            stream.read_exact(&mut subscription).unwrap();
+            assert_eq!(subscription, b"\x01rawtx");
+        }
+
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let raw_block = genesis.clone();
+        let publisher = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            handshake(&mut stream);
+            zmq::write_frame(&mut stream, 0x01, b"rawtx").unwrap();
+            zmq::write_frame(&mut stream, 0x01, &raw_block[81..]).unwrap();
+            zmq::write_frame(&mut stream, 0x00, &7u32.to_le_bytes()).unwrap();
+            zmq::write_frame(&mut stream, 0x01, b"rawblock").unwrap();
+            zmq::write_frame(&mut stream, 0x01, &raw_block).unwrap();
+            zmq::write_frame(&mut stream, 0x00, &8u32.to_le_bytes()).unwrap();
+        });
+
+        let mut subscriber = zmq::ZmqSubscriber::connect(addr, &["rawtx"]).unwrap();
+        let (block, _) = Block::from_bytes(&genesis).unwrap();
+        assert_eq!(
+            subscriber.next().unwrap().unwrap(),
+            zmq::Notification::RawTx {
+                tx: block.transactions[0].clone(),
+                sequence: 7
+            }
+        );
+        assert_eq!(
+            subscriber.recv().unwrap(),
+            zmq::Notification::RawBlock { block, sequence: 8 }
+        );
+        publisher.join().unwrap();
+        // The publisher hung up: one error, then the iterator ends.
+        assert!(matches!(subscriber.next(), Some(Err(BitcoinError::Io(_)))));
+        assert!(subscriber.next().is_none());
+
+        // A frame declaring a huge length is refused before allocating.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let publisher = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            handshake(&mut stream);
+            stream.write_all(&[0x02]).unwrap();
+            stream.write_all(&(1u64 << 40).to_be_bytes()).unwrap();
+            stream
+        });
+        let mut subscriber = zmq::ZmqSubscriber::connect(addr, &["rawtx"]).unwrap();
+        assert_eq!(subscriber.next(), Some(Err(BitcoinError::InvalidFormat)));
+        assert!(subscriber.next().is_none());
+        drop(publisher.join().unwrap());
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_rpc_client_against_fake_node() {
+        let tx_hex = &GENESIS_BLOCK_HEX[162..];
+        let txid = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let (url, server) = fake_http_server(vec![
+            (
+                200,
+                format!(r#"{{"result":"{}","error":null,"id":0}}"#, tx_hex).into_bytes(),
+            ),
+            (
+                500,
+                r#"{"result":null,"error":{"code":-25,"message":"bad-txns-inputs-missingorspent"},"id":1}"#
+                    .as_bytes()
+                    .to_vec(),
+            ),
+        ]);
+
+        let client = rpc::RpcClient::new(&url, "alice", "secret");
+        let tx_id = Txid(core_json::hash_from_reversed_hex(txid).unwrap());
+        let tx = client.get_raw_transaction(&tx_id).unwrap();
+        assert_eq!(tx.txid(), tx_id);
+        assert_eq!(
+            client.send_raw_transaction(&tx),
+            Err(BitcoinError::Rpc {
+                code: -25,
+                message: "bad-txns-inputs-missingorspent".to_string()
+            })
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("Authorization: Basic YWxpY2U6c2VjcmV0"));
+        assert!(requests[0].contains(r#""method":"getrawtransaction""#));
+        assert!(requests[0].contains(txid));
+        assert!(requests[1].contains(r#""method":"sendrawtransaction""#));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_rest_client_retries_and_parses() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let hash = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let (url, server) = fake_http_server(vec![
+            (503, b"busy".to_vec()),
+            (200, genesis.clone()),
+            (200, hash.as_bytes().to_vec()),
+            (404, b"Block not found".to_vec()),
+        ]);
+        let client = rest::RestClient::new(&url, rest::Backend::Esplora)
+            .with_retries(2, std::time::Duration::from_millis(1));
+
+        let block_hash = core_json::hash_from_reversed_hex(hash).unwrap();
+        let block = client.get_block(&block_hash).unwrap();
+        assert_eq!(block.block_hash(), block_hash);
+        assert_eq!(client.get_block_hash(0).unwrap(), block_hash);
+        assert!(matches!(
+            client.get_block(&[0u8; 32]),
+            Err(BitcoinError::Http(_))
+        ));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with(&format!("GET /block/{}/raw", hash)));
+        assert_eq!(requests[0], requests[1]);
+        assert!(requests[2].starts_with("GET /block-height/0 "));
+    }
+
+    #[test]
+    fn test_electrum_messages() {
+        // Example from the Electrum protocol docs: the genesis P2PKH output.
+        let script =
+            Script::new(hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap());
+        assert_eq!(
+            electrum::script_hash(&script),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+
+        let line = electrum::Request::scripthash_listunspent(3, &script).to_line();
+        assert!(line.ends_with('\n'));
+        let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(request["method"], "blockchain.scripthash.listunspent");
+        assert_eq!(request["params"][0], electrum::script_hash(&script));
+
+        let txid = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let response = electrum::Response::from_line(&format!(
+            r#"{{"jsonrpc":"2.0","id":3,"result":[{{"tx_hash":"{}","tx_pos":0,"height":0,"value":5000000000}}]}}"#,
+            txid
+        ))
+        .unwrap();
+        let unspent = response.into_unspent().unwrap();
+        assert_eq!(unspent[0].value, 5_000_000_000);
+        assert_eq!(
+            unspent[0].outpoint().unwrap(),
+            OutPoint::new(core_json::hash_from_reversed_hex(txid).unwrap(), 0)
+        );
+
+        let get = electrum::Request::transaction_get(4, &unspent[0].outpoint().unwrap().txid);
+        assert_eq!(get.params[0], txid);
+        let response = electrum::Response::from_line(&format!(
+            r#"{{"jsonrpc":"2.0","id":4,"result":"{}"}}"#,
+            &GENESIS_BLOCK_HEX[162..]
+        ))
+        .unwrap();
+        assert_eq!(
+            core_json::reversed_hex(&response.into_transaction().unwrap().txid().0),
+            txid
+        );
+
+        let error = electrum::Response::from_line(
+            r#"{"jsonrpc":"2.0","id":5,"error":{"code":2,"message":"daemon error"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            error.into_result(),
+            Err(BitcoinError::Rpc { code: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_script_asm_and_opcodes() {
+        let p2pkh =
+            Script::new(hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap());
+        assert_eq!(
+            p2pkh.to_asm(),
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+        // Small pushes print as script numbers, like Core's asm.
+        let script = Script::new(vec![0x00, 0x4f, 0x52, 0x02, 0xe8, 0x03, 0x01, 0x81, 0x4c]);
+        assert_eq!(script.to_asm(), "0 -1 2 1000 -1 [error]");
+        assert_eq!(opcodes::name(0xba), "OP_CHECKSIGADD");
+        assert_eq!(
+            opcodes::from_name("OP_CHECKSIG"),
+            Some(opcodes::OP_CHECKSIG)
+        );
+        assert_eq!(
+            opcodes::from_name("csv"),
+            Some(opcodes::OP_CHECKSEQUENCEVERIFY)
+        );
+        assert_eq!(opcodes::from_name("16"), Some(opcodes::OP_16));
+        assert_eq!(opcodes::from_name("OP_BOGUS"), None);
+    }
+
+    #[test]
+    fn test_transaction_format_with_options() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xEE), 1),
+            Script::new(vec![0x51]),
+            0xFFFFFFFF,
+        );
+        input.witness = Witness::new(vec![vec![0xAB, 0xCD]]);
+        let tx =
+            BitcoinTransaction::new(2, vec![input], 0).with_outputs(vec![TransactionOutput::new(
+                150_000_000,
+                Script::new(vec![0x6a]),
+            )]);
+
+        assert_eq!(
+            tx.format_with(&format::FormatOptions::default()),
+            tx.to_string()
+        );
+
+        let verbose = tx.format_with(&format::FormatOptions::verbose());
+        assert!(verbose.starts_with(&format!(
+            "Txid: {}\n",
+            core_json::reversed_hex(&tx.txid().0)
+        )));
+        assert!(verbose.contains(&format!("  Previous Output Txid: ee{}\n", "00".repeat(31))));
+        assert!(verbose.contains("  ScriptSig: 1\n"));
+        assert!(verbose.contains("  Witness: 1 items\n    abcd\n"));
+        assert!(verbose.contains("  Output Value: 1.50000000 BTC\n"));
+        assert!(verbose.contains("  ScriptPubKey: OP_RETURN\n"));
+
+        let hex_scripts = format::FormatOptions {
+            script_format: format::ScriptFormat::Hex,
+            ..Default::default()
+        };
+        assert!(tx.format_with(&hex_scripts).contains("ScriptPubKey: 6a\n"));
+    }
+
+    #[test]
+    fn test_alternate_display_hex_dump() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xEE), 1),
+            Script::new(vec![0x51]),
+            0xFFFFFFFF,
+        );
+        input.witness = Witness::new(vec![vec![0xAB, 0xCD]]);
+        let tx =
+            BitcoinTransaction::new(2, vec![input], 0).with_outputs(vec![TransactionOutput::new(
+                1000,
+                Script::new(vec![0x6a, 0x02, 0xCA, 0xFE]),
+            )]);
+
+        let dump = tx.hex_dump();
+        let bytes: Vec<u8> = dump.rows.iter().flat_map(|row| row.bytes.clone()).collect();
+        assert_eq!(bytes, tx.to_bytes());
+        assert_eq!(dump.rows[1].field, "marker, flag");
+
+        let text = format!("{:#}", tx);
+        assert!(text.starts_with(&format!("000000  {:<32}  version: 2\n", "02000000")));
+        assert!(text.contains("input[0] vout: 1\n"));
+        assert!(text.contains("output[0] scriptPubKey: OP_RETURN -32458\n"));
+        assert!(text.contains("input[0] txid: "));
+        assert!(text.contains(&format!("\n000017  {}ee\n", "00".repeat(15))));
+
+        let script = Script::new(vec![0x6a, 0x02, 0xCA, 0xFE]);
+        assert_eq!(script.to_string(), "OP_RETURN -32458");
+        let dump = script.hex_dump();
+        assert_eq!(dump.rows.len(), 3);
+        assert_eq!(dump.rows[2].bytes, vec![0x02, 0xCA, 0xFE]);
+        assert_eq!(dump.rows[2].value, "push 2 bytes");
+        assert!(format!("{:#}", script).contains("script: OP_RETURN\n"));
+
+        let outpoint = OutPoint::new(dummy_txid(0xEE), 3);
+        assert_eq!(outpoint.to_string(), format!("ee{}:3", "00".repeat(31)));
+        assert!(format!("{:#}", outpoint).ends_with(&format!("{:<32}  vout: 3\n", "03000000")));
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn test_colored_format() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(0xEE), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(
+            1000,
+            Script::new(vec![0x6a, 0x01, 0x07]),
+        )]);
+        let options = format::FormatOptions {
+            color: true,
+            ..format::FormatOptions::verbose()
+        };
+        let text = tx.format_with(&options);
+        assert!(text.contains(&format!(
+            "Previous Output Txid: \x1b[33mee{}\x1b[0m\n",
+            "00".repeat(31)
+        )));
+        assert!(text.contains("Output Value: \x1b[32m0.00001000 BTC\x1b[0m\n"));
+        assert!(text.contains("ScriptPubKey: \x1b[36mOP_RETURN\x1b[0m 7\n"));
+
+        let plain = tx.format_with(&format::FormatOptions::verbose());
+        assert!(!plain.contains('\x1b'));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_events() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct Fields(Vec<String>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        struct Collector(Arc<Mutex<Vec<String>>>);
+
+        impl Subscriber for Collector {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                let name = span.metadata().name();
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("span {} {}", name, fields.0.join(" ")));
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0.join(" "));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        tracing::subscriber::with_default(Collector(log.clone()), || {
+            Block::from_bytes(&bytes).unwrap();
+        });
+
+        let log = log.lock().unwrap();
+        assert_eq!(log[0], "span from_bytes len=285");
+        assert!(log[1].starts_with("message=decoded transaction size=204 inputs=1 outputs=1"));
+        assert!(log[2].starts_with("message=decoded block size=285 tx_count=1 elapsed_us="));
+    }
+
+    #[test]
+    fn test_check_standard() {
+        let p2wpkh = Script::new([vec![0x00, 0x14], vec![0x11; 20]].concat());
+        let p2pkh =
+            Script::new([vec![0x76, 0xa9, 0x14], vec![0x22; 20], vec![0x88, 0xac]].concat());
+        let prevout = TransactionOutput::new(50_000, p2wpkh.clone());
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(294, p2wpkh.clone()),
+            TransactionOutput::new(546, p2pkh.clone()),
+            TransactionOutput::new(0, Script::new(vec![0x6a, 0x01, 0x01])),
+        ]);
+        assert!(tx.check_standard(std::slice::from_ref(&prevout)).is_empty());
+
+        let policy = policy::Policy::default();
+        assert_eq!(policy.dust_threshold(&tx.outputs[0]), 294);
+        assert_eq!(policy.dust_threshold(&tx.outputs[1]), 546);
+
+        let mut bad = tx.clone();
+        bad.version = 4;
+        bad.inputs[0].script_sig = Script::new(vec![0x76]);
+        bad.outputs[0].value = 293;
+        bad.outputs[1].value = 1;
+        bad.outputs
+            .push(TransactionOutput::new(0, Script::new(vec![0x6a])));
+        bad.outputs
+            .push(TransactionOutput::new(1000, Script::new(vec![0xff])));
+        assert_eq!(
+            bad.check_standard(&[TransactionOutput::new(1, Script::new(vec![0xff]))]),
+            vec![
+                "version",
+                "scriptsig-not-pushonly",
+                "dust",
+                "scriptpubkey",
+                "multi-op-return",
+                "bad-txns-nonstandard-inputs",
+            ]
+        );
+        assert_eq!(
+            tx.check_standard(&[]),
+            vec!["bad-txns-inputs-missingorspent"]
+        );
+
+        // 1-of-2 bare multisig is standard unless the node disables it.
+        let multisig = Script::new(
+            [
+                vec![0x51, 0x21],
+                vec![0x02; 33],
+                vec![0x21],
+                vec![0x03; 33],
+                vec![0x52, 0xae],
+            ]
+            .concat(),
+        );
+        let mut bare = tx.clone();
+        bare.outputs = vec![TransactionOutput::new(10_000, multisig)];
+        assert!(
+            bare.check_standard(std::slice::from_ref(&prevout))
+                .is_empty()
+        );
+        let strict = policy::Policy {
+            permit_bare_multisig: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            bare.check_standard_with(std::slice::from_ref(&prevout), &strict),
+            vec!["bare-multisig"]
+        );
+
+        // OP_1 OP_1 OP_CHECKMULTISIG has no keys and is not multisig at all.
+        bare.outputs = vec![TransactionOutput::new(
+            10_000,
+            Script::new(vec![0x51, 0x51, 0xae]),
+        )];
+        assert_eq!(bare.check_standard(&[prevout]), vec!["scriptpubkey"]);
+    }
+
+    #[test]
+    fn test_check_transaction() {
+        let bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let (genesis, _) = Block::from_bytes(&bytes).unwrap();
+        let coinbase = &genesis.transactions[0];
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.check_transaction(), Ok(()));
+
+        let mut short = coinbase.clone();
+        short.inputs[0].script_sig = Script::new(vec![0x51]);
+        assert_eq!(
+            short.check_transaction(),
+            Err(BitcoinError::InvalidTransaction("bad-cb-length"))
+        );
+
+        let null = OutPoint::new([0; 32], u32::MAX);
+        assert!(null.is_null());
+        let mut spend = coinbase.clone();
+        spend.inputs.insert(
+            0,
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0),
+        );
+        assert!(!spend.is_coinbase());
+        assert_eq!(
+            spend.check_transaction(),
+            Err(BitcoinError::InvalidTransaction("bad-txns-prevout-null"))
+        );
+
+        let mut oversize = coinbase.clone();
+        oversize.outputs[0].script_pubkey = Script::new(vec![0x6a; 1_000_000]);
+        assert_eq!(
+            oversize.check_transaction(),
+            Err(BitcoinError::InvalidTransaction("bad-txns-oversize"))
+        );
+
+        let mut overflow = coinbase.clone();
+        overflow.outputs[0].value = consensus::MAX_MONEY + 1;
+        assert_eq!(
+            overflow.check_transaction(),
+            Err(BitcoinError::InvalidTransaction("bad-txns-vout-toolarge"))
+        );
+    }
+
+    #[test]
+    fn test_fee_from_prevouts() {
+        let script = Script::new(vec![0x51]);
+        let funding = BitcoinTransaction::new(2, vec![], 0).with_outputs(vec![
+            TransactionOutput::new(60_000, script.clone()),
+            TransactionOutput::new(40_000, script.clone()),
+        ]);
+        let txid = funding.txid();
+        let lookup = |outpoint: &OutPoint| {
+            if outpoint.txid == txid {
+                funding.outputs.get(outpoint.vout as usize).cloned()
+            } else {
+                None
+            }
+        };
+
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new(txid.0, 0), Script::new(vec![]), 0),
+                TransactionInput::new(OutPoint::new(txid.0, 1), Script::new(vec![]), 0),
+            ],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(99_000, script.clone())]);
+        assert_eq!(spend.fee(lookup), Ok(1_000));
+
+        let mut overspend = spend.clone();
+        overspend.outputs[0].value = 100_001;
+        assert_eq!(
+            overspend.fee(lookup),
+            Err(BitcoinError::InvalidTransaction("bad-txns-in-belowout"))
+        );
+
+        let mut missing = spend.clone();
+        missing.inputs[1].previous_output.vout = 2;
+        assert_eq!(
+            missing.fee(lookup),
+            Err(BitcoinError::MissingPrevout(OutPoint::new(txid.0, 2)))
+        );
+    }
+
+    #[test]
+    fn test_locktime_finality_and_bip68() {
+        use locktime::{LockReason, RelativeLockTime, SequenceLocks};
+
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                sequence,
+            )
+        };
+        let mut tx = BitcoinTransaction::new(2, vec![input(0xFFFFFFFE)], 800_000);
+        assert!(!tx.is_final(800_000, 0));
+        assert!(tx.is_final(800_001, 0));
+        tx.inputs[0].sequence = 0xFFFFFFFF;
+        assert!(tx.is_final(1, 0));
+
+        tx.lock_time = 1_700_000_000;
+        tx.inputs[0].sequence = 0;
+        assert!(!tx.is_final(900_000, 1_700_000_000));
+        assert!(tx.is_final(900_000, 1_700_000_001));
+
+        // 10 blocks, and 3 * 512 seconds.
+        let tx = BitcoinTransaction::new(2, vec![input(10), input((1 << 22) | 3)], 0);
+        assert_eq!(
+            tx.inputs[0].relative_lock_time(),
+            Some(RelativeLockTime::Blocks(10))
+        );
+        assert_eq!(
+            tx.inputs[1].relative_lock_time(),
+            Some(RelativeLockTime::Time(1536))
+        );
+        assert_eq!(input(1 << 31).relative_lock_time(), None);
+        assert!(RelativeLockTime::Blocks(10).is_satisfied(100, 0, 110, 0));
+        assert!(!RelativeLockTime::Blocks(10).is_satisfied(100, 0, 109, 0));
+
+        let locks = tx.sequence_locks(&[100, 200], |height| height * 600);
+        assert_eq!(
+            locks,
+            SequenceLocks {
+                min_height: 109,
+                min_time: 199 * 600 + 1535,
+            }
+        );
+        assert!(!locks.is_satisfied(109, 200_000));
+        assert!(!locks.is_satisfied(110, 199 * 600 + 1535));
+        assert!(locks.is_satisfied(110, 199 * 600 + 1536));
+
+        let mut v1 = tx.clone();
+        v1.version = 1;
+        assert!(v1.sequence_locks(&[100, 200], |_| 0).is_satisfied(0, 0));
+
+        let mtp_at = |height| height * 600;
+        assert_eq!(
+            tx.is_mineable_at(109, 200_000, &[100, 200], mtp_at),
+            Err(LockReason::RelativeHeight(110))
+        );
+        assert_eq!(
+            tx.is_mineable_at(110, 199 * 600 + 1535, &[100, 200], mtp_at),
+            Err(LockReason::RelativeTime(199 * 600 + 1536))
+        );
+        assert_eq!(
+            tx.is_mineable_at(110, 199 * 600 + 1536, &[100, 200], mtp_at),
+            Ok(())
+        );
+        assert_eq!(
+            tx.is_mineable_at(110, 200_000, &[100], mtp_at),
+            Err(LockReason::MissingConfirmationHeight)
+        );
+        let mut locked = tx.clone();
+        locked.lock_time = 500;
+        assert_eq!(
+            locked.is_mineable_at(500, 200_000, &[100, 200], mtp_at),
+            Err(LockReason::LockTime(500))
+        );
+    }
+
+    #[test]
+    fn test_bip125_replacement_rules() {
+        use rbf::{RbfRule, Replacement};
+
+        let p2wpkh = Script::new([vec![0x00, 0x14], vec![0x11; 20]].concat());
+        let confirmed = OutPoint::new(dummy_txid(1), 0);
+        let unconfirmed = OutPoint::new(dummy_txid(2), 0);
+        let original = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                confirmed.clone(),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(90_000, p2wpkh.clone())]);
+        let mut bumped = original.clone();
+        bumped.outputs[0].value = 80_000;
+        assert!(rbf::signals_rbf(&original));
+
+        let mempool = [unconfirmed.clone()];
+        let replacement = Replacement {
+            original: &original,
+            original_fee: 10_000,
+            descendant_fees: 500,
+            descendant_count: 1,
+            replacement: &bumped,
+            replacement_fee: 20_000,
+            unconfirmed: &mempool,
+            incremental_relay_fee: rbf::DEFAULT_INCREMENTAL_RELAY_FEE,
+        };
+        assert!(replacement.check().is_empty());
+
+        // Just over the evicted fees, but not by enough to pay for relay.
+        let stingy = Replacement {
+            replacement_fee: 10_501,
+            ..replacement.clone()
+        };
+        assert_eq!(stingy.check(), vec![RbfRule::RelayFee]);
+
+        let mut final_original = original.clone();
+        final_original.inputs[0].sequence = 0xFFFFFFFE;
+        let mut new_input = bumped.clone();
+        new_input
+            .inputs
+            .push(TransactionInput::new(unconfirmed, Script::new(vec![]), 0));
+        let bad = Replacement {
+            original: &final_original,
+            replacement: &new_input,
+            replacement_fee: 10_000,
+            descendant_count: 100,
+            ..replacement
+        };
+        assert_eq!(
+            bad.check(),
+            vec![
+                RbfRule::Signaling,
+                RbfRule::NewUnconfirmedInputs,
+                RbfRule::AbsoluteFee,
+                RbfRule::TooManyReplacements,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_taproot_control_block() {
+        // BIP341 wallet test vector: single tapscript leaf.
+        let script = Script::new(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        );
+        let control_bytes =
+            hex::decode("c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+                .unwrap();
+        let (control, used) = taproot::ControlBlock::from_bytes(&control_bytes).unwrap();
+        assert_eq!(used, 33);
+        assert_eq!(control.leaf_version, taproot::TAPROOT_LEAF_TAPSCRIPT);
+        assert!(control.output_key_parity);
+        assert!(control.merkle_branch.is_empty());
+        assert_eq!(control.to_bytes(), control_bytes);
+        assert_eq!(
+            hex::encode(control.merkle_root(&script)),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+
+        let witness = Witness::new(vec![
+            vec![0x01; 64],
+            script.bytes.clone(),
+            control_bytes.clone(),
+            vec![0x50, 0xAA],
+        ]);
+        let (leaf, parsed) = taproot::ControlBlock::from_witness(&witness)
+            .unwrap()
+            .unwrap();
+        assert_eq!(leaf, script);
+        assert_eq!(parsed, control);
+        assert!(taproot::ControlBlock::from_witness(&Witness::new(vec![vec![0x01; 64]])).is_none());
+        assert_eq!(
+            taproot::ControlBlock::from_bytes(&[control_bytes, vec![0; 31]].concat()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut output_key = [0u8; 32];
+        hex::decode_to_slice(
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+            &mut output_key,
+        )
+        .unwrap();
+        let verified = control.verify_taproot_commitment(&output_key, &script);
+        #[cfg(feature = "secp")]
+        {
+            assert_eq!(verified, Ok(true));
+            assert_eq!(
+                control.verify_taproot_commitment(&output_key, &Script::new(vec![0x51])),
+                Ok(false)
+            );
+        }
+        #[cfg(not(feature = "secp"))]
+        assert_eq!(verified, Err(BitcoinError::Unsupported));
+    }
+
+    #[test]
+    fn test_taproot_builder() {
+        use taproot::TaprootBuilder;
+
+        assert_eq!(
+            TaprootBuilder::new().finalize(),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let script = Script::new(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        );
+        let single = TaprootBuilder::new()
+            .add_leaf(1, script.clone())
+            .finalize()
+            .unwrap();
+        assert_eq!(
+            hex::encode(single.merkle_root),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+        assert!(single.leaves[0].merkle_branch.is_empty());
+
+        let (a, b, c) = (
+            Script::new(vec![0x51]),
+            Script::new(vec![0x52]),
+            Script::new(vec![0x53]),
+        );
+        let tree = TaprootBuilder::new()
+            .add_leaf(1, a.clone())
+            .add_leaf(5, b.clone())
+            .add_leaf(1, c.clone())
+            .finalize()
+            .unwrap();
+        // The two light leaves are paired below the heavy one.
+        let depths: Vec<usize> = tree.leaves.iter().map(|l| l.merkle_branch.len()).collect();
+        assert_eq!(depths, vec![2, 1, 2]);
+        let ac = taproot::tapbranch_hash(&tree.leaves[0].leaf_hash(), &tree.leaves[2].leaf_hash());
+        assert_eq!(
+            tree.merkle_root,
+            taproot::tapbranch_hash(&ac, &tree.leaves[1].leaf_hash())
+        );
+
+        let internal_key = [0x11; 32];
+        for (i, script) in [a, b, c].iter().enumerate() {
+            assert_eq!(&tree.leaves[i].script, script);
+            let control = tree.control_block(i, internal_key, false).unwrap();
+            assert_eq!(control.merkle_root(script), tree.merkle_root);
+        }
+        assert!(tree.control_block(3, internal_key, false).is_none());
+    }
+
+    #[cfg(feature = "secp")]
+    #[test]
+    fn test_taproot_spend_info() {
+        use taproot::{TaprootBuilder, TaprootSpendInfo};
+
+        let key = |hex_key: &str| {
+            let mut key = [0u8; 32];
+            hex::decode_to_slice(hex_key, &mut key).unwrap();
+            key
+        };
+
+        // BIP341 wallet test vectors: key path only, then a single leaf.
+        let key_only = TaprootSpendInfo::new(
+            key("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(key_only.script_pubkey().bytes),
+            "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+        );
+        assert_eq!(key_only.script_pubkey().script_type(), ScriptType::P2tr);
+        assert!(key_only.control_block(0).is_none());
+
+        let script = Script::new(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        );
+        let tree = TaprootBuilder::new()
+            .add_leaf(1, script.clone())
+            .finalize()
+            .unwrap();
+        let info = TaprootSpendInfo::new(
+            key("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"),
+            Some(tree),
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(info.output_key),
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        );
+        let control = info.control_block(0).unwrap();
+        assert_eq!(
+            hex::encode(control.to_bytes()),
+            "c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"
+        );
+        assert_eq!(
+            control.verify_taproot_commitment(&info.output_key, &script),
+            Ok(true)
+        );
+
+        assert_eq!(
+            TaprootSpendInfo::new([0xff; 32], None),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_witness_taproot_annex() {
+        let signature = vec![0x01; 64];
+        let key_path = Witness::new(vec![signature.clone()]);
+        assert_eq!(key_path.taproot_annex(), None);
+        assert_eq!(key_path.without_annex(), std::slice::from_ref(&signature));
+
+        // A lone element is never an annex, even with the prefix.
+        assert_eq!(Witness::new(vec![vec![0x50, 0x01]]).taproot_annex(), None);
+
+        let with_annex = Witness::new(vec![signature.clone(), vec![0x50, 0xAB, 0xCD]]);
+        assert_eq!(with_annex.taproot_annex(), Some(&[0x50, 0xAB, 0xCD][..]));
+        assert_eq!(with_annex.without_annex(), &[signature]);
+        assert_eq!(Witness::default().taproot_annex(), None);
+    }
+
+    #[test]
+    fn test_witness_program() {
+        use witness_program::{WitnessProgram, WitnessVersion};
+
+        let p2wpkh = WitnessProgram::p2wpkh([0x11; 20]);
+        let script = p2wpkh.to_script();
+        assert_eq!(script.bytes[..2], [0x00, 0x14]);
+        assert_eq!(script.script_type(), ScriptType::P2wpkh);
+        assert_eq!(script.witness_program(), Some(p2wpkh));
+
+        let p2tr = WitnessProgram::p2tr([0x22; 32]).to_script();
+        assert_eq!(p2tr.bytes[..2], [0x51, 0x20]);
+        assert!(p2tr.witness_program().unwrap().is_p2tr());
+        assert_eq!(p2tr.script_type(), ScriptType::P2tr);
+
+        // v1 programs of other lengths are valid but not taproot.
+        let anchor = WitnessProgram::new(WitnessVersion::V1, vec![0x4e, 0x73]).unwrap();
+        assert!(!anchor.is_p2tr());
+        assert_eq!(anchor.to_script().script_type(), ScriptType::Anchor);
+
+        let v16 = WitnessVersion::new(16).unwrap();
+        assert_eq!(v16.opcode(), 0x60);
+        assert_eq!(WitnessVersion::from_opcode(0x60), Ok(v16));
+        assert_eq!(WitnessVersion::new(17), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            WitnessProgram::new(WitnessVersion::V0, vec![0; 21]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            WitnessProgram::new(v16, vec![0; 41]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert!(
+            Script::new(vec![0x00, 0x15, 0x00])
+                .witness_program()
+                .is_none()
+        );
+        assert!(
+            Script::new([vec![0x00, 0x15], vec![0; 21]].concat())
+                .witness_program()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_script_hash_helpers() {
+        // OP_1 as a witness script, as in BIP141 examples.
+        let witness_script = Script::new(vec![0x51]);
+        assert_eq!(
+            hex::encode(witness_script.wscript_hash()),
+            "4ae81572f06e1b88fd5ced7a1a000945432e83e1551e6f721ee9c00b8cc33260"
+        );
+        let p2wsh = witness_script.to_p2wsh();
+        assert_eq!(p2wsh.script_type(), ScriptType::P2wsh);
+        assert_eq!(p2wsh.bytes[2..], witness_script.wscript_hash());
+
+        let leaf = Script::new(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        );
+        assert_eq!(
+            hex::encode(leaf.tapleaf_hash()),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+    }
+
+    #[test]
+    fn test_miniscript_subset() {
+        use miniscript::{BasicType, Miniscript};
+
+        let a = format!("02{}", "aa".repeat(32));
+        let b = format!("03{}", "bb".repeat(32));
+        let c = format!("02{}", "cc".repeat(32));
+
+        let policy = format!("or_d(pk({}),and_v(v:pkh({}),older(144)))", a, b);
+        let ms: Miniscript = policy.parse().unwrap();
+        assert_eq!(ms.to_string(), policy);
+        assert_eq!(ms.basic_type(), Ok(BasicType::B));
+        let b_hash = hex::encode(hashes::hash160(&hex::decode(&b).unwrap()));
+        assert_eq!(
+            ms.compile().to_asm(),
+            format!(
+                "{} OP_CHECKSIG OP_IFDUP OP_NOTIF OP_DUP OP_HASH160 {} OP_EQUALVERIFY \
+                 OP_CHECKSIGVERIFY 144 OP_CHECKSEQUENCEVERIFY OP_ENDIF",
+                a, b_hash
+            )
+        );
+        // The fallback branch: signature and key for B, empty signature for A.
+        assert_eq!(ms.max_satisfaction_size(), Some(74 + 34 + 1));
+
+        let thresh: Miniscript = format!("thresh(2,pk({}),s:pk({}),s:pk({}))", a, b, c)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            thresh.compile().to_asm(),
+            format!(
+                "{} OP_CHECKSIG OP_SWAP {} OP_CHECKSIG OP_ADD OP_SWAP {} OP_CHECKSIG OP_ADD 2 OP_EQUAL",
+                a, b, c
+            )
+        );
+        assert_eq!(thresh.max_satisfaction_size(), Some(2 * 74 + 1));
+
+        let timelocked: Miniscript = format!("and_v(v:pk({}),after(800000))", a).parse().unwrap();
+        assert_eq!(
+            timelocked.compile().to_asm(),
+            format!("{} OP_CHECKSIGVERIFY 800000 OP_CHECKLOCKTIMEVERIFY", a)
+        );
+
+        for bad in [
+            format!("pk({})", &a[2..]),
+            format!("and_v(pk({}),older(1))", a),
+            format!("thresh(2,pk({}),pk({}))", a, b),
+            format!("thresh(3,pk({}),s:pk({}))", a, b),
+            format!("v:pk({})", a),
+            "older(0)".to_string(),
+            format!("x:pk({})", a),
+            format!("pk({}", a),
+        ] {
+            assert_eq!(
+                bad.parse::<Miniscript>(),
+                Err(BitcoinError::InvalidFormat),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_push_helpers() {
+        for n in [0i64, 1, 16, 17, 127, 128, 255, -1, -128, 800_000, -800_000] {
+            let mut script = Script::new(vec![]);
+            script.push_int(n);
+            let parsed = match script.instructions().next().unwrap().unwrap() {
+                script::Instruction::PushBytes(data) => script::script_num(data),
+                script::Instruction::Op(0x00) => 0,
+                script::Instruction::Op(0x4f) => -1,
+                script::Instruction::Op(op) => (op - 0x50) as i64,
+            };
+            assert_eq!(parsed, n);
+        }
+        assert_eq!(script::encode_script_num(128), vec![0x80, 0x00]);
+        assert_eq!(script::encode_script_num(-128), vec![0x80, 0x80]);
+
+        let mut script = Script::new(vec![]);
+        script.push_slice(&[0xAB; 80]).push_opcode(0x87);
+        assert_eq!(script.bytes[..2], [0x4c, 80]);
+        assert_eq!(script.len(), 83);
+    }
+
+    #[test]
+    fn test_descriptor_parsing() {
+        use descriptor::{Descriptor, DescriptorKey, HARDENED, Wildcard};
+
+        // BIP380 checksum test vector.
+        assert_eq!(descriptor::checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        let raw: Descriptor = "raw(deadbeef)#89f8spxm".parse().unwrap();
+        assert_eq!(raw, Descriptor::Raw(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(raw.to_string(), "raw(deadbeef)#89f8spxm");
+        assert_eq!(
+            "raw(deadbeef)#89f8spxn".parse::<Descriptor>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let key = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+        let wpkh: Descriptor = format!("sh(wpkh({}))", key).parse().unwrap();
+        assert_eq!(
+            wpkh,
+            Descriptor::ShWpkh(DescriptorKey::Single {
+                origin: None,
+                key: hex::decode(key).unwrap(),
+            })
+        );
+        assert!(!wpkh.has_wildcard());
+
+        let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+        let text = format!("wsh(multi(1,[d34db33f/48h/0h/0h/2h]{}/0/*,{}))", xpub, key);
+        let multi: Descriptor = text.parse().unwrap();
+        let Descriptor::WshMulti(1, keys) = &multi else {
+            panic!("expected wsh(multi)");
+        };
+        let DescriptorKey::Extended {
+            origin: Some(origin),
+            path,
+            wildcard,
+            ..
+        } = &keys[0]
+        else {
+            panic!("expected an extended key");
+        };
+        assert_eq!(origin.fingerprint, [0xd3, 0x4d, 0xb3, 0x3f]);
+        assert_eq!(
+            origin.path,
+            vec![48 | HARDENED, HARDENED, HARDENED, 2 | HARDENED]
+        );
+        assert_eq!(path, &vec![0]);
+        assert_eq!(*wildcard, Wildcard::Unhardened);
+        assert!(multi.has_wildcard());
+
+        // Display normalizes hardened markers and appends the checksum.
+        let normalized = multi.to_string();
+        assert!(normalized.starts_with("wsh(multi(1,[d34db33f/48'/0'/0'/2']xpub"));
+        assert_eq!(normalized.parse::<Descriptor>().unwrap(), multi);
+
+        let tr: Descriptor = format!("tr({})", &key[2..]).parse().unwrap();
+        assert_eq!(tr.keys().len(), 1);
+
+        for bad in [
+            format!("wpkh({})", &key[2..]),
+            format!("wsh(multi(3,{},{}))", key, key),
+            format!("sh(pkh({}))", key),
+            format!("wpkh({}", key),
+            "wpkh(ypub123/0/*)".to_string(),
+        ] {
+            assert_eq!(
+                bad.parse::<Descriptor>(),
+                Err(BitcoinError::InvalidFormat),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_base58_check() {
+        assert_eq!(base58::encode(&[0, 0, 0x61]), "112g");
+        assert_eq!(base58::decode("112g").unwrap(), vec![0, 0, 0x61]);
+        let encoded = base58::encode_check(b"hello");
+        assert_eq!(base58::decode_check(&encoded).unwrap(), b"hello");
+        let mut corrupted = encoded.into_bytes();
+        corrupted[0] = if corrupted[0] == b'2' { b'3' } else { b'2' };
+        assert_eq!(
+            base58::decode_check(std::str::from_utf8(&corrupted).unwrap()),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(base58::decode("0OIl"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[cfg(feature = "secp")]
+    #[test]
+    fn test_descriptor_derive_script_pubkey() {
+        use bip32::Xpub;
+        use descriptor::Descriptor;
+
+        // BIP32 test vector 2: m and m/0.
+        let master = "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB";
+        let xpub: Xpub = master.parse().unwrap();
+        assert_eq!(xpub.to_string(), master);
+        let child = xpub.derive_child(0).unwrap();
+        assert_eq!(
+            child.to_string(),
+            "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH"
+        );
+        assert_eq!(child.parent_fingerprint, xpub.fingerprint());
+        assert_eq!(
+            xpub.derive_child(bip32::HARDENED),
+            Err(BitcoinError::Unsupported)
+        );
+
+        let wpkh: Descriptor = format!("wpkh({}/*)", master).parse().unwrap();
+        let expected = witness_program::WitnessProgram::p2wpkh(hashes::hash160(&child.public_key));
+        assert_eq!(wpkh.derive_script_pubkey(0).unwrap(), expected.to_script());
+        assert_ne!(wpkh.derive_script_pubkey(1).unwrap(), expected.to_script());
+
+        let fixed: Descriptor = format!("sh(wpkh({}/0))", master).parse().unwrap();
+        let p2sh = fixed.derive_script_pubkey(7).unwrap();
+        assert_eq!(p2sh.script_type(), ScriptType::P2sh);
+        assert_eq!(
+            p2sh.bytes[2..22],
+            hashes::hash160(&expected.to_script().bytes)
+        );
+
+        let multi: Descriptor = format!("wsh(multi(1,{}/*,{}/1/*))", master, master)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            multi.derive_script_pubkey(3).unwrap().script_type(),
+            ScriptType::P2wsh
+        );
+        let tr: Descriptor = format!("tr({}/*)", master).parse().unwrap();
+        assert_eq!(
+            tr.derive_script_pubkey(0).unwrap().script_type(),
+            ScriptType::P2tr
+        );
+
+        let hardened: Descriptor = format!("wpkh({}/*')", master).parse().unwrap();
+        assert_eq!(
+            hardened.derive_script_pubkey(0),
+            Err(BitcoinError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_bip32_paths_and_serialization() {
+        use bip32::{DerivationPath, Fingerprint, HARDENED, Xpriv, Xpub};
+
+        let path: DerivationPath = "m/84h/0'/0'/1/5".parse().unwrap();
+        assert_eq!(*path, vec![84 | HARDENED, HARDENED, HARDENED, 1, 5]);
+        assert_eq!(path.to_string(), "m/84'/0'/0'/1/5");
+        assert_eq!("m".parse::<DerivationPath>().unwrap().len(), 0);
+        assert_eq!(
+            "m/2147483648".parse::<DerivationPath>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let fingerprint: Fingerprint = "d34db33f".parse().unwrap();
+        assert_eq!(fingerprint.to_string(), "d34db33f");
+
+        // BIP32 test vector 1.
+        let master = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+        let xpriv: Xpriv = master.parse().unwrap();
+        assert_eq!(xpriv.to_string(), master);
+        assert_eq!(xpriv.depth, 0);
+        assert!(master.parse::<Xpub>().is_err());
+
+        // BIP32 test vector 5: master keys with a parent, and private keys
+        // of zero and of the curve order.
+        for key in [
+            "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+            "xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+        ] {
+            assert_eq!(key.parse::<Xpub>(), Err(BitcoinError::InvalidFormat));
+        }
+        for key in [
+            "xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD5SDKr24z3aiUvKr9bJpdrcLg1y3G",
+        ] {
+            assert_eq!(key.parse::<Xpriv>(), Err(BitcoinError::InvalidFormat));
+        }
+        // The largest valid private key, n - 1, still parses.
+        let mut max_key = xpriv.clone();
+        max_key.private_key =
+            hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        assert_eq!(max_key.to_string().parse::<Xpriv>(), Ok(max_key));
+        // 02 followed by x = 7 is not on the curve.
+        #[cfg(feature = "secp")]
+        assert_eq!(
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY"
+                .parse::<Xpub>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        #[cfg(feature = "secp")]
+        {
+            let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+            assert_eq!(Xpriv::new_master(&seed, false).unwrap(), xpriv);
+
+            let hardened = xpriv.derive_child(HARDENED).unwrap();
+            assert_eq!(
+                hardened.to_string(),
+                "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"
+            );
+            assert_eq!(
+                hardened.to_xpub().unwrap().to_string(),
+                "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw"
+            );
+            assert_eq!(hardened.parent_fingerprint, xpriv.fingerprint().unwrap());
+
+            // Public and private derivation agree below a hardened step.
+            let path: DerivationPath = "m/0'/1".parse().unwrap();
+            let private = xpriv.derive_path(&path).unwrap().to_xpub().unwrap();
+            let public = hardened.to_xpub().unwrap().derive_child(1).unwrap();
+            assert_eq!(private, public);
+            assert_eq!(
+                public.to_string(),
+                "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ"
+            );
+        }
+    }
+
+    #[test]
+    fn test_slip132_versions() {
+        use bip32::{KeyScriptType, Xpriv, Xpub};
+        use descriptor::{Descriptor, Wildcard};
+
+        // BIP84 account 0 for "abandon ... about".
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+        let zprv = "zprvAdG4iTXWBoARxkkzNpNh8r6Qag3irQB8PzEMkAFeTRXxHpbF9z4QgEvBRmfvqWvGp42t42nvgGpNgYSJA9iefm1yYNZKEm7z6qUWCroSQnE";
+        let xpub: Xpub = zpub.parse().unwrap();
+        assert_eq!(xpub.script_type(), Ok(KeyScriptType::P2wpkh));
+        assert_eq!(xpub.is_testnet(), Ok(false));
+        assert_eq!(xpub.to_string(), zpub);
+
+        let plain = xpub.with_script_type(KeyScriptType::P2pkh).unwrap();
+        assert!(plain.to_string().starts_with("xpub"));
+        assert_eq!(
+            plain
+                .with_script_type(KeyScriptType::P2shP2wpkh)
+                .unwrap()
+                .to_string()[..4],
+            *"ypub"
+        );
+        assert_eq!(
+            plain
+                .with_script_type(KeyScriptType::P2wpkh)
+                .unwrap()
+                .to_string(),
+            zpub
+        );
+
+        let xpriv: Xpriv = zprv.parse().unwrap();
+        assert_eq!(xpriv.script_type(), Ok(KeyScriptType::P2wpkh));
+
+        // The fields are public, so a key can carry any version.
+        let unknown = Xpub {
+            version: [0xde, 0xad, 0xbe, 0xef],
+            ..xpub.clone()
+        };
+        assert_eq!(unknown.script_type(), Err(BitcoinError::InvalidFormat));
+        assert_eq!(unknown.is_testnet(), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            unknown.with_script_type(KeyScriptType::P2pkh),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let unknown_xpriv = Xpriv {
+            version: [0xde, 0xad, 0xbe, 0xef],
+            ..xpriv.clone()
+        };
+        assert_eq!(
+            unknown_xpriv.with_script_type(KeyScriptType::P2wpkh),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(xpriv.to_string(), zprv);
+        assert!(zprv.parse::<Xpub>().is_err());
+
+        let descriptor = Descriptor::from_slip132(zpub, vec![0], Wildcard::Unhardened).unwrap();
+        assert!(matches!(descriptor, Descriptor::Wpkh(_)));
+        assert!(
+            descriptor
+                .to_string()
+                .starts_with(&format!("wpkh({}/0/*)#", plain))
+        );
+        assert_eq!(
+            Descriptor::from_slip132(&plain.to_string(), vec![], Wildcard::None),
+            Err(BitcoinError::Unsupported)
+        );
+
+        #[cfg(feature = "secp")]
+        {
+            assert_eq!(xpriv.to_xpub().unwrap(), xpub);
+            assert_eq!(unknown_xpriv.to_xpub(), Err(BitcoinError::InvalidFormat));
+            assert_eq!(
+                hex::encode(&descriptor.derive_script_pubkey(0).unwrap().bytes),
+                "0014c0cebcd6c3d3ca8c75dc5ec62ebe55330ef910e2"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bip39_mnemonic() {
+        use bip39::Mnemonic;
+
+        let mnemonic = Mnemonic::from_entropy(&[0u8; 16]).unwrap();
+        assert_eq!(
+            mnemonic.to_string(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        assert_eq!(
+            hex::encode(mnemonic.to_seed("TREZOR")),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+
+        let sentence =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let parsed: Mnemonic = sentence.parse().unwrap();
+        assert_eq!(parsed.entropy(), [0x7f; 16]);
+        assert_eq!(Mnemonic::from_entropy(&[0x7f; 16]).unwrap(), parsed);
+
+        let long = Mnemonic::from_entropy(&[0xff; 32]).unwrap();
+        assert_eq!(long.words().count(), 24);
+        assert_eq!(long.words().last(), Some("vote"));
+        assert_eq!(
+            long.to_string().parse::<Mnemonic>().unwrap().entropy(),
+            [0xff; 32]
+        );
+
+        // Bad checksum, unknown word, bad length.
+        let bad = sentence.replace("yellow", "year");
+        assert_eq!(bad.parse::<Mnemonic>(), Err(BitcoinError::InvalidFormat));
+        assert!(
+            sentence
+                .replace("wave", "waves")
+                .parse::<Mnemonic>()
+                .is_err()
+        );
+        assert!("abandon about".parse::<Mnemonic>().is_err());
+        assert!(Mnemonic::from_entropy(&[0; 15]).is_err());
+
+        #[cfg(feature = "secp")]
+        assert_eq!(
+            mnemonic.to_xpriv("TREZOR", false).unwrap().to_string(),
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF"
+        );
+    }
+
+    #[cfg(feature = "secp")]
+    #[test]
+    fn test_silent_payments() {
+        use hashes::hash160;
+        use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+        use silent_payments::{
+            NUMS_H, Receiver, SilentPaymentAddress, input_public_key, tweak_data,
+        };
+
+        let secp = Secp256k1::new();
+        let public = |secret: [u8; 32]| {
+            PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&secret).unwrap()).serialize()
+        };
+        let (a1, a2) = ([1u8; 32], [2u8; 32]);
+        let (scan, spend) = ([3u8; 32], [4u8; 32]);
+
+        let mut wpkh = vec![0x00, 0x14];
+        wpkh.extend_from_slice(&hash160(&public(a1)));
+        let mut tr = vec![0x51, 0x20];
+        tr.extend_from_slice(&public(a2)[1..]);
+        let mut nums_control = vec![0xc0];
+        nums_control.extend_from_slice(&NUMS_H);
+        let prevouts = vec![
+            TransactionOutput::new(10_000, Script::new(wpkh)),
+            TransactionOutput::new(10_000, Script::new(tr.clone())),
+            TransactionOutput::new(10_000, Script::new(tr)),
+        ];
+        let mut inputs = vec![
+            TransactionInput::new(OutPoint::new([7; 32], 1), Script::new(vec![]), 0xffffffff),
+            TransactionInput::new(OutPoint::new([5; 32], 3), Script::new(vec![]), 0xffffffff),
+            TransactionInput::new(OutPoint::new([6; 32], 0), Script::new(vec![]), 0xffffffff),
+        ];
+        inputs[0].witness = Witness::new(vec![vec![0x30; 71], public(a1).to_vec()]);
+        inputs[1].witness = Witness::new(vec![vec![0; 64]]);
+        // A script-path spend with the NUMS internal key contributes nothing.
+        inputs[2].witness = Witness::new(vec![vec![0x51], nums_control]);
+        assert_eq!(
+            input_public_key(&inputs[2], &prevouts[2].script_pubkey),
+            None
+        );
+
+        let address = SilentPaymentAddress {
+            scan_key: public(scan),
+            spend_key: public(spend),
+        };
+        let outpoints: Vec<OutPoint> = inputs.iter().map(|i| i.previous_output.clone()).collect();
+        let secrets = [(a1, false), (a2, true)];
+        let p2tr = |key: [u8; 32]| {
+            let mut script = vec![0x51, 0x20];
+            script.extend_from_slice(&key);
+            TransactionOutput::new(1_000, Script::new(script))
+        };
+        let k0 = address.output_key(&secrets, &outpoints, 0).unwrap();
+        let k1 = address.output_key(&secrets, &outpoints, 1).unwrap();
+        assert_ne!(k0, k1);
+        let tx = BitcoinTransaction::new(2, inputs, 0).with_outputs(vec![
+            prevouts[0].clone(),
+            p2tr(k1),
+            p2tr(k0),
+        ]);
+
+        let receiver = Receiver {
+            scan_secret: scan,
+            spend_key: public(spend),
+        };
+        let found = receiver.scan(&tx, &prevouts).unwrap();
+        assert_eq!(found.iter().map(|o| o.vout).collect::<Vec<_>>(), [2, 1]);
+        for output in &found {
+            let secret = SecretKey::from_slice(&spend)
+                .unwrap()
+                .add_tweak(&Scalar::from_be_bytes(output.tweak).unwrap())
+                .unwrap();
+            assert_eq!(
+                secret.x_only_public_key(&secp).0.serialize(),
+                output.output_key
+            );
+        }
+
+        let other = Receiver {
+            scan_secret: [8; 32],
+            ..receiver.clone()
+        };
+        assert!(other.scan(&tx, &prevouts).unwrap().is_empty());
+
+        // No taproot outputs, or a spent witness v2 output, rules a transaction out.
+        let mut no_taproot = tx.clone();
+        no_taproot.outputs.truncate(1);
+        assert_eq!(tweak_data(&no_taproot, &prevouts), Ok(None));
+        let mut v2 = prevouts.clone();
+        v2[2].script_pubkey = Script::new([&[0x52, 0x20][..], &[1; 32]].concat());
+        assert_eq!(tweak_data(&tx, &v2), Ok(None));
+    }
+
+    #[cfg(feature = "secp")]
+    #[test]
+    fn test_batch_signature_verification() {
+        use secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+        use sigverify::BatchVerifier;
+
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let keypair = Keypair::from_secret_key(&secp, &secret);
+        let public_key = secret.public_key(&secp).serialize();
+        let x_only = keypair.x_only_public_key().0.serialize();
+        let sighash = hashes::sha256(b"sighash");
+        let msg = Message::from_digest(sighash);
+        let der = secp.sign_ecdsa(&msg, &secret).serialize_der();
+        let schnorr = *secp.sign_schnorr_no_aux_rand(&msg, &keypair).as_ref();
+
+        let mut batch = BatchVerifier::new();
+        assert!(batch.is_empty() && batch.verify_all());
+        for _ in 0..50 {
+            batch.push_ecdsa(sighash, &der, &public_key);
+            batch.push_schnorr(sighash, schnorr, x_only);
+        }
+        assert_eq!(batch.len(), 100);
+        assert!(batch.verify().is_empty());
+        assert!(batch.verify_all());
+
+        batch.push_ecdsa(hashes::sha256(b"other"), &der, &public_key);
+        batch.push_schnorr(sighash, [0; 64], x_only);
+        batch.push_ecdsa(sighash, &der[..10], &public_key);
+        assert_eq!(batch.verify(), [100, 101, 102]);
+        assert!(!batch.verify_all());
+    }
+
+    #[test]
+    fn test_typed_hashes() {
+        use hash_types::{FilterHash, FilterHeader, ScriptHash, WScriptHash};
+
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let hash = BlockHash::hash(&genesis[..80]);
+        assert_eq!(
+            hash.to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(hash.to_string().parse::<BlockHash>(), Ok(hash));
+        assert_eq!(hash.0, Block::from_bytes(&genesis).unwrap().0.block_hash());
+        assert_eq!(
+            serde_json::to_string(&hash).unwrap(),
+            format!("\"{}\"", hash)
+        );
+        assert_eq!(
+            serde_json::from_str::<BlockHash>(&format!("\"{}\"", hash)).unwrap(),
+            hash
+        );
+        assert!("00".parse::<BlockHash>().is_err());
+
+        // Legacy transactions have wtxid == txid.
+        let tx = &Block::from_bytes(&genesis).unwrap().0.transactions[0];
+        let wtxid: Wtxid = tx.wtxid();
+        assert_eq!(wtxid.0, tx.txid().0);
+
+        // Script hashes display in stored order.
+        let script = [0x51];
+        assert_eq!(
+            ScriptHash::hash(&script).to_string(),
+            hex::encode(hashes::hash160(&script))
+        );
+        assert_eq!(WScriptHash::hash(&script).0, hashes::sha256(&script));
+        assert_eq!(
+            ScriptHash::from_slice(&[0; 32]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // BIP158 test vector: the genesis block's basic filter header.
+        let filter = FilterHash::hash(&hex::decode("019dfca8").unwrap());
+        assert_eq!(
+            filter.filter_header(&FilterHeader::default()).to_string(),
+            "21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"
+        );
+    }
+
+    #[test]
+    fn test_script_compression() {
+        use compress::{compress_script, decompress_script};
+
+        let p2pkh = Script::new([&[0x76, 0xa9, 0x14][..], &[0xab; 20], &[0x88, 0xac]].concat());
+        let p2sh = Script::new([&[0xa9, 0x14][..], &[0xcd; 20], &[0x87]].concat());
+        let p2pk = Script::new([&[0x21, 0x03][..], &[0xef; 32], &[0xac]].concat());
+        let op_return = Script::new(vec![0x6a, 0x01, 0xff]);
+        let cases = [
+            (&p2pkh, [&[0x00][..], &[0xab; 20]].concat()),
+            (&p2sh, [&[0x01][..], &[0xcd; 20]].concat()),
+            (&p2pk, [&[0x03][..], &[0xef; 32]].concat()),
+            (&op_return, vec![0x09, 0x6a, 0x01, 0xff]),
+        ];
+        for (script, compressed) in cases {
+            assert_eq!(compress_script(script).unwrap(), compressed);
+            assert_eq!(
+                decompress_script(&compressed),
+                Ok((script.clone(), compressed.len()))
+            );
+        }
+        assert_eq!(
+            decompress_script(&[0x00, 0xab]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        // Sizes past MAX_SCRIPT_SIZE are rejected before slicing.
+        for size in [10_000 + 7, u64::MAX] {
+            let mut bytes = VarInt::new(size).to_bytes();
+            bytes.extend_from_slice(&[0u8; 16]);
+            assert_eq!(decompress_script(&bytes), Err(BitcoinError::InvalidFormat));
+        }
+
+        // The genesis coinbase pays to an uncompressed key.
+        let genesis = Block::from_bytes(&hex::decode(GENESIS_BLOCK_HEX).unwrap())
+            .unwrap()
+            .0;
+        let script = &genesis.transactions[0].outputs[0].script_pubkey;
+        #[cfg(feature = "secp")]
+        {
+            let compressed = compress_script(script).unwrap();
+            assert_eq!(compressed.len(), 33);
+            // The key's y coordinate is odd.
+            assert_eq!(compressed[0], 0x05);
+            assert_eq!(decompress_script(&compressed).unwrap().0, *script);
+        }
+        #[cfg(not(feature = "secp"))]
+        assert_eq!(compress_script(script), Err(BitcoinError::Unsupported));
+    }
+
+    #[test]
+    fn test_amount_compression() {
+        use compress::{compress_amount, decompress_amount};
+
+        // Vectors from Core's compress_tests.
+        let tests = [
+            (0u64, 0u64),
+            (1, 1),
+            (1_000_000, 7),
+            (100_000_000, 9),
+            (5_000_000_000, 50),
+            (2_100_000_000_000_000, 21_000_000),
+        ];
+        for (amount, compressed) in tests {
+            assert_eq!(compress_amount(amount), compressed);
+            assert_eq!(decompress_amount(compressed), amount);
+        }
+        for amount in (0..100_000).chain([123_456_789, 2_099_999_997_690_000]) {
+            assert_eq!(decompress_amount(compress_amount(amount)), amount);
+        }
+
+        // Malformed maximal amounts wrap instead of panicking.
+        let wrapped = decompress_amount(u64::MAX);
+        assert_eq!(
+            wrapped,
+            ((u64::MAX - 1) / 10 / 9)
+                .wrapping_mul(10)
+                .wrapping_add((u64::MAX - 1) / 10 % 9 + 1)
+                .wrapping_mul(10_u64.pow(4))
+        );
+        let mut coin = VarInt::new(0).to_bytes();
+        coin.extend(VarInt::new(u64::MAX).to_bytes());
+        coin.extend([0x00; 21]);
+        assert_eq!(
+            undo::Coin::from_bytes(&coin).unwrap().0.output.value,
+            wrapped
+        );
+    }
+
+    #[test]
+    fn test_utxo_snapshot_reader() {
+        use snapshot::{SnapshotMetadata, SnapshotReader};
+        use undo::Coin;
+
+        let p2pkh = Script::new([&[0x76, 0xa9, 0x14][..], &[0xab; 20], &[0x88, 0xac]].concat());
+        let op_true = Script::new(vec![0x51]);
+        let coins = [
+            (
+                OutPoint::new([1; 32], 0),
+                Coin::new(100, true, TransactionOutput::new(5_000_000_000, p2pkh)),
+            ),
+            (
+                OutPoint::new([1; 32], 300),
+                Coin::new(101, false, TransactionOutput::new(1234, op_true.clone())),
+            ),
+            (
+                OutPoint::new([2; 32], 1),
+                Coin::new(0, false, TransactionOutput::new(0, op_true)),
+            ),
+        ];
+        let metadata = SnapshotMetadata {
+            network: Network::Regtest,
+            base_blockhash: BlockHash([9; 32]),
+            coins_count: 3,
+        };
+        let mut bytes = metadata.to_bytes();
+        for (txid, group) in [([1u8; 32], &coins[..2]), ([2; 32], &coins[2..])] {
+            bytes.extend_from_slice(&txid);
+            bytes.extend(CompactSize::new(group.len() as u64).to_bytes());
+            for (outpoint, coin) in group {
+                bytes.extend(CompactSize::new(outpoint.vout as u64).to_bytes());
+                bytes.extend(coin.to_bytes().unwrap());
+            }
+        }
+
+        let reader = SnapshotReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.metadata, metadata);
+        let entries: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(entries, coins);
+
+        // Truncated data ends the stream with an error.
+        let mut truncated = SnapshotReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(truncated.nth(2).unwrap().is_err());
+        assert!(truncated.next().is_none());
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'x';
+        assert!(SnapshotReader::new(&bad_magic[..]).is_err());
+    }
+
+    #[test]
+    fn test_truc_policy() {
+        use policy::{TrucViolation, UnconfirmedParent, Version};
+
+        assert!(Version(3).is_standard() && Version(3).is_truc());
+        assert!(!Version(0).is_standard() && !Version(4).is_standard());
+        assert_eq!(Version::TWO.to_string(), "2");
+
+        let spend = |txid: u8, version: u32, padding: usize| {
+            BitcoinTransaction::new(
+                version,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(txid), 0),
+                    Script::new(vec![]),
+                    0xfffffffd,
+                )],
+                0,
+            )
+            .with_outputs(vec![TransactionOutput::new(
+                1000,
+                Script::new(vec![0x6a; padding]),
+            )])
+        };
+        let parent = spend(1, 3, 1);
+        let child = spend(2, 3, 1);
+        assert_eq!(parent.tx_version(), Version::THREE);
+        let unconfirmed = |tx| UnconfirmedParent {
+            tx,
+            has_unconfirmed_parents: false,
+            child_count: 0,
+        };
+
+        assert!(parent.check_truc(&[]).is_empty());
+        assert!(child.check_truc(&[unconfirmed(&parent)]).is_empty());
+        assert_eq!(spend(3, 3, 11_000).check_truc(&[]), [TrucViolation::TooBig]);
+        assert_eq!(
+            spend(3, 3, 1_000).check_truc(&[unconfirmed(&parent)]),
+            [TrucViolation::ChildTooBig]
+        );
+
+        let v2 = spend(4, 2, 1);
+        assert_eq!(
+            child.check_truc(&[unconfirmed(&v2)]),
+            [TrucViolation::SpendsNonTruc]
+        );
+        assert_eq!(
+            v2.check_truc(&[unconfirmed(&parent)]),
+            [TrucViolation::NonTrucSpendsTruc]
+        );
+
+        let grandparent = UnconfirmedParent {
+            has_unconfirmed_parents: true,
+            child_count: 1,
+            ..unconfirmed(&parent)
+        };
+        assert_eq!(
+            child.check_truc(&[grandparent, unconfirmed(&parent)]),
+            [
+                TrucViolation::TooManyAncestors,
+                TrucViolation::TooManyDescendants
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anchor_outputs() {
+        use witness_program::WitnessProgram;
+
+        let p2a = WitnessProgram::p2a().to_script();
+        assert_eq!(hex::encode(&p2a.bytes), "51024e73");
+        assert_eq!(p2a.script_type().as_str(), "anchor");
+        let other_v1 = Script::new(hex::decode("51024e74").unwrap());
+        assert_eq!(other_v1.script_type(), ScriptType::WitnessUnknown);
+
+        let tx = BitcoinTransaction::new(
+            3,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xfffffffd,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(10_000, WitnessProgram::p2wpkh([1; 20]).to_script()),
+            TransactionOutput::new(0, p2a.clone()),
+            TransactionOutput::new(240, p2a),
+        ]);
+        assert_eq!(tx.anchor_outputs(), [1, 2]);
+        assert!(tx.outputs[1].is_ephemeral_anchor());
+        assert!(!tx.outputs[2].is_ephemeral_anchor());
+        assert!(!tx.outputs[0].is_anchor());
+        assert_eq!(
+            policy::Policy::default().dust_threshold(&tx.outputs[2]),
+            240
+        );
+    }
+
+    #[test]
+    fn test_ctv_template_hash() {
+        let input = |byte| {
+            TransactionInput::new(OutPoint::new(dummy_txid(byte), 0), Script::new(vec![]), 0)
+        };
+        let tx = BitcoinTransaction::new(2, vec![input(1), input(2)], 0)
+            .with_outputs(vec![TransactionOutput::new(1000, Script::new(vec![0x51]))]);
+        let hash = tx.ctv_template_hash(0).unwrap();
+        assert_ne!(hash, tx.ctv_template_hash(1).unwrap());
+        assert_eq!(tx.ctv_template_hash(2), Err(BitcoinError::InvalidFormat));
+
+        // The prevouts themselves aren't committed to, but scriptSigs are.
+        let mut other_prevouts = tx.clone();
+        other_prevouts.inputs[0].previous_output = OutPoint::new(dummy_txid(9), 3);
+        assert_eq!(other_prevouts.ctv_template_hash(0).unwrap(), hash);
+        let mut with_script_sig = tx.clone();
+        with_script_sig.inputs[1].script_sig = Script::new(vec![0x00]);
+        assert_ne!(with_script_sig.ctv_template_hash(0).unwrap(), hash);
+
+        let script = ctv::ctv_script(&hash);
+        assert_eq!(script.len(), 34);
+        assert_eq!(script.to_asm(), format!("{} OP_NOP4", hex::encode(hash)));
+    }
+
+    #[cfg(feature = "vectors")]
+    #[test]
+    fn test_core_vector_runner() {
+        use vectors::{TxVectors, parse_core_script, run_script_vectors, run_tx_vectors};
+
+        let genesis = Block::from_bytes(&hex::decode(GENESIS_BLOCK_HEX).unwrap())
+            .unwrap()
+            .0;
+        let coinbase = &genesis.transactions[0];
+        let null = "0000000000000000000000000000000000000000000000000000000000000000";
+        let mut duplicate = coinbase.clone();
+        duplicate.inputs[0].previous_output = OutPoint::new([1; 32], 0);
+        duplicate.inputs.push(duplicate.inputs[0].clone());
+        let prev = core_json::reversed_hex(&[1; 32]);
+
+        let valid = format!(
+            r#"[["A comment"], [[["{null}", -1, "1"]], "{}", "NONE"]]"#,
+            hex::encode(coinbase.to_bytes())
+        );
+        let report = run_tx_vectors(&valid, TxVectors::Valid).unwrap();
+        assert!(report.is_ok());
+        assert_eq!((report.passed, report.skipped), (1, 1));
+
+        let invalid = format!(
+            r#"[[[["{prev}", 0, "1"]], "{}", "NONE"], [[["{null}", -1, "1"]], "{}", "NONE"], [[], "00", "NONE"]]"#,
+            hex::encode(duplicate.to_bytes()),
+            hex::encode(coinbase.to_bytes()),
+        );
+        let report = run_tx_vectors(&invalid, TxVectors::Invalid).unwrap();
+        // Undecodable and duplicate-input transactions are rejected; the
+        // valid coinbase would need script checks to reject.
+        assert_eq!((report.passed, report.skipped), (2, 1));
+        let report = run_tx_vectors(&invalid, TxVectors::Valid).unwrap();
+        assert_eq!(report.failures.len(), 2);
+
+        let scripts = r#"[
+            ["Format is: scriptSig, scriptPubKey, flags, expected"],
+            ["0x01 0x0b", "11 EQUAL", "P2SH,STRICTENC", "OK"],
+            [["00", 0.00000001], "", "0 0x14 0x751e76e8199196d454941c45d1b3a323f1433bd6", "P2SH,WITNESS", "OK"],
+            ["'Az'", "NOT_AN_OPCODE", "NONE", "BAD_OPCODE"]
+        ]"#;
+        let report = run_script_vectors(scripts).unwrap();
+        assert_eq!((report.passed, report.skipped), (2, 1));
+        assert_eq!(report.failures, [(3, "invalid format".to_string())]);
+
+        assert_eq!(
+            parse_core_script("'Az' -1 1000 OP_CHECKSIG 0x4c 0x01 0x07")
+                .unwrap()
+                .bytes,
+            [
+                0x02, b'A', b'z', 0x4f, 0x02, 0xe8, 0x03, 0xac, 0x4c, 0x01, 0x07
+            ]
+        );
+    }
+
+    #[test]
+    fn test_genesis_blocks() {
+        let mainnet = Block::genesis(Network::Mainnet);
+        assert_eq!(hex::encode(mainnet.to_bytes()), GENESIS_BLOCK_HEX);
+        for (network, hash) in [
+            (
+                Network::Mainnet,
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+            ),
+            (
+                Network::Testnet,
+                "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
+            ),
+            (
+                Network::Signet,
+                "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+            ),
+            (
+                Network::Regtest,
+                "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+            ),
+        ] {
+            let genesis = Block::genesis(network);
+            assert_eq!(BlockHash(genesis.block_hash()).to_string(), hash);
+            assert_eq!(genesis.transactions, mainnet.transactions);
+        }
+    }
+
+    #[test]
+    fn test_signet_solution() {
+        use signet::{SIGNET_HEADER, SignetSolution};
+
+        let genesis = Block::genesis(Network::Signet);
+        let mut commitment = Script::new(hex::decode("6a24aa21a9ed").unwrap());
+        commitment.bytes.extend_from_slice(&[0x42; 32]);
+        let mut unsigned = commitment.clone();
+        unsigned.push_slice(&SIGNET_HEADER);
+        let coinbase = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new([0; 32], u32::MAX),
+                Script::new(vec![0x51, 0x51]),
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(50 * 100_000_000, Script::new(vec![0x51])),
+            TransactionOutput::new(0, unsigned),
+        ]);
+        let header = BlockHeader::new(
+            0x2000_0000,
+            genesis.block_hash(),
+            coinbase.txid().0,
+            1_598_918_460,
+            0x1e0377ae,
+            0,
+        );
+        let block = Block::new(header, vec![coinbase]);
+        assert_eq!(block.witness_commitment_index(), Some(1));
+        assert_eq!(block.signet_solution().unwrap(), None);
+
+        let solution = SignetSolution::new(
+            Script::new(vec![0x00, 0x01, 0x01]),
+            Witness::new(vec![vec![0xab]]),
+        );
+        let bytes = solution.to_bytes();
+        assert_eq!(
+            SignetSolution::from_bytes(&bytes).unwrap(),
+            (solution.clone(), 7)
+        );
+        let mut signed = commitment.clone();
+        signed.push_slice(&[&SIGNET_HEADER[..], &bytes].concat());
+        let mut with_solution = block.clone();
+        with_solution.transactions[0].outputs[1].script_pubkey = signed;
+        assert_eq!(with_solution.signet_solution().unwrap(), Some(solution));
+
+        // The solution is cleared before committing to the block.
+        let challenge = Script::new(vec![0x51]);
+        let (to_spend, to_sign) = block.signet_transactions(&challenge).unwrap();
+        let (to_spend2, _) = with_solution.signet_transactions(&challenge).unwrap();
+        assert_eq!(to_spend, to_spend2);
+        assert_eq!(to_sign.inputs[0].previous_output.txid, to_spend.txid());
+        assert_eq!(to_spend.outputs[0].script_pubkey, challenge);
+
+        let no_commitment = Block::new(block.header.clone(), vec![genesis.transactions[0].clone()]);
+        assert!(no_commitment.signet_transactions(&challenge).is_err());
+
+        #[cfg(feature = "secp")]
+        {
+            use secp256k1::{Message, Secp256k1, SecretKey};
+
+            assert!(genesis.check_signet_solution(&challenge).is_ok());
+            assert!(block.check_signet_solution(&challenge).is_ok());
+
+            let mut block = block;
+            let secp = Secp256k1::new();
+            let secrets = [[0x11; 32], [0x22; 32]].map(|s| SecretKey::from_slice(&s).unwrap());
+            let mut challenge = Script::new(Vec::new());
+            challenge.push_opcode(opcodes::OP_1);
+            for secret in &secrets {
+                challenge.push_slice(&secret.public_key(&secp).serialize());
+            }
+            challenge
+                .push_opcode(opcodes::OP_1 + 1)
+                .push_opcode(opcodes::OP_CHECKMULTISIG);
+
+            let sighash = block.signet_sighash(&challenge).unwrap();
+            let sign = |secret| {
+                let mut sig = secp
+                    .sign_ecdsa(&Message::from_digest(sighash), secret)
+                    .serialize_der()
+                    .to_vec();
+                sig.push(0x01);
+                let mut script_sig = Script::new(Vec::new());
+                script_sig.push_opcode(opcodes::OP_0).push_slice(&sig);
+                let solution = SignetSolution::new(script_sig, Witness::default());
+                let mut script = commitment.clone();
+                script.push_slice(&[&SIGNET_HEADER[..], &solution.to_bytes()].concat());
+                script
+            };
+            block.transactions[0].outputs[1].script_pubkey = sign(&secrets[1]);
+            assert!(block.check_signet_solution(&challenge).is_ok());
+
+            block.header.time += 1;
+            assert_eq!(
+                block.check_signet_solution(&challenge),
+                Err(BitcoinError::InvalidTransaction("bad-signet-blksig"))
+            );
+            block.header.time -= 1;
+            let other = SecretKey::from_slice(&[0x33; 32]).unwrap();
+            block.transactions[0].outputs[1].script_pubkey = sign(&other);
+            assert!(block.check_signet_solution(&challenge).is_err());
+            assert_eq!(
+                block.check_signet_solution(&Script::new(vec![0x52])),
+                Err(BitcoinError::Unsupported)
+            );
+        }
+    }
+
+    #[test]
+    fn test_version_bits() {
+        use versionbits::{SignalTally, period_start};
+
+        let header = |version| BlockHeader::new(version, [0; 32], [0; 32], 0, 0, 0);
+        let taproot = header(0x2000_0004);
+        assert!(taproot.uses_version_bits());
+        assert!(taproot.signals(2) && !taproot.signals(1));
+        assert_eq!(header(0x2000_0005).signaling_bits(), [0, 2]);
+        // Old-style versions and the top bits themselves signal nothing.
+        assert!(!header(4).uses_version_bits());
+        assert!(header(0x6000_0004).signaling_bits().is_empty());
+        assert!(!taproot.signals(29));
+
+        let window = Network::Mainnet.miner_confirmation_window();
+        let headers: Vec<BlockHeader> = (0..window)
+            .map(|i| header(if i < 1815 { 0x2000_0004 } else { 0x2000_0000 }))
+            .collect();
+        let tally: SignalTally = headers.iter().collect();
+        assert_eq!(
+            (tally.blocks, tally.count(2), tally.count(0)),
+            (2016, 1815, 0)
+        );
+        assert!(tally.threshold_reached(2, Network::Mainnet));
+        assert!(!tally.threshold_reached(0, Network::Mainnet));
+        assert_eq!(period_start(709_631, Network::Mainnet), 707_616);
+        assert_eq!(period_start(300, Network::Regtest), 288);
+    }
+
+    #[test]
+    fn test_sv2_template_messages() {
+        use sv2::{Message, NewTemplate, SetNewPrevHash, SubmitSolution};
+
+        let commitment = TransactionOutput::new(0, Script::new(vec![0x6a, 0x01, 0x00]));
+        let sibling = [0x33; 32];
+        let template = NewTemplate {
+            template_id: 7,
+            future_template: false,
+            version: 0x2000_0000,
+            coinbase_tx_version: 2,
+            coinbase_prefix: vec![0x03, 0x40, 0x0d, 0x03],
+            coinbase_tx_input_sequence: u32::MAX,
+            coinbase_tx_value_remaining: 312_500_000,
+            coinbase_tx_outputs_count: 1,
+            coinbase_tx_outputs: commitment.to_bytes(),
+            coinbase_tx_locktime: 0,
+            merkle_path: vec![sibling],
+        };
+        let prev = SetNewPrevHash {
+            template_id: 7,
+            prev_hash: BlockHash([0x11; 32]),
+            header_timestamp: 1_700_000_000,
+            n_bits: 0x1703_4219,
+            target: [0xff; 32],
+        };
+
+        let reward = TransactionOutput::new(312_500_000, Script::new(vec![0x51]));
+        let coinbase = template.coinbase(&[0xaa; 8], vec![reward.clone()]).unwrap();
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.inputs[0].script_sig.len(), 12);
+        assert_eq!(coinbase.outputs, [reward, commitment]);
+        assert_eq!(
+            template.merkle_root(&coinbase),
+            block::merkle_root(&[coinbase.txid().0, sibling])
+        );
+
+        let solution = SubmitSolution {
+            template_id: 7,
+            version: 0x2000_0000,
+            header_timestamp: 1_700_000_010,
+            header_nonce: 42,
+            coinbase_tx: coinbase,
+        };
+        let header = solution.block_header(&template, &prev);
+        assert_eq!(header.prev_blockhash, [0x11; 32]);
+        assert_eq!((header.bits, header.nonce), (0x1703_4219, 42));
+
+        for message in [
+            Message::NewTemplate(template.clone()),
+            Message::SetNewPrevHash(prev),
+            Message::SubmitSolution(solution),
+        ] {
+            let frame = message.to_frame().unwrap();
+            assert_eq!(frame[2], message.msg_type());
+            assert_eq!(Message::from_frame(&frame).unwrap(), (message, frame.len()));
+            assert!(Message::from_frame(&frame[..frame.len() - 1]).is_err());
+        }
+
+        let mut payload = template.to_bytes().unwrap();
+        payload[8] = 2;
+        assert_eq!(
+            NewTemplate::from_bytes(&payload),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut long = template;
+        long.coinbase_prefix = vec![0; 256];
+        assert!(long.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_coinbase_extranonce_split() {
+        use mining::CoinbaseSplit;
+
+        // Height push, then eight placeholder extranonce bytes.
+        let mut script_sig = Script::new(vec![0x03, 0x40, 0x0d, 0x03]);
+        script_sig.push_slice(&[0; 8]);
+        let mut coinbase = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new([0; 32], u32::MAX),
+                script_sig,
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(
+            312_500_000,
+            Script::new(vec![0x51]),
+        )]);
+        coinbase.inputs[0].witness = Witness::new(vec![vec![0; 32]]);
+
+        let split = CoinbaseSplit::from_transaction(&coinbase, 5..13).unwrap();
+        assert_eq!(split.extranonce_len, 8);
+        assert_eq!(split.coinb1.len(), 4 + 1 + 36 + 1 + 5);
+        let extranonce = [0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 1];
+        let rebuilt = split.coinbase(&extranonce).unwrap();
+        assert_eq!(&rebuilt.inputs[0].script_sig[5..], extranonce);
+        assert_eq!(rebuilt.outputs, coinbase.outputs);
+        assert_eq!(split.txid(&extranonce).unwrap(), rebuilt.txid());
+        // The witness doesn't change the txid.
+        assert_eq!(split.txid(&[0; 8]).unwrap(), coinbase.txid());
+
+        let branch = [[0x22; 32], [0x33; 32]];
+        let mut block = Block::new(
+            BlockHeader::new(1, [0; 32], [0; 32], 0, 0, 0),
+            vec![rebuilt],
+        );
+        block.transactions[0].inputs[0].witness = Witness::default();
+        let level1 = hashes::sha256d(&[block.transactions[0].txid().0, branch[0]].concat());
+        assert_eq!(
+            split.merkle_root(&extranonce, &branch).unwrap(),
+            hashes::sha256d(&[level1, branch[1]].concat())
+        );
+
+        assert!(split.assemble(&[0; 4]).is_err());
+        let raw = split.assemble(&[0; 8]).unwrap();
+        assert_eq!(CoinbaseSplit::new(&raw, 47..55).unwrap(), split);
+        // The region must stay within the scriptSig.
+        assert!(CoinbaseSplit::new(&raw, 41..49).is_err());
+        assert!(CoinbaseSplit::new(&raw, 50..60).is_err());
+        assert!(CoinbaseSplit::new(&coinbase.to_bytes(), 47..55).is_err());
+    }
+
+    #[test]
+    fn test_merkle_branches() {
+        use block::verify_merkle_branch;
+
+        let genesis = Block::genesis(Network::Mainnet);
+        let coinbase_txid = genesis.transactions[0].txid();
+        let branch = genesis.merkle_branch(&coinbase_txid).unwrap();
+        assert!(branch.hashes.is_empty());
+        assert!(verify_merkle_branch(
+            &coinbase_txid,
+            &branch,
+            &genesis.header.merkle_root
+        ));
+
+        let transactions: Vec<BitcoinTransaction> = (0..5u32)
+            .map(|i| {
+                let mut tx = genesis.transactions[0].clone();
+                tx.lock_time = i;
+                tx
+            })
+            .collect();
+        let block = Block::new(genesis.header.clone(), transactions);
+        let root = block.compute_merkle_root();
+        for (i, tx) in block.transactions.iter().enumerate() {
+            let branch = block.merkle_branch(&tx.txid()).unwrap();
+            assert_eq!((branch.index as usize, branch.hashes.len()), (i, 3));
+            assert!(verify_merkle_branch(&tx.txid(), &branch, &root));
+            let mut moved = branch.clone();
+            // The last transaction is paired with itself, so either side works.
+            moved.index ^= 1;
+            assert_eq!(verify_merkle_branch(&tx.txid(), &moved, &root), i == 4);
+            moved.index = branch.index + 8;
+            assert!(!verify_merkle_branch(&tx.txid(), &moved, &root));
+        }
+        assert_eq!(block.merkle_branch(&Txid([0x99; 32])), None);
+        let branch = block.merkle_branch(&block.transactions[1].txid()).unwrap();
+        assert!(!verify_merkle_branch(
+            &block.transactions[2].txid(),
+            &branch,
+            &root
+        ));
+    }
+
+    #[test]
+    fn test_spv_proofs() {
+        use spv::{MerkleBlock, SpvProof, validate_header_chain};
+
+        let genesis = Block::genesis(Network::Regtest);
+        assert!(genesis.header.check_pow());
+        assert!(Block::genesis(Network::Mainnet).header.check_pow());
+        let mut target = [0u8; 32];
+        target[26..29].copy_from_slice(&[0xff, 0xff, 0x00]);
+        assert_eq!(
+            Block::genesis(Network::Mainnet).header.target(),
+            Some(target)
+        );
+
+        let transactions: Vec<BitcoinTransaction> = (0..7u32)
+            .map(|i| {
+                let mut tx = genesis.transactions[0].clone();
+                tx.lock_time = i;
+                tx
+            })
+            .collect();
+        let mut block = Block::new(genesis.header.clone(), transactions);
+        block.header.prev_blockhash = genesis.block_hash();
+        block.header.merkle_root = block.compute_merkle_root();
+        let mine = |header: &mut BlockHeader| {
+            while !header.check_pow() {
+                header.nonce += 1;
+            }
+        };
+        mine(&mut block.header);
+        let mut next = BlockHeader::new(1, block.block_hash(), [0; 32], 0, 0x207fffff, 0);
+        mine(&mut next);
+        let headers = [genesis.header.clone(), block.header.clone(), next];
+        validate_header_chain(&headers).unwrap();
+
+        let wanted = [block.transactions[2].txid(), block.transactions[6].txid()];
+        let merkle_block = MerkleBlock::from_block(&block, &wanted);
+        let bytes = merkle_block.to_bytes();
+        assert_eq!(
+            MerkleBlock::from_bytes(&bytes).unwrap(),
+            (merkle_block.clone(), bytes.len())
+        );
+        let (root, matches) = merkle_block.extract_matches().unwrap();
+        assert_eq!(root, block.header.merkle_root);
+        let matched: Vec<(Txid, u32)> = matches
+            .iter()
+            .map(|(txid, branch)| (txid.clone(), branch.index))
+            .collect();
+        assert_eq!(matched, [(wanted[0].clone(), 2), (wanted[1].clone(), 6)]);
+        assert_eq!(matches[0].1, block.merkle_branch(&wanted[0]).unwrap());
+
+        let proof = SpvProof::from_merkle_block(&merkle_block, wanted[1].clone()).unwrap();
+        assert_eq!(proof.verify(&headers), Ok(2));
+        assert_eq!(proof.verify(&headers[..2]), Ok(1));
+        assert!(proof.verify(&headers[2..]).is_err());
+        assert_eq!(
+            SpvProof::from_block(&block, wanted[1].clone()),
+            Some(proof.clone())
+        );
+        assert!(SpvProof::from_merkle_block(&merkle_block, block.transactions[0].txid()).is_err());
+
+        let mut broken = headers.clone();
+        broken[2].prev_blockhash = [0; 32];
+        mine(&mut broken[2]);
+        assert_eq!(
+            proof.verify(&broken),
+            Err(BitcoinError::InvalidTransaction("bad-prevblk"))
+        );
+        let mut tampered = merkle_block.clone();
+        tampered.hashes[0][0] ^= 1;
+        assert!(SpvProof::from_merkle_block(&tampered, wanted[1].clone()).is_err());
+        let mut extra = merkle_block;
+        extra.hashes.push([0; 32]);
+        assert!(extra.extract_matches().is_err());
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let coinbase = Block::genesis(Network::Mainnet).transactions[0].clone();
+        let spend = |parent: [u8; 32], vout| {
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(parent, vout),
+                    Script::new(Vec::new()),
+                    u32::MAX,
+                )],
+                0,
+            )
+            .with_outputs(vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))])
+        };
+        let child = spend(coinbase.txid().0, 0);
+        let orphan = spend(dummy_txid(7), 3);
+        let dot = dot::to_dot(&[coinbase.clone(), child.clone(), orphan.clone()]);
+
+        let id = |tx: &BitcoinTransaction| core_json::reversed_hex(&tx.txid().0);
+        assert!(dot.starts_with("digraph transactions {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("[label=\"4a5e1e4b…fdeda33b\"];"));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [label=\"0 (5000000000 sat)\"];",
+            id(&coinbase),
+            id(&child)
+        )));
+        let external = core_json::reversed_hex(&dummy_txid(7));
+        assert!(dot.contains(&format!("\"{external}\" [label=")));
+        assert!(dot.contains("style=dashed"));
+        assert!(dot.contains(&format!(
+            "\"{external}\" -> \"{}\" [label=\"3\"];",
+            id(&orphan)
+        )));
+        // Three transactions, one external parent, two edges.
+        assert_eq!(dot.matches("[label=").count(), 6);
+    }
+
+    #[test]
+    fn test_size_breakdown() {
+        let coinbase = Block::genesis(Network::Mainnet).transactions[0].clone();
+        let breakdown = coinbase.size_breakdown();
+        assert_eq!(breakdown.marker_flag, 0);
+        assert_eq!(breakdown.inputs[0].script_sig, 1 + 77);
+        assert_eq!(breakdown.outputs[0].script_pubkey, 1 + 67);
+        assert_eq!(breakdown.total().witness, 0);
+        assert_eq!(breakdown.total().total(), coinbase.to_bytes().len());
+        assert_eq!(breakdown.weight(), coinbase.weight());
+
+        // Two P2WPKH-style inputs, one with an empty witness.
+        let mut tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0),
+                TransactionInput::new(OutPoint::new(dummy_txid(2), 1), Script::new(vec![]), 0),
+            ],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(
+            1_000,
+            witness_program::WitnessProgram::p2wpkh([0; 20]).to_script(),
+        )]);
+        tx.inputs[0].witness = Witness::new(vec![vec![0; 72], vec![2; 33]]);
+        let breakdown = tx.size_breakdown();
+        assert_eq!(breakdown.marker_flag, 2);
+        assert_eq!(breakdown.inputs[0].witness, 1 + 73 + 34);
+        assert_eq!(breakdown.inputs[1].witness, 1);
+        assert_eq!(breakdown.inputs[0].bytes().weight(), 41 * 4 + 108);
+        assert_eq!(breakdown.outputs[0].bytes().total(), 8 + 23);
+        assert_eq!(breakdown.overhead().weight(), (4 + 1 + 1 + 4) * 4 + 2);
+        assert_eq!(breakdown.total().total(), tx.to_bytes().len());
+        assert_eq!(breakdown.total().base, tx.to_bytes_without_witness().len());
+        assert_eq!(breakdown.weight(), tx.weight());
+    }
+
+    #[test]
+    fn test_script_templates() {
+        use script::ScriptTemplate;
+
+        let p2pkh: ScriptTemplate = "OP_DUP OP_HASH160 <20> OP_EQUALVERIFY OP_CHECKSIG"
+            .parse()
+            .unwrap();
+        let script =
+            Script::new(hex::decode("76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac").unwrap());
+        assert!(script.matches_template(&p2pkh));
+        assert_eq!(p2pkh.captures(&script).unwrap(), [&script.bytes[3..23]]);
+        // Wrong push length, trailing opcode, truncated script.
+        let mut longer = script.clone();
+        longer.bytes[2] = 0x15;
+        longer.bytes.insert(3, 0);
+        assert!(!longer.matches_template(&p2pkh));
+        let mut extra = script.clone();
+        extra.push_opcode(opcodes::OP_DROP);
+        assert!(!extra.matches_template(&p2pkh));
+        assert!(!Script::new(script.bytes[..22].to_vec()).matches_template(&p2pkh));
+
+        // A made-up protocol: OP_RETURN, a tag, then any payload.
+        let protocol: ScriptTemplate = "RETURN <4> <*>".parse().unwrap();
+        let mut tagged = Script::new(vec![opcodes::OP_RETURN]);
+        tagged.push_slice(b"omni").push_slice(&[0xaa; 100]);
+        assert_eq!(protocol.captures(&tagged).unwrap()[1].len(), 100);
+
+        let wildcard: ScriptTemplate = "0 * 2 CHECKMULTISIG".parse().unwrap();
+        let script = Script::new(vec![0x00, 0x75, 0x52, 0xae]);
+        assert!(script.matches_template(&wildcard));
+        assert_eq!(
+            "OP_DUP <x>".parse::<ScriptTemplate>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert!("NOT_AN_OPCODE".parse::<ScriptTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_parse_multisig() {
+        let keys: Vec<Vec<u8>> = (1..=3u8)
+            .map(|i| {
+                let mut key = vec![0x02];
+                key.extend_from_slice(&[i; 32]);
+                key
+            })
+            .collect();
+        let mut script = Script::new(Vec::new());
+        script.push_int(2);
+        for key in &keys {
+            script.push_slice(key);
+        }
+        script.push_int(3).push_opcode(opcodes::OP_CHECKMULTISIG);
+        let multisig = script.parse_multisig().unwrap();
+        assert_eq!((multisig.required, &multisig.keys), (2, &keys));
+
+        // Nested in a P2SH scriptSig and a P2WSH witness.
+        let mut script_sig = Script::new(Vec::new());
+        script_sig
+            .push_opcode(opcodes::OP_0)
+            .push_slice(&[0x30; 71])
+            .push_slice(&script.bytes);
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), script_sig, u32::MAX);
+        assert_eq!(input.multisig(), Some(multisig.clone()));
+        input.script_sig = Script::new(Vec::new());
+        input.witness = Witness::new(vec![vec![], vec![0x30; 71], script.bytes.clone()]);
+        assert_eq!(input.multisig(), Some(multisig));
+
+        // 17 keys need a pushed count.
+        let mut wide = Script::new(Vec::new());
+        wide.push_int(1);
+        for _ in 0..17 {
+            wide.push_slice(&keys[0]);
+        }
+        wide.push_int(17).push_opcode(opcodes::OP_CHECKMULTISIG);
+        assert_eq!(wide.parse_multisig().unwrap().keys.len(), 17);
+
+        let mut wrong_count = script.clone();
+        let n = wrong_count.len() - 2;
+        wrong_count.bytes[n] = 0x52;
+        assert_eq!(wrong_count.parse_multisig(), None);
+        let mut too_many_required = script.clone();
+        too_many_required.bytes[0] = 0x54;
+        assert_eq!(too_many_required.parse_multisig(), None);
+        assert_eq!(Script::new(vec![0x51, 0xae]).parse_multisig(), None);
+        let p2pkh =
+            Script::new(hex::decode("76a914000000000000000000000000000000000000000088ac").unwrap());
+        assert_eq!(p2pkh.parse_multisig(), None);
+    }
+
+    #[test]
+    fn test_address_network_validation() {
+        use address::{Address, AddressError, Payload};
+
+        let p2wpkh: Address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"
+            .parse()
+            .unwrap();
+        assert_eq!(p2wpkh.network, Network::Mainnet);
+        assert_eq!(
+            hex::encode(&p2wpkh.script_pubkey().bytes),
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        assert_eq!(
+            p2wpkh.to_string(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        let p2tr: Address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Address::from_script(&p2tr.script_pubkey(), Network::Mainnet).unwrap(),
+            p2tr
+        );
+        let p2pkh: Address = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".parse().unwrap();
+        assert_eq!(
+            p2pkh.payload,
+            Payload::PubkeyHash(
+                hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18")
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            )
+        );
+        assert_eq!(p2pkh.to_string(), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+
+        // Testnet and signet share prefixes; regtest only the Base58 ones.
+        let testnet: Address = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"
+            .parse()
+            .unwrap();
+        assert_eq!(testnet.network, Network::Testnet);
+        assert!(testnet.is_valid_for_network(Network::Signet));
+        assert!(!testnet.is_valid_for_network(Network::Regtest));
+        assert!(!testnet.is_valid_for_network(Network::Mainnet));
+        let signet = testnet.clone().require_network(Network::Signet).unwrap();
+        assert_eq!(signet.to_string(), testnet.to_string());
+        let regtest = testnet.for_network(Network::Regtest).unwrap();
+        assert!(regtest.to_string().starts_with("bcrt1q"));
+        assert_eq!(regtest.to_string().parse::<Address>().unwrap(), regtest);
+        let base58 = Address::new(Network::Testnet, p2pkh.payload.clone());
+        assert!(base58.is_valid_for_network(Network::Regtest));
+
+        // Testnet coins must never go to a mainnet address.
+        assert_eq!(
+            p2wpkh.clone().require_network(Network::Testnet),
+            Err(AddressError::NetworkMismatch {
+                expected: Network::Testnet,
+                found: Network::Mainnet,
+            })
+        );
+        assert!(p2wpkh.for_network(Network::Testnet).is_err());
+        assert!(testnet.for_network(Network::Mainnet).is_err());
+
+        // A v1 program with a bech32 (not bech32m) checksum.
+        let mut data = vec![1];
+        data.extend(bech32::convert_bits(&[0x4e; 32], 8, 5, true).unwrap());
+        let v1_bech32 = bech32::encode("bc", &data, bech32::Variant::Bech32);
+        assert_eq!(
+            v1_bech32.parse::<Address>(),
+            Err(AddressError::WrongVariant)
+        );
+        assert_eq!(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5".parse::<Address>(),
+            Err(AddressError::Bech32)
+        );
+        assert_eq!(
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb".parse::<Address>(),
+            Err(AddressError::Base58)
+        );
+    }
+
+    #[test]
+    fn test_witness_commitment_reserved_value() {
+        let genesis = Block::genesis(Network::Regtest);
+        let mut coinbase = genesis.transactions[0].clone();
+        assert_eq!(coinbase.witness_reserved_value(), None);
+        coinbase.set_witness_reserved_value([7; 32]).unwrap();
+        assert_eq!(coinbase.witness_reserved_value(), Some([7; 32]));
+        coinbase.inputs[0].witness.items.push(vec![0; 32]);
+        assert_eq!(coinbase.witness_reserved_value(), None);
+
+        let spend = TransactionInput::new(
+            OutPoint::new(genesis.transactions[0].txid().0, 0),
+            Script::new(vec![]),
+            u32::MAX,
+        )
+        .with_witness(Witness::new(vec![vec![0x51]]));
+        let spend = BitcoinTransaction::new(2, vec![spend], 0)
+            .with_outputs(vec![TransactionOutput::new(1_000, Script::new(vec![0x51]))]);
+        let mut block = Block::new(
+            genesis.header.clone(),
+            vec![genesis.transactions[0].clone(), spend],
+        );
+        assert_eq!(
+            block.check_witness_commitment(),
+            Err(BitcoinError::InvalidTransaction("unexpected-witness"))
+        );
+
+        block.add_witness_commitment([0; 32]).unwrap();
+        assert_eq!(block.witness_commitment_index(), Some(1));
+        assert_eq!(block.header.merkle_root, block.compute_merkle_root());
+        assert_eq!(block.check_witness_commitment(), Ok(()));
+        // Updating in place keeps the single commitment output.
+        block.add_witness_commitment([1; 32]).unwrap();
+        assert_eq!(block.transactions[0].outputs.len(), 2);
+        assert_eq!(block.check_witness_commitment(), Ok(()));
+
+        let mut malleated = block.clone();
+        malleated.transactions[1].inputs[0].witness = Witness::new(vec![vec![0x52]]);
+        assert_eq!(
+            malleated.check_witness_commitment(),
+            Err(BitcoinError::InvalidTransaction("bad-witness-merkle-match"))
+        );
+        let mut no_nonce = block.clone();
+        no_nonce.transactions[0].clear_witnesses();
+        assert_eq!(
+            no_nonce.check_witness_commitment(),
+            Err(BitcoinError::InvalidTransaction("bad-witness-nonce-size"))
+        );
+        assert_eq!(genesis.check_witness_commitment(), Ok(()));
+    }
+
+    #[test]
+    fn test_block_weight_and_limits() {
+        let genesis = Block::genesis(Network::Mainnet);
+        assert_eq!(genesis.weight(), genesis.to_bytes().len() * 4);
+        assert_eq!(genesis.check_block_limits(), Ok(()));
+        let reject = |reason| Err(BitcoinError::InvalidTransaction(reason));
+
+        let coinbase = genesis.transactions[0].clone();
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(coinbase.txid().0, 0),
+                Script::new(vec![]),
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(1, Script::new(vec![0x51]))]);
+        let block = |transactions| Block::new(genesis.header.clone(), transactions);
+        assert_eq!(block(vec![]).check_block_limits(), reject("bad-blk-length"));
+        assert_eq!(
+            block(vec![spend.clone()]).check_block_limits(),
+            reject("bad-cb-missing")
+        );
+        assert_eq!(
+            block(vec![coinbase.clone(), coinbase.clone()]).check_block_limits(),
+            reject("bad-cb-multiple")
+        );
+
+        // 2-of-2 multisig: 2 sigops counted accurately, 20 otherwise.
+        let multisig = Script::new(
+            [
+                &[0x52, 0x21][..],
+                &[0x02; 33],
+                &[0x21],
+                &[0x03; 33],
+                &[0x52, 0xae],
+            ]
+            .concat(),
+        );
+        assert_eq!(multisig.sigop_count(true), 2);
+        assert_eq!(multisig.sigop_count(false), 20);
+        assert_eq!(Script::new(vec![0xac, 0x4c]).sigop_count(false), 1);
+
+        // The coinbase's output key has one sigop; 19_999 more reach the cap.
+        let mut heavy = spend.clone();
+        heavy.outputs[0].script_pubkey = Script::new(vec![0xac; 19_999]);
+        assert_eq!(
+            block(vec![coinbase.clone(), heavy.clone()]).check_block_limits(),
+            Ok(())
+        );
+        heavy.outputs[0].script_pubkey.bytes.push(0xac);
+        assert_eq!(
+            block(vec![coinbase.clone(), heavy]).check_block_limits(),
+            reject("bad-blk-sigops")
+        );
+
+        let mut witness_heavy = spend;
+        witness_heavy.inputs[0].witness = Witness::new(vec![vec![0; consensus::MAX_BLOCK_WEIGHT]]);
+        assert_eq!(
+            block(vec![coinbase, witness_heavy]).check_block_limits(),
+            reject("bad-blk-weight")
+        );
+    }
+
+    #[test]
+    fn test_chainstate_coin_decoding() {
+        use undo::Coin;
+
+        let outpoint = OutPoint::new(dummy_txid(9), 128);
+        let key = chainstate::coin_key(&outpoint);
+        assert_eq!(key[0], b'C');
+        // Core's VARINT: 128 is 0x80 0x00.
+        assert_eq!(&key[33..], &[0x80, 0x00]);
+        assert_eq!(chainstate::decode_coin_key(&key), Ok(outpoint.clone()));
+        assert!(chainstate::decode_coin_key(&key[..33]).is_err());
+        assert!(chainstate::decode_coin_key(b"B").is_err());
+
+        let obfuscation =
+            chainstate::decode_obfuscation_key(&hex::decode("08a1b2c3d4e5f60718").unwrap())
+                .unwrap();
+        assert_eq!(
+            obfuscation,
+            [0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18]
+        );
+        assert!(chainstate::decode_obfuscation_key(&[0x07; 8]).is_err());
+
+        let p2pkh =
+            Script::new(hex::decode("76a914000102030405060708090a0b0c0d0e0f1011121388ac").unwrap());
+        let coin = Coin::new(840_000, true, TransactionOutput::new(312_500_000, p2pkh));
+        let raw = coin.to_bytes().unwrap();
+        let value = chainstate::deobfuscate(&raw, obfuscation);
+        assert_ne!(value, raw);
+        assert_eq!(
+            chainstate::decode_coin(&key, &value, obfuscation),
+            Ok((outpoint, coin))
+        );
+        let mut trailing = raw.clone();
+        trailing.push(0);
+        assert!(chainstate::decode_coin(&key, &trailing, [0; 8]).is_err());
+
+        let best = chainstate::deobfuscate(&[0x11; 32], obfuscation);
+        assert_eq!(
+            chainstate::decode_best_block(&best, obfuscation),
+            Ok([0x11; 32])
+        );
+    }
+
+    #[test]
+    fn test_muhash3072() {
+        use muhash::MuHash3072;
+        use undo::Coin;
+
+        let element = |i: u8| {
+            let mut data = [0u8; 32];
+            data[0] = i;
+            data
+        };
+        // Core's crypto_tests: {0} * {1} / {2}.
+        let mut acc = MuHash3072::new();
+        acc.insert(&element(0));
+        acc.insert(&element(1));
+        acc.remove(&element(2));
+        let mut out = acc.finalize();
+        out.reverse();
+        assert_eq!(
+            hex::encode(out),
+            "10d312b100cbd32ada024a6646e40d3482fcff103668d2625f10002a607d5863"
+        );
+
+        // Order doesn't matter, and removing undoes inserting.
+        let mut a = MuHash3072::new();
+        a.insert(b"x");
+        a.insert(b"y");
+        let mut b = MuHash3072::new();
+        b.insert(b"y");
+        b.insert(b"z");
+        b.insert(b"x");
+        b.remove(b"z");
+        assert_eq!(a.finalize(), b.finalize());
+        let mut combined = MuHash3072::new();
+        let mut z = MuHash3072::new();
+        z.insert(b"z");
+        combined.combine(&z);
+        combined.combine(&a);
+        assert_ne!(combined.finalize(), a.finalize());
+        a.insert(b"z");
+        assert_eq!(combined.finalize(), a.finalize());
+
+        let coin = Coin::new(
+            1,
+            true,
+            TransactionOutput::new(50 * 100_000_000, Script::new(vec![0x51])),
+        );
+        let outpoint = OutPoint::new(dummy_txid(3), 0);
+        assert_eq!(muhash::coin_bytes(&outpoint, &coin).len(), 36 + 4 + 8 + 2);
+        let mut set = MuHash3072::new();
+        set.insert_coin(&outpoint, &coin);
+        set.remove_coin(&outpoint, &coin);
+        assert_eq!(set.finalize(), MuHash3072::new().finalize());
+    }
+
+    #[test]
+    fn test_script_index() {
+        use index::{MemoryStore, ScriptIndex, Spend};
+
+        let genesis = Block::genesis(Network::Regtest);
+        let genesis_script = genesis.transactions[0].outputs[0].script_pubkey.clone();
+        let alice = Script::new(vec![0x51]);
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(genesis.transactions[0].txid().0, 0),
+                Script::new(vec![]),
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(1_000, alice.clone()),
+            TransactionOutput::new(2_000, alice.clone()),
+        ]);
+        let mut coinbase = genesis.transactions[0].clone();
+        coinbase.inputs[0].script_sig = Script::new(vec![0x51, 0x51]);
+        let block = Block::new(genesis.header.clone(), vec![coinbase, spend.clone()]);
+
+        let mut index = ScriptIndex::new(MemoryStore::default());
+        assert_eq!(index.tip_height(), Ok(None));
+        index.index_block(&genesis, 0).unwrap();
+        assert_eq!(index.balance(&genesis_script), Ok(50 * 100_000_000));
+        assert!(index.index_block(&block, 2).is_err());
+        index.index_block(&block, 1).unwrap();
+        assert_eq!(index.tip_height(), Ok(Some(1)));
+
+        let genesis_outputs = index.outputs(&genesis_script).unwrap();
+        // The genesis output and the new coinbase's, one of them spent.
+        assert_eq!(genesis_outputs.len(), 2);
+        let spent = genesis_outputs
+            .iter()
+            .find(|output| output.outpoint.txid == genesis.transactions[0].txid())
+            .unwrap();
+        assert_eq!(
+            spent.spent_by,
+            Some(Spend {
+                txid: spend.txid(),
+                input: 0,
+                height: 1,
+            })
+        );
+        assert_eq!(index.balance(&genesis_script), Ok(50 * 100_000_000));
+        assert_eq!(index.unspent(&alice).unwrap().len(), 2);
+        assert_eq!(index.balance(&alice), Ok(3_000));
+        assert_eq!(index.outputs(&Script::new(vec![0x52])), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_rescan_match_block() {
+        use rescan::ScriptSet;
+
+        let genesis = Block::genesis(Network::Regtest);
+        let wallet = Script::new(vec![0x00, 0x14, 0xaa]);
+        let other = Script::new(vec![0x51]);
+        let fund = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(1_000, other.clone()),
+            TransactionOutput::new(2_000, wallet.clone()),
+        ]);
+        // Spends the wallet's new output within the same block.
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(fund.txid().0, 1),
+                Script::new(vec![]),
+                u32::MAX,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(1_500, other.clone())]);
+        let block = Block::new(
+            genesis.header.clone(),
+            vec![genesis.transactions[0].clone(), fund.clone(), spend],
+        );
+
+        let mut set = ScriptSet::new();
+        assert!(set.insert_script(wallet.clone()));
+        assert!(!set.insert_script(wallet.clone()));
+        assert!(set.contains_script(&wallet));
+        assert!(!set.contains_script(&other));
+
+        let matches = set.match_block(&block);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            (
+                matches[0].index,
+                matches[0].inputs.clone(),
+                matches[0].outputs.clone()
+            ),
+            (1, vec![], vec![1])
+        );
+        assert_eq!(
+            (
+                matches[1].index,
+                matches[1].inputs.clone(),
+                matches[1].outputs.clone()
+            ),
+            (2, vec![0], vec![])
+        );
+        assert!(set.contains_outpoint(&OutPoint::new(fund.txid().0, 1)));
+
+        let mut by_outpoint = ScriptSet::new();
+        by_outpoint.insert_outpoint(OutPoint::new(dummy_txid(1), 0));
+        let matches = by_outpoint.match_block(&block);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].tx, &fund);
+    }
+
+    #[test]
+    fn test_change_heuristics() {
+        use change::{Heuristic, guess_change};
+        use rescan::ScriptSet;
+
+        let p2wpkh = |byte| Script::new([vec![0x00, 0x14], vec![byte; 20]].concat());
+        let p2tr = Script::new([vec![0x51, 0x20], vec![0x33; 32]].concat());
+        let spent = vec![TransactionOutput::new(1_000_000, p2wpkh(1))];
+        let tx = |outputs| {
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![]),
+                    u32::MAX,
+                )],
+                0,
+            )
+            .with_outputs(outputs)
+        };
+
+        // A round payment to a taproot output, change back to P2WPKH.
+        let payment = tx(vec![
+            TransactionOutput::new(500_000, p2tr.clone()),
+            TransactionOutput::new(498_761, p2wpkh(2)),
+        ]);
+        let guesses = guess_change(&payment, &spent, &ScriptSet::new());
+        assert_eq!(guesses.len(), 1);
+        assert_eq!(guesses[0].vout, 1);
+        assert_eq!(
+            guesses[0].heuristics,
+            vec![Heuristic::ScriptTypeMatch, Heuristic::RoundPayment]
+        );
+        assert!((guesses[0].confidence - (1.0 - 0.7 * 0.6)).abs() < 1e-9);
+
+        // Known wallet scripts and reuse outweigh the amount heuristic.
+        let mut wallet = ScriptSet::new();
+        wallet.insert_script(p2wpkh(3));
+        let reuse = tx(vec![
+            TransactionOutput::new(123_456, p2wpkh(3)),
+            TransactionOutput::new(200_000, p2wpkh(1)),
+            TransactionOutput::new(0, Script::new(vec![0x6a])),
+        ]);
+        let guesses = guess_change(&reuse, &spent, &wallet);
+        assert_eq!(
+            guesses.iter().map(|g| g.vout).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            guesses[0].heuristics,
+            vec![Heuristic::KnownScript, Heuristic::RoundPayment]
+        );
+        assert_eq!(guesses[1].heuristics, vec![Heuristic::AddressReuse]);
+
+        let single = tx(vec![TransactionOutput::new(999_000, p2wpkh(2))]);
+        assert!(guess_change(&single, &spent, &wallet).is_empty());
+    }
+
+    #[test]
+    fn test_sighash_types_from_signatures() {
+        use sighash::{SighashType, is_ecdsa_signature};
+
+        let der = |sighash: u8| {
+            [
+                &[0x30, 0x44, 0x02, 0x20][..],
+                &[0x11; 32],
+                &[0x02, 0x20],
+                &[0x22; 32],
+                &[sighash],
+            ]
+            .concat()
+        };
+        assert!(is_ecdsa_signature(&der(0x01)));
+        let mut negative = der(0x01);
+        negative[4] = 0x80;
+        assert!(!is_ecdsa_signature(&negative));
+        assert!(!is_ecdsa_signature(&der(0x01)[..70]));
+
+        // P2PKH with SIGHASH_NONE|ANYONECANPAY.
+        let mut script_sig = Script::new(Vec::new());
+        script_sig.push_slice(&der(0x82)).push_slice(&[0x02; 33]);
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), script_sig, u32::MAX);
+        let types = input.sighash_types();
+        assert_eq!(types, vec![SighashType(0x82)]);
+        assert!(types[0].is_none() && types[0].anyone_can_pay());
+        assert_eq!(types[0].to_string(), "NONE|ANYONECANPAY");
+
+        // P2WPKH: the pubkey isn't mistaken for a control block.
+        input.script_sig = Script::new(Vec::new());
+        input.witness = Witness::new(vec![der(0x01), vec![0x02; 33]]);
+        assert_eq!(input.sighash_types(), vec![SighashType::ALL]);
+
+        // Taproot key path, with and without an explicit type and annex.
+        input.witness = Witness::new(vec![vec![0x33; 64]]);
+        assert_eq!(input.sighash_types(), vec![SighashType::DEFAULT]);
+        assert_eq!(SighashType::DEFAULT.base(), SighashType::ALL);
+        input.witness = Witness::new(vec![[vec![0x33; 64], vec![0x83]].concat(), vec![0x50]]);
+        assert_eq!(input.sighash_types(), vec![SighashType(0x83)]);
+        assert!(input.sighash_types()[0].is_single());
+
+        // Tapscript: two signatures under the leaf script and control block.
+        let control = [vec![0xc0], vec![0x44; 32]].concat();
+        input.witness = Witness::new(vec![
+            vec![0x33; 64],
+            [vec![0x33; 64], vec![0x02]].concat(),
+            vec![0xac],
+            control,
+        ]);
+        assert_eq!(
+            input.sighash_types(),
+            vec![SighashType::DEFAULT, SighashType::NONE]
+        );
+        assert_eq!(SighashType(0x04).to_string(), "0x04");
+    }
+
+    #[test]
+    fn test_extract_signatures() {
+        use sighash::{SighashType, Signature, SignaturePosition};
+
+        let der = [
+            &[0x30, 0x44, 0x02, 0x20][..],
+            &[0x11; 32],
+            &[0x02, 0x20],
+            &[0x22; 32],
+            &[0x01],
+        ]
+        .concat();
+        let key = [vec![0x03], vec![0x55; 32]].concat();
+
+        let mut script_sig = Script::new(Vec::new());
+        script_sig.push_slice(&der).push_slice(&key);
+        let p2pkh = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), script_sig, u32::MAX);
+        let found = p2pkh.extract_signatures();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].signature, Signature::Ecdsa(der[..70].to_vec()));
+        assert_eq!(found[0].sighash_type, SighashType::ALL);
+        assert_eq!(found[0].public_key, Some(key.clone()));
+        assert_eq!(found[0].position, SignaturePosition::ScriptSig(0));
+
+        let p2wpkh = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            u32::MAX,
+        )
+        .with_witness(Witness::new(vec![der.clone(), key.clone()]));
+        assert_eq!(
+            p2wpkh.extract_signatures()[0].position,
+            SignaturePosition::Witness(0)
+        );
+        let mut nested = p2wpkh.clone();
+        nested
+            .script_sig
+            .push_slice(&[vec![0x00, 0x14], vec![0x66; 20]].concat());
+        assert_eq!(nested.extract_signatures(), p2wpkh.extract_signatures());
+        let mut other_redeem = p2wpkh.clone();
+        other_redeem.script_sig.push_slice(&[0x51]);
+        assert!(other_redeem.extract_signatures().is_empty());
+
+        let keypath = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            u32::MAX,
+        )
+        .with_witness(Witness::new(vec![vec![0x77; 64]]));
+        let found = keypath.extract_signatures();
+        assert_eq!(found[0].signature, Signature::Schnorr([0x77; 64]));
+        assert_eq!(found[0].sighash_type, SighashType::DEFAULT);
+        assert_eq!(found[0].public_key, None);
+
+        // A bad key or a multisig spend isn't a recognized shape.
+        let bad_key = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            u32::MAX,
+        )
+        .with_witness(Witness::new(vec![der.clone(), vec![0x05; 33]]));
+        assert!(bad_key.extract_signatures().is_empty());
+        let multisig = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            u32::MAX,
+        )
+        .with_witness(Witness::new(vec![vec![], der.clone(), vec![0x51, 0xae]]));
+        assert!(multisig.extract_signatures().is_empty());
+    }
+
+    #[test]
+    fn test_ntxid_ignores_unlocking_data() {
+        let genesis = Block::genesis(Network::Mainnet);
+        let tx = genesis.transactions[0].clone();
+        let mut malleated = tx.clone();
+        malleated.inputs[0].script_sig.push_opcode(opcodes::OP_NOP);
+        malleated.inputs[0].witness = Witness::new(vec![vec![1]]);
+        assert_ne!(malleated.txid(), tx.txid());
+        assert_eq!(malleated.ntxid(), tx.ntxid());
+        assert_ne!(tx.ntxid().0, tx.txid().0);
+
+        let mut other_payment = tx.clone();
+        other_payment.outputs[0].value -= 1;
+        assert_ne!(other_payment.ntxid(), tx.ntxid());
+    }
+
+    #[test]
+    fn test_estimate_weight_matches_signed() {
+        use fee::InputType;
+        let push = |len: usize| {
+            let mut bytes = if len > 75 {
+                vec![0x4c, len as u8]
+            } else {
+                vec![len as u8]
+            };
+            bytes.extend(vec![0x30; len]);
+            bytes
+        };
+        let multisig = vec![0x52; 3 + 3 * 34];
+        let planned = [
+            (InputType::P2pkh, [push(72), push(33)].concat(), vec![]),
+            (
+                InputType::P2shP2wpkh,
+                push(22),
+                vec![vec![0x30; 72], vec![2; 33]],
+            ),
+            (InputType::P2wpkh, vec![], vec![vec![0x30; 72], vec![2; 33]]),
+            (InputType::P2trKeyPath, vec![], vec![vec![0x77; 64]]),
+            (
+                InputType::P2shMultisig {
+                    required: 2,
+                    keys: 3,
+                },
+                [vec![0x00], push(72), push(72), push(multisig.len())].concat(),
+                vec![],
+            ),
+            (
+                InputType::P2wshMultisig {
+                    required: 2,
+                    keys: 3,
+                },
+                vec![],
+                vec![vec![], vec![0x30; 72], vec![0x30; 72], multisig.clone()],
+            ),
+        ];
+        let outputs = vec![
+            TransactionOutput::new(50_000, Script::new([&[0x00, 0x14][..], &[1; 20]].concat())),
+            TransactionOutput::new(10_000, Script::new([&[0x51, 0x20][..], &[2; 32]].concat())),
+        ];
+
+        let mut types = Vec::new();
+        let mut inputs = Vec::new();
+        for (i, (input_type, script_sig, witness)) in planned.into_iter().enumerate() {
+            types.push(input_type);
+            inputs.push(
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(i as u8), 0),
+                    Script::new(script_sig),
+                    u32::MAX,
+                )
+                .with_witness(Witness::new(witness)),
+            );
+            let signed =
+                BitcoinTransaction::new(2, inputs.clone(), 0).with_outputs(outputs.clone());
+            assert_eq!(
+                fee::estimate_weight(&types, &outputs),
+                signed.weight(),
+                "{:?}",
+                types
+            );
+            assert_eq!(signed.estimate_signed_weight(&types), signed.weight());
+        }
+
+        // The common 1-in 2-out P2WPKH spend: 141 vbytes.
+        let p2wpkh_outputs = vec![outputs[0].clone(), outputs[0].clone()];
+        let weight = fee::estimate_weight(&[InputType::P2wpkh], &p2wpkh_outputs);
+        assert_eq!(weight.div_ceil(4), 141);
+        assert_eq!(
+            fee::estimate_fee(&[InputType::P2wpkh], &p2wpkh_outputs, 1000),
+            141
+        );
+        assert_eq!(fee::fee_for_weight(weight, 2500), 353);
+        assert_eq!(fee::estimate_weight(&[InputType::P2pkh], &outputs) % 4, 0);
+    }
+
+    #[test]
+    fn test_bump_fee() {
+        let p2wpkh = |n: u8| Script::new([&[0x00, 0x14][..], &[n; 20]].concat());
+        let funding = TransactionOutput::new(100_000, p2wpkh(1));
+        let coin = (
+            OutPoint::new(dummy_txid(2), 0),
+            TransactionOutput::new(200_000, p2wpkh(1)),
+        );
+        let small = (
+            OutPoint::new(dummy_txid(3), 0),
+            TransactionOutput::new(30_000, p2wpkh(1)),
+        );
+        let lookup = |outpoint: &OutPoint| {
+            [
+                (OutPoint::new(dummy_txid(1), 0), funding.clone()),
+                coin.clone(),
+                small.clone(),
+            ]
+            .into_iter()
+            .find(|(candidate, _)| candidate == outpoint)
+            .map(|(_, output)| output)
+        };
+        let original = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![]),
+                    0xfffffffd,
+                )
+                .with_witness(Witness::new(vec![vec![0x30; 72], vec![2; 33]])),
+            ],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(50_000, p2wpkh(9)),
+            TransactionOutput::new(49_000, p2wpkh(1)),
+        ]);
+        let coins = [small.clone(), coin.clone()];
+
+        // 141 vbytes at 10 sat/vB comes out of change.
+        let bumped = rbf::bump_fee(&original, lookup, 10_000, 1, &coins).unwrap();
+        assert_eq!(bumped.outputs[1].value, 48_590);
+        assert_eq!(bumped.inputs.len(), 1);
+        assert!(!bumped.has_witness());
+        assert!(rbf::signals_rbf(&bumped));
+
+        // Change below dust goes to fees.
+        let bumped = rbf::bump_fee(&original, lookup, 353_000, 1, &coins).unwrap();
+        assert_eq!(bumped.outputs, original.outputs[..1]);
+
+        // Otherwise the largest coin is added, with the same sequence.
+        let bumped = rbf::bump_fee(&original, lookup, 1_000_000, 1, &coins).unwrap();
+        assert_eq!(bumped.inputs.len(), 2);
+        assert_eq!(bumped.inputs[1].previous_output, coin.0);
+        assert_eq!(bumped.inputs[1].sequence, 0xfffffffd);
+        assert_eq!(bumped.outputs[1].value, 41_000);
+        assert_eq!(bumped.fee(lookup), Ok(209_000));
+
+        assert_eq!(
+            rbf::bump_fee(&original, lookup, 1_000_000, 1, &[]),
+            Err(BitcoinError::InvalidTransaction("insufficient-funds"))
+        );
+        // The new fee never drops below the original's plus relay.
+        let bumped = rbf::bump_fee(&original, lookup, 0, 1, &[]).unwrap();
+        assert_eq!(bumped.outputs[1].value, 49_000 - 141);
+
+        let p2sh_lookup = |_: &OutPoint| {
+            Some(TransactionOutput::new(
+                100_000,
+                Script::new([&[0xa9, 0x14][..], &[1; 20], &[0x87]].concat()),
+            ))
+        };
+        assert_eq!(
+            rbf::bump_fee(&original, p2sh_lookup, 10_000, 1, &coins),
+            Err(BitcoinError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_cpfp_child() {
+        let p2wpkh = |n: u8| Script::new([&[0x00, 0x14][..], &[n; 20]].concat());
+        let anchor = Script::new(vec![0x51, 0x02, 0x4e, 0x73]);
+        let parent = BitcoinTransaction::new(
+            3,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![]),
+                    0xfffffffd,
+                )
+                .with_witness(Witness::new(vec![vec![0x30; 72], vec![2; 33]])),
+            ],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(90_000, p2wpkh(1)),
+            TransactionOutput::new(240, anchor),
+        ]);
+        let coins = [(
+            OutPoint::new(dummy_txid(5), 0),
+            TransactionOutput::new(50_000, p2wpkh(2)),
+        )];
+
+        // A zero-fee parent: the anchor child brings in a coin and pays for both.
+        let child = cpfp::build_child(&parent, 0, 1, 5_000, &coins, p2wpkh(3)).unwrap();
+        assert_eq!(child.version, 3);
+        assert_eq!(
+            child.inputs[0].previous_output,
+            OutPoint::new(parent.txid().0, 1)
+        );
+        assert_eq!(child.inputs[1].previous_output, coins[0].0);
+        let fee = 50_240 - child.outputs[0].value;
+        let types = [fee::InputType::Anchor, fee::InputType::P2wpkh];
+        let child_vsize = fee::estimate_weight(&types, &child.outputs).div_ceil(4);
+        assert!(fee * 1000 >= 5_000 * (parent.vsize() + child_vsize) as u64);
+        assert!((fee - 1) * 1000 < 5_000 * (parent.vsize() + child_vsize) as u64);
+
+        // Spending a funded output needs no coins.
+        let child = cpfp::build_child(&parent, 0, 0, 5_000, &coins, p2wpkh(3)).unwrap();
+        assert_eq!(child.inputs.len(), 1);
+        assert!(rbf::signals_rbf(&child));
+
+        // A parent already above the target leaves the child its own fee.
+        assert_eq!(cpfp::child_fee(400, 10_000, 440, 2_000), 220);
+        assert_eq!(cpfp::child_fee(400, 0, 440, 2_000), 420);
+
+        assert_eq!(
+            cpfp::build_child(&parent, 0, 1, 5_000, &[], p2wpkh(3)),
+            Err(BitcoinError::InvalidTransaction("insufficient-funds"))
+        );
+    }
+
+    #[test]
+    fn test_builder_change_split_and_drop() {
+        use fee::InputType;
+        let p2wpkh = |n: u8| Script::new([&[0x00, 0x14][..], &[n; 20]].concat());
+        let p2tr = Script::new([&[0x51, 0x20][..], &[7; 32]].concat());
+        let scripts = [p2wpkh(2), p2tr.clone()];
+        let build = |input_value: u64| {
+            TransactionBuilder::new()
+                .add_input(OutPoint::new(dummy_txid(1), 0), 0xfffffffd)
+                .add_output(50_000, p2wpkh(1))
+                .add_change(input_value, &[InputType::P2wpkh], &scripts, 2_000)
+                .build()
+                .unwrap()
+        };
+
+        // 184 vbytes at 2 sat/vB, the rest split over both scripts.
+        let tx = build(100_000);
+        assert_eq!(tx.outputs.len(), 3);
+        assert_eq!(tx.outputs[1], TransactionOutput::new(24_816, p2wpkh(2)));
+        assert_eq!(tx.outputs[2], TransactionOutput::new(24_816, p2tr));
+
+        // Halves would be dust, so one output takes it all.
+        let tx = build(50_700);
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(tx.outputs[1].value, 700 - 282);
+
+        // Below dust even alone: dropped to fees.
+        assert_eq!(build(50_400).outputs.len(), 1);
+        assert_eq!(build(40_000).outputs.len(), 1);
+    }
+
+    #[test]
+    fn test_shared_script() {
+        let script = Script::new([&[0x00, 0x14][..], &[1; 20]].concat());
+        let shared = SharedScript::from(&script);
+        let copy = shared.clone();
+        assert_eq!(copy.as_ptr(), shared.as_ptr());
+        assert_eq!(shared, script);
+        assert_eq!(shared.len(), 22);
+        assert_eq!(shared.script_type(), ScriptType::P2wpkh);
+        assert_eq!(Script::from(copy), script);
+        assert_eq!(shared.to_string(), script.to_string());
+
+        let json = serde_json::to_string(&shared).unwrap();
+        assert_eq!(json, serde_json::to_string(&script).unwrap());
+        assert_eq!(serde_json::from_str::<SharedScript>(&json).unwrap(), shared);
+    }
+
+    #[test]
+    fn test_encoder_and_buffer_pool() {
+        use encode::{BufferPool, Encoder};
+        let block = Block::genesis(Network::Mainnet);
+        let tx = &block.transactions[0];
+
+        let mut out = Vec::new();
+        block.encode_to(&mut out);
+        assert_eq!(out, block.to_bytes());
+        assert_eq!(hex::encode(&out), GENESIS_BLOCK_HEX);
+
+        let mut encoder = Encoder::new();
+        assert_eq!(encoder.encode(tx), tx.to_bytes());
+        assert_eq!(encoder.encode(&block.header), block.header.to_bytes());
+        assert_eq!(
+            encoder.encode(&CompactSize::new(0x10000)),
+            [0xfe, 0, 0, 1, 0]
+        );
+
+        let pool = BufferPool::new(1);
+        let first = pool.encode(tx);
+        let ptr = first.as_ptr();
+        assert_eq!(first[..], tx.to_bytes()[..]);
+        drop(first);
+        assert_eq!(pool.free_len(), 1);
+        let second = pool.encode(&tx.outputs[0]);
+        assert_eq!(second.as_ptr(), ptr);
+        assert_eq!(second[..], tx.outputs[0].to_bytes()[..]);
+
+        // Beyond `max_free`, returned buffers are freed.
+        let (a, b) = (pool.encode(tx), pool.encode(tx));
+        drop((a, b, second));
+        assert_eq!(pool.free_len(), 1);
+        assert_eq!(pool.encode(tx).into_vec(), tx.to_bytes());
+        assert_eq!(pool.free_len(), 0);
+    }
+
+    #[test]
+    fn test_hex_paths_match_hex_crate() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let script = Script::new(bytes.clone());
+        let json = serde_json::to_string(&script).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex::encode(&bytes)));
+        let upper: Script = serde_json::from_str(&json.to_uppercase()).unwrap();
+        assert_eq!(upper, script);
+
+        let txid = Txid([0xab; 32]);
+        let json = serde_json::to_string(&txid).unwrap();
+        assert_eq!(serde_json::from_str::<Txid>(&json).unwrap(), txid);
+        let mut hash = [0u8; 32];
+        hash[0] = 0x01;
+        assert_eq!(
+            core_json::reversed_hex(&hash),
+            format!("{}01", "00".repeat(31))
+        );
+        assert_eq!(
+            core_json::hash_from_reversed_hex(&core_json::reversed_hex(&hash)),
+            Ok(hash)
+        );
+
+        for bad in ["\"0g\"", "\"abc\"", "\"zz\""] {
+            assert!(serde_json::from_str::<Script>(bad).is_err());
+        }
+        assert!(serde_json::from_str::<Txid>(&format!("\"{}\"", "0g".repeat(32))).is_err());
+        assert!(serde_json::from_str::<Txid>("\"00\"").is_err());
+        assert!(core_json::hash_from_reversed_hex(&"x".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        use rust_week_3_exercises::lenient::Diagnostic;
+
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new([1; 32], 0), Script::new(vec![]), 0)
+                    .with_witness(Witness::new(vec![vec![0xaa; 3]])),
+            ],
+            7,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(1_000, Script::new(vec![0x51])),
+            TransactionOutput::new(2_000, Script::new(vec![0x52])),
+        ]);
+        let bytes = tx.to_bytes();
+        let full = BitcoinTransaction::parse_lenient(&bytes);
+        assert!(full.is_complete());
+        assert_eq!(full.value, tx);
+        assert_eq!(full.consumed, bytes.len());
+
+        // Cut inside the second output: the first survives.
+        let second_output = 4 + 2 + 1 + 41 + 1 + 10;
+        let cut = BitcoinTransaction::parse_lenient(&bytes[..second_output + 5]);
+        assert_eq!(cut.value.inputs.len(), 1);
+        assert_eq!(cut.value.outputs, tx.outputs[..1]);
+        assert_eq!(cut.consumed, second_output);
+        assert_eq!(
+            cut.diagnostics,
+            vec![Diagnostic {
+                offset: second_output,
+                field: "outputs[1]".to_string(),
+                reason: BitcoinError::InsufficientBytes,
+            }]
+        );
+
+        // A bad segwit flag is noted and skipped.
+        let mut bad_flag = bytes.clone();
+        bad_flag[5] = 0x02;
+        let parsed = BitcoinTransaction::parse_lenient(&bad_flag);
+        assert_eq!(parsed.value, tx);
+        assert_eq!(parsed.diagnostics.len(), 1);
+        assert_eq!(
+            (parsed.diagnostics[0].offset, &*parsed.diagnostics[0].field),
+            (5, "flag")
+        );
+
+        let block = Block::new(
+            Block::genesis(Network::Mainnet).header,
+            vec![tx.clone(), tx],
+        );
+        let bytes = block.to_bytes();
+        assert_eq!(Block::parse_lenient(&bytes).value, block);
+        let cut = Block::parse_lenient(&bytes[..bytes.len() - 2]);
+        assert_eq!(cut.value.transactions.len(), 2);
+        assert_eq!(cut.value.transactions[0], block.transactions[0]);
+        assert_eq!(cut.value.transactions[1].outputs.len(), 2);
+        assert_eq!(cut.diagnostics[0].field, "transactions[1].lock_time");
+        assert_eq!(cut.diagnostics[0].offset, bytes.len() - 4);
+
+        let short = Block::parse_lenient(&bytes[..40]);
+        assert!(short.value.transactions.is_empty());
+        assert_eq!(
+            (short.consumed, &*short.diagnostics[0].field),
+            (0, "header")
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_exact() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new([3; 32], 1),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(500, Script::new(vec![0x51]))]);
+        let mut bytes = tx.to_bytes();
+        assert_eq!(BitcoinTransaction::from_bytes_exact(&bytes), Ok(tx.clone()));
+        assert_eq!(
+            BitcoinTransaction::from_hex_exact(&hex::encode(&bytes)),
+            Ok(tx.clone())
+        );
+        bytes.extend_from_slice(&[0xde, 0xad, 0xbe]);
+        assert_eq!(BitcoinTransaction::from_bytes(&bytes).unwrap().0, tx);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_exact(&bytes),
+            Err(BitcoinError::TrailingBytes(3))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_hex_exact(&hex::encode(&bytes)),
+            Err(BitcoinError::TrailingBytes(3))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_hex_exact("0g"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinError::TrailingBytes(3).to_string(),
+            "3 trailing bytes"
+        );
+
+        let block = Block::genesis(Network::Mainnet);
+        let mut bytes = block.to_bytes();
+        assert_eq!(Block::from_hex_exact(&hex::encode(&bytes)), Ok(block));
+        bytes.push(0);
+        assert_eq!(
+            Block::from_bytes_exact(&bytes),
+            Err(BitcoinError::TrailingBytes(1))
+        );
+    }
+
+    #[test]
+    fn test_transaction_diff() {
+        use rust_week_3_exercises::diff::TxDifference;
+
+        let unsigned = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new([1; 32], 0), Script::new(vec![]), 0xfffffffd),
+                TransactionInput::new(OutPoint::new([2; 32], 1), Script::new(vec![]), 0xfffffffd),
+            ],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(9_000, Script::new(vec![0x51]))]);
+        assert!(unsigned.diff(&unsigned).is_empty());
+
+        let mut confirmed = unsigned.clone();
+        confirmed.inputs[1].sequence = 0xffffffff;
+        confirmed.inputs[0].witness = Witness::new(vec![vec![0x30], vec![0x02]]);
+        confirmed
+            .outputs
+            .push(TransactionOutput::new(1, Script::new(vec![0x52])));
+        confirmed.lock_time = 800_000;
+        let diffs = unsigned.diff(&confirmed);
+        assert_eq!(
+            diffs,
+            vec![
+                TxDifference::Sequence {
+                    input: 1,
+                    left: 0xfffffffd,
+                    right: 0xffffffff
+                },
+                TxDifference::OutputCount { left: 1, right: 2 },
+                TxDifference::WitnessItemCount {
+                    input: 0,
+                    left: 0,
+                    right: 2
+                },
+                TxDifference::LockTime {
+                    left: 0,
+                    right: 800_000
+                },
+            ]
+        );
+        assert_eq!(
+            diffs[0].to_string(),
+            "inputs[1].sequence: 0xfffffffd != 0xffffffff"
+        );
+
+        let mut resigned = confirmed.clone();
+        resigned.inputs[0].witness.items[0] = vec![0x31];
+        resigned.outputs[0].value = 8_000;
+        assert_eq!(
+            confirmed.diff(&resigned),
+            vec![
+                TxDifference::Value {
+                    output: 0,
+                    left: 9_000,
+                    right: 8_000
+                },
+                TxDifference::WitnessItem {
+                    input: 0,
+                    item: 0,
+                    left: vec![0x30],
+                    right: vec![0x31]
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_json_schemas() {
+        let schema = serde_json::to_value(schemars::schema_for!(BitcoinTransaction)).unwrap();
+        let definitions = &schema["definitions"];
+        assert_eq!(
+            definitions["Txid"]["pattern"],
+            serde_json::json!("^[0-9a-fA-F]{64}$")
+        );
+        assert_eq!(definitions["Script"]["type"], serde_json::json!("string"));
+        let required = &definitions["TransactionInput"]["required"];
+        assert!(required.as_array().unwrap().contains(&"sequence".into()));
+        assert!(!required.as_array().unwrap().contains(&"witness".into()));
+
+        let schema = serde_json::to_value(schema::core_block_schema()).unwrap();
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&"nTx".into()));
+        assert!(!required.contains(&"previousblockhash".into()));
+        let input = &schema["definitions"]["CoreInput"];
+        assert_eq!(input["required"], serde_json::json!(["sequence"]));
+        assert!(input["properties"]["scriptSig"].is_object());
+
+        // Every field core_json emits is in the schema.
+        let json = core_json::block_to_json(&Block::genesis(Network::Mainnet));
+        let properties = &schema["properties"];
+        for key in json.as_object().unwrap().keys() {
+            assert!(properties.get(key).is_some(), "{key}");
+        }
+        let tx_schema = serde_json::to_value(schema::core_transaction_schema()).unwrap();
+        for key in json["tx"][0].as_object().unwrap().keys() {
+            assert!(tx_schema["properties"].get(key).is_some(), "{key}");
+        }
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn test_proto_round_trip() {
+        use prost::Message;
+
+        let outpoint = proto::OutPoint::from(&OutPoint::new([1; 32], 1));
+        let bytes = outpoint.encode_to_vec();
+        assert_eq!(bytes[..2], [0x0a, 0x20]);
+        assert_eq!(bytes[34..], [0x10, 0x01]);
+
+        let block = Block::from_bytes(&hex::decode(GENESIS_BLOCK_HEX).unwrap())
+            .unwrap()
+            .0;
+        let mut tx = block.transactions[0].clone();
+        tx.inputs[0].witness = Witness::new(vec![vec![0; 32]]);
+        let message = proto::Transaction::from(&tx);
+        let decoded = proto::Transaction::decode(&message.encode_to_vec()[..]).unwrap();
+        assert_eq!(BitcoinTransaction::try_from(decoded), Ok(tx));
+
+        let message = proto::Block::from(&block);
+        let decoded = proto::Block::decode(&message.encode_to_vec()[..]).unwrap();
+        assert_eq!(Block::try_from(decoded), Ok(block));
+
+        let mut short = message.clone();
+        short.transactions[0].inputs[0]
+            .previous_output
+            .as_mut()
+            .unwrap()
+            .txid
+            .pop();
+        assert_eq!(Block::try_from(short), Err(BitcoinError::InvalidFormat));
+        let mut headless = message;
+        headless.header = None;
+        assert_eq!(Block::try_from(headless), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_columnar_export() {
+        use arrow_array::{Array, StringArray, UInt64Array};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let genesis = Block::genesis(Network::Mainnet);
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(genesis.transactions[0].txid().0, 0),
+                Script::new(vec![0x51]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(1_000, Script::new([&[0x00, 0x14][..], &[1; 20]].concat())),
+            TransactionOutput::new(2_000, Script::new(vec![0x6a])),
+        ]);
+        let batches = columnar::blocks_to_batches([&genesis]);
+        assert_eq!(batches.transactions.num_rows(), 1);
+        assert_eq!(batches.inputs.num_rows(), 1);
+        assert_eq!(batches.outputs.num_rows(), 1);
+        let hashes = batches.transactions.column(0);
+        let hashes = hashes.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(
+            hashes.value(0),
+            core_json::reversed_hex(&genesis.block_hash())
+        );
+
+        let batches = columnar::transactions_to_batches([&genesis.transactions[0], &spend]);
+        assert!(batches.transactions.column(0).is_null(1));
+        let prev = batches.inputs.column_by_name("prev_txid").unwrap();
+        let prev = prev.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(
+            prev.value(1),
+            core_json::reversed_hex(&genesis.transactions[0].txid().0)
+        );
+        let types = batches.outputs.column_by_name("script_type").unwrap();
+        let types = types.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(
+            (types.value(0), types.value(1), types.value(2)),
+            ("pubkey", "witness_v0_keyhash", "nulldata")
+        );
+
+        let dir = std::env::temp_dir().join(format!("parquet-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        batches.write_parquet_dir(&dir).unwrap();
+        let file = std::fs::File::open(dir.join("outputs.parquet")).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let read = reader.next().unwrap().unwrap();
+        assert_eq!(read, batches.outputs);
+        let values = read.column_by_name("value").unwrap();
+        let values = values.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(values.values()[1..], [1_000, 2_000]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let genesis = Block::genesis(Network::Mainnet);
+        let coinbase = genesis.transactions[0].txid();
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(coinbase.0, 0),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(
+            1_000,
+            Script::new([&[0x00, 0x14][..], &[0; 20]].concat()),
+        )]);
+        let block = Block::new(
+            genesis.header.clone(),
+            vec![genesis.transactions[0].clone(), spend.clone()],
+        );
+        let csv = csv::to_csv(std::slice::from_ref(&block), Network::Mainnet);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], csv::HEADER);
+        let (hash, coinbase) = (
+            core_json::reversed_hex(&block.block_hash()),
+            core_json::reversed_hex(&coinbase.0),
+        );
+        assert_eq!(rows[1], format!("{hash},{coinbase},input,0,,,,,"));
+        assert_eq!(
+            rows[2],
+            format!("{hash},{coinbase},output,0,,,5000000000,pubkey,")
+        );
+        let txid = core_json::reversed_hex(&spend.txid().0);
+        assert_eq!(rows[3], format!("{hash},{txid},input,0,{coinbase},0,,,"));
+        assert_eq!(
+            rows[4],
+            format!(
+                "{hash},{txid},output,0,,,1000,witness_v0_keyhash,\
+                 bc1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq9e75rs"
+            )
+        );
+        assert!(rows.iter().all(|row| row.split(',').count() == 9));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_index() {
+        use rust_week_3_exercises::sqlite::SqliteIndex;
+
+        let genesis = Block::genesis(Network::Mainnet);
+        let coinbase = genesis.transactions[0].clone();
+        let pay_to = Script::new(vec![0x51]);
+        let spend = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(coinbase.txid().0, 0),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(1_000, pay_to.clone()),
+            TransactionOutput::new(2_000, pay_to.clone()),
+        ]);
+        let mut header = genesis.header.clone();
+        header.prev_blockhash = genesis.block_hash();
+        let next = Block::new(header, vec![spend.clone()]);
+
+        let path = std::env::temp_dir().join(format!("sqlite-index-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let mut index = SqliteIndex::open(&path).unwrap();
+            assert_eq!(index.tip(), Ok(None));
+            index.index_block(&genesis, 0).unwrap();
+            // Out of order, or not building on the tip.
+            assert_eq!(
+                index.index_block(&next, 2),
+                Err(BitcoinError::InvalidFormat)
+            );
+            assert_eq!(
+                index.index_block(&genesis, 1),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+        // Reopening resumes from the tip.
+        let mut index = SqliteIndex::open(&path).unwrap();
+        assert_eq!(index.tip(), Ok(Some((0, genesis.block_hash()))));
+        index.index_block(&next, 1).unwrap();
+        assert_eq!(index.tip(), Ok(Some((1, next.block_hash()))));
+        assert_eq!(index.block_hash(0), Ok(Some(genesis.block_hash())));
+        assert_eq!(index.block_hash(5), Ok(None));
+        assert_eq!(index.transaction_location(&spend.txid()), Ok(Some((1, 0))));
+        assert_eq!(
+            index.spent_by(&OutPoint::new(coinbase.txid().0, 0)),
+            Ok(Some((spend.txid(), 0)))
+        );
+        assert_eq!(index.spent_by(&OutPoint::new(spend.txid().0, 0)), Ok(None));
+        assert_eq!(
+            index.unspent(&pay_to).unwrap(),
+            vec![
+                (OutPoint::new(spend.txid().0, 0), 1_000),
+                (OutPoint::new(spend.txid().0, 1), 2_000),
+            ]
+        );
+        assert!(
+            index
+                .unspent(&coinbase.outputs[0].script_pubkey)
+                .unwrap()
+                .is_empty()
+        );
+        drop(index);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_kv_store_indexes() {
+        use rust_week_3_exercises::store::{
+            HeaderChain, KvStore, MemoryStore, UtxoSet, WriteBatch,
+        };
+
+        let mut store = MemoryStore::default();
+        let mut batch = WriteBatch::new();
+        batch.put(b"a1", b"x");
+        batch.put(b"a2", b"y");
+        batch.put(b"b1", b"z");
+        batch.delete(b"a1");
+        store.write_batch(batch).unwrap();
+        assert_eq!(store.get(b"a1"), Ok(None));
+        assert_eq!(
+            store.scan_prefix(b"a"),
+            Ok(vec![(b"a2".to_vec(), b"y".to_vec())])
+        );
+
+        // Header chain: genesis, then mainnet block 1.
+        let genesis = Block::genesis(Network::Mainnet);
+        let block1 = BlockHeader::from_bytes(
+            &hex::decode(
+                "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000\
+                 982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e\
+                 61bc6649ffff001d01e36299",
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .0;
+        let mut chain = HeaderChain::new(MemoryStore::default());
+        assert_eq!(chain.tip(), Ok(None));
+        assert_eq!(chain.push(&genesis.header), Ok(0));
+        assert_eq!(
+            chain.push(&genesis.header),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let mut unmined = block1.clone();
+        unmined.nonce += 1;
+        assert_eq!(chain.push(&unmined), Err(BitcoinError::InvalidFormat));
+        assert_eq!(chain.push(&block1), Ok(1));
+        assert_eq!(chain.tip(), Ok(Some((1, block1.clone()))));
+        assert_eq!(chain.height_of(&block1.block_hash()), Ok(Some(1)));
+        assert_eq!(chain.get(0), Ok(Some(genesis.header.clone())));
+
+        // UTXO set: a block spending the genesis coinbase and, within the
+        // block, one of its own outputs.
+        let mut utxos = UtxoSet::new(MemoryStore::default());
+        assert_eq!(utxos.connect_block(&genesis, 0), Ok(vec![]));
+        let coinbase = OutPoint::new(genesis.transactions[0].txid().0, 0);
+        let coin = utxos.get(&coinbase).unwrap().unwrap();
+        assert!(coin.coinbase);
+        assert_eq!(coin.output, genesis.transactions[0].outputs[0]);
+
+        let first = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                coinbase.clone(),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![
+            TransactionOutput::new(4_000, Script::new([&[0x00, 0x14][..], &[1; 20]].concat())),
+            TransactionOutput::new(0, Script::new(vec![0x6a, 0x01, 0x00])),
+        ]);
+        let second = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(first.txid().0, 0),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        )
+        .with_outputs(vec![TransactionOutput::new(
+            3_000,
+            Script::new([&[0x00, 0x14][..], &[2; 20]].concat()),
+        )]);
+        let block = Block::new(block1, vec![first.clone(), second.clone()]);
+        let spent = utxos.connect_block(&block, 1).unwrap();
+        assert_eq!(spent.len(), 2);
+        assert_eq!(spent[0], coin);
+        assert_eq!(spent[1].output.value, 4_000);
+        assert_eq!(utxos.get(&coinbase), Ok(None));
+        assert_eq!(utxos.get(&OutPoint::new(first.txid().0, 1)), Ok(None));
+        assert_eq!(utxos.store().len(), 1);
+        let created = utxos
+            .get(&OutPoint::new(second.txid().0, 0))
+            .unwrap()
+            .unwrap();
+        assert_eq!((created.height, created.coinbase), (1, false));
+
+        let before = utxos.store().len();
+        assert_eq!(
+            utxos.connect_block(&block, 2),
+            Err(BitcoinError::MissingPrevout(coinbase))
+        );
+        assert_eq!(utxos.store().len(), before);
+    }
+
+    #[test]
+    fn test_tx_cache() {
+        use rust_week_3_exercises::cache::TxCache;
+
+        let tx = |n: u32| {
+            BitcoinTransaction::new(
+                n,
+                vec![
+                    TransactionInput::new(OutPoint::new([n as u8; 32], 0), Script::new(vec![]), 0)
+                        .with_witness(Witness::new(vec![vec![1]])),
+                ],
+                0,
+            )
+        };
+        let mut cache = TxCache::new(2);
+        let first = cache.insert(tx(1));
+        assert_eq!(first.txid, tx(1).txid());
+        assert_eq!(first.wtxid, tx(1).wtxid());
+        assert_eq!(first.weight, tx(1).weight());
+        cache.insert(tx(2));
+        // Using tx 1 makes tx 2 the one evicted.
+        assert!(cache.get(&tx(1).txid()).is_some());
+        cache.insert(tx(3));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&tx(1).txid()));
+        assert!(!cache.contains(&tx(2).txid()));
+        assert!(cache.get(&tx(2).txid()).is_none());
+
+        // Reinserting doesn't grow the cache.
+        cache.insert(tx(3));
+        assert_eq!(cache.len(), 2);
+
+        let mut decoded = 0;
+        let hit = cache
+            .get_or_insert_with(&tx(3).txid(), || {
+                decoded += 1;
+                Ok::<_, BitcoinError>(tx(3))
+            })
+            .unwrap();
+        assert_eq!((hit.tx.version, decoded), (3, 0));
+        let bytes = tx(4).to_bytes();
+        let miss = cache
+            .get_or_insert_with(&tx(4).txid(), || {
+                BitcoinTransaction::from_bytes(&bytes).map(|(tx, _)| tx)
+            })
+            .unwrap();
+        assert_eq!(miss.tx, tx(4));
+        assert!(!cache.contains(&tx(1).txid()));
+
+        assert!(cache.remove(&tx(4).txid()).is_some());
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let mut disabled = TxCache::new(0);
+        disabled.insert(tx(1));
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn test_ingest_pipeline() {
+        use rust_week_3_exercises::ingest::{IngestOptions, ingest};
+
+        // Blocks with distinct coinbases, so order is checkable.
+        let genesis = Block::genesis(Network::Regtest);
+        let blocks: Vec<Block> = (0..40u32)
+            .map(|i| {
+                let mut block = genesis.clone();
+                block.transactions[0].lock_time = i;
+                block
+            })
+            .collect();
+        let dir = std::env::temp_dir().join(format!("ingest-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut paths = Vec::new();
+        for (n, chunk) in blocks.chunks(15).enumerate() {
+            let mut file = Vec::new();
+            for block in chunk {
+                let bytes = block.to_bytes();
+                file.extend_from_slice(&Network::Regtest.magic());
+                file.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                file.extend_from_slice(&bytes);
+            }
+            let path = dir.join(format!("blk{n:05}.dat"));
+            std::fs::write(&path, file).unwrap();
+            paths.push(path);
+        }
+
+        let options = IngestOptions {
+            decode_threads: 4,
+            queue_len: 2,
+            ..Default::default()
+        };
+        let mut seen = Vec::new();
+        ingest(&paths, Network::Regtest, &options, |ingested| {
+            assert_eq!(ingested.txids, vec![ingested.block.transactions[0].txid()]);
+            seen.push((ingested.index, ingested.block));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(seen.len(), blocks.len());
+        for (i, (index, block)) in seen.into_iter().enumerate() {
+            assert_eq!((index, block), (i as u64, blocks[i].clone()));
+        }
+
+        // A callback error stops the run early.
+        let mut calls = 0;
+        let result = ingest(&paths, Network::Regtest, &options, |_| {
+            calls += 1;
+            if calls == 3 {
+                return Err(BitcoinError::Unsupported);
+            }
+            Ok(())
+        });
+        assert_eq!((result, calls), (Err(BitcoinError::Unsupported), 3));
+
+        // So does a missing file, after the blocks before it.
+        paths.insert(1, dir.join("blk99999.dat"));
+        let mut count = 0;
+        let result = ingest(&paths, Network::Regtest, &IngestOptions::default(), |_| {
+            count += 1;
+            Ok(())
+        });
+        assert!(matches!(result, Err(BitcoinError::Io(_))));
+        assert_eq!(count, 15);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_tx_stream() {
+        use rust_week_3_exercises::stream::TxStream;
+
+        let genesis = Block::genesis(Network::Regtest);
+        let blocks: Vec<Block> = (0..5u32)
+            .map(|i| {
+                let mut block = genesis.clone();
+                block.transactions[0].lock_time = i;
+                block
+            })
+            .collect();
+        let dir = std::env::temp_dir().join(format!("stream-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut file = Vec::new();
+        for block in &blocks {
+            let bytes = block.to_bytes();
+            file.extend_from_slice(&Network::Regtest.magic());
+            file.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            file.extend_from_slice(&bytes);
+        }
+        let path = dir.join("blk00000.dat");
+        std::fs::write(&path, file).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut stream = TxStream::from_block_files([&path], Network::Regtest, 1);
+            let mut lock_times = Vec::new();
+            while let Some(tx) = stream.next().await {
+                lock_times.push(tx.unwrap().lock_time);
+            }
+            assert_eq!(lock_times, vec![0, 1, 2, 3, 4]);
+
+            // Errors end the stream.
+            let mut stream =
+                TxStream::from_block_files([dir.join("missing.dat")], Network::Regtest, 4);
+            assert!(matches!(
+                stream.next().await,
+                Some(Err(BitcoinError::Io(_)))
+            ));
+            assert!(stream.next().await.is_none());
+
+            let source = vec![
+                Ok(genesis.transactions[0].clone()),
+                Err(BitcoinError::InvalidFormat),
+            ];
+            let mut stream = TxStream::from_iter(source.into_iter().cycle(), 2);
+            assert!(matches!(stream.next().await, Some(Ok(_))));
+            assert!(matches!(stream.next().await, Some(Err(_))));
+            assert!(stream.next().await.is_none());
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_progress_reporting() {
+        use rust_week_3_exercises::ingest::{IngestOptions, ingest_with_progress};
+        use rust_week_3_exercises::progress::Progress;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        let genesis = Block::genesis(Network::Regtest);
+        let bytes = genesis.to_bytes();
+        let mut file = vec![0u8; 5];
+        for _ in 0..3 {
+            file.extend_from_slice(&Network::Regtest.magic());
+            file.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            file.extend_from_slice(&bytes);
+        }
+        let record_len = bytes.len() as u64 + 8;
+        let dir = std::env::temp_dir().join(format!("progress-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blk00000.dat");
+        std::fs::write(&path, &file).unwrap();
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let reader = blockfile::BlockFileReader::open(&path, Network::Regtest)
+            .unwrap()
+            .with_progress(move |p: &Progress| sink.lock().unwrap().push(*p));
+        assert_eq!(reader.count(), 3);
+        let reports = reports.lock().unwrap();
+        let seen: Vec<_> = reports
+            .iter()
+            .map(|p| (p.blocks_parsed, p.bytes_processed, p.total_bytes))
+            .collect();
+        let total = Some(file.len() as u64);
+        assert_eq!(
+            seen,
+            vec![
+                (1, 5 + record_len, total),
+                (2, 5 + 2 * record_len, total),
+                (3, 5 + 3 * record_len, total),
+            ]
+        );
+        assert_eq!(reports[2].fraction(), Some(1.0));
+        assert_eq!(reports[2].eta(), Some(Duration::ZERO));
+
+        let mut positions = Vec::new();
+        let options = IngestOptions {
+            decode_threads: 2,
+            queue_len: 1,
+            ..Default::default()
+        };
+        ingest_with_progress(
+            &[&path, &path],
+            Network::Regtest,
+            &options,
+            |p: &Progress| positions.push((p.blocks_parsed, p.bytes_processed)),
+            |_| Ok(()),
+        )
+        .unwrap();
+        let len = file.len() as u64;
+        assert_eq!(positions.len(), 6);
+        assert_eq!(positions[3], (4, len + 5 + record_len));
+        assert_eq!(positions[5], (6, 2 * len));
+
+        let halfway = Progress {
+            bytes_processed: 50,
+            total_bytes: Some(200),
+            blocks_parsed: 1,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(halfway.fraction(), Some(0.25));
+        assert_eq!(halfway.eta(), Some(Duration::from_secs(30)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancellation() {
+        use rust_week_3_exercises::cancel::CancellationToken;
+        use rust_week_3_exercises::ingest::{IngestOptions, ingest};
+        use rust_week_3_exercises::rescan::ScriptSet;
+
+        let genesis = Block::genesis(Network::Regtest);
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert_eq!(token, clone);
+        assert_ne!(token, CancellationToken::new());
+        assert_eq!(token.check(), Ok(()));
+
+        // Rescans stop before the block after the one that cancelled.
+        let mut set = ScriptSet::new();
+        set.insert_script(genesis.transactions[0].outputs[0].script_pubkey.clone());
+        let blocks = std::iter::repeat_with(|| Ok(genesis.clone())).take(5);
+        let mut matched = 0;
+        let result = set.rescan(blocks.clone(), &token, |_, relevant| {
+            assert_eq!(relevant.len(), 1);
+            matched += 1;
+            if matched == 2 {
+                clone.cancel();
+            }
+        });
+        assert_eq!((result, matched), (Err(BitcoinError::Cancelled), 2));
+        assert_eq!(
+            set.rescan(blocks, &CancellationToken::new(), |_, _| {}),
+            Ok(5)
+        );
+
+        let bytes = genesis.to_bytes();
+        let mut file = Vec::new();
+        for _ in 0..20 {
+            file.extend_from_slice(&Network::Regtest.magic());
+            file.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            file.extend_from_slice(&bytes);
+        }
+        let dir = std::env::temp_dir().join(format!("cancel-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blk00000.dat");
+        std::fs::write(&path, &file).unwrap();
+
+        let options = IngestOptions {
+            decode_threads: 2,
+            queue_len: 1,
+            ..Default::default()
+        };
+        let mut delivered = 0;
+        let result = ingest(&[&path], Network::Regtest, &options, |_| {
+            delivered += 1;
+            if delivered == 3 {
+                options.cancel.cancel();
+            }
+            Ok(())
+        });
+        assert_eq!((result, delivered), (Err(BitcoinError::Cancelled), 3));
+
+        #[cfg(feature = "rayon")]
+        {
+            let parsed = blockfile::parse_blocks_parallel_cancellable(
+                &[&path],
+                Network::Regtest,
+                &options.cancel,
+            );
+            assert_eq!(parsed, Err(BitcoinError::Cancelled));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_length_overflow() {
+        // Lengths that can't fit usize are errors, not truncated reads.
+        let huge = CompactSize::new(u64::MAX);
+        if usize::BITS < 64 {
+            assert_eq!(huge.to_usize(), Err(BitcoinError::NumericOverflow));
+        } else {
+            assert_eq!(huge.to_usize(), Ok(usize::MAX));
+        }
+        assert_eq!(CompactSize::new(520).to_usize(), Ok(520));
+
+        // Lengths near usize::MAX must not overflow the bounds check.
+        let mut bytes = huge.to_bytes();
+        bytes.extend_from_slice(&[0u8; 16]);
+        assert!(matches!(
+            Script::from_bytes(&bytes),
+            Err(BitcoinError::InsufficientBytes | BitcoinError::NumericOverflow)
+        ));
+        let mut witness = vec![0x01];
+        witness.extend_from_slice(&bytes);
+        assert!(matches!(
+            Witness::from_bytes(&witness),
+            Err(BitcoinError::InsufficientBytes | BitcoinError::NumericOverflow)
+        ));
+        assert_eq!(
+            BitcoinError::NumericOverflow.to_string(),
+            "numeric overflow"
+        );
+    }
+
+    #[test]
+    fn test_block_hash_display_and_work() {
+        let genesis = Block::genesis(Network::Mainnet);
+        let hash = genesis.hash();
+        let expected = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        assert_eq!(hash.to_string(), expected);
+        assert_eq!(format!("{hash:?}"), format!("BlockHash({expected})"));
+        assert_eq!(hash.leading_zero_bits(), 43);
+        assert_eq!(BlockHash([0; 32]).to_string(), "0".repeat(64));
+        assert_eq!(BlockHash([0; 32]).leading_zero_bits(), 256);
+        assert_eq!(BlockHash([0; 32]).work(), [0xff; 32]);
+
+        let as_u128 = |work: [u8; 32]| {
+            assert_eq!(work[16..], [0; 16]);
+            u128::from_le_bytes(work[..16].try_into().unwrap())
+        };
+        // Difficulty 1 is 2^32 + 2^16 + 1 hashes.
+        assert_eq!(genesis.header.work().map(as_u128), Some(4_295_032_833));
+        assert_eq!(as_u128(hash.work()), 10_894_034_230_307);
+        assert_eq!(as_u128(BlockHash([0xff; 32]).work()), 1);
+        let mut top_bit = [0u8; 32];
+        top_bit[31] = 0x80;
+        assert_eq!(as_u128(BlockHash(top_bit).work()), 1);
+    }
+
+    #[test]
+    fn test_display_shows_txids_sequences_and_script_types() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(0xAB), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(0xCD), 1),
+                Script::new(vec![]),
+                0xFFFFFFFD,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(0xEF), 2), Script::new(vec![]), 10),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(0x01), 3),
+                Script::new(vec![]),
+                0xFFFFFFFE,
+            ),
+        ];
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&[0x11; 20]);
+        let tx = BitcoinTransaction::new(2, inputs, 0)
+            .with_outputs(vec![TransactionOutput::new(5_000, Script::new(p2wpkh))]);
+        let text = tx.to_string();
+        assert!(text.starts_with(&format!(
+            "Txid: {}\n",
+            core_json::reversed_hex(&tx.txid().0)
+        )));
+        assert!(text.contains(&format!("Previous Output Txid: ab{}\n", "00".repeat(31))));
+        assert!(text.contains("Sequence: 4294967295 (final)\n"));
+        assert!(text.contains("Sequence: 4294967293 (replaceable)\n"));
+        assert!(text.contains("Sequence: 10 (replaceable, relative lock 10 blocks)\n"));
+        assert!(text.contains("Sequence: 4294967294 (lock time enabled)\n"));
+        assert!(text.contains("Output Value: 5000\n"));
+        assert!(text.contains("Script Type: witness_v0_keyhash\n"));
+
+        // BIP68 doesn't apply before version 2; time locks count 512s units.
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(0x02), 0), Script::new(vec![]), 0);
+        input.sequence = (1 << 22) | 3;
+        assert_eq!(format::describe_sequence(&input, 1), "replaceable");
+        assert_eq!(
+            format::describe_sequence(&input, 2),
+            "replaceable, relative lock 1536 seconds"
+        );
+    }
+
+    #[test]
+    fn test_skeleton_constructors() {
+        let null = OutPoint::null();
+        assert!(null.is_null());
+        assert_eq!(null, OutPoint::new([0; 32], u32::MAX));
+
+        let input = TransactionInput::coinbase(Script::new(vec![0x51]));
+        assert!(input.previous_output.is_null());
+        assert_eq!(input.sequence, 0xFFFFFFFF);
+        assert!(input.witness.is_empty());
+
+        let mut tx = BitcoinTransaction::empty(2);
+        assert_eq!(tx, BitcoinTransaction::new(2, vec![], 0));
+        tx.inputs.push(input);
+        assert!(tx.is_coinbase());
+
+        let genesis = Block::genesis(Network::Mainnet);
+        assert_eq!(
+            genesis.transactions[0].inputs[0],
+            TransactionInput::coinbase(genesis.transactions[0].inputs[0].script_sig.clone())
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        use rust_week_3_exercises::constants::*;
+
+        assert_eq!(MAX_MONEY, 2_100_000_000_000_000);
+        assert_eq!(COIN, 100_000_000);
+        // The old paths name the same values.
+        assert_eq!(consensus::MAX_BLOCK_WEIGHT, MAX_BLOCK_WEIGHT);
+        assert_eq!(locktime::LOCKTIME_THRESHOLD, LOCKTIME_THRESHOLD);
+        assert_eq!(
+            blockfile::MAX_BLOCK_SERIALIZED_SIZE,
+            MAX_BLOCK_SERIALIZED_SIZE
+        );
+        assert_eq!(builder::MAX_MONEY, MAX_MONEY);
+
+        let tx = Block::genesis(Network::Mainnet).transactions[0].clone();
+        assert_eq!(tx.outputs[0].value, 50 * COIN);
+        assert_eq!(tx.inputs[0].sequence, SEQUENCE_FINAL);
+        assert_eq!(tx.weight(), tx.to_bytes().len() * WITNESS_SCALE_FACTOR);
+
+        let mut replaceable = tx.clone();
+        replaceable.inputs[0].sequence = MAX_BIP125_RBF_SEQUENCE;
+        assert!(rbf::signals_rbf(&replaceable));
+        replaceable.inputs[0].sequence = MAX_BIP125_RBF_SEQUENCE + 1;
+        assert!(!rbf::signals_rbf(&replaceable));
+    }
+
+    #[test]
+    fn test_oversized_script_sizes() {
+        use snapshot::{SnapshotMetadata, SnapshotReader};
+        use undo::{BlockUndo, Coin};
+
+        let rejected = |result: Result<_, BitcoinError>| {
+            matches!(
+                result,
+                Err(BitcoinError::InvalidFormat | BitcoinError::NumericOverflow)
+            )
+        };
+        for size in [10_007, u32::MAX as u64 + 6, u64::MAX] {
+            // Height 1, amount 1, then the script size.
+            let mut coin = VarInt::new(2).to_bytes();
+            coin.extend(VarInt::new(1).to_bytes());
+            coin.extend(VarInt::new(size).to_bytes());
+            coin.extend([0u8; 32]);
+            assert!(rejected(Coin::from_bytes(&coin).map(|_| ())));
+
+            // The same coin in undo data, after its version.
+            let mut undo = vec![0x01, 0x01];
+            undo.extend(VarInt::new(2).to_bytes());
+            undo.extend(VarInt::new(2).to_bytes());
+            undo.extend(&coin[1..]);
+            assert!(rejected(BlockUndo::from_bytes(&undo).map(|_| ())));
+
+            let metadata = SnapshotMetadata {
+                network: Network::Regtest,
+                base_blockhash: BlockHash([9; 32]),
+                coins_count: 1,
+            };
+            let mut bytes = metadata.to_bytes();
+            bytes.extend([1u8; 32]);
+            bytes.extend([0x01, 0x00]);
+            bytes.extend(&coin);
+            let mut reader = SnapshotReader::new(&bytes[..]).unwrap();
+            assert!(rejected(reader.next().unwrap().map(|_| ())));
+        }
+    }
+
+    #[test]
+    fn test_satoshis_to_btc_fixed_point() {
+        use rust_week_3_exercises::constants::MAX_MONEY;
+        use rust_week_3_exercises::core_json::{satoshis_to_btc, to_string};
+
+        assert_eq!(to_string(&satoshis_to_btc(1)), "0.00000001");
+        assert_eq!(to_string(&satoshis_to_btc(0)), "0.00000000");
+        assert_eq!(to_string(&satoshis_to_btc(5_000_000_000)), "50.00000000");
+        assert_eq!(to_string(&satoshis_to_btc(MAX_MONEY)), "21000000.00000000");
+        assert_eq!(
+            to_string(&satoshis_to_btc(MAX_MONEY - 1)),
+            "20999999.99999999"
+        );
+        assert_eq!(
+            to_string(&serde_json::json!({"n": 0, "value": satoshis_to_btc(1)})),
+            r#"{"n":0,"value":0.00000001}"#
+        );
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_inline_script() {
+        let p2tr = Script::new([&[0x51, 0x20][..], &[2; 32]].concat());
+        let (inline, consumed) = InlineScript::from_bytes(&p2tr.to_bytes()).unwrap();
+        assert_eq!(consumed, 35);
+        assert!(inline.is_inline());
+        assert_eq!(inline, p2tr);
+        assert_eq!(inline.script_type(), ScriptType::P2tr);
+        assert_eq!(Script::from(inline), p2tr);
+
+        let bare = Script::new([&[0x41][..], &[0x04; 65], &[0xac]].concat());
+        let spilled = InlineScript::from(&bare);
+        assert!(!spilled.is_inline());
+        assert_eq!(spilled.to_script(), bare);
+        assert_eq!(
+            InlineScript::from_bytes(&[0x05, 0x51]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
 }